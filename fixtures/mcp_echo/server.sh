@@ -0,0 +1,72 @@
+#!/bin/sh
+# A tiny MCP server for tests: newline-delimited JSON-RPC over stdio.
+#
+# Tools: echo (returns its text), fail (returns an error result), hang
+# (never answers) and grow (adds an "extra" tool and announces the change).
+# tools/list is split over two pages. There is one prompt, review, and one
+# resource, mem://notes. Expects compact JSON with sorted keys, as
+# serde_json writes it.
+
+field() {
+    printf '%s\n' "$1" | sed -n "s/.*\"$2\":\"\\([^\"]*\\)\".*/\\1/p"
+}
+
+grown=
+
+while IFS= read -r line; do
+    id=$(printf '%s\n' "$line" | sed -n 's/^{"id":\([0-9][0-9]*\),.*/\1/p')
+    method=$(field "$line" method)
+    [ -n "$id" ] || continue
+    case "$method" in
+        initialize)
+            result='{"protocolVersion":"2025-03-26","capabilities":{"prompts":{},"resources":{},"tools":{"listChanged":true}},"serverInfo":{"name":"echo","version":"1.0.0"}}'
+            ;;
+        tools/list)
+            if [ "$(field "$line" cursor)" = "page2" ]; then
+                extra=
+                [ -n "$grown" ] && extra=',{"name":"extra","inputSchema":{"type":"object"}}'
+                result='{"tools":[{"name":"fail","description":"Always fails","inputSchema":{"type":"object","properties":{}}},{"name":"grow","description":"Add a tool","inputSchema":{"type":"object"}},{"name":"hang","description":"Never answers","inputSchema":{"type":"object"}}'"$extra"']}'
+            else
+                result='{"tools":[{"name":"echo","description":"Echo the given text","inputSchema":{"type":"object","properties":{"text":{"type":"string"}},"required":["text"]}}],"nextCursor":"page2"}'
+            fi
+            ;;
+        tools/call)
+            case "$(field "$line" name)" in
+                echo) result="{\"content\":[{\"type\":\"text\",\"text\":\"$(field "$line" text)\"}]}" ;;
+                fail) result='{"content":[{"type":"text","text":"something broke"}],"isError":true}' ;;
+                hang) continue ;;
+                grow)
+                    grown=1
+                    printf '%s\n' '{"jsonrpc":"2.0","method":"notifications/tools/list_changed"}'
+                    result='{"content":[{"type":"text","text":"grown"}]}'
+                    ;;
+                *) printf '{"jsonrpc":"2.0","id":%s,"error":{"code":-32602,"message":"Unknown tool"}}\n' "$id"; continue ;;
+            esac
+            ;;
+        prompts/list)
+            result='{"prompts":[{"name":"review","description":"Review a file","arguments":[{"name":"path","required":true},{"name":"focus"}]}]}'
+            ;;
+        prompts/get)
+            result="{\"messages\":[{\"role\":\"user\",\"content\":{\"type\":\"text\",\"text\":\"Review $(field "$line" path)\"}}]}"
+            ;;
+        resources/list)
+            result='{"resources":[{"uri":"mem://notes","name":"notes","mimeType":"text/plain"}]}'
+            ;;
+        resources/read)
+            if [ "$(field "$line" uri)" = "mem://notes" ]; then
+                result='{"contents":[{"uri":"mem://notes","mimeType":"text/plain","text":"remember the milk"}]}'
+            else
+                printf '{"jsonrpc":"2.0","id":%s,"error":{"code":-32002,"message":"Resource not found"}}\n' "$id"
+                continue
+            fi
+            ;;
+        ping)
+            result='{}'
+            ;;
+        *)
+            printf '{"jsonrpc":"2.0","id":%s,"error":{"code":-32601,"message":"Method not found"}}\n' "$id"
+            continue
+            ;;
+    esac
+    printf '{"jsonrpc":"2.0","id":%s,"result":%s}\n' "$id" "$result"
+done
@@ -2,9 +2,10 @@
 
 use crate::{
     cli::Context,
+    hooks::PostToolUseResult,
     llm::{self, LlmClient},
     plan::{self, PlanPhase},
-    policy::Decision,
+    policy::{Decision, PermissionCheck, PolicyEngine},
     tool_display, tools,
 };
 use anyhow::Result;
@@ -59,6 +60,87 @@ Use Bash for running builds, tests, formatters, and git operations.
 Never use curl or wget - they are blocked by policy.
 Keep edits minimal and precise."#;
 
+/// Check a call against `policy`, letting PermissionRequest hooks answer
+/// before a prompt would be shown; Notification hooks hear about prompts
+pub fn check_permission(
+    ctx: &Context,
+    policy: &mut PolicyEngine,
+    tool: &str,
+    args: &Value,
+) -> PermissionCheck {
+    let (decision, rule) = policy.decide(tool, args);
+    if decision == Decision::Ask {
+        let answer = ctx
+            .hooks
+            .borrow()
+            .permission_request(tool, args, rule.as_deref());
+        if let Some(answer) = answer {
+            if !answer.allow {
+                eprintln!(
+                    "Permission denied: {} - denied by PermissionRequest hook",
+                    tool
+                );
+            }
+            return PermissionCheck {
+                allowed: answer.allow,
+                decision,
+                rule,
+                added_rules: Vec::new(),
+                feedback: answer.message,
+                by_hook: true,
+            };
+        }
+        if policy.prompts() {
+            ctx.hooks.borrow().notify(
+                "permission_prompt",
+                &format!("yo needs your permission to use {}", tool),
+            );
+        }
+    }
+    policy.check_permission(tool, args)
+}
+
+/// Apply what PostToolUse hooks returned to a tool result, logging each
+/// change to the transcript
+fn apply_post_tool_use(
+    ctx: &Context,
+    tool: &str,
+    mut result: Value,
+    post: PostToolUseResult,
+) -> Value {
+    let mut transcript = ctx.transcript.borrow_mut();
+    if let Some(feedback) = post.block_feedback {
+        let _ = transcript.hook_modification(
+            "PostToolUse",
+            Some(tool),
+            "block_with_feedback",
+            &json!(feedback),
+        );
+        result = json!({ "error": { "code": "hook_blocked", "message": feedback } });
+    } else if let Some(updated) = post.updated_result {
+        let _ = transcript.hook_modification("PostToolUse", Some(tool), "updated_result", &updated);
+        result = updated;
+    }
+    for context in &post.additional_context {
+        let _ = transcript.hook_modification(
+            "PostToolUse",
+            Some(tool),
+            "additional_context",
+            &json!(context),
+        );
+    }
+    if !post.additional_context.is_empty() {
+        let context = post.additional_context.join("\n\n");
+        match result.as_object_mut() {
+            Some(object) => {
+                object.insert("additional_context".to_string(), json!(context));
+            }
+            None => result = json!({ "result": result, "additional_context": context }),
+        }
+    }
+    result
+}
+
 fn trace(ctx: &Context, label: &str, content: &str) {
     if *ctx.tracing.borrow() {
         eprintln!("[TRACE:{}] {}", label, content);
@@ -71,6 +153,92 @@ fn verbose(ctx: &Context, message: &str) {
     }
 }
 
+/// Tool schemas for the next request: read-only tools while planning, and
+/// only the tools active skills allow
+fn turn_tool_schemas(
+    ctx: &Context,
+    in_planning_mode: bool,
+    schema_opts: &tools::SchemaOptions,
+) -> Vec<Value> {
+    // Built-in tool schemas (including Task for main agent) and MCP tools
+    let mut tool_schemas = if in_planning_mode {
+        // In planning mode, only provide read-only tools
+        tools::schemas(schema_opts)
+            .into_iter()
+            .filter(|schema| {
+                if let Some(name) = schema
+                    .get("function")
+                    .and_then(|f| f.get("name"))
+                    .and_then(|n| n.as_str())
+                {
+                    matches!(
+                        name,
+                        "Read" | "ReadMany" | "Glob" | "List" | "Search" | "CodeSearch"
+                    )
+                } else {
+                    false
+                }
+            })
+            .collect()
+    } else {
+        tools::schemas_with_task(schema_opts, &ctx.mcp.borrow())
+    };
+
+    // Apply allowed-tools restriction from active skills
+    let active_skills = ctx.active_skills.borrow();
+    let effective_allowed = active_skills.effective_allowed_tools();
+    drop(active_skills);
+
+    if let Some(allowed) = &effective_allowed {
+        tool_schemas.retain(|schema| {
+            if let Some(name) = schema
+                .get("function")
+                .and_then(|f| f.get("name"))
+                .and_then(|n| n.as_str())
+            {
+                // ActivateSkill is always available
+                if name == "ActivateSkill" {
+                    return true;
+                }
+                // Task is always available for subagent delegation
+                if name == "Task" {
+                    return true;
+                }
+                allowed.iter().any(|a| a == name)
+            } else {
+                false
+            }
+        });
+    }
+
+    tool_schemas
+}
+
+/// Pick up tool lists that connected MCP servers changed since the last
+/// request; returns whether any did
+fn refresh_mcp(ctx: &Context) -> bool {
+    let refreshed = ctx.mcp.borrow_mut().refresh();
+    let mut transcript = ctx.transcript.borrow_mut();
+    for (server, result) in &refreshed {
+        match result {
+            Ok(count) => {
+                trace(ctx, "MCP", &format!("{} tools changed ({})", server, count));
+                let _ = transcript.mcp_server(server, "tools_changed", Some(*count), None);
+            }
+            Err(e) => {
+                eprintln!("Warning: MCP server '{}': {:#}", server, e);
+                let _ = transcript.mcp_server(
+                    server,
+                    "refresh_failed",
+                    None,
+                    Some(&format!("{:#}", e)),
+                );
+            }
+        }
+    }
+    !refreshed.is_empty()
+}
+
 pub fn run_turn(ctx: &Context, user_input: &str, messages: &mut Vec<Value>) -> Result<TurnResult> {
     let mut turn_result = TurnResult::default();
     let _ = ctx.transcript.borrow_mut().user_message(user_input);
@@ -93,6 +261,8 @@ pub fn run_turn(ctx: &Context, user_input: &str, messages: &mut Vec<Value>) -> R
         }
     };
     let bash_config = ctx.config.borrow().bash.clone();
+    let format_config = ctx.config.borrow().format.clone();
+    ctx.limits.borrow_mut().start_turn();
 
     trace(ctx, "TARGET", &target.to_string());
 
@@ -124,54 +294,10 @@ pub fn run_turn(ctx: &Context, user_input: &str, messages: &mut Vec<Value>) -> R
         }
     }
 
-    // Get built-in tool schemas (including Task for main agent) and add MCP tools
-    let schema_opts = tools::SchemaOptions::new(ctx.args.optimize);
-    let mut tool_schemas = if in_planning_mode {
-        // In planning mode, only provide read-only tools
-        tools::schemas(&schema_opts)
-            .into_iter()
-            .filter(|schema| {
-                if let Some(name) = schema
-                    .get("function")
-                    .and_then(|f| f.get("name"))
-                    .and_then(|n| n.as_str())
-                {
-                    matches!(name, "Read" | "Glob" | "Search")
-                } else {
-                    false
-                }
-            })
-            .collect()
-    } else {
-        tools::schemas_with_task(&schema_opts)
-    };
-
-    // Apply allowed-tools restriction from active skills
-    let active_skills = ctx.active_skills.borrow();
-    let effective_allowed = active_skills.effective_allowed_tools();
-    drop(active_skills);
-
-    if let Some(allowed) = &effective_allowed {
-        tool_schemas.retain(|schema| {
-            if let Some(name) = schema
-                .get("function")
-                .and_then(|f| f.get("name"))
-                .and_then(|n| n.as_str())
-            {
-                // ActivateSkill is always available
-                if name == "ActivateSkill" {
-                    return true;
-                }
-                // Task is always available for subagent delegation
-                if name == "Task" {
-                    return true;
-                }
-                allowed.iter().any(|a| a == name)
-            } else {
-                false
-            }
-        });
-    }
+    let schema_opts =
+        tools::SchemaOptions::new(ctx.args.optimize).with_shell(bash_config.shell.is_some());
+    refresh_mcp(ctx);
+    let mut tool_schemas = turn_tool_schemas(ctx, in_planning_mode, &schema_opts);
 
     // Use max_turns from CLI if provided, otherwise default
     let max_iterations = ctx.args.max_turns.unwrap_or(MAX_ITERATIONS);
@@ -179,6 +305,25 @@ pub fn run_turn(ctx: &Context, user_input: &str, messages: &mut Vec<Value>) -> R
     for iteration in 1..=max_iterations {
         trace(ctx, "ITER", &format!("Starting iteration {}", iteration));
 
+        // A tool call may have changed a server's tools
+        if iteration > 1 && refresh_mcp(ctx) {
+            tool_schemas = turn_tool_schemas(ctx, in_planning_mode, &schema_opts);
+        }
+
+        // Stop the turn once it has run past max_turn_seconds
+        if let Err(exceeded) = ctx.limits.borrow().check_time() {
+            eprintln!(
+                "⚠️  {}; stopping this turn ({})",
+                exceeded.message, exceeded.limit
+            );
+            let _ = ctx.transcript.borrow_mut().limit_exceeded(
+                &exceeded.limit,
+                None,
+                &exceeded.message,
+            );
+            break;
+        }
+
         // Get client for target's backend (lazy-loaded)
         let response = {
             let mut backends = ctx.backends.borrow_mut();
@@ -213,6 +358,20 @@ pub fn run_turn(ctx: &Context, user_input: &str, messages: &mut Vec<Value>) -> R
             }
             drop(active_skills);
 
+            // Add repository map
+            if let Some(map) = ctx.repo_map.borrow().as_deref() {
+                system_prompt.push_str("\n\n");
+                system_prompt.push_str(map);
+            }
+
+            // Add context from SessionStart hooks
+            let session_context = ctx.session_context.borrow();
+            if !session_context.is_empty() {
+                system_prompt.push_str("\n\n# Session context\n\n");
+                system_prompt.push_str(&session_context.join("\n\n"));
+            }
+            drop(session_context);
+
             let mut req_messages = vec![json!({
                 "role": "system",
                 "content": system_prompt
@@ -340,8 +499,10 @@ pub fn run_turn(ctx: &Context, user_input: &str, messages: &mut Vec<Value>) -> R
         messages.push(assistant_msg);
 
         for tc in tool_calls {
-            let name = &tc.function.name;
+            // MCP tools are called by their API-safe names; use mcp.<server>.<tool>
+            let name = &tools::mcp_dispatch::tool_name(&ctx.mcp.borrow(), &tc.function.name);
             let args: Value = serde_json::from_str(&tc.function.arguments).unwrap_or(json!({}));
+            let args = tools::prepare_args(name, args, &ctx.root);
 
             // Count this tool use
             turn_result.stats.tool_uses += 1;
@@ -366,30 +527,66 @@ pub fn run_turn(ctx: &Context, user_input: &str, messages: &mut Vec<Value>) -> R
 
             let _ = ctx.transcript.borrow_mut().tool_call(name, &args);
 
-            // Use PolicyEngine for permission decisions
-            let (allowed, decision, matched_rule) =
-                ctx.policy.borrow().check_permission(name, &args);
+            // Calls over a [limits] budget are refused before any prompt
+            let within_limits = ctx.limits.borrow_mut().check_call(name, &args);
+            if let Err(exceeded) = within_limits {
+                eprintln!("⚠️  {} ({})", exceeded.message, exceeded.limit);
+                let result = exceeded.to_result();
+                let mut transcript = ctx.transcript.borrow_mut();
+                let _ = transcript.limit_exceeded(&exceeded.limit, Some(name), &exceeded.message);
+                let _ = transcript.tool_result(name, false, &result);
+                drop(transcript);
+                eprintln!("{}", tool_display::format_tool_result(name, &result));
+                messages.push(json!({
+                    "role": "tool",
+                    "tool_call_id": tc.id,
+                    "content": serde_json::to_string(&result)?
+                }));
+                continue;
+            }
 
-            // Log policy decision to transcript
-            let decision_str = match decision {
-                Decision::Allow => "allowed",
-                Decision::Deny => "denied",
-                Decision::Ask => {
-                    if allowed {
-                        "prompted_yes"
-                    } else {
-                        "prompted_no"
+            // Use PolicyEngine for permission decisions
+            let check = check_permission(ctx, &mut ctx.policy.borrow_mut(), name, &args);
+            let outcome = check.outcome();
+            let PermissionCheck {
+                allowed,
+                decision,
+                rule: matched_rule,
+                added_rules,
+                feedback,
+                by_hook,
+            } = check;
+
+            // "Always allow" answers are saved to the local config
+            if !added_rules.is_empty() {
+                let mut config = ctx.config.borrow_mut();
+                for rule in &added_rules {
+                    if !config.permissions.allow.contains(rule) {
+                        config.permissions.allow.push(rule.clone());
                     }
                 }
-            };
-            let _ = ctx.transcript.borrow_mut().policy_decision(
-                name,
-                decision_str,
-                matched_rule.as_deref(),
-            );
+                match config.save_local_permissions() {
+                    Ok(()) => eprintln!("Saved allow rule: {}", added_rules.join(", ")),
+                    Err(e) => eprintln!("Warning: failed to save permissions: {}", e),
+                }
+            }
+
+            // Log policy decision to transcript
+            let _ =
+                ctx.transcript
+                    .borrow_mut()
+                    .policy_decision(name, outcome, matched_rule.as_deref());
 
             // Run PreToolUse hooks (can block or modify args)
             let (hook_proceed, updated_args) = ctx.hooks.borrow().pre_tool_use(name, &args);
+            if let Some(updated) = &updated_args {
+                let _ = ctx.transcript.borrow_mut().hook_modification(
+                    "PreToolUse",
+                    Some(name),
+                    "updated_args",
+                    updated,
+                );
+            }
             let args = updated_args.unwrap_or(args);
 
             // Track tool execution time
@@ -453,6 +650,11 @@ pub fn run_turn(ctx: &Context, user_input: &str, messages: &mut Vec<Value>) -> R
                 } else if name == "TodoWrite" {
                     // Execute TodoWrite tool
                     tools::todo::execute(args.clone(), &ctx.todo_state)
+                } else if name == "Lsp" {
+                    // Lsp keeps language servers running across calls
+                    tools::lsp::execute(args.clone(), &mut ctx.lsp.borrow_mut())?
+                } else if name.starts_with("mcp.") {
+                    tools::mcp_dispatch::execute(name, args.clone(), &mut ctx.mcp.borrow_mut())
                 } else if name == "AskUserQuestion" {
                     // Validate questions and signal that we need user input
                     match tools::ask_user::validate(&args) {
@@ -478,25 +680,46 @@ pub fn run_turn(ctx: &Context, user_input: &str, messages: &mut Vec<Value>) -> R
                     // Exit plan mode
                     tools::plan_mode::execute_exit(&ctx.plan_mode)
                 } else {
-                    // Execute built-in tool
-                    tools::execute(name, args.clone(), &ctx.root, &bash_config)?
+                    // Execute built-in tool, then run any configured formatters
+                    let secrets = ctx.policy.borrow().secret_files();
+                    let mut result =
+                        tools::execute(name, args.clone(), &ctx.root, &bash_config, &secrets)?;
+                    tools::format::after_mutation(
+                        name,
+                        &mut result,
+                        &ctx.root,
+                        &format_config,
+                        &bash_config,
+                    );
+                    result
                 }
             } else {
-                let reason = match decision {
-                    Decision::Deny => "Denied by policy",
-                    _ => "User denied permission",
+                let reason = match (decision, feedback) {
+                    (Decision::Deny, _) => "Denied by policy".to_string(),
+                    (_, Some(feedback)) if by_hook => {
+                        format!("Denied by PermissionRequest hook: {}", feedback)
+                    }
+                    _ if by_hook => "Denied by PermissionRequest hook".to_string(),
+                    (_, Some(feedback)) => format!("User denied permission: {}", feedback),
+                    _ => "User denied permission".to_string(),
                 };
                 json!({ "error": { "code": "permission_denied", "message": reason } })
             };
 
+            ctx.limits
+                .borrow_mut()
+                .record_result(name, &args, &result, &ctx.root);
+
             let ok = result.get("error").is_none();
             let tool_duration_ms = tool_start.elapsed().as_millis() as u64;
             let _ = ctx.transcript.borrow_mut().tool_result(name, ok, &result);
 
-            // Run PostToolUse hooks
-            ctx.hooks
+            // Run PostToolUse hooks; they may change what the model sees
+            let post = ctx
+                .hooks
                 .borrow()
                 .post_tool_use(name, &args, &result, tool_duration_ms);
+            let result = apply_post_tool_use(ctx, name, result, post);
 
             trace(
                 ctx,
@@ -0,0 +1,181 @@
+//! Environment scrubbing for child processes (Bash tool and hooks).
+//!
+//! Children never inherit yo's environment verbatim: backend API keys and
+//! anything matching the deny globs are removed, an optional allowlist
+//! restricts what passes through, and configured variables are injected.
+
+use crate::config::BashEnvConfig;
+use glob::{MatchOptions, Pattern};
+use std::process::Command;
+
+/// Deny patterns that are always applied, regardless of config
+const DEFAULT_DENY_PATTERNS: &[&str] = &["*_API_KEY"];
+
+/// Variable names are matched case-insensitively (e.g. `venice_api_key`)
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: false,
+    require_literal_separator: false,
+    require_literal_leading_dot: false,
+};
+
+fn matches_any(name: &str, patterns: &[Pattern]) -> bool {
+    patterns.iter().any(|p| p.matches_with(name, MATCH_OPTIONS))
+}
+
+/// Invalid patterns are skipped; for the allowlist that means they match
+/// nothing (see `filtered_vars`)
+fn compile(patterns: &[String]) -> Vec<Pattern> {
+    patterns
+        .iter()
+        .filter_map(|p| Pattern::new(p).ok())
+        .collect()
+}
+
+/// Filter a set of variables according to the env policy and add injected vars
+pub fn filtered_vars<I>(config: &BashEnvConfig, vars: I) -> Vec<(String, String)>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let allow = compile(&config.allow);
+    let mut deny = compile(&config.deny);
+    deny.extend(
+        DEFAULT_DENY_PATTERNS
+            .iter()
+            .filter_map(|p| Pattern::new(p).ok()),
+    );
+
+    let mut result: Vec<(String, String)> = vars
+        .into_iter()
+        .filter(|(name, _)| {
+            if config.scrub.iter().any(|s| s.eq_ignore_ascii_case(name)) {
+                return false;
+            }
+            // Checked against the configured list, not the compiled one, so
+            // an allowlist of only invalid patterns passes nothing
+            if !config.allow.is_empty() && !matches_any(name, &allow) {
+                return false;
+            }
+            !matches_any(name, &deny)
+        })
+        .filter(|(name, _)| !config.set.contains_key(name))
+        .collect();
+
+    let mut injected: Vec<(String, String)> = config
+        .set
+        .iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    injected.sort();
+    result.extend(injected);
+    result
+}
+
+/// Replace a command's inherited environment with the filtered one
+pub fn apply(config: &BashEnvConfig, cmd: &mut Command) {
+    cmd.env_clear();
+    cmd.envs(filtered_vars(config, std::env::vars()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn names(vars: &[(String, String)]) -> Vec<&str> {
+        vars.iter().map(|(k, _)| k.as_str()).collect()
+    }
+
+    #[test]
+    fn test_default_deny_api_keys() {
+        let config = BashEnvConfig::default();
+        let result = filtered_vars(
+            &config,
+            vars(&[
+                ("PATH", "/bin"),
+                ("OPENAI_API_KEY", "sk-1"),
+                ("venice_api_key", "v-1"),
+            ]),
+        );
+        assert_eq!(names(&result), vec!["PATH"]);
+    }
+
+    #[test]
+    fn test_scrub_backend_key_vars() {
+        let config = BashEnvConfig {
+            scrub: vec!["MY_LLM_TOKEN".to_string()],
+            ..Default::default()
+        };
+        let result = filtered_vars(
+            &config,
+            vars(&[("HOME", "/root"), ("MY_LLM_TOKEN", "secret")]),
+        );
+        assert_eq!(names(&result), vec!["HOME"]);
+    }
+
+    #[test]
+    fn test_allowlist_restricts() {
+        let config = BashEnvConfig {
+            allow: vec!["PATH".to_string(), "CARGO_*".to_string()],
+            ..Default::default()
+        };
+        let result = filtered_vars(
+            &config,
+            vars(&[
+                ("PATH", "/bin"),
+                ("CARGO_HOME", "/cargo"),
+                ("AWS_SECRET_ACCESS_KEY", "x"),
+            ]),
+        );
+        assert_eq!(names(&result), vec!["PATH", "CARGO_HOME"]);
+
+        // Invalid allow patterns match nothing rather than lifting the allowlist
+        let config = BashEnvConfig {
+            allow: vec!["[PATH".to_string()],
+            ..Default::default()
+        };
+        let result = filtered_vars(&config, vars(&[("PATH", "/bin"), ("HOME", "/h")]));
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn test_denylist_globs() {
+        let config = BashEnvConfig {
+            deny: vec!["AWS_*".to_string(), "*TOKEN*".to_string()],
+            ..Default::default()
+        };
+        let result = filtered_vars(
+            &config,
+            vars(&[
+                ("PATH", "/bin"),
+                ("AWS_SECRET_ACCESS_KEY", "x"),
+                ("GITHUB_TOKEN", "y"),
+            ]),
+        );
+        assert_eq!(names(&result), vec!["PATH"]);
+    }
+
+    #[test]
+    fn test_injected_vars_override() {
+        let mut config = BashEnvConfig::default();
+        config
+            .set
+            .insert("YO_SESSION_ID".to_string(), "abc".to_string());
+        let result = filtered_vars(
+            &config,
+            vars(&[("PATH", "/bin"), ("YO_SESSION_ID", "stale")]),
+        );
+        assert_eq!(
+            result,
+            vec![
+                ("PATH".to_string(), "/bin".to_string()),
+                ("YO_SESSION_ID".to_string(), "abc".to_string()),
+            ]
+        );
+    }
+}
@@ -4,14 +4,18 @@ use crate::{
     commands::CommandIndex,
     compact,
     config::Config,
+    config::HookEvent,
     config::PermissionMode,
     config::Target,
     cost::{format_cost, SessionCosts},
-    hooks::HookManager,
+    hooks::{HookManager, PlanStepDecision, SessionMetrics},
+    limits::LimitTracker,
+    lsp::LspManager,
+    mcp::{content as mcp_content, McpManager},
     model_routing::ModelRouter,
     plan::{self, PlanModeState},
     policy::PolicyEngine,
-    session,
+    policy_check, session,
     skillpacks::{ActiveSkills, SkillIndex},
     tools::{ask_user, todo::TodoState},
     transcript::Transcript,
@@ -54,6 +58,15 @@ pub struct Context {
     pub command_index: RefCell<CommandIndex>,
     // Todo list for task tracking
     pub todo_state: RefCell<TodoState>,
+    // Language servers for the Lsp tool
+    pub lsp: RefCell<LspManager>,
+    pub mcp: RefCell<McpManager>,
+    // Rendered repository map for the system prompt (None when disabled)
+    pub repo_map: RefCell<Option<String>>,
+    // Usage against the [limits] budgets
+    pub limits: RefCell<LimitTracker>,
+    // Context from SessionStart hooks, added to the system prompt
+    pub session_context: RefCell<Vec<String>>,
 }
 
 /// Print command stats to stderr
@@ -84,12 +97,10 @@ fn print_stats(duration: Duration, stats: &CommandStats, cost: Option<f64>) {
 
 pub fn run_once(ctx: &Context, prompt: &str) -> Result<()> {
     // Run UserPromptSubmit hooks
-    let (proceed, updated_prompt) = ctx.hooks.borrow().user_prompt_submit(prompt);
-    if !proceed {
+    let Some(prompt) = submit_prompt(ctx, prompt) else {
         eprintln!("Prompt blocked by hook");
         return Ok(());
-    }
-    let prompt = updated_prompt.as_deref().unwrap_or(prompt);
+    };
 
     // Increment turn counter
     let turn_number = {
@@ -101,7 +112,7 @@ pub fn run_once(ctx: &Context, prompt: &str) -> Result<()> {
     let start = Instant::now();
     let mut messages = Vec::new();
     let mut rl = DefaultEditor::new()?;
-    let result = agent::run_turn(ctx, prompt, &mut messages)?;
+    let result = agent::run_turn(ctx, &prompt, &mut messages)?;
 
     let mut total_stats = result.stats.clone();
     let mut current_result = result;
@@ -175,6 +186,7 @@ pub fn run_repl(ctx: Context, initial_messages: Option<Vec<serde_json::Value>>)
 
     println!("yo - type /help for commands, /exit to quit");
 
+    let mut end_reason = "prompt_input_exit";
     loop {
         match rl.readline(">>> ") {
             Ok(line) => {
@@ -192,12 +204,10 @@ pub fn run_repl(ctx: Context, initial_messages: Option<Vec<serde_json::Value>>)
                 }
 
                 // Run UserPromptSubmit hooks
-                let (proceed, updated_prompt) = ctx.hooks.borrow().user_prompt_submit(line);
-                if !proceed {
+                let Some(line) = submit_prompt(&ctx, line) else {
                     eprintln!("Prompt blocked by hook");
                     continue;
-                }
-                let line = updated_prompt.unwrap_or_else(|| line.to_string());
+                };
 
                 // Increment turn counter
                 let turn_number = {
@@ -291,10 +301,16 @@ pub fn run_repl(ctx: Context, initial_messages: Option<Vec<serde_json::Value>>)
                         eprintln!("Error: {}", e);
                     }
                 }
+
+                // Back at the prompt, waiting for the user
+                ctx.hooks
+                    .borrow()
+                    .notify("idle_prompt", "yo is waiting for your input");
             }
             Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
             Err(e) => {
                 eprintln!("Input error: {}", e);
+                end_reason = "other";
                 break;
             }
         }
@@ -312,9 +328,87 @@ pub fn run_repl(ctx: Context, initial_messages: Option<Vec<serde_json::Value>>)
         let _ = session::save_session(&ctx.session_id, &messages, turn_count);
     }
 
+    end_session(&ctx, end_reason);
     Ok(())
 }
 
+/// Run UserPromptSubmit hooks on a prompt. Returns the prompt to send, with
+/// any context the hooks added and the MCP resources it mentions, or None if
+/// a hook blocked it.
+fn submit_prompt(ctx: &Context, prompt: &str) -> Option<String> {
+    let submitted = ctx.hooks.borrow().user_prompt_submit(prompt);
+    if !submitted.proceed {
+        return None;
+    }
+
+    let mut transcript = ctx.transcript.borrow_mut();
+    let mut prompt = match submitted.updated_prompt {
+        Some(updated) => {
+            let _ = transcript.hook_modification(
+                "UserPromptSubmit",
+                None,
+                "updated_prompt",
+                &serde_json::json!(updated),
+            );
+            updated
+        }
+        None => prompt.to_string(),
+    };
+    attach_mcp_resources(ctx, &mut prompt);
+    for context in &submitted.additional_context {
+        let _ = transcript.hook_modification(
+            "UserPromptSubmit",
+            None,
+            "additional_context",
+            &serde_json::json!(context),
+        );
+    }
+    if !submitted.additional_context.is_empty() {
+        prompt.push_str(&format!(
+            "\n\n<hook-context>\n{}\n</hook-context>",
+            submitted.additional_context.join("\n\n")
+        ));
+    }
+    Some(prompt)
+}
+
+/// Inline the resources named by `@server:uri` mentions of connected MCP
+/// servers; the mentions stay in the text
+fn attach_mcp_resources(ctx: &Context, prompt: &mut String) {
+    let mut mcp = ctx.mcp.borrow_mut();
+    let mentions = mcp_content::mentions(prompt, |server| mcp.is_connected(server));
+    for (server, uri) in mentions {
+        match mcp.read_resource(&server, &uri) {
+            Ok(result) => prompt.push_str(&format!(
+                "\n\n<mcp-resource server=\"{}\" uri=\"{}\">\n{}\n</mcp-resource>",
+                server,
+                uri,
+                mcp_content::resource_text(&result)
+            )),
+            Err(e) => eprintln!("Warning: could not read @{}:{}: {:#}", server, uri, e),
+        }
+    }
+}
+
+/// Run SessionEnd hooks with the session's totals
+pub fn end_session(ctx: &Context, reason: &str) {
+    let mode = if ctx.args.prompt.is_some() {
+        "one-shot"
+    } else {
+        "repl"
+    };
+    let metrics = {
+        let costs = ctx.session_costs.borrow();
+        SessionMetrics {
+            turns: *ctx.turn_counter.borrow(),
+            input_tokens: costs.input_tokens(),
+            output_tokens: costs.output_tokens(),
+            cost_usd: costs.total_cost(),
+        }
+    };
+    ctx.hooks.borrow().on_session_end(reason, mode, metrics);
+}
+
 fn handle_command(ctx: &Context, cmd: &str, messages: &mut Vec<serde_json::Value>) -> bool {
     let parts: Vec<&str> = cmd.splitn(2, ' ').collect();
     match parts[0] {
@@ -333,10 +427,24 @@ fn handle_command(ctx: &Context, cmd: &str, messages: &mut Vec<serde_json::Value
             println!("  /permissions    - show permission rules");
             println!("  /permissions add allow|ask|deny \"pattern\"");
             println!("  /permissions rm allow|ask|deny <index>");
+            println!("  /permissions test <Tool> [arg] - explain how a call would be decided");
+            println!("  /permissions lint - find malformed, duplicate or unreachable rules");
+            println!("Hooks:");
+            println!("  /hooks                 - list hooks with last exit code and latency");
+            println!("  /hooks test <n> [json] - run hook n on a sample input (fields in json override it)");
             println!("Context:");
             println!("  /context        - show context usage stats");
             println!("  /compact        - compact conversation history");
             println!("  /cost           - show session cost breakdown");
+            println!("  /map [refresh]  - show or rebuild the repository map");
+            println!("MCP:");
+            println!("  /mcp list              - list configured MCP servers");
+            println!("  /mcp connect <name>    - start and connect to a server");
+            println!("  /mcp disconnect <name> - disconnect from a server");
+            println!("  /mcp tools <name>      - list a connected server's tools");
+            println!("  /mcp resources <name>  - list a connected server's resources");
+            println!("  /mcp prompts <name>    - list a connected server's prompts");
+            println!("  @<server>:<uri>        - attach an MCP resource to a prompt");
             println!("Subagents:");
             println!("  /agents                - list available subagents");
             println!("  /task <agent> <prompt> - run a subagent with the given prompt");
@@ -426,6 +534,9 @@ fn handle_command(ctx: &Context, cmd: &str, messages: &mut Vec<serde_json::Value
         "/permissions" => {
             handle_permissions_command(ctx, if parts.len() > 1 { parts[1] } else { "" });
         }
+        "/hooks" => {
+            handle_hooks_command(ctx, if parts.len() > 1 { parts[1] } else { "" });
+        }
         "/context" => {
             let total_chars: usize = messages
                 .iter()
@@ -447,12 +558,18 @@ fn handle_command(ctx: &Context, cmd: &str, messages: &mut Vec<serde_json::Value
         "/cost" => {
             handle_cost_command(ctx);
         }
+        "/map" => {
+            handle_map_command(ctx, if parts.len() > 1 { parts[1] } else { "" });
+        }
         "/commands" => {
             handle_commands_list(ctx);
         }
         "/agents" => {
             handle_agents_command(ctx);
         }
+        "/mcp" => {
+            handle_mcp_command(ctx, if parts.len() > 1 { parts[1] } else { "" });
+        }
         "/task" => {
             handle_task_command(ctx, if parts.len() > 1 { parts[1] } else { "" });
         }
@@ -469,7 +586,9 @@ fn handle_command(ctx: &Context, cmd: &str, messages: &mut Vec<serde_json::Value
             // Check for user-defined slash commands
             let cmd_name = &parts[0][1..]; // Remove leading /
             let args = if parts.len() > 1 { parts[1] } else { "" };
-            if !try_run_slash_command(ctx, cmd_name, args, messages) {
+            if !try_run_slash_command(ctx, cmd_name, args, messages)
+                && !try_run_mcp_prompt(ctx, cmd_name, args, messages)
+            {
                 println!("Unknown command: {}", parts[0]);
             }
         }
@@ -477,6 +596,38 @@ fn handle_command(ctx: &Context, cmd: &str, messages: &mut Vec<serde_json::Value
     false
 }
 
+fn handle_map_command(ctx: &Context, args: &str) {
+    let config = ctx.config.borrow().repo_map.clone();
+    if !config.enabled() {
+        println!("Repository map is disabled ([repo_map] enabled = false)");
+        return;
+    }
+    match args.trim() {
+        "" => {}
+        "refresh" => {
+            let map = crate::repo_map::prompt_section(&ctx.root, &config, true);
+            *ctx.repo_map.borrow_mut() = map;
+            println!("Repository map rebuilt");
+        }
+        other => {
+            println!("Unknown /map argument: {}", other);
+            println!("Usage: /map [refresh]");
+            return;
+        }
+    }
+    match ctx.repo_map.borrow().as_deref() {
+        Some(map) => {
+            println!("{}", map);
+            println!(
+                "\n(~{} tokens of {} budget)",
+                map.len() / 4,
+                config.max_tokens()
+            );
+        }
+        None => println!("Repository map is empty"),
+    }
+}
+
 fn handle_permissions_command(ctx: &Context, args: &str) {
     let parts: Vec<&str> = args.split_whitespace().collect();
 
@@ -501,6 +652,29 @@ fn handle_permissions_command(ctx: &Context, args: &str) {
     }
 
     match parts[0] {
+        "test" if parts.len() >= 2 => {
+            let rest = args.trim_start()["test".len()..].trim();
+            let (tool, arg) = match rest.split_once(char::is_whitespace) {
+                Some((tool, arg)) => (tool, Some(arg.trim().trim_matches(['"', '\'']))),
+                None => (rest, None),
+            };
+            let call = policy_check::call_args(tool, arg);
+            let layers = policy_check::layers(&ctx.args);
+            let hooks = ctx.hooks.borrow();
+            let pre_hooks = hooks.tool_hooks(HookEvent::PreToolUse, tool, Some(&call));
+            let policy = ctx.policy.borrow();
+            print!(
+                "{}",
+                policy_check::explain(&policy, tool, &call, &layers, &pre_hooks)
+            );
+        }
+        "lint" => {
+            let policy = ctx.policy.borrow();
+            let mut config = ctx.config.borrow().clone();
+            config.permissions = policy.config().clone();
+            let layers = policy_check::layers(&ctx.args);
+            print!("{}", policy_check::lint(&config, &policy, &layers).0);
+        }
         "add" if parts.len() >= 3 => {
             let decision_type = parts[1];
             // Join remaining parts and strip quotes
@@ -566,7 +740,98 @@ fn handle_permissions_command(ctx: &Context, args: &str) {
             println!("  /permissions                    - show current rules");
             println!("  /permissions add allow|ask|deny \"pattern\"");
             println!("  /permissions rm allow|ask|deny <index>");
+            println!("  /permissions test <Tool> [arg]  - explain how a call would be decided");
+            println!("  /permissions lint               - find malformed or unreachable rules");
+        }
+    }
+}
+
+fn handle_hooks_command(ctx: &Context, args: &str) {
+    let hooks = ctx.hooks.borrow();
+    let args = args.trim();
+
+    if args.is_empty() {
+        let configured = hooks.hooks();
+        if configured.is_empty() {
+            println!("No hooks configured.");
+            println!("Add [[hooks]] entries to .yo/config.toml");
+            return;
+        }
+        println!("Hooks (matching hooks for an event run concurrently):");
+        for (i, (hook, stats)) in configured.iter().enumerate() {
+            let last = match (stats.runs, stats.last_exit_code) {
+                (0, _) => "never run".to_string(),
+                (runs, Some(code)) => {
+                    format!(
+                        "last exit {} in {}ms, {} runs",
+                        code, stats.last_latency_ms, runs
+                    )
+                }
+                (runs, None) => format!(
+                    "last run failed or timed out after {}ms, {} runs",
+                    stats.last_latency_ms, runs
+                ),
+            };
+            println!(
+                "  {}. {:?} [{}] {} (timeout {}ms) - {}",
+                i + 1,
+                hook.event,
+                hook.matcher.as_deref().unwrap_or("*"),
+                hook.command.join(" "),
+                hook.timeout_ms,
+                last
+            );
+        }
+        return;
+    }
+
+    let rest = args.strip_prefix("test").map(str::trim);
+    let Some(rest) = rest.filter(|r| !r.is_empty()) else {
+        println!("Usage:");
+        println!("  /hooks                 - list hooks with last exit code and latency");
+        println!(
+            "  /hooks test <n> [json] - run hook n on a sample input (fields in json override it)"
+        );
+        return;
+    };
+    let (number, json) = match rest.split_once(char::is_whitespace) {
+        Some((number, json)) => (number, Some(json.trim())),
+        None => (rest, None),
+    };
+    let Some(index) = number.parse::<usize>().ok().filter(|&n| n > 0) else {
+        println!("Invalid hook number: {}. Use /hooks to list.", number);
+        return;
+    };
+    let overrides = match json.map(serde_json::from_str::<serde_json::Value>) {
+        Some(Ok(value)) => Some(value),
+        Some(Err(e)) => {
+            println!("Invalid JSON: {}", e);
+            return;
         }
+        None => None,
+    };
+
+    match hooks.dry_run(index - 1, overrides) {
+        Ok((input, result, latency)) => {
+            println!(
+                "Input:\n{}",
+                serde_json::to_string_pretty(&input).unwrap_or_default()
+            );
+            let outcome = match result.exit_code {
+                Some(0) => "allow".to_string(),
+                Some(2) => "block".to_string(),
+                Some(code) => format!("warning (exit {})", code),
+                None => "failed".to_string(),
+            };
+            println!("Result: {} in {}ms", outcome, latency.as_millis());
+            if !result.stdout.trim().is_empty() {
+                println!("stdout:\n{}", result.stdout.trim_end());
+            }
+            if !result.stderr.trim().is_empty() {
+                println!("stderr:\n{}", result.stderr.trim_end());
+            }
+        }
+        Err(e) => println!("{}", e),
     }
 }
 
@@ -630,6 +895,136 @@ fn handle_cost_command(ctx: &Context) {
     }
 }
 
+fn handle_mcp_command(ctx: &Context, args: &str) {
+    let parts: Vec<&str> = args.split_whitespace().collect();
+    let mut mcp = ctx.mcp.borrow_mut();
+
+    match parts.as_slice() {
+        [] | ["list"] => {
+            let servers = mcp.list();
+            if servers.is_empty() {
+                println!("No MCP servers configured.");
+                println!("Add [mcp.servers.<name>] entries to .yo/config.toml");
+                return;
+            }
+            println!("MCP servers:");
+            for server in servers {
+                let status = match (server.tools, server.enabled) {
+                    (Some(tools), _) => format!("connected, {} tools", tools),
+                    (None, true) => "not connected".to_string(),
+                    (None, false) => "disabled".to_string(),
+                };
+                let auto = if server.auto_start {
+                    ", auto-start"
+                } else {
+                    ""
+                };
+                println!(
+                    "  {} [{}{}] - {}",
+                    server.name,
+                    server.transport.as_str(),
+                    auto,
+                    status
+                );
+            }
+        }
+        ["connect", name] => {
+            if mcp.is_connected(name) {
+                println!("Already connected to {}", name);
+                return;
+            }
+            match mcp.connect(name) {
+                Ok(tools) => {
+                    println!("Connected to {} ({} tools)", name, tools);
+                    let _ = ctx.transcript.borrow_mut().mcp_server(
+                        name,
+                        "connected",
+                        Some(tools),
+                        None,
+                    );
+                }
+                Err(e) => {
+                    println!("Failed to connect: {:#}", e);
+                    let _ = ctx.transcript.borrow_mut().mcp_server(
+                        name,
+                        "failed",
+                        None,
+                        Some(&format!("{:#}", e)),
+                    );
+                }
+            }
+        }
+        ["disconnect", name] => match mcp.disconnect(name) {
+            Ok(()) => {
+                println!("Disconnected from {}", name);
+                let _ = ctx
+                    .transcript
+                    .borrow_mut()
+                    .mcp_server(name, "disconnected", None, None);
+            }
+            Err(e) => println!("{:#}", e),
+        },
+        ["tools", name] => match mcp.server_tools(name) {
+            Ok([]) => println!("{} offers no tools", name),
+            Ok(tools) => {
+                println!("Tools from {}:", name);
+                for tool in tools {
+                    let description = tool.description.as_deref().unwrap_or("");
+                    let summary = description.lines().next().unwrap_or("");
+                    println!("  mcp.{}.{} - {}", name, tool.name, summary);
+                }
+            }
+            Err(e) => println!("{:#}", e),
+        },
+        ["resources", name] => match mcp.resources(name) {
+            Ok(resources) if resources.is_empty() => println!("{} lists no resources", name),
+            Ok(resources) => {
+                println!("Resources from {} (attach with @{}:<uri>):", name, name);
+                for resource in resources {
+                    let mime = resource.mime_type.as_deref().unwrap_or("?");
+                    println!("  {} - {} [{}]", resource.uri, resource.name, mime);
+                }
+            }
+            Err(e) => println!("{:#}", e),
+        },
+        ["prompts", name] => {
+            if !mcp.is_connected(name) {
+                println!("MCP server '{}' is not connected", name);
+                return;
+            }
+            let prompts: Vec<_> = mcp
+                .prompts()
+                .into_iter()
+                .filter(|(s, _)| s == name)
+                .collect();
+            if prompts.is_empty() {
+                println!("{} offers no prompts", name);
+                return;
+            }
+            println!("Prompts from {}:", name);
+            for (server, prompt) in prompts {
+                let desc = prompt.description.as_deref().unwrap_or("");
+                let desc = desc.lines().next().unwrap_or("");
+                println!(
+                    "  /{} {} - {}",
+                    prompt.command_name(server),
+                    prompt.usage(),
+                    desc
+                );
+            }
+        }
+        _ => {
+            println!("Usage:");
+            println!("  /mcp list              - list configured MCP servers");
+            println!("  /mcp connect <name>    - start and connect to a server");
+            println!("  /mcp disconnect <name> - disconnect from a server");
+            println!("  /mcp tools <name>      - list a connected server's tools");
+            println!("  /mcp resources <name>  - list a connected server's resources");
+            println!("  /mcp prompts <name>    - list a connected server's prompts");
+        }
+    }
+}
+
 fn handle_agents_command(ctx: &Context) {
     let config = ctx.config.borrow();
     if config.agents.is_empty() {
@@ -994,6 +1389,53 @@ fn handle_plan_execute(ctx: &Context, messages: &mut Vec<serde_json::Value>) {
             return;
         };
 
+        // PrePlanStep hooks may skip the step or stop the plan
+        let (plan_name, total_steps) = ctx
+            .plan_mode
+            .borrow()
+            .current_plan
+            .as_ref()
+            .map(|p| (p.name.clone(), p.steps.len()))
+            .unwrap_or_default();
+        let decision = ctx
+            .hooks
+            .borrow()
+            .pre_plan_step(&plan_name, &step, total_steps);
+        match decision {
+            PlanStepDecision::Run => {}
+            PlanStepDecision::Skip(reason) => {
+                println!(
+                    "Skipping step {}: {} (PrePlanStep hook{})",
+                    step.number,
+                    step.title,
+                    reason.map(|r| format!(": {}", r)).unwrap_or_default()
+                );
+                let mut state = ctx.plan_mode.borrow_mut();
+                if let Some(s) = state
+                    .current_plan
+                    .as_mut()
+                    .and_then(|p| p.step_mut(step.number))
+                {
+                    s.status = plan::PlanStepStatus::Skipped;
+                }
+                drop(state);
+                let _ =
+                    ctx.transcript
+                        .borrow_mut()
+                        .plan_step_end(&plan_name, step.number, "skipped");
+                continue;
+            }
+            PlanStepDecision::Block(reason) => {
+                println!(
+                    "Stopping before step {}: blocked by PrePlanStep hook{}",
+                    step.number,
+                    reason.map(|r| format!(": {}", r)).unwrap_or_default()
+                );
+                ctx.plan_mode.borrow_mut().enter_review();
+                return;
+            }
+        }
+
         println!(
             "=== Step {}: {} ===\n{}",
             step.number, step.title, step.description
@@ -1014,13 +1456,6 @@ fn handle_plan_execute(ctx: &Context, messages: &mut Vec<serde_json::Value>) {
         }
 
         // Log step start
-        let plan_name = ctx
-            .plan_mode
-            .borrow()
-            .current_plan
-            .as_ref()
-            .map(|p| p.name.clone())
-            .unwrap_or_default();
         let _ = ctx
             .transcript
             .borrow_mut()
@@ -1259,6 +1694,7 @@ fn handle_compact_command(ctx: &Context, messages: &mut Vec<serde_json::Value>)
         }
     };
 
+    ctx.hooks.borrow().pre_compact("manual", messages);
     println!("Compacting conversation...");
 
     // Get context config before borrowing backends
@@ -1321,6 +1757,22 @@ fn handle_commands_list(ctx: &Context) {
     for (path, error) in index.errors() {
         eprintln!("  [error] {}: {}", path.display(), error);
     }
+
+    let mcp = ctx.mcp.borrow();
+    let prompts = mcp.prompts();
+    if !prompts.is_empty() {
+        println!("MCP Prompts ({}):", prompts.len());
+        for (server, prompt) in prompts {
+            let usage = prompt.usage();
+            let desc = prompt.description.as_deref().unwrap_or("(no description)");
+            let desc = desc.lines().next().unwrap_or("");
+            if usage.is_empty() {
+                println!("  /{} - {}", prompt.command_name(server), desc);
+            } else {
+                println!("  /{} {} - {}", prompt.command_name(server), usage, desc);
+            }
+        }
+    }
 }
 
 /// Try to run a user-defined slash command
@@ -1342,19 +1794,60 @@ fn try_run_slash_command(
 
     // Expand the command with arguments
     let prompt = command.expand(args);
+    run_command_prompt(ctx, cmd_name, &prompt, messages);
+    true
+}
 
+/// Run a prompt from an MCP server, invoked as `/mcp__<server>__<prompt> args`
+/// Returns true if a prompt was found
+fn try_run_mcp_prompt(
+    ctx: &Context,
+    cmd_name: &str,
+    args: &str,
+    messages: &mut Vec<serde_json::Value>,
+) -> bool {
+    let Some((server, prompt)) = ctx.mcp.borrow().find_prompt(cmd_name) else {
+        return false;
+    };
+    let arguments = match prompt.parse_arguments(args) {
+        Ok(arguments) => arguments,
+        Err(e) => {
+            println!("{:#}", e);
+            println!("Usage: /{} {}", cmd_name, prompt.usage());
+            return true;
+        }
+    };
+    let result = ctx
+        .mcp
+        .borrow_mut()
+        .get_prompt(&server, &prompt.name, arguments);
+    match result {
+        Ok(result) => {
+            let text = mcp_content::prompt_text(&result);
+            run_command_prompt(ctx, cmd_name, &text, messages);
+        }
+        Err(e) => println!("Failed to get prompt: {:#}", e),
+    }
+    true
+}
+
+/// Run an expanded slash command as a user prompt
+fn run_command_prompt(
+    ctx: &Context,
+    cmd_name: &str,
+    prompt: &str,
+    messages: &mut Vec<serde_json::Value>,
+) {
     println!("Running command: /{}", cmd_name);
     if ctx.args.verbose {
         println!("Expanded prompt: {}", prompt);
     }
 
     // Run UserPromptSubmit hooks
-    let (proceed, updated_prompt) = ctx.hooks.borrow().user_prompt_submit(&prompt);
-    if !proceed {
+    let Some(prompt) = submit_prompt(ctx, prompt) else {
         eprintln!("Command blocked by hook");
-        return true;
-    }
-    let prompt = updated_prompt.unwrap_or(prompt);
+        return;
+    };
 
     // Increment turn counter
     let turn_number = {
@@ -1394,6 +1887,4 @@ fn try_run_slash_command(
             eprintln!("Command error: {}", e);
         }
     }
-
-    true
 }
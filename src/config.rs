@@ -1,7 +1,7 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 
 /// A validation error in the configuration
 #[derive(Debug, Clone)]
@@ -35,6 +35,11 @@ pub enum HookEvent {
     Stop,
     SubagentStop,
     SessionStart,
+    SessionEnd,
+    PreCompact,
+    PermissionRequest,
+    Notification,
+    PrePlanStep,
 }
 
 /// Configuration for a single hook
@@ -43,7 +48,7 @@ pub struct HookConfig {
     pub event: HookEvent,
     pub command: Vec<String>,
     #[serde(default)]
-    pub matcher: Option<String>, // Regex pattern for tool name (PreToolUse/PostToolUse only)
+    pub matcher: Option<String>, // Permission rule like `Bash(git commit:*)` or regex for the tool name, or the Notification type, PreCompact trigger or SessionEnd reason
     #[serde(default = "default_hook_timeout")]
     pub timeout_ms: u64,
 }
@@ -84,6 +89,10 @@ pub struct PermissionsConfig {
     pub ask: Vec<String>,
     #[serde(default)]
     pub deny: Vec<String>,
+    /// Globs of files no tool may touch, checked before any rule; `!glob`
+    /// re-allows earlier matches. Unset means the built-in list.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_files: Option<Vec<String>>,
 }
 
 /// Configuration for the Bash tool
@@ -93,6 +102,40 @@ pub struct BashConfig {
     pub timeout_ms: Option<u64>,
     #[serde(default)]
     pub max_output_bytes: Option<usize>,
+    /// Bytes kept from the start of long output (default: a quarter of max_output_bytes);
+    /// the rest of the budget keeps the end
+    #[serde(default)]
+    pub head_bytes: Option<usize>,
+    /// Stream a rolling tail of output to the terminal while commands run
+    /// (default: on in the REPL, off in -p mode)
+    #[serde(default)]
+    pub live_output: Option<bool>,
+    /// Number of lines in the live view (default: 5)
+    #[serde(default)]
+    pub live_lines: Option<usize>,
+    /// Run commands through this shell (`shell -c`) instead of splitting them
+    /// into argv; pipes, redirections and `&&` then work as in a terminal
+    #[serde(default)]
+    pub shell: Option<String>,
+    #[serde(default)]
+    pub env: BashEnvConfig,
+}
+
+/// Environment policy for Bash and hook child processes
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct BashEnvConfig {
+    /// Glob patterns of variables to pass through (empty = pass everything not denied)
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Glob patterns of variables to remove (applied after allow)
+    #[serde(default)]
+    pub deny: Vec<String>,
+    /// Variables to inject into every child process
+    #[serde(default)]
+    pub set: HashMap<String, String>,
+    /// Exact variable names always removed (filled at startup from backend api_key_env)
+    #[serde(skip)]
+    pub scrub: Vec<String>,
 }
 
 /// Specification for a subagent
@@ -170,6 +213,185 @@ pub fn load_agents_from_dir(dir: &Path) -> HashMap<String, AgentSpec> {
     agents
 }
 
+/// Language servers used by the Lsp tool
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct LspConfig {
+    /// Servers by name; rust-analyzer, pyright and typescript-language-server
+    /// are used for their extensions when not configured here
+    #[serde(default)]
+    pub servers: HashMap<String, LspServerConfig>,
+    /// Per-request timeout in milliseconds (default: 10000)
+    #[serde(default)]
+    pub request_timeout_ms: Option<u64>,
+}
+
+/// A language server launched over stdio
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LspServerConfig {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// File extensions (without the dot) handled by this server
+    pub extensions: Vec<String>,
+    /// LSP languageId for opened documents (default: the file extension)
+    #[serde(default)]
+    pub language_id: Option<String>,
+    #[serde(default)]
+    pub initialization_options: Option<serde_json::Value>,
+}
+
+/// Repository map appended to the system prompt
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct RepoMapConfig {
+    /// Build and inject the map (default: true)
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    /// Approximate token budget for the rendered map (default: 1024)
+    #[serde(default)]
+    pub max_tokens: Option<usize>,
+}
+
+impl RepoMapConfig {
+    pub fn enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+
+    pub fn max_tokens(&self) -> usize {
+        self.max_tokens.unwrap_or(1024)
+    }
+}
+
+/// Formatters run on files changed by Write, Edit and Patch
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct FormatConfig {
+    /// Formatter command by glob, matched against the path relative to the
+    /// project root; `{file}` is replaced by that path, or it is appended
+    #[serde(default)]
+    pub commands: BTreeMap<String, String>,
+    /// Per-command timeout in milliseconds (default: 30000)
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+/// Budgets that stop a runaway agent, per turn or per session; unset means
+/// unlimited. Subagent calls count toward the turn that started them.
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct LimitsConfig {
+    /// Tool calls per turn, across all tools
+    #[serde(default)]
+    pub max_tool_calls_per_turn: Option<u64>,
+    /// Tool calls per session, across all tools
+    #[serde(default)]
+    pub max_tool_calls_per_session: Option<u64>,
+    /// Tool calls per turn by tool name or pattern ("Bash", "mcp.github.*")
+    /// or category ("read_only", "mutation", "execution")
+    #[serde(default)]
+    pub tool_calls_per_turn: BTreeMap<String, u64>,
+    /// Tool calls per session, keyed like `tool_calls_per_turn`
+    #[serde(default)]
+    pub tool_calls_per_session: BTreeMap<String, u64>,
+    /// Distinct files the file tools may change per session
+    #[serde(default)]
+    pub max_files_modified: Option<u64>,
+    /// Bytes the file tools may write per session
+    #[serde(default)]
+    pub max_bytes_written: Option<u64>,
+    /// Wall-clock seconds per turn
+    #[serde(default)]
+    pub max_turn_seconds: Option<u64>,
+}
+
+/// Session transcript integrity
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct TranscriptConfig {
+    /// Key file for HMAC-signing each event (created with a random key if
+    /// missing), relative to the project root unless absolute or `~/`;
+    /// without it events are only hash-chained
+    #[serde(default)]
+    pub hmac_key_file: Option<String>,
+}
+
+impl TranscriptConfig {
+    /// Key file path with a leading `~/` expanded and a relative path
+    /// resolved against the project `root`
+    pub fn hmac_key_path(&self, root: &Path) -> Option<PathBuf> {
+        let file = self.hmac_key_file.as_deref()?;
+        match file.strip_prefix("~/") {
+            Some(rest) => dirs::home_dir().map(|home| home.join(rest)),
+            None => Some(root.join(file)),
+        }
+    }
+}
+
+/// External tool servers speaking the Model Context Protocol
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct McpConfig {
+    /// Servers by name; their tools are exposed as `mcp.<name>.<tool>`
+    #[serde(default)]
+    pub servers: HashMap<String, McpServerConfig>,
+}
+
+/// How yo talks to an MCP server
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum McpTransportKind {
+    /// Spawn `command` and exchange newline-delimited JSON over stdio
+    #[default]
+    Stdio,
+    /// Streamable HTTP at `url`
+    Http,
+    /// Legacy HTTP+SSE at `url`
+    Sse,
+}
+
+impl McpTransportKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            McpTransportKind::Stdio => "stdio",
+            McpTransportKind::Http => "http",
+            McpTransportKind::Sse => "sse",
+        }
+    }
+}
+
+/// One MCP server
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct McpServerConfig {
+    /// Executable to spawn (stdio transport)
+    #[serde(default)]
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Variables set for the server process, on top of the `[bash.env]` policy
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Working directory, relative to the project root (default: the root)
+    #[serde(default)]
+    pub cwd: Option<String>,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Connect when yo starts
+    #[serde(default, alias = "autoStart")]
+    pub auto_start: bool,
+    /// Per-request timeout in milliseconds (default: 30000)
+    #[serde(default, alias = "timeoutMs")]
+    pub timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub transport: McpTransportKind,
+    /// Endpoint for the http and sse transports
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Extra HTTP headers; `${VAR}` in a value is read from the environment
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Environment variable holding a token sent as `Authorization: Bearer`
+    #[serde(default)]
+    pub bearer_token_env: Option<String>,
+}
+
+/// Categories `[limits]` tables accept as keys besides tool names
+pub const LIMIT_CATEGORIES: &[&str] = &["read_only", "mutation", "execution"];
+
 /// Configuration for context management
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ContextConfig {
@@ -291,6 +513,18 @@ pub struct Config {
     pub cost_tracking: CostConfig,
     #[serde(default)]
     pub model_pricing: HashMap<String, ModelPricing>,
+    #[serde(default)]
+    pub lsp: LspConfig,
+    #[serde(default)]
+    pub repo_map: RepoMapConfig,
+    #[serde(default)]
+    pub format: FormatConfig,
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    #[serde(default)]
+    pub transcript: TranscriptConfig,
+    #[serde(default)]
+    pub mcp: McpConfig,
     #[serde(skip)]
     pub agents: HashMap<String, AgentSpec>,
 }
@@ -347,6 +581,12 @@ impl Config {
             hooks: Vec::new(),
             cost_tracking: CostConfig::default(),
             model_pricing: HashMap::new(),
+            lsp: LspConfig::default(),
+            repo_map: RepoMapConfig::default(),
+            format: FormatConfig::default(),
+            limits: LimitsConfig::default(),
+            transcript: TranscriptConfig::default(),
+            mcp: McpConfig::default(),
             agents: HashMap::new(),
         }
     }
@@ -396,6 +636,30 @@ impl Config {
         Ok(config)
     }
 
+    /// Permission rules from each file `load` merges, labelled with its layer
+    /// ("user", "project" or "local") and path; just `path` when one is given
+    pub fn permission_layers(path: Option<&Path>) -> Vec<(String, PermissionsConfig)> {
+        let files = match path {
+            Some(path) => vec![("config", path.to_path_buf())],
+            None => {
+                let mut files = Vec::new();
+                if let Some(home) = dirs::home_dir() {
+                    files.push(("user", home.join(".yo").join("config.toml")));
+                }
+                files.push(("project", Path::new(".yo").join("config.toml")));
+                files.push(("local", Path::new(".yo").join("config.local.toml")));
+                files
+            }
+        };
+        files
+            .into_iter()
+            .filter_map(|(layer, file)| {
+                let config = Self::load_from(&file).ok()?;
+                Some((format!("{} {}", layer, file.display()), config.permissions))
+            })
+            .collect()
+    }
+
     /// Load configuration from a specific path
     pub fn load_from(path: &Path) -> Result<Self> {
         let content = std::fs::read_to_string(path)?;
@@ -404,7 +668,8 @@ impl Config {
     }
 
     /// Merge another config into this one (other takes priority)
-    /// For permissions: arrays are concatenated, mode is overridden if non-default
+    /// For permissions: arrays are concatenated, mode is overridden if non-default,
+    /// secret_files is replaced if set
     /// For bash/context: scalars are overridden if set
     pub fn merge(&mut self, other: Config) {
         // Merge backends
@@ -421,6 +686,9 @@ impl Config {
         self.permissions.allow.extend(other.permissions.allow);
         self.permissions.ask.extend(other.permissions.ask);
         self.permissions.deny.extend(other.permissions.deny);
+        if other.permissions.secret_files.is_some() {
+            self.permissions.secret_files = other.permissions.secret_files;
+        }
         if other.permissions.mode != PermissionMode::Default {
             self.permissions.mode = other.permissions.mode;
         }
@@ -432,6 +700,21 @@ impl Config {
         if other.bash.max_output_bytes.is_some() {
             self.bash.max_output_bytes = other.bash.max_output_bytes;
         }
+        if other.bash.head_bytes.is_some() {
+            self.bash.head_bytes = other.bash.head_bytes;
+        }
+        if other.bash.live_output.is_some() {
+            self.bash.live_output = other.bash.live_output;
+        }
+        if other.bash.live_lines.is_some() {
+            self.bash.live_lines = other.bash.live_lines;
+        }
+        if other.bash.shell.is_some() {
+            self.bash.shell = other.bash.shell;
+        }
+        self.bash.env.allow.extend(other.bash.env.allow);
+        self.bash.env.deny.extend(other.bash.env.deny);
+        self.bash.env.set.extend(other.bash.env.set);
 
         // Merge context config: always override with other's values
         // (since there's no Option wrapper, we check if they differ from defaults)
@@ -448,6 +731,57 @@ impl Config {
         for (model, pricing) in other.model_pricing {
             self.model_pricing.insert(model, pricing);
         }
+
+        // Merge language servers (other takes priority)
+        self.lsp.servers.extend(other.lsp.servers);
+        if other.lsp.request_timeout_ms.is_some() {
+            self.lsp.request_timeout_ms = other.lsp.request_timeout_ms;
+        }
+
+        // Merge repo map config: override if set
+        if other.repo_map.enabled.is_some() {
+            self.repo_map.enabled = other.repo_map.enabled;
+        }
+        if other.repo_map.max_tokens.is_some() {
+            self.repo_map.max_tokens = other.repo_map.max_tokens;
+        }
+
+        // Merge formatters (other takes priority per glob)
+        self.format.commands.extend(other.format.commands);
+        if other.format.timeout_ms.is_some() {
+            self.format.timeout_ms = other.format.timeout_ms;
+        }
+
+        // Merge limits: scalars override if set, per-tool budgets by key
+        let limits = other.limits;
+        if limits.max_tool_calls_per_turn.is_some() {
+            self.limits.max_tool_calls_per_turn = limits.max_tool_calls_per_turn;
+        }
+        if limits.max_tool_calls_per_session.is_some() {
+            self.limits.max_tool_calls_per_session = limits.max_tool_calls_per_session;
+        }
+        if limits.max_files_modified.is_some() {
+            self.limits.max_files_modified = limits.max_files_modified;
+        }
+        if limits.max_bytes_written.is_some() {
+            self.limits.max_bytes_written = limits.max_bytes_written;
+        }
+        if limits.max_turn_seconds.is_some() {
+            self.limits.max_turn_seconds = limits.max_turn_seconds;
+        }
+        self.limits
+            .tool_calls_per_turn
+            .extend(limits.tool_calls_per_turn);
+        self.limits
+            .tool_calls_per_session
+            .extend(limits.tool_calls_per_session);
+
+        if other.transcript.hmac_key_file.is_some() {
+            self.transcript.hmac_key_file = other.transcript.hmac_key_file;
+        }
+
+        // Merge MCP servers (other takes priority)
+        self.mcp.servers.extend(other.mcp.servers);
     }
 
     /// Get the default target
@@ -490,6 +824,25 @@ impl Config {
         config
     }
 
+    /// Names of the environment variables holding backend API keys and MCP
+    /// server tokens
+    pub fn api_key_env_vars(&self) -> Vec<String> {
+        let mut vars: Vec<String> = self
+            .backends
+            .values()
+            .filter_map(|b| b.api_key_env.clone())
+            .chain(
+                self.mcp
+                    .servers
+                    .values()
+                    .filter_map(|s| s.bearer_token_env.clone()),
+            )
+            .collect();
+        vars.sort();
+        vars.dedup();
+        vars
+    }
+
     /// Check if config has any backends defined
     pub fn has_backends(&self) -> bool {
         !self.backends.is_empty()
@@ -540,10 +893,164 @@ impl Config {
             }
         }
 
-        // Validate hook matchers are valid regex
+        // Validate bash env glob patterns
+        for (list, patterns) in [
+            ("allow", &self.bash.env.allow),
+            ("deny", &self.bash.env.deny),
+        ] {
+            for (i, pattern) in patterns.iter().enumerate() {
+                if glob::Pattern::new(pattern).is_err() {
+                    errors.push(ValidationError {
+                        field: format!("bash.env.{}[{}]", list, i),
+                        message: format!("Invalid glob pattern '{}'", pattern),
+                    });
+                }
+            }
+        }
+
+        // Validate permission rule arguments and secret file globs
+        for (list, rules) in [
+            ("allow", &self.permissions.allow),
+            ("ask", &self.permissions.ask),
+            ("deny", &self.permissions.deny),
+        ] {
+            for (i, rule) in rules.iter().enumerate() {
+                if let Err(message) = crate::tool_filter::validate_rule(rule) {
+                    errors.push(ValidationError {
+                        field: format!("permissions.{}[{}]", list, i),
+                        message: format!("Invalid rule '{}': {}", rule, message),
+                    });
+                }
+            }
+        }
+        let secret_files = self.permissions.secret_files.iter().flatten();
+        for (i, pattern) in secret_files.enumerate() {
+            let glob = pattern.strip_prefix('!').unwrap_or(pattern);
+            if glob::Pattern::new(glob).is_err() {
+                errors.push(ValidationError {
+                    field: format!("permissions.secret_files[{}]", i),
+                    message: format!("Invalid glob pattern '{}'", pattern),
+                });
+            }
+        }
+
+        // Validate language servers
+        for (name, server) in &self.lsp.servers {
+            if server.command.is_empty() {
+                errors.push(ValidationError {
+                    field: format!("lsp.servers.{}.command", name),
+                    message: "Command must not be empty".to_string(),
+                });
+            }
+            if server.extensions.is_empty() {
+                errors.push(ValidationError {
+                    field: format!("lsp.servers.{}.extensions", name),
+                    message: "At least one file extension is required".to_string(),
+                });
+            }
+        }
+
+        // Validate MCP servers; names become part of tool names
+        for (name, server) in &self.mcp.servers {
+            let valid_name = !name.is_empty()
+                && !name.contains("__")
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !valid_name {
+                errors.push(ValidationError {
+                    field: format!("mcp.servers.{}", name),
+                    message: "Server names may only use letters, digits, '-' and single '_'"
+                        .to_string(),
+                });
+            }
+            match server.transport {
+                McpTransportKind::Stdio if server.command.is_empty() => {
+                    errors.push(ValidationError {
+                        field: format!("mcp.servers.{}.command", name),
+                        message: "Command is required for the stdio transport".to_string(),
+                    });
+                }
+                McpTransportKind::Http | McpTransportKind::Sse => {
+                    let url = server.url.as_deref().unwrap_or("");
+                    if !url.starts_with("http://") && !url.starts_with("https://") {
+                        errors.push(ValidationError {
+                            field: format!("mcp.servers.{}.url", name),
+                            message: format!(
+                                "An http:// or https:// url is required for the {} transport",
+                                server.transport.as_str()
+                            ),
+                        });
+                    }
+                }
+                _ => {}
+            }
+            if server.timeout_ms == Some(0) {
+                errors.push(ValidationError {
+                    field: format!("mcp.servers.{}.timeout_ms", name),
+                    message: "Must be greater than 0".to_string(),
+                });
+            }
+        }
+
+        // Validate formatter globs and commands
+        for (pattern, command) in &self.format.commands {
+            if glob::Pattern::new(pattern).is_err() {
+                errors.push(ValidationError {
+                    field: format!("format.commands.{}", pattern),
+                    message: format!("Invalid glob pattern '{}'", pattern),
+                });
+            }
+            if !shell_words::split(command).is_ok_and(|words| !words.is_empty()) {
+                errors.push(ValidationError {
+                    field: format!("format.commands.{}", pattern),
+                    message: format!("Invalid command '{}'", command),
+                });
+            }
+        }
+
+        // Validate limit keys: tool names and patterns are capitalized or
+        // "mcp.", anything else must be a category
+        for (table, budgets) in [
+            ("tool_calls_per_turn", &self.limits.tool_calls_per_turn),
+            (
+                "tool_calls_per_session",
+                &self.limits.tool_calls_per_session,
+            ),
+        ] {
+            for key in budgets.keys() {
+                let tool =
+                    key.starts_with(|c: char| c.is_ascii_uppercase()) || key.starts_with("mcp.");
+                if !tool && !LIMIT_CATEGORIES.contains(&key.as_str()) {
+                    errors.push(ValidationError {
+                        field: format!("limits.{}.{}", table, key),
+                        message: format!(
+                            "Unknown tool or category '{}', expected a tool name or one of: {}",
+                            key,
+                            LIMIT_CATEGORIES.join(", ")
+                        ),
+                    });
+                }
+            }
+        }
+        if self.limits.max_turn_seconds == Some(0) {
+            errors.push(ValidationError {
+                field: "limits.max_turn_seconds".to_string(),
+                message: "Must be greater than 0".to_string(),
+            });
+        }
+
+        // Validate hook matchers are valid rules or regexes
         for (i, hook) in self.hooks.iter().enumerate() {
             if let Some(matcher) = &hook.matcher {
-                if regex::Regex::new(matcher).is_err() {
+                if crate::tool_filter::is_call_rule(matcher) {
+                    if let Err(e) = crate::tool_filter::validate_rule(matcher) {
+                        errors.push(ValidationError {
+                            field: format!("hooks[{}].matcher", i),
+                            message: format!("Invalid rule '{}': {}", matcher, e),
+                        });
+                    }
+                } else if regex::Regex::new(matcher).is_err() {
                     errors.push(ValidationError {
                         field: format!("hooks[{}].matcher", i),
                         message: format!("Invalid regex pattern '{}'", matcher),
@@ -661,6 +1168,13 @@ mod tests {
         assert_eq!(errors.len(), 1);
         assert!(errors[0].field.contains("hooks"));
         assert!(errors[0].message.contains("Invalid regex"));
+
+        // Rule matchers are checked as permission rules, not regexes
+        config.hooks[0].matcher = Some("Bash(git commit:*)".to_string());
+        assert!(config.validate().is_ok());
+        config.hooks[0].matcher = Some("Bash(re:[unclosed)".to_string());
+        let errors = config.validate().unwrap_err();
+        assert!(errors[0].message.contains("Invalid rule"));
     }
 
     #[test]
@@ -676,4 +1190,138 @@ mod tests {
         assert_eq!(errors.len(), 1);
         assert!(errors[0].message.contains("empty"));
     }
+
+    #[test]
+    fn test_validate_lsp_server_needs_extensions() {
+        let mut config = Config::with_builtin_backends();
+        let server: LspServerConfig =
+            toml::from_str("command = \"gopls\"\nextensions = []").unwrap();
+        config.lsp.servers.insert("gopls".to_string(), server);
+        let errors = config.validate().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "lsp.servers.gopls.extensions");
+    }
+
+    #[test]
+    fn test_mcp_server_config() {
+        // Both the README's snake_case and the camelCase keys are accepted
+        let mut config: Config = toml::from_str(
+            "[mcp.servers.calc]\ncommand = \"mcp-calc\"\nautoStart = true\ntimeoutMs = 500\n\n[mcp.servers.remote]\ntransport = \"http\"\nauto_start = true\nbearer_token_env = \"REMOTE_TOKEN\"\n\n[mcp.servers.legacy]\ntransport = \"sse\"\nurl = \"localhost:8080/sse\"\n",
+        )
+        .unwrap();
+        let calc = &config.mcp.servers["calc"];
+        assert!(calc.enabled && calc.auto_start);
+        assert_eq!(calc.timeout_ms, Some(500));
+        assert_eq!(calc.transport, McpTransportKind::Stdio);
+
+        // Server tokens are kept out of child processes like backend keys
+        assert!(config
+            .api_key_env_vars()
+            .contains(&"REMOTE_TOKEN".to_string()));
+
+        let errors = config.validate().unwrap_err();
+        let mut fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        fields.sort();
+        assert_eq!(fields, ["mcp.servers.legacy.url", "mcp.servers.remote.url"]);
+
+        config.mcp.servers.remove("remote");
+        config.mcp.servers.remove("legacy");
+        let server = config.mcp.servers["calc"].clone();
+        config
+            .mcp
+            .servers
+            .insert("my.calc".to_string(), server.clone());
+        let errors = config.validate().unwrap_err();
+        assert_eq!(errors[0].field, "mcp.servers.my.calc");
+        config.mcp.servers.remove("my.calc");
+        for name in ["my calc", "my/calc", "", "my__calc"] {
+            config.mcp.servers.insert(name.to_string(), server.clone());
+            let errors = config.validate().unwrap_err();
+            assert_eq!(errors[0].field, format!("mcp.servers.{}", name));
+            config.mcp.servers.remove(name);
+        }
+    }
+
+    #[test]
+    fn test_format_config_merge_and_validate() {
+        let mut config = Config::with_builtin_backends();
+        let other: Config = toml::from_str(
+            "[format]\ntimeout_ms = 5000\n[format.commands]\n\"**/*.rs\" = \"rustfmt --edition 2021 {file}\"\n\"[*.ts\" = \"prettier --write {file}\"\n",
+        )
+        .unwrap();
+        config.merge(other);
+        assert_eq!(config.format.timeout_ms, Some(5000));
+        assert_eq!(config.format.commands.len(), 2);
+        let errors = config.validate().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "format.commands.[*.ts");
+    }
+
+    #[test]
+    fn test_limits_merge_and_validate() {
+        let mut config = Config::with_builtin_backends();
+        let user: Config = toml::from_str(
+            "[limits]\nmax_bytes_written = 1000\n[limits.tool_calls_per_turn]\nBash = 10\nmutation = 5\n",
+        )
+        .unwrap();
+        let project: Config = toml::from_str(
+            "[limits]\nmax_turn_seconds = 0\n[limits.tool_calls_per_turn]\nBash = 20\nedits = 3\n",
+        )
+        .unwrap();
+        config.merge(user);
+        config.merge(project);
+        assert_eq!(config.limits.max_bytes_written, Some(1000));
+        assert_eq!(config.limits.tool_calls_per_turn["Bash"], 20);
+        assert_eq!(config.limits.tool_calls_per_turn["mutation"], 5);
+
+        let errors = config.validate().unwrap_err();
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            [
+                "limits.tool_calls_per_turn.edits",
+                "limits.max_turn_seconds"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hmac_key_path_resolution() {
+        let root = Path::new("/proj");
+        let mut transcript = TranscriptConfig::default();
+        assert_eq!(transcript.hmac_key_path(root), None);
+        transcript.hmac_key_file = Some(".yo/transcript.key".to_string());
+        assert_eq!(
+            transcript.hmac_key_path(root),
+            Some(PathBuf::from("/proj/.yo/transcript.key"))
+        );
+        transcript.hmac_key_file = Some("/keys/t.key".to_string());
+        assert_eq!(
+            transcript.hmac_key_path(root),
+            Some(PathBuf::from("/keys/t.key"))
+        );
+    }
+
+    #[test]
+    fn test_secret_files_merge_and_validate() {
+        let mut config = Config::with_builtin_backends();
+        assert!(config.permissions.secret_files.is_none());
+        let other: Config = toml::from_str(
+            "[permissions]\nsecret_files = [\"**/.env\", \"![x\"]\ndeny = [\"Bash(re:(\"]\n",
+        )
+        .unwrap();
+        config.merge(other);
+        assert_eq!(config.permissions.secret_files.as_ref().unwrap().len(), 2);
+
+        // An unset list in a later file keeps the earlier one
+        config.merge(toml::from_str("[permissions]\nallow = [\"Read\"]\n").unwrap());
+        assert!(config.permissions.secret_files.is_some());
+
+        let errors = config.validate().unwrap_err();
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            ["permissions.deny[0]", "permissions.secret_files[1]"]
+        );
+    }
 }
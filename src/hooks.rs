@@ -2,22 +2,37 @@
 //!
 //! Implements Claude Code-compatible hooks with JSON input/output via stdin/stdout.
 //! Exit codes: 0 = allow, 2 = block, other = warn (continue with warning).
-
-use crate::config::{HookConfig, HookEvent};
+//!
+//! Matchers are permission rules (`Bash(git commit:*)`, `Edit(src/**/*.rs)`)
+//! checked against the call's arguments, or regexes on the tool name. All
+//! hooks matching an event run concurrently; their outputs are merged in
+//! config order, so the outcome does not depend on which finishes first.
+
+use crate::config::{BashEnvConfig, HookConfig, HookEvent, PermissionsConfig};
+use crate::plan::PlanStep;
+use crate::policy::PolicyEngine;
+use crate::tool_filter;
+use crate::tools::bash::{drain_pipe, StreamCapture};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::io::Write;
 use std::path::PathBuf;
 use std::process::{Command, Stdio};
-use std::time::Duration;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use wait_timeout::ChildExt;
 
+/// Hook output kept in memory; anything longer goes through a spill file
+const MAX_HOOK_OUTPUT_BYTES: usize = 16 * 1024 * 1024;
+
 /// Base session info included in all hook inputs
 #[derive(Debug, Clone, Serialize)]
 pub struct SessionInfo {
     pub session_id: String,
     pub cwd: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transcript_path: Option<PathBuf>,
 }
 
 /// Input for PreToolUse hook
@@ -83,6 +98,79 @@ pub struct SessionStartInput {
     pub mode: String,
 }
 
+/// Input for SessionEnd hook
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionEndInput {
+    #[serde(flatten)]
+    pub session: SessionInfo,
+    pub hook_event: &'static str,
+    /// "prompt_input_exit" (/exit, Ctrl-C, Ctrl-D) or "other"
+    pub reason: String,
+    pub mode: String,
+    pub duration_ms: u64,
+    #[serde(flatten)]
+    pub metrics: SessionMetrics,
+}
+
+/// Session totals reported to SessionEnd hooks
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SessionMetrics {
+    pub turns: u32,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+}
+
+/// Input for PreCompact hook
+#[derive(Debug, Clone, Serialize)]
+pub struct PreCompactInput {
+    #[serde(flatten)]
+    pub session: SessionInfo,
+    pub hook_event: &'static str,
+    /// "manual" (/compact) or "auto"
+    pub trigger: String,
+    /// The conversation about to be compacted
+    pub messages: Vec<Value>,
+}
+
+/// Input for PermissionRequest hook
+#[derive(Debug, Clone, Serialize)]
+pub struct PermissionRequestInput {
+    #[serde(flatten)]
+    pub session: SessionInfo,
+    pub hook_event: &'static str,
+    pub tool_name: String,
+    pub tool_args: Value,
+    /// The ask rule that applies, if any (otherwise the permission mode asks)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+}
+
+/// Input for Notification hook
+#[derive(Debug, Clone, Serialize)]
+pub struct NotificationInput {
+    #[serde(flatten)]
+    pub session: SessionInfo,
+    pub hook_event: &'static str,
+    /// "permission_prompt" or "idle_prompt"
+    pub notification_type: String,
+    pub message: String,
+}
+
+/// Input for PrePlanStep hook
+#[derive(Debug, Clone, Serialize)]
+pub struct PrePlanStepInput {
+    #[serde(flatten)]
+    pub session: SessionInfo,
+    pub hook_event: &'static str,
+    pub plan_name: String,
+    pub step_number: usize,
+    pub total_steps: usize,
+    pub step_title: String,
+    pub step_description: String,
+    pub files: Vec<String>,
+}
+
 /// Output from PreToolUse hook (parsed from stdout JSON)
 #[derive(Debug, Clone, Default, Deserialize)]
 pub struct PreToolUseOutput {
@@ -99,6 +187,31 @@ pub struct UserPromptSubmitOutput {
     pub decision: Option<String>, // "allow" | "block"
     #[serde(default)]
     pub updated_prompt: Option<String>,
+    /// Text added to the prompt as context for the model
+    #[serde(default)]
+    pub additional_context: Option<String>,
+}
+
+/// Output from PostToolUse hook
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PostToolUseOutput {
+    /// Replaces the result the model sees
+    #[serde(default)]
+    pub updated_result: Option<Value>,
+    /// Replaces the result with an error carrying this message
+    #[serde(default)]
+    pub block_with_feedback: Option<String>,
+    /// Text added to the result for the model
+    #[serde(default)]
+    pub additional_context: Option<String>,
+}
+
+/// Output from SessionStart hook
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SessionStartOutput {
+    /// Text added to the system prompt for the whole session
+    #[serde(default)]
+    pub additional_context: Option<String>,
 }
 
 /// Output from Stop hook
@@ -110,6 +223,57 @@ pub struct StopOutput {
     pub continue_prompt: Option<String>,
 }
 
+/// Output from PermissionRequest hook
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PermissionRequestOutput {
+    #[serde(default)]
+    pub decision: Option<String>, // "allow" | "deny"; anything else shows the prompt
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// Output from PrePlanStep hook
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PrePlanStepOutput {
+    #[serde(default)]
+    pub decision: Option<String>, // "block" | "skip"
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// What UserPromptSubmit hooks decided
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PromptHookResult {
+    pub proceed: bool,
+    pub updated_prompt: Option<String>,
+    pub additional_context: Vec<String>,
+}
+
+/// What PostToolUse hooks changed about a tool result
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PostToolUseResult {
+    pub updated_result: Option<Value>,
+    /// Set when a hook blocked the result (exit code 2 or
+    /// `block_with_feedback`); the model sees this message as an error
+    pub block_feedback: Option<String>,
+    pub additional_context: Vec<String>,
+}
+
+/// What a PermissionRequest hook decided in place of the prompt
+#[derive(Debug, Clone, PartialEq)]
+pub struct PermissionHookDecision {
+    pub allow: bool,
+    pub message: Option<String>,
+}
+
+/// What PrePlanStep hooks decided for a step
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlanStepDecision {
+    Run,
+    Skip(Option<String>),
+    Block(Option<String>),
+}
+
 /// Result of hook execution
 #[derive(Debug)]
 pub struct HookResult {
@@ -130,26 +294,93 @@ impl HookResult {
     }
 }
 
+/// How a hook did the last time it ran, for `/hooks`
+#[derive(Debug, Clone, Default)]
+pub struct HookStats {
+    pub runs: u64,
+    /// None if it timed out, failed to start or was killed by a signal
+    pub last_exit_code: Option<i32>,
+    pub last_latency_ms: u64,
+}
+
 /// Manager for executing hooks at lifecycle points
 pub struct HookManager {
     hooks: Vec<HookConfig>,
     session_info: SessionInfo,
+    env: BashEnvConfig,
+    started: Instant,
+    /// Matches rule matchers against calls the way permission rules are
+    rules: PolicyEngine,
+    /// One entry per hook, in config order
+    stats: Mutex<Vec<HookStats>>,
 }
 
 impl HookManager {
     /// Create a new HookManager with the given configuration
     pub fn new(hooks: Vec<HookConfig>, session_id: String, cwd: PathBuf) -> Self {
+        let rules =
+            PolicyEngine::new(PermissionsConfig::default(), true, false).with_root(cwd.clone());
+        let stats = Mutex::new(vec![HookStats::default(); hooks.len()]);
         Self {
             hooks,
-            session_info: SessionInfo { session_id, cwd },
+            session_info: SessionInfo {
+                session_id,
+                cwd,
+                transcript_path: None,
+            },
+            env: BashEnvConfig::default(),
+            started: Instant::now(),
+            rules,
+            stats,
         }
     }
 
+    /// Include the session transcript's path in every hook input
+    pub fn with_transcript_path(mut self, path: PathBuf) -> Self {
+        self.session_info.transcript_path = Some(path);
+        self
+    }
+
+    /// Apply the given environment policy to hook child processes
+    pub fn with_env(mut self, env: BashEnvConfig) -> Self {
+        self.env = env;
+        self
+    }
+
     /// Get hooks for a specific event
     fn get_hooks(&self, event: HookEvent) -> Vec<&HookConfig> {
         self.hooks.iter().filter(|h| h.event == event).collect()
     }
 
+    /// Hooks for an event whose matcher applies to a tool call (for events
+    /// without a tool: the notification type, compaction trigger or session
+    /// end reason, with no args)
+    pub fn tool_hooks(
+        &self,
+        event: HookEvent,
+        tool_name: &str,
+        tool_args: Option<&Value>,
+    ) -> Vec<&HookConfig> {
+        self.get_hooks(event)
+            .into_iter()
+            .filter(|h| self.matches_call(h, tool_name, tool_args))
+            .collect()
+    }
+
+    /// Check if a hook's matcher applies to a call. Rule matchers see the
+    /// arguments; without them only the rule's tool name is checked.
+    fn matches_call(&self, hook: &HookConfig, tool_name: &str, tool_args: Option<&Value>) -> bool {
+        match (&hook.matcher, tool_args) {
+            (Some(rule), Some(args)) if tool_filter::is_call_rule(rule) => {
+                self.rules.matches_call(rule, tool_name, args)
+            }
+            (Some(rule), None) if tool_filter::is_call_rule(rule) => {
+                tool_filter::tool_matches(tool_name, rule, None)
+            }
+            _ => self.matches_tool(hook, tool_name),
+        }
+    }
+
     /// Check if a hook's matcher applies to a tool name
     fn matches_tool(&self, hook: &HookConfig, tool_name: &str) -> bool {
         match &hook.matcher {
@@ -163,19 +394,197 @@ impl HookManager {
         }
     }
 
-    /// Execute a single hook with the given JSON input
-    fn execute_hook<T: Serialize>(&self, hook: &HookConfig, input: &T) -> HookResult {
+    /// Configured hooks with how each did on its last run
+    pub fn hooks(&self) -> Vec<(&HookConfig, HookStats)> {
+        let stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        self.hooks.iter().zip(stats.iter().cloned()).collect()
+    }
+
+    /// Run hook `index` once with a sample input for its event, with the
+    /// top-level fields of `overrides` replacing the sample's. Stats are not
+    /// recorded. Returns the input used, the result and the latency.
+    pub fn dry_run(
+        &self,
+        index: usize,
+        overrides: Option<Value>,
+    ) -> Result<(Value, HookResult, Duration), String> {
+        let hook = self
+            .hooks
+            .get(index)
+            .ok_or_else(|| format!("No hook #{} ({} configured)", index + 1, self.hooks.len()))?;
+        let mut input = self.sample_input(hook.event);
+        match overrides {
+            Some(Value::Object(fields)) => {
+                if let Value::Object(sample) = &mut input {
+                    sample.extend(fields);
+                }
+            }
+            Some(_) => return Err("Sample input overrides must be a JSON object".to_string()),
+            None => {}
+        }
+        let start = Instant::now();
+        let result = self.execute_json(hook, &input.to_string());
+        Ok((input, result, start.elapsed()))
+    }
+
+    /// A representative input for `event`, as the real one would be sent
+    fn sample_input(&self, event: HookEvent) -> Value {
+        let session = self.session_info.clone();
+        let tool_args = serde_json::json!({ "command": "git status" });
+        let input = match event {
+            HookEvent::PreToolUse => serde_json::to_value(PreToolUseInput {
+                session,
+                hook_event: "PreToolUse",
+                tool_name: "Bash".to_string(),
+                tool_args,
+            }),
+            HookEvent::PostToolUse => serde_json::to_value(PostToolUseInput {
+                session,
+                hook_event: "PostToolUse",
+                tool_name: "Bash".to_string(),
+                tool_args,
+                tool_result: serde_json::json!({
+                    "exit_code": 0,
+                    "stdout": "On branch main\nnothing to commit, working tree clean\n",
+                    "stderr": ""
+                }),
+                duration_ms: 12,
+            }),
+            HookEvent::UserPromptSubmit => serde_json::to_value(UserPromptSubmitInput {
+                session,
+                hook_event: "UserPromptSubmit",
+                prompt: "Add a test for the config parser".to_string(),
+            }),
+            HookEvent::Stop => serde_json::to_value(StopInput {
+                session,
+                hook_event: "Stop",
+                stop_reason: "end_turn".to_string(),
+                assistant_message: Some("Added the test; all tests pass.".to_string()),
+            }),
+            HookEvent::SubagentStop => serde_json::to_value(SubagentStopInput {
+                session,
+                hook_event: "SubagentStop",
+                agent_name: "reviewer".to_string(),
+                ok: true,
+                output_text: "No issues found.".to_string(),
+                duration_ms: 2400,
+            }),
+            HookEvent::SessionStart => serde_json::to_value(SessionStartInput {
+                session,
+                hook_event: "SessionStart",
+                mode: "repl".to_string(),
+            }),
+            HookEvent::SessionEnd => serde_json::to_value(SessionEndInput {
+                session,
+                hook_event: "SessionEnd",
+                reason: "prompt_input_exit".to_string(),
+                mode: "repl".to_string(),
+                duration_ms: 60_000,
+                metrics: SessionMetrics {
+                    turns: 3,
+                    input_tokens: 12_000,
+                    output_tokens: 800,
+                    cost_usd: 0.05,
+                },
+            }),
+            HookEvent::PreCompact => serde_json::to_value(PreCompactInput {
+                session,
+                hook_event: "PreCompact",
+                trigger: "manual".to_string(),
+                messages: vec![serde_json::json!({
+                    "role": "user",
+                    "content": "Add a test for the config parser"
+                })],
+            }),
+            HookEvent::PermissionRequest => serde_json::to_value(PermissionRequestInput {
+                session,
+                hook_event: "PermissionRequest",
+                tool_name: "Bash".to_string(),
+                tool_args,
+                rule: None,
+            }),
+            HookEvent::Notification => serde_json::to_value(NotificationInput {
+                session,
+                hook_event: "Notification",
+                notification_type: "idle_prompt".to_string(),
+                message: "Waiting for input".to_string(),
+            }),
+            HookEvent::PrePlanStep => serde_json::to_value(PrePlanStepInput {
+                session,
+                hook_event: "PrePlanStep",
+                plan_name: "add-parser-test".to_string(),
+                step_number: 1,
+                total_steps: 2,
+                step_title: "Write the test".to_string(),
+                step_description: "Add a unit test for the config parser".to_string(),
+                files: vec!["src/config.rs".to_string()],
+            }),
+        };
+        input.unwrap_or(Value::Null)
+    }
+
+    /// Run hooks with the same input, concurrently when there is more than
+    /// one. Results come back in config order.
+    fn run_hooks<T: Serialize>(&self, hooks: &[&HookConfig], input: &T) -> Vec<HookResult> {
         let input_json = match serde_json::to_string(input) {
             Ok(json) => json,
             Err(e) => {
-                return HookResult {
-                    exit_code: None,
-                    stdout: String::new(),
-                    stderr: format!("Failed to serialize input: {}", e),
-                };
+                return hooks
+                    .iter()
+                    .map(|_| HookResult {
+                        exit_code: None,
+                        stdout: String::new(),
+                        stderr: format!("Failed to serialize input: {}", e),
+                    })
+                    .collect();
             }
         };
 
+        let run = |hook: &HookConfig| {
+            let start = Instant::now();
+            let result = self.execute_json(hook, &input_json);
+            self.record(hook, &result, start.elapsed());
+            result
+        };
+
+        if hooks.len() <= 1 {
+            return hooks.iter().map(|hook| run(hook)).collect();
+        }
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = hooks
+                .iter()
+                .map(|hook| {
+                    let run = &run;
+                    scope.spawn(move || run(hook))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle.join().unwrap_or_else(|_| HookResult {
+                        exit_code: None,
+                        stdout: String::new(),
+                        stderr: "Hook runner panicked".to_string(),
+                    })
+                })
+                .collect()
+        })
+    }
+
+    /// Update the stats of the hook that produced `result`
+    fn record(&self, hook: &HookConfig, result: &HookResult, latency: Duration) {
+        let Some(index) = self.hooks.iter().position(|h| std::ptr::eq(h, hook)) else {
+            return;
+        };
+        let mut stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = &mut stats[index];
+        entry.runs += 1;
+        entry.last_exit_code = result.exit_code;
+        entry.last_latency_ms = latency.as_millis() as u64;
+    }
+
+    /// Execute a single hook with the given serialized input
+    fn execute_json(&self, hook: &HookConfig, input_json: &str) -> HookResult {
         // Build command
         let (cmd, args) = match hook.command.split_first() {
             Some((c, a)) => (c, a),
@@ -189,14 +598,16 @@ impl HookManager {
         };
 
         // Spawn process
-        let mut child = match Command::new(cmd)
+        let mut command = Command::new(cmd);
+        command
             .args(args)
             .current_dir(&self.session_info.cwd)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-        {
+            .stderr(Stdio::piped());
+        crate::child_env::apply(&self.env, &mut command);
+
+        let mut child = match command.spawn() {
             Ok(c) => c,
             Err(e) => {
                 return HookResult {
@@ -207,19 +618,38 @@ impl HookManager {
             }
         };
 
-        // Write input to stdin
+        // Write input to stdin from another thread, so a hook that never
+        // reads a large input still times out instead of blocking us
         if let Some(mut stdin) = child.stdin.take() {
-            let _ = stdin.write_all(input_json.as_bytes());
+            let input = input_json.to_string();
+            std::thread::spawn(move || {
+                let _ = stdin.write_all(input.as_bytes());
+            });
         }
 
+        // Drain both pipes while waiting, so a hook printing more than a pipe
+        // buffer holds doesn't block until the timeout
+        let cwd = &self.session_info.cwd;
+        let stdout = drain_pipe(
+            child.stdout.take(),
+            StreamCapture::new(cwd, MAX_HOOK_OUTPUT_BYTES, 0),
+            None,
+        );
+        let stderr = drain_pipe(
+            child.stderr.take(),
+            StreamCapture::new(cwd, MAX_HOOK_OUTPUT_BYTES, 0),
+            None,
+        );
+
         // Wait with timeout
         let timeout = Duration::from_millis(hook.timeout_ms);
         let status = match child.wait_timeout(timeout) {
             Ok(Some(s)) => s,
             Ok(None) => {
-                // Timeout - kill the process
+                // Timeout - kill the process and stop the readers
                 let _ = child.kill();
                 let _ = child.wait();
+                let _ = (stdout.finish(), stderr.finish());
                 return HookResult {
                     exit_code: None,
                     stdout: String::new(),
@@ -235,26 +665,8 @@ impl HookManager {
             }
         };
 
-        // Read outputs
-        let stdout = child
-            .stdout
-            .map(|mut out| {
-                let mut buf = String::new();
-                use std::io::Read;
-                let _ = out.read_to_string(&mut buf);
-                buf
-            })
-            .unwrap_or_default();
-
-        let stderr = child
-            .stderr
-            .map(|mut err| {
-                let mut buf = String::new();
-                use std::io::Read;
-                let _ = err.read_to_string(&mut buf);
-                buf
-            })
-            .unwrap_or_default();
+        let stdout = String::from_utf8_lossy(&stdout.finish().full()).into_owned();
+        let stderr = String::from_utf8_lossy(&stderr.finish().full()).into_owned();
 
         HookResult {
             exit_code: status.code(),
@@ -266,11 +678,7 @@ impl HookManager {
     /// Run PreToolUse hooks for a tool call
     /// Returns (should_proceed, updated_args)
     pub fn pre_tool_use(&self, tool_name: &str, tool_args: &Value) -> (bool, Option<Value>) {
-        let hooks: Vec<_> = self
-            .get_hooks(HookEvent::PreToolUse)
-            .into_iter()
-            .filter(|h| self.matches_tool(h, tool_name))
-            .collect();
+        let hooks = self.tool_hooks(HookEvent::PreToolUse, tool_name, Some(tool_args));
 
         if hooks.is_empty() {
             return (true, None);
@@ -285,9 +693,7 @@ impl HookManager {
 
         let mut updated_args: Option<Value> = None;
 
-        for hook in hooks {
-            let result = self.execute_hook(hook, &input);
-
+        for result in self.run_hooks(&hooks, &input) {
             // Log hook execution (stderr goes to our stderr as warning)
             if !result.stderr.is_empty() {
                 eprintln!("[Hook:PreToolUse] {}", result.stderr.trim());
@@ -318,22 +724,20 @@ impl HookManager {
         (true, updated_args)
     }
 
-    /// Run PostToolUse hooks for a completed tool call
+    /// Run PostToolUse hooks for a completed tool call. The first hook to
+    /// block or replace the result wins; context from every hook is kept.
     pub fn post_tool_use(
         &self,
         tool_name: &str,
         tool_args: &Value,
         tool_result: &Value,
         duration_ms: u64,
-    ) {
-        let hooks: Vec<_> = self
-            .get_hooks(HookEvent::PostToolUse)
-            .into_iter()
-            .filter(|h| self.matches_tool(h, tool_name))
-            .collect();
+    ) -> PostToolUseResult {
+        let hooks = self.tool_hooks(HookEvent::PostToolUse, tool_name, Some(tool_args));
 
+        let mut post = PostToolUseResult::default();
         if hooks.is_empty() {
-            return;
+            return post;
         }
 
         let input = PostToolUseInput {
@@ -345,23 +749,51 @@ impl HookManager {
             duration_ms,
         };
 
-        for hook in hooks {
-            let result = self.execute_hook(hook, &input);
+        for result in self.run_hooks(&hooks, &input) {
+            // Exit code 2 = block; stderr is the feedback for the model
+            if result.is_blocked() {
+                if post.block_feedback.is_none() {
+                    let feedback = result.stderr.trim();
+                    post.block_feedback = Some(if feedback.is_empty() {
+                        "Blocked by PostToolUse hook".to_string()
+                    } else {
+                        feedback.to_string()
+                    });
+                }
+                continue;
+            }
 
             // Log any stderr output
             if !result.stderr.is_empty() {
                 eprintln!("[Hook:PostToolUse] {}", result.stderr.trim());
             }
+
+            if result.is_allowed() && !result.stdout.is_empty() {
+                if let Ok(output) = serde_json::from_str::<PostToolUseOutput>(&result.stdout) {
+                    if post.block_feedback.is_none() && output.block_with_feedback.is_some() {
+                        post.block_feedback = output.block_with_feedback;
+                    }
+                    if post.updated_result.is_none() && output.updated_result.is_some() {
+                        post.updated_result = output.updated_result;
+                    }
+                    post.additional_context.extend(output.additional_context);
+                }
+            }
         }
+
+        post
     }
 
     /// Run UserPromptSubmit hooks
-    /// Returns (should_proceed, updated_prompt)
-    pub fn user_prompt_submit(&self, prompt: &str) -> (bool, Option<String>) {
+    pub fn user_prompt_submit(&self, prompt: &str) -> PromptHookResult {
         let hooks = self.get_hooks(HookEvent::UserPromptSubmit);
+        let blocked = PromptHookResult::default();
 
         if hooks.is_empty() {
-            return (true, None);
+            return PromptHookResult {
+                proceed: true,
+                ..Default::default()
+            };
         }
 
         let input = UserPromptSubmitInput {
@@ -370,11 +802,12 @@ impl HookManager {
             prompt: prompt.to_string(),
         };
 
-        let mut updated_prompt: Option<String> = None;
-
-        for hook in hooks {
-            let result = self.execute_hook(hook, &input);
+        let mut submitted = PromptHookResult {
+            proceed: true,
+            ..Default::default()
+        };
 
+        for result in self.run_hooks(&hooks, &input) {
             // Log any stderr output
             if !result.stderr.is_empty() {
                 eprintln!("[Hook:UserPromptSubmit] {}", result.stderr.trim());
@@ -382,7 +815,7 @@ impl HookManager {
 
             // Exit code 2 = block
             if result.is_blocked() {
-                return (false, None);
+                return blocked;
             }
 
             // Parse output
@@ -390,17 +823,20 @@ impl HookManager {
                 if let Ok(output) = serde_json::from_str::<UserPromptSubmitOutput>(&result.stdout) {
                     if let Some(decision) = &output.decision {
                         if decision == "block" {
-                            return (false, None);
+                            return blocked;
                         }
                     }
-                    if updated_prompt.is_none() && output.updated_prompt.is_some() {
-                        updated_prompt = output.updated_prompt;
+                    if submitted.updated_prompt.is_none() && output.updated_prompt.is_some() {
+                        submitted.updated_prompt = output.updated_prompt;
                     }
+                    submitted
+                        .additional_context
+                        .extend(output.additional_context);
                 }
             }
         }
 
-        (true, updated_prompt)
+        submitted
     }
 
     /// Run Stop hooks
@@ -423,9 +859,7 @@ impl HookManager {
             assistant_message: assistant_message.map(|s| s.to_string()),
         };
 
-        for hook in hooks {
-            let result = self.execute_hook(hook, &input);
-
+        for result in self.run_hooks(&hooks, &input) {
             // Log any stderr output
             if !result.stderr.is_empty() {
                 eprintln!("[Hook:Stop] {}", result.stderr.trim());
@@ -467,9 +901,7 @@ impl HookManager {
             duration_ms,
         };
 
-        for hook in hooks {
-            let result = self.execute_hook(hook, &input);
-
+        for result in self.run_hooks(&hooks, &input) {
             // Log any stderr output
             if !result.stderr.is_empty() {
                 eprintln!("[Hook:SubagentStop] {}", result.stderr.trim());
@@ -478,11 +910,13 @@ impl HookManager {
     }
 
     /// Run SessionStart hooks
-    pub fn on_session_start(&self, mode: &str) {
+    /// Returns the context they added, for the system prompt
+    pub fn on_session_start(&self, mode: &str) -> Vec<String> {
         let hooks = self.get_hooks(HookEvent::SessionStart);
+        let mut context = Vec::new();
 
         if hooks.is_empty() {
-            return;
+            return context;
         }
 
         let input = SessionStartInput {
@@ -491,15 +925,209 @@ impl HookManager {
             mode: mode.to_string(),
         };
 
-        for hook in hooks {
-            let result = self.execute_hook(hook, &input);
-
+        for result in self.run_hooks(&hooks, &input) {
             // Log any stderr output
             if !result.stderr.is_empty() {
                 eprintln!("[Hook:SessionStart] {}", result.stderr.trim());
             }
+
+            if result.is_allowed() && !result.stdout.is_empty() {
+                if let Ok(output) = serde_json::from_str::<SessionStartOutput>(&result.stdout) {
+                    context.extend(output.additional_context);
+                }
+            }
+        }
+
+        context
+    }
+
+    /// Run SessionEnd hooks; they cannot stop the session ending
+    pub fn on_session_end(&self, reason: &str, mode: &str, metrics: SessionMetrics) {
+        let hooks = self.tool_hooks(HookEvent::SessionEnd, reason, None);
+
+        if hooks.is_empty() {
+            return;
+        }
+
+        let input = SessionEndInput {
+            session: self.session_info.clone(),
+            hook_event: "SessionEnd",
+            reason: reason.to_string(),
+            mode: mode.to_string(),
+            duration_ms: self.started.elapsed().as_millis() as u64,
+            metrics,
+        };
+
+        for result in self.run_hooks(&hooks, &input) {
+            // Log any stderr output
+            if !result.stderr.is_empty() {
+                eprintln!("[Hook:SessionEnd] {}", result.stderr.trim());
+            }
+        }
+    }
+
+    /// Run PreCompact hooks before the conversation is summarized; they see
+    /// the full history but cannot stop the compaction
+    pub fn pre_compact(&self, trigger: &str, messages: &[Value]) {
+        let hooks = self.tool_hooks(HookEvent::PreCompact, trigger, None);
+
+        if hooks.is_empty() {
+            return;
+        }
+
+        let input = PreCompactInput {
+            session: self.session_info.clone(),
+            hook_event: "PreCompact",
+            trigger: trigger.to_string(),
+            messages: messages.to_vec(),
+        };
+
+        for result in self.run_hooks(&hooks, &input) {
+            // Log any stderr output
+            if !result.stderr.is_empty() {
+                eprintln!("[Hook:PreCompact] {}", result.stderr.trim());
+            }
+        }
+    }
+
+    /// Run PermissionRequest hooks before a permission prompt is shown.
+    /// Returns a decision, or None to show the prompt. Any deny wins over
+    /// an allow. Exit code 2 denies, with stderr as the message.
+    pub fn permission_request(
+        &self,
+        tool_name: &str,
+        tool_args: &Value,
+        rule: Option<&str>,
+    ) -> Option<PermissionHookDecision> {
+        let hooks = self.tool_hooks(HookEvent::PermissionRequest, tool_name, Some(tool_args));
+
+        if hooks.is_empty() {
+            return None;
+        }
+
+        let input = PermissionRequestInput {
+            session: self.session_info.clone(),
+            hook_event: "PermissionRequest",
+            tool_name: tool_name.to_string(),
+            tool_args: tool_args.clone(),
+            rule: rule.map(|r| r.to_string()),
+        };
+
+        let mut allowed = None;
+        for result in self.run_hooks(&hooks, &input) {
+            // Exit code 2 = deny; stderr is the reason, not a warning
+            if result.is_blocked() {
+                let message = result.stderr.trim();
+                return Some(PermissionHookDecision {
+                    allow: false,
+                    message: (!message.is_empty()).then(|| message.to_string()),
+                });
+            }
+
+            // Log any stderr output
+            if !result.stderr.is_empty() {
+                eprintln!("[Hook:PermissionRequest] {}", result.stderr.trim());
+            }
+
+            if result.is_allowed() && !result.stdout.is_empty() {
+                if let Ok(output) = serde_json::from_str::<PermissionRequestOutput>(&result.stdout)
+                {
+                    match output.decision.as_deref() {
+                        Some("deny") => {
+                            return Some(PermissionHookDecision {
+                                allow: false,
+                                message: output.message,
+                            });
+                        }
+                        Some("allow") if allowed.is_none() => {
+                            allowed = Some(PermissionHookDecision {
+                                allow: true,
+                                message: output.message,
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        allowed
+    }
+
+    /// Run Notification hooks (the user is being asked for something)
+    pub fn notify(&self, notification_type: &str, message: &str) {
+        let hooks = self.tool_hooks(HookEvent::Notification, notification_type, None);
+
+        if hooks.is_empty() {
+            return;
+        }
+
+        let input = NotificationInput {
+            session: self.session_info.clone(),
+            hook_event: "Notification",
+            notification_type: notification_type.to_string(),
+            message: message.to_string(),
+        };
+
+        for result in self.run_hooks(&hooks, &input) {
+            // Log any stderr output
+            if !result.stderr.is_empty() {
+                eprintln!("[Hook:Notification] {}", result.stderr.trim());
+            }
         }
     }
+
+    /// Run PrePlanStep hooks before a plan step executes. Exit code 2 or
+    /// `"decision": "block"` stops the plan; `"skip"` skips the step.
+    pub fn pre_plan_step(
+        &self,
+        plan_name: &str,
+        step: &PlanStep,
+        total_steps: usize,
+    ) -> PlanStepDecision {
+        let hooks = self.get_hooks(HookEvent::PrePlanStep);
+
+        if hooks.is_empty() {
+            return PlanStepDecision::Run;
+        }
+
+        let input = PrePlanStepInput {
+            session: self.session_info.clone(),
+            hook_event: "PrePlanStep",
+            plan_name: plan_name.to_string(),
+            step_number: step.number,
+            total_steps,
+            step_title: step.title.clone(),
+            step_description: step.description.clone(),
+            files: step.files.clone(),
+        };
+
+        let mut decision = PlanStepDecision::Run;
+        for result in self.run_hooks(&hooks, &input) {
+            // Exit code 2 = block; stderr is the reason
+            if result.is_blocked() {
+                let reason = result.stderr.trim();
+                return PlanStepDecision::Block((!reason.is_empty()).then(|| reason.to_string()));
+            }
+
+            // Log any stderr output
+            if !result.stderr.is_empty() {
+                eprintln!("[Hook:PrePlanStep] {}", result.stderr.trim());
+            }
+
+            if result.is_allowed() && !result.stdout.is_empty() {
+                if let Ok(output) = serde_json::from_str::<PrePlanStepOutput>(&result.stdout) {
+                    match output.decision.as_deref() {
+                        Some("block") => return PlanStepDecision::Block(output.reason),
+                        Some("skip") => decision = PlanStepDecision::Skip(output.reason),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        decision
+    }
 }
 
 #[cfg(test)]
@@ -515,9 +1143,9 @@ mod tests {
         assert!(proceed);
         assert!(updated.is_none());
 
-        let (proceed, updated) = manager.user_prompt_submit("test prompt");
-        assert!(proceed);
-        assert!(updated.is_none());
+        let submitted = manager.user_prompt_submit("test prompt");
+        assert!(submitted.proceed);
+        assert!(submitted.updated_prompt.is_none());
     }
 
     #[test]
@@ -581,4 +1209,286 @@ mod tests {
         assert!(!manager.matches_tool(&hook, "Bash"));
         assert!(!manager.matches_tool(&hook, "Grep"));
     }
+
+    fn sh_hook(event: HookEvent, matcher: Option<&str>, script: &str) -> HookConfig {
+        HookConfig {
+            event,
+            command: vec!["sh".to_string(), "-c".to_string(), script.to_string()],
+            matcher: matcher.map(|m| m.to_string()),
+            timeout_ms: 5000,
+        }
+    }
+
+    #[test]
+    fn test_permission_request_decides() {
+        let hooks = vec![
+            sh_hook(
+                HookEvent::PermissionRequest,
+                Some("^Bash$"),
+                "grep -q 'cargo test' && echo '{\"decision\":\"allow\"}'",
+            ),
+            sh_hook(
+                HookEvent::PermissionRequest,
+                Some("^Write$"),
+                "echo 'no writes on Fridays' >&2; exit 2",
+            ),
+        ];
+        let manager = HookManager::new(hooks, "s".to_string(), PathBuf::from("."));
+
+        let decision = manager.permission_request(
+            "Bash",
+            &serde_json::json!({ "command": "cargo test" }),
+            None,
+        );
+        assert_eq!(
+            decision,
+            Some(PermissionHookDecision {
+                allow: true,
+                message: None
+            })
+        );
+        // No decision on stdout: the prompt is shown
+        let decision =
+            manager.permission_request("Bash", &serde_json::json!({ "command": "ls" }), None);
+        assert_eq!(decision, None);
+
+        let decision = manager.permission_request("Write", &serde_json::json!({}), None);
+        assert_eq!(
+            decision,
+            Some(PermissionHookDecision {
+                allow: false,
+                message: Some("no writes on Fridays".to_string())
+            })
+        );
+    }
+
+    #[test]
+    fn test_notification_and_session_end_inputs() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let log = dir.path().join("log");
+        let append = format!("cat >> {}; echo >> {}", log.display(), log.display());
+        let hooks = vec![
+            sh_hook(HookEvent::Notification, Some("^idle_prompt$"), &append),
+            sh_hook(HookEvent::SessionEnd, None, &append),
+        ];
+        let manager = HookManager::new(hooks, "s".to_string(), dir.path().to_path_buf())
+            .with_transcript_path(dir.path().join("s.jsonl"));
+
+        manager.notify("permission_prompt", "yo needs your permission to use Bash");
+        manager.notify("idle_prompt", "yo is waiting for your input");
+        let metrics = SessionMetrics {
+            turns: 3,
+            ..Default::default()
+        };
+        manager.on_session_end("prompt_input_exit", "repl", metrics);
+
+        let content = std::fs::read_to_string(&log).unwrap();
+        let events: Vec<Value> = content
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["notification_type"], "idle_prompt");
+        assert_eq!(events[1]["hook_event"], "SessionEnd");
+        assert_eq!(events[1]["reason"], "prompt_input_exit");
+        assert_eq!(events[1]["turns"], 3);
+        assert!(events[1]["transcript_path"]
+            .as_str()
+            .unwrap()
+            .ends_with("s.jsonl"));
+    }
+
+    #[test]
+    fn test_pre_plan_step_skip_and_block() {
+        let step = PlanStep::new(2, "Deploy".to_string(), "Push to production".to_string());
+        let skip = sh_hook(
+            HookEvent::PrePlanStep,
+            None,
+            "grep -q Deploy && echo '{\"decision\":\"skip\",\"reason\":\"manual\"}'",
+        );
+        let manager = HookManager::new(vec![skip.clone()], "s".to_string(), PathBuf::from("."));
+        assert_eq!(
+            manager.pre_plan_step("release", &step, 3),
+            PlanStepDecision::Skip(Some("manual".to_string()))
+        );
+
+        let block = sh_hook(HookEvent::PrePlanStep, None, "exit 2");
+        let manager = HookManager::new(vec![skip, block], "s".to_string(), PathBuf::from("."));
+        assert_eq!(
+            manager.pre_plan_step("release", &step, 3),
+            PlanStepDecision::Block(None)
+        );
+    }
+
+    #[test]
+    fn test_post_tool_use_rewrites_result() {
+        let hooks = vec![
+            sh_hook(
+                HookEvent::PostToolUse,
+                Some("^Read$"),
+                "echo '{\"updated_result\":{\"content\":\"[redacted]\"},\"additional_context\":\"file is generated\"}'",
+            ),
+            sh_hook(
+                HookEvent::PostToolUse,
+                Some("^Read$"),
+                "echo '{\"additional_context\":\"do not edit\"}'",
+            ),
+            sh_hook(
+                HookEvent::PostToolUse,
+                Some("^Bash$"),
+                "echo 'tests must pass first' >&2; exit 2",
+            ),
+        ];
+        let manager = HookManager::new(hooks, "s".to_string(), PathBuf::from("."));
+        let args = serde_json::json!({ "path": "a" });
+
+        let post = manager.post_tool_use("Read", &args, &serde_json::json!({}), 1);
+        assert_eq!(
+            post.updated_result,
+            Some(serde_json::json!({ "content": "[redacted]" }))
+        );
+        assert_eq!(
+            post.additional_context,
+            ["file is generated", "do not edit"]
+        );
+        assert_eq!(post.block_feedback, None);
+
+        let post = manager.post_tool_use("Bash", &args, &serde_json::json!({}), 1);
+        assert_eq!(
+            post.block_feedback,
+            Some("tests must pass first".to_string())
+        );
+    }
+
+    #[test]
+    fn test_large_hook_output_is_read() {
+        // Well past a pipe buffer, like a rewritten Bash result
+        let script = "printf '{\"updated_result\":{\"content\":\"'; \
+                      head -c 300000 /dev/zero | tr '\\0' x; \
+                      head -c 100000 /dev/zero | tr '\\0' y >&2; \
+                      printf '\"}}'";
+        let hooks = vec![sh_hook(HookEvent::PostToolUse, None, script)];
+        let manager = HookManager::new(hooks, "s".to_string(), PathBuf::from("."));
+        let args = serde_json::json!({ "command": "ls" });
+        let post = manager.post_tool_use("Bash", &args, &serde_json::json!({}), 1);
+        let content = post.updated_result.unwrap()["content"].clone();
+        assert_eq!(content.as_str().unwrap().len(), 300000);
+    }
+
+    #[test]
+    fn test_prompt_and_session_context() {
+        let hooks = vec![
+            sh_hook(
+                HookEvent::UserPromptSubmit,
+                None,
+                "echo '{\"additional_context\":\"branch: main\"}'",
+            ),
+            sh_hook(
+                HookEvent::SessionStart,
+                None,
+                "echo '{\"additional_context\":\"ticket ABC-1\"}'",
+            ),
+        ];
+        let manager = HookManager::new(hooks, "s".to_string(), PathBuf::from("."));
+        let submitted = manager.user_prompt_submit("fix it");
+        assert!(submitted.proceed);
+        assert_eq!(submitted.updated_prompt, None);
+        assert_eq!(submitted.additional_context, ["branch: main"]);
+        assert_eq!(manager.on_session_start("repl"), ["ticket ABC-1"]);
+    }
+
+    #[test]
+    fn test_rule_matchers_see_arguments() {
+        let hooks = vec![
+            sh_hook(HookEvent::PreToolUse, Some("Bash(git commit:*)"), "exit 2"),
+            sh_hook(HookEvent::PreToolUse, Some("Edit(src/**/*.rs)"), "exit 2"),
+        ];
+        let manager = HookManager::new(hooks, "s".to_string(), PathBuf::from("."));
+        let bash = |command: &str| serde_json::json!({ "command": command });
+
+        assert!(!manager.pre_tool_use("Bash", &bash("git commit -m wip")).0);
+        // Each command of a compound line is matched, as with permissions
+        assert!(
+            !manager
+                .pre_tool_use("Bash", &bash("cargo fmt && git commit -am x"))
+                .0
+        );
+        assert!(manager.pre_tool_use("Bash", &bash("git status")).0);
+        assert!(
+            !manager
+                .pre_tool_use("Edit", &serde_json::json!({ "path": "src/a/b.rs" }))
+                .0
+        );
+        assert!(
+            manager
+                .pre_tool_use("Edit", &serde_json::json!({ "path": "README.md" }))
+                .0
+        );
+        // A regex that looks like a call is still a regex
+        assert!(!tool_filter::is_call_rule("^(Read|Write)$"));
+        assert!(!tool_filter::is_call_rule("Bash(es)?"));
+    }
+
+    #[test]
+    fn test_parallel_hooks_merge_in_config_order() {
+        // The slow first hook's rewrite wins over the fast second one's, any
+        // deny beats an allow, and contexts keep config order
+        let hooks = vec![
+            sh_hook(
+                HookEvent::PostToolUse,
+                None,
+                "sleep 0.3; echo '{\"updated_result\":\"first\",\"additional_context\":\"a\"}'",
+            ),
+            sh_hook(
+                HookEvent::PostToolUse,
+                None,
+                "echo '{\"updated_result\":\"second\",\"additional_context\":\"b\"}'",
+            ),
+            sh_hook(
+                HookEvent::PermissionRequest,
+                None,
+                "echo '{\"decision\":\"allow\"}'",
+            ),
+            sh_hook(
+                HookEvent::PermissionRequest,
+                None,
+                "sleep 0.3; echo '{\"decision\":\"deny\"}'",
+            ),
+        ];
+        let manager = HookManager::new(hooks, "s".to_string(), PathBuf::from("."));
+
+        let start = Instant::now();
+        let post = manager.post_tool_use("Read", &serde_json::json!({}), &Value::Null, 1);
+        assert!(start.elapsed() < Duration::from_millis(550));
+        assert_eq!(post.updated_result, Some(serde_json::json!("first")));
+        assert_eq!(post.additional_context, ["a", "b"]);
+
+        let decision = manager.permission_request("Bash", &serde_json::json!({}), None);
+        assert_eq!(decision.map(|d| d.allow), Some(false));
+
+        let stats = manager.hooks();
+        assert_eq!(stats[0].1.runs, 1);
+        assert_eq!(stats[0].1.last_exit_code, Some(0));
+        assert!(stats[0].1.last_latency_ms >= 300);
+    }
+
+    #[test]
+    fn test_dry_run_uses_sample_input() {
+        let hooks = vec![sh_hook(
+            HookEvent::PreToolUse,
+            None,
+            "grep -q '\"tool_name\":\"Edit\"'",
+        )];
+        let manager = HookManager::new(hooks, "s".to_string(), PathBuf::from("."));
+
+        let (input, result, _) = manager
+            .dry_run(0, Some(serde_json::json!({ "tool_name": "Edit" })))
+            .unwrap();
+        assert_eq!(input["hook_event"], "PreToolUse");
+        assert_eq!(input["tool_args"]["command"], "git status");
+        assert_eq!(result.exit_code, Some(0));
+        // Dry runs are not counted
+        assert_eq!(manager.hooks()[0].1.runs, 0);
+        assert!(manager.dry_run(1, None).is_err());
+    }
 }
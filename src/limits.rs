@@ -0,0 +1,388 @@
+//! Per-session action budgets from `[limits]`.
+//!
+//! Tool calls are counted per turn and per session, in total and by tool or
+//! category; file tools add the files they change and the bytes they write;
+//! and the current turn's wall-clock time is tracked. A call that would go
+//! over a budget is not run: the model gets a `limit_exceeded` error instead.
+//! Subagents share the tracker, so their calls count toward the parent turn.
+
+use crate::config::LimitsConfig;
+use crate::policy::ToolCategory;
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use walkdir::WalkDir;
+
+/// A budget that a call would exceed
+#[derive(Debug, Clone)]
+pub struct LimitExceeded {
+    /// Config key of the budget, e.g. "limits.tool_calls_per_turn.Bash"
+    pub limit: String,
+    pub message: String,
+}
+
+impl LimitExceeded {
+    /// Tool result reporting the exceeded budget to the model
+    pub fn to_result(&self) -> Value {
+        json!({
+            "error": {
+                "code": "limit_exceeded",
+                "message": format!("{} ({}). Stop and summarize what is left to do.", self.message, self.limit)
+            }
+        })
+    }
+}
+
+/// Tracks usage against the configured budgets for one session
+#[derive(Debug)]
+pub struct LimitTracker {
+    config: LimitsConfig,
+    turn_start: Instant,
+    turn_calls: HashMap<String, u64>,
+    session_calls: HashMap<String, u64>,
+    files_modified: BTreeSet<String>,
+    bytes_written: u64,
+}
+
+/// Counter key for calls across all tools
+const ALL_TOOLS: &str = "*";
+
+impl LimitTracker {
+    pub fn new(config: LimitsConfig) -> Self {
+        Self {
+            config,
+            turn_start: Instant::now(),
+            turn_calls: HashMap::new(),
+            session_calls: HashMap::new(),
+            files_modified: BTreeSet::new(),
+            bytes_written: 0,
+        }
+    }
+
+    /// Reset the per-turn counters and clock
+    pub fn start_turn(&mut self) {
+        self.turn_start = Instant::now();
+        self.turn_calls.clear();
+    }
+
+    /// Fails once the turn has run longer than `max_turn_seconds`
+    pub fn check_time(&self) -> Result<(), LimitExceeded> {
+        let Some(max) = self.config.max_turn_seconds else {
+            return Ok(());
+        };
+        if self.turn_start.elapsed() < Duration::from_secs(max) {
+            return Ok(());
+        }
+        Err(LimitExceeded {
+            limit: "limits.max_turn_seconds".to_string(),
+            message: format!("This turn has run for more than {}s", max),
+        })
+    }
+
+    /// Check a call against every budget and, if it fits, count it
+    pub fn check_call(&mut self, tool: &str, args: &Value) -> Result<(), LimitExceeded> {
+        self.check_time()?;
+
+        let keys = self.matching_keys(tool, args);
+        for (scope, counts, budgets, total) in [
+            (
+                "turn",
+                &self.turn_calls,
+                &self.config.tool_calls_per_turn,
+                self.config.max_tool_calls_per_turn,
+            ),
+            (
+                "session",
+                &self.session_calls,
+                &self.config.tool_calls_per_session,
+                self.config.max_tool_calls_per_session,
+            ),
+        ] {
+            for key in &keys {
+                let max = match key.as_str() {
+                    ALL_TOOLS => total,
+                    _ => budgets.get(key).copied(),
+                };
+                let Some(max) = max else { continue };
+                if counts.get(key).copied().unwrap_or(0) >= max {
+                    let (limit, what) = match key.as_str() {
+                        ALL_TOOLS => (
+                            format!("limits.max_tool_calls_per_{}", scope),
+                            "tool calls".to_string(),
+                        ),
+                        _ => (
+                            format!("limits.tool_calls_per_{}.{}", scope, key),
+                            format!("{} calls", key),
+                        ),
+                    };
+                    return Err(LimitExceeded {
+                        limit,
+                        message: format!("Reached {} {} this {}", max, what, scope),
+                    });
+                }
+            }
+        }
+
+        self.check_files(tool, args)?;
+
+        for key in keys {
+            *self.turn_calls.entry(key.clone()).or_default() += 1;
+            *self.session_calls.entry(key).or_default() += 1;
+        }
+        Ok(())
+    }
+
+    /// Counter keys a call counts toward: all tools, plus every configured
+    /// tool pattern or category that matches it
+    fn matching_keys(&self, tool: &str, args: &Value) -> Vec<String> {
+        let category = match ToolCategory::from_tool_call(tool, args["subcommand"].as_str()) {
+            ToolCategory::ReadOnly => "read_only",
+            ToolCategory::Mutation => "mutation",
+            ToolCategory::Execution => "execution",
+        };
+        let mut keys = vec![ALL_TOOLS.to_string()];
+        let configured = self
+            .config
+            .tool_calls_per_turn
+            .keys()
+            .chain(self.config.tool_calls_per_session.keys());
+        for key in configured {
+            let matches = key == category || crate::tool_filter::tool_matches(tool, key, None);
+            if matches && !keys.contains(key) {
+                keys.push(key.clone());
+            }
+        }
+        keys
+    }
+
+    /// Refuse file tool calls once the file or byte budget is used up, or
+    /// when a Write would go over it
+    fn check_files(&self, tool: &str, args: &Value) -> Result<(), LimitExceeded> {
+        if let Some(max) = self.config.max_files_modified {
+            let new_files = target_paths(tool, args)
+                .into_iter()
+                .filter(|p| !self.files_modified.contains(p))
+                .count() as u64;
+            if new_files > 0 && self.files_modified.len() as u64 + new_files > max {
+                return Err(LimitExceeded {
+                    limit: "limits.max_files_modified".to_string(),
+                    message: format!("Reached {} files modified this session", max),
+                });
+            }
+        }
+        if let Some(max) = self.config.max_bytes_written {
+            let writes = matches!(tool, "Write" | "Edit" | "Patch" | "Copy");
+            let incoming = bytes_to_write(tool, args);
+            if writes && (self.bytes_written >= max || self.bytes_written + incoming > max) {
+                return Err(LimitExceeded {
+                    limit: "limits.max_bytes_written".to_string(),
+                    message: format!(
+                        "Writing would exceed {} bytes this session ({} written so far)",
+                        max, self.bytes_written
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Account for the files a successful call changed and the bytes it wrote
+    pub fn record_result(&mut self, tool: &str, args: &Value, result: &Value, root: &Path) {
+        if result.get("error").is_some() {
+            return;
+        }
+        let mut paths = target_paths(tool, args);
+        if tool == "Patch" {
+            let files = result["files"].as_array().into_iter().flatten();
+            paths.extend(files.filter_map(|f| f["path"].as_str()).map(normalize));
+        }
+
+        self.bytes_written += match tool {
+            "Copy" => paths.iter().map(|path| disk_size(&root.join(path))).sum(),
+            "Patch" if result["dry_run"].as_bool() == Some(true) => 0,
+            _ => bytes_to_write(tool, args),
+        };
+        self.files_modified.extend(paths);
+    }
+}
+
+/// Paths a file tool changes, as given in its arguments (Patch paths come
+/// from its result)
+fn target_paths(tool: &str, args: &Value) -> Vec<String> {
+    let keys: &[&str] = match tool {
+        "Write" | "Edit" | "Delete" => &["path"],
+        "Move" => &["source", "destination"],
+        "Copy" => &["destination"],
+        _ => &[],
+    };
+    keys.iter()
+        .filter_map(|key| args[key].as_str())
+        .map(normalize)
+        .collect()
+}
+
+/// Bytes a Write, Edit or Patch call puts into files: the new content, the
+/// replacement text of each edit, or the added lines of a patch
+fn bytes_to_write(tool: &str, args: &Value) -> u64 {
+    match tool {
+        "Write" => args["content"].as_str().map_or(0, |c| c.len() as u64),
+        "Edit" => {
+            let edits = args["edits"].as_array().into_iter().flatten();
+            edits
+                .map(|edit| {
+                    let replace = edit["replace"].as_str().map_or(0, str::len) as u64;
+                    replace * edit["count"].as_u64().unwrap_or(1).max(1)
+                })
+                .sum()
+        }
+        "Patch" => {
+            let patch = args["patch"].as_str().unwrap_or("");
+            patch
+                .lines()
+                .filter(|line| !line.starts_with("+++"))
+                .filter_map(|line| line.strip_prefix('+'))
+                .map(|line| line.len() as u64 + 1)
+                .sum()
+        }
+        _ => 0,
+    }
+}
+
+/// A project-relative path with `.` and `..` resolved, so different
+/// spellings of one file count once
+fn normalize(path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+/// Size of a file, or of all files under a directory
+fn disk_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_call_budgets_by_tool_and_category() {
+        let config = LimitsConfig {
+            max_tool_calls_per_session: Some(4),
+            tool_calls_per_turn: BTreeMap::from([
+                ("Bash".to_string(), 2),
+                ("read_only".to_string(), 1),
+            ]),
+            ..Default::default()
+        };
+        let mut limits = LimitTracker::new(config);
+        let bash = json!({ "command": "ls" });
+        assert!(limits.check_call("Bash", &bash).is_ok());
+        assert!(limits.check_call("Bash", &bash).is_ok());
+        let exceeded = limits.check_call("Bash", &bash).unwrap_err();
+        assert_eq!(exceeded.limit, "limits.tool_calls_per_turn.Bash");
+        assert_eq!(exceeded.message, "Reached 2 Bash calls this turn");
+
+        // Git status is read-only, so it counts toward read_only
+        assert!(limits
+            .check_call("Git", &json!({ "subcommand": "status" }))
+            .is_ok());
+        assert!(limits.check_call("Read", &json!({ "path": "a" })).is_err());
+
+        // A new turn resets per-turn counts, but not the session total
+        limits.start_turn();
+        assert!(limits.check_call("Bash", &bash).is_ok());
+        let exceeded = limits.check_call("Bash", &bash).unwrap_err();
+        assert_eq!(exceeded.limit, "limits.max_tool_calls_per_session");
+        assert_eq!(
+            exceeded.to_result()["error"]["code"],
+            json!("limit_exceeded")
+        );
+    }
+
+    #[test]
+    fn test_file_and_byte_budgets() {
+        let dir = TempDir::new().unwrap();
+        let config = LimitsConfig {
+            max_files_modified: Some(2),
+            max_bytes_written: Some(10),
+            ..Default::default()
+        };
+        let mut limits = LimitTracker::new(config);
+
+        let write = |path: &str, content: &str| json!({ "path": path, "content": content });
+        let args = write("a.txt", "123456");
+        assert!(limits.check_call("Write", &args).is_ok());
+        fs::write(dir.path().join("a.txt"), "123456").unwrap();
+        limits.record_result("Write", &args, &json!({ "path": "a.txt" }), dir.path());
+
+        // Would go over the byte budget
+        let exceeded = limits
+            .check_call("Write", &write("b.txt", "12345"))
+            .unwrap_err();
+        assert_eq!(exceeded.limit, "limits.max_bytes_written");
+
+        let args = json!({ "path": "b.txt" });
+        assert!(limits.check_call("Delete", &args).is_ok());
+        limits.record_result("Delete", &args, &json!({ "ok": true }), dir.path());
+        let exceeded = limits
+            .check_call("Delete", &json!({ "path": "d.txt" }))
+            .unwrap_err();
+        assert_eq!(exceeded.limit, "limits.max_files_modified");
+        let args = json!({ "source": "a.txt", "destination": "c.txt" });
+        assert!(limits.check_call("Move", &args).is_err());
+        // Files already counted can still be changed
+        assert!(limits.check_call("Write", &write("./a.txt", "1")).is_ok());
+        assert!(limits
+            .check_call("Write", &write("b.txt/../a.txt", "1"))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_bytes_written_counts_new_text() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("big.txt"), "x".repeat(10_000)).unwrap();
+        let config = LimitsConfig {
+            max_files_modified: Some(1),
+            max_bytes_written: Some(100),
+            ..Default::default()
+        };
+        let mut limits = LimitTracker::new(config);
+
+        // Editing a large file counts only the replacement text
+        let edit = json!({ "path": "./big.txt", "edits": [{ "find": "xx", "replace": "yyyy" }] });
+        assert!(limits.check_call("Edit", &edit).is_ok());
+        limits.record_result("Edit", &edit, &json!({ "applied": 1 }), dir.path());
+        let patch = json!({
+            "patch": "--- a/big.txt\n+++ b/big.txt\n@@ -1 +1 @@\n-x\n+0123456789\n"
+        });
+        assert!(limits.check_call("Patch", &patch).is_ok());
+        let result = json!({ "files": [{ "path": "big.txt" }] });
+        limits.record_result("Patch", &patch, &result, dir.path());
+        assert_eq!(limits.bytes_written, 4 + 11);
+        assert_eq!(limits.files_modified.len(), 1);
+
+        // The budget still refuses a large Write
+        let args = json!({ "path": "big.txt", "content": "z".repeat(90) });
+        let exceeded = limits.check_call("Write", &args).unwrap_err();
+        assert_eq!(exceeded.limit, "limits.max_bytes_written");
+    }
+}
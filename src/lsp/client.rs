@@ -0,0 +1,215 @@
+//! JSON-RPC client for a single language server.
+//!
+//! Messages use LSP's `Content-Length` framing. A reader thread parses incoming
+//! messages onto a channel; requests block on that channel until their response
+//! arrives, answering server-to-client requests and recording
+//! `textDocument/publishDiagnostics` notifications along the way.
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::Child;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// How long to wait for a server to acknowledge `shutdown`
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_millis(500);
+
+pub struct LspClient {
+    writer: Box<dyn Write + Send>,
+    rx: Receiver<Value>,
+    child: Option<Child>,
+    next_id: i64,
+    timeout: Duration,
+    /// Latest diagnostics per document URI
+    diagnostics: HashMap<String, Vec<Value>>,
+    /// URIs whose diagnostics arrived since they were last taken
+    fresh_diagnostics: Vec<String>,
+    closed: bool,
+}
+
+impl LspClient {
+    /// Wrap a server's output and input streams
+    pub fn new<R, W>(reader: R, writer: W, child: Option<Child>, timeout: Duration) -> Self
+    where
+        R: Read + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(reader);
+            while let Some(msg) = read_message(&mut reader) {
+                if tx.send(msg).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            writer: Box::new(writer),
+            rx,
+            child,
+            next_id: 1,
+            timeout,
+            diagnostics: HashMap::new(),
+            fresh_diagnostics: Vec::new(),
+            closed: false,
+        }
+    }
+
+    fn send(&mut self, msg: &Value) -> Result<()> {
+        write_message(&mut self.writer, msg)?;
+        Ok(())
+    }
+
+    /// Send a notification (no response expected)
+    pub fn notify(&mut self, method: &str, params: Value) -> Result<()> {
+        self.send(&json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+    }
+
+    /// Send a request and wait for its result
+    pub fn request(&mut self, method: &str, params: Value) -> Result<Value> {
+        self.request_with_timeout(method, params, self.timeout)
+    }
+
+    fn request_with_timeout(
+        &mut self,
+        method: &str,
+        params: Value,
+        timeout: Duration,
+    ) -> Result<Value> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))?;
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let msg = match self.rx.recv_timeout(remaining) {
+                Ok(msg) => msg,
+                Err(RecvTimeoutError::Timeout) => {
+                    bail!("{} timed out after {}ms", method, timeout.as_millis())
+                }
+                Err(RecvTimeoutError::Disconnected) => bail!("language server exited"),
+            };
+            let is_response = msg.get("method").is_none();
+            if is_response && msg["id"] == json!(id) {
+                if let Some(err) = msg.get("error") {
+                    let message = err["message"].as_str().unwrap_or("unknown error");
+                    return Err(anyhow!("{}: {}", method, message));
+                }
+                return Ok(msg.get("result").cloned().unwrap_or(Value::Null));
+            }
+            self.handle_incoming(msg)?;
+        }
+    }
+
+    /// Process server-initiated messages (notifications and requests)
+    fn handle_incoming(&mut self, msg: Value) -> Result<()> {
+        let Some(method) = msg.get("method").and_then(|m| m.as_str()) else {
+            return Ok(()); // Stale response to a request that timed out
+        };
+
+        if method == "textDocument/publishDiagnostics" {
+            let uri = msg["params"]["uri"].as_str().unwrap_or("").to_string();
+            let items = msg["params"]["diagnostics"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default();
+            self.diagnostics.insert(uri.clone(), items);
+            self.fresh_diagnostics.push(uri);
+            return Ok(());
+        }
+
+        // Server requests need an answer or some servers stall
+        if let Some(id) = msg.get("id").cloned() {
+            let result = match method {
+                "workspace/configuration" => {
+                    let n = msg["params"]["items"].as_array().map_or(0, |a| a.len());
+                    Value::Array(vec![Value::Null; n])
+                }
+                _ => Value::Null,
+            };
+            self.send(&json!({ "jsonrpc": "2.0", "id": id, "result": result }))?;
+        }
+        Ok(())
+    }
+
+    /// Wait up to `wait` for fresh diagnostics for `uri`; falls back to the last known set
+    pub fn diagnostics_for(&mut self, uri: &str, wait: Duration) -> Result<Vec<Value>> {
+        let deadline = Instant::now() + wait;
+        while !self.fresh_diagnostics.iter().any(|u| u == uri) {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            match self.rx.recv_timeout(remaining) {
+                Ok(msg) => self.handle_incoming(msg)?,
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => bail!("language server exited"),
+            }
+        }
+        self.fresh_diagnostics.retain(|u| u != uri);
+        Ok(self.diagnostics.get(uri).cloned().unwrap_or_default())
+    }
+
+    /// Politely stop the server, then make sure the process is gone
+    pub fn shutdown(&mut self) {
+        if self.closed {
+            return;
+        }
+        self.closed = true;
+        if self
+            .request_with_timeout("shutdown", Value::Null, SHUTDOWN_TIMEOUT)
+            .is_ok()
+        {
+            let _ = self.notify("exit", Value::Null);
+        }
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+impl Drop for LspClient {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Read one framed message; None on EOF or a malformed stream
+pub fn read_message<R: BufRead>(reader: &mut R) -> Option<Value> {
+    loop {
+        let mut length = None;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).ok()? == 0 {
+                return None;
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse::<usize>().ok();
+                }
+            }
+        }
+        let length = length?;
+        let mut body = vec![0u8; length];
+        reader.read_exact(&mut body).ok()?;
+        // Skip bodies that aren't JSON rather than dropping the connection
+        if let Ok(msg) = serde_json::from_slice(&body) {
+            return Some(msg);
+        }
+    }
+}
+
+/// Write one framed message
+pub fn write_message<W: Write>(writer: &mut W, msg: &Value) -> std::io::Result<()> {
+    let body = msg.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
@@ -0,0 +1,371 @@
+//! Starts language servers on demand and keeps their view of files current.
+//!
+//! A server is chosen by file extension: configured `[lsp.servers]` entries
+//! first, then the built-in defaults. Servers are spawned lazily on the first
+//! request for one of their files and stay up for the session. Before each
+//! request, open documents are compared with disk and re-sent when they changed
+//! (e.g. after an Edit), so answers never refer to stale text.
+
+use super::client::LspClient;
+use crate::config::{BashEnvConfig, LspConfig, LspServerConfig};
+use anyhow::{anyhow, bail, Context as _, Result};
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+const DEFAULT_TIMEOUT_MS: u64 = 10_000;
+
+/// Servers used for extensions that no configured server claims
+fn builtin_servers() -> Vec<(&'static str, LspServerConfig)> {
+    let server = |command: &str, args: &[&str], extensions: &[&str]| LspServerConfig {
+        command: command.to_string(),
+        args: args.iter().map(|s| s.to_string()).collect(),
+        extensions: extensions.iter().map(|s| s.to_string()).collect(),
+        language_id: None,
+        initialization_options: None,
+    };
+    vec![
+        ("rust-analyzer", server("rust-analyzer", &[], &["rs"])),
+        (
+            "pyright",
+            server("pyright-langserver", &["--stdio"], &["py", "pyi"]),
+        ),
+        (
+            "typescript-language-server",
+            server(
+                "typescript-language-server",
+                &["--stdio"],
+                &["ts", "tsx", "js", "jsx", "mjs", "cjs"],
+            ),
+        ),
+    ]
+}
+
+/// languageId for a file, from the server config or the extension
+fn language_id(server: &LspServerConfig, ext: &str) -> String {
+    if let Some(id) = &server.language_id {
+        return id.clone();
+    }
+    match ext {
+        "rs" => "rust",
+        "py" | "pyi" => "python",
+        "ts" => "typescript",
+        "tsx" => "typescriptreact",
+        "js" | "mjs" | "cjs" => "javascript",
+        "jsx" => "javascriptreact",
+        other => other,
+    }
+    .to_string()
+}
+
+struct OpenDoc {
+    server: String,
+    version: i64,
+    text: String,
+}
+
+pub struct LspManager {
+    root: PathBuf,
+    config: LspConfig,
+    env: BashEnvConfig,
+    clients: HashMap<String, LspClient>,
+    /// Servers that failed to start, so we don't retry on every call
+    failed: HashMap<String, String>,
+    open_docs: HashMap<String, OpenDoc>,
+}
+
+impl LspManager {
+    pub fn new(config: LspConfig, root: PathBuf, env: BashEnvConfig) -> Self {
+        Self {
+            root,
+            config,
+            env,
+            clients: HashMap::new(),
+            failed: HashMap::new(),
+            open_docs: HashMap::new(),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_millis(self.config.request_timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS))
+    }
+
+    /// The server responsible for a file, by extension
+    fn server_for(&self, path: &Path) -> Option<(String, LspServerConfig)> {
+        let ext = path.extension()?.to_str()?;
+        let mut configured: Vec<_> = self.config.servers.iter().collect();
+        configured.sort_by_key(|(name, _)| *name);
+        configured
+            .into_iter()
+            .map(|(name, s)| (name.clone(), s.clone()))
+            .chain(
+                builtin_servers()
+                    .into_iter()
+                    .map(|(name, s)| (name.to_string(), s)),
+            )
+            .find(|(_, s)| s.extensions.iter().any(|e| e == ext))
+    }
+
+    /// Name of a running server, for requests that aren't tied to a file
+    pub fn any_running(&self) -> Option<String> {
+        let mut names: Vec<&String> = self.clients.keys().collect();
+        names.sort();
+        names.first().map(|n| n.to_string())
+    }
+
+    fn start(&mut self, name: &str, server: &LspServerConfig) -> Result<()> {
+        if self.clients.contains_key(name) {
+            return Ok(());
+        }
+        if let Some(err) = self.failed.get(name) {
+            bail!("{}", err);
+        }
+        match self.spawn(server) {
+            Ok(client) => {
+                self.clients.insert(name.to_string(), client);
+                Ok(())
+            }
+            Err(e) => {
+                let msg = format!("language server '{}' unavailable: {:#}", name, e);
+                self.failed.insert(name.to_string(), msg.clone());
+                bail!("{}", msg)
+            }
+        }
+    }
+
+    fn spawn(&self, server: &LspServerConfig) -> Result<LspClient> {
+        let mut cmd = Command::new(&server.command);
+        cmd.args(&server.args)
+            .current_dir(&self.root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null());
+        crate::child_env::apply(&self.env, &mut cmd);
+        let mut child = cmd
+            .spawn()
+            .with_context(|| format!("failed to run {}", server.command))?;
+        let stdin = child.stdin.take().ok_or_else(|| anyhow!("no stdin"))?;
+        let stdout = child.stdout.take().ok_or_else(|| anyhow!("no stdout"))?;
+        let mut client = LspClient::new(stdout, stdin, Some(child), self.timeout());
+        self.initialize(&mut client, server)?;
+        Ok(client)
+    }
+
+    fn initialize(&self, client: &mut LspClient, server: &LspServerConfig) -> Result<()> {
+        let root_uri = path_to_uri(&self.root);
+        let name = self
+            .root
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mut params = json!({
+            "processId": std::process::id(),
+            "rootUri": root_uri,
+            "rootPath": self.root.to_string_lossy(),
+            "workspaceFolders": [{ "uri": root_uri, "name": name }],
+            "capabilities": {
+                "general": { "positionEncodings": ["utf-16"] },
+                "textDocument": {
+                    "synchronization": { "didSave": false },
+                    "definition": { "linkSupport": true },
+                    "references": {},
+                    "hover": { "contentFormat": ["markdown", "plaintext"] },
+                    "documentSymbol": { "hierarchicalDocumentSymbolSupport": true },
+                    "rename": { "prepareSupport": false },
+                    "publishDiagnostics": {}
+                },
+                "workspace": {
+                    "symbol": {},
+                    "workspaceFolders": true,
+                    "configuration": true
+                }
+            }
+        });
+        if let Some(opts) = &server.initialization_options {
+            params["initializationOptions"] = opts.clone();
+        }
+        client.request("initialize", params)?;
+        client.notify("initialized", json!({}))?;
+        Ok(())
+    }
+
+    /// Open `path` (absolute, inside the root) with its server, or re-sync it,
+    /// and return the server name and document URI
+    pub fn open(&mut self, path: &Path) -> Result<(String, String)> {
+        let (name, server) = self.server_for(path).ok_or_else(|| {
+            anyhow!(
+                "no language server configured for {}",
+                path.extension()
+                    .map(|e| format!(".{} files", e.to_string_lossy()))
+                    .unwrap_or_else(|| "files without an extension".to_string())
+            )
+        })?;
+        self.start(&name, &server)?;
+        self.refresh_open_docs()?;
+
+        let uri = path_to_uri(path);
+        if !self.open_docs.contains_key(&uri) {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("cannot read {}", path.display()))?;
+            let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+            let params = json!({
+                "textDocument": {
+                    "uri": uri,
+                    "languageId": language_id(&server, ext),
+                    "version": 1,
+                    "text": text
+                }
+            });
+            self.client(&name)?.notify("textDocument/didOpen", params)?;
+            self.open_docs.insert(
+                uri.clone(),
+                OpenDoc {
+                    server: name.clone(),
+                    version: 1,
+                    text,
+                },
+            );
+        }
+        Ok((name, uri))
+    }
+
+    /// Re-send any open document whose contents changed on disk
+    fn refresh_open_docs(&mut self) -> Result<()> {
+        let mut changed = Vec::new();
+        for (uri, doc) in &self.open_docs {
+            let Some(path) = uri_to_path(uri) else {
+                continue;
+            };
+            if let Ok(text) = std::fs::read_to_string(&path) {
+                if text != doc.text {
+                    changed.push((uri.clone(), text));
+                }
+            }
+        }
+        for (uri, text) in changed {
+            let Some(doc) = self.open_docs.get_mut(&uri) else {
+                continue;
+            };
+            doc.version += 1;
+            let params = json!({
+                "textDocument": { "uri": uri, "version": doc.version },
+                "contentChanges": [{ "text": text }]
+            });
+            doc.text = text;
+            let server = doc.server.clone();
+            if let Some(client) = self.clients.get_mut(&server) {
+                client.notify("textDocument/didChange", params)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn client(&mut self, name: &str) -> Result<&mut LspClient> {
+        self.clients
+            .get_mut(name)
+            .ok_or_else(|| anyhow!("language server '{}' is not running", name))
+    }
+
+    /// Start the server for `path` without opening it (for workspace queries)
+    pub fn server_name_for(&mut self, path: &Path) -> Result<String> {
+        let (name, server) = self
+            .server_for(path)
+            .ok_or_else(|| anyhow!("no language server configured for {}", path.display()))?;
+        self.start(&name, &server)?;
+        self.refresh_open_docs()?;
+        Ok(name)
+    }
+
+    /// Register an already-connected client (used by tests with a fake server)
+    #[cfg(test)]
+    pub fn insert_client(&mut self, name: &str, mut client: LspClient) -> Result<()> {
+        let server = self
+            .config
+            .servers
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("unknown server {}", name))?;
+        self.initialize(&mut client, &server)?;
+        self.clients.insert(name.to_string(), client);
+        Ok(())
+    }
+}
+
+/// Characters left unescaped in file URIs
+fn is_uri_safe(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~' | b'/')
+}
+
+/// `file://` URI for an absolute path
+pub fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for &b in path.to_string_lossy().as_bytes() {
+        if is_uri_safe(b) {
+            uri.push(b as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", b));
+        }
+    }
+    uri
+}
+
+/// Absolute path for a `file://` URI
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let rest = uri.strip_prefix("file://")?;
+    let bytes = rest.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok()?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Some(PathBuf::from(String::from_utf8(out).ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uri_round_trip() {
+        let path = Path::new("/tmp/my project/src/ünï.rs");
+        let uri = path_to_uri(path);
+        assert_eq!(uri, "file:///tmp/my%20project/src/%C3%BCn%C3%AF.rs");
+        assert_eq!(uri_to_path(&uri).unwrap(), path);
+        assert_eq!(uri_to_path("http://x"), None);
+    }
+
+    #[test]
+    fn test_server_for_prefers_configured() {
+        let mut config = LspConfig::default();
+        config.servers.insert(
+            "ra-nightly".to_string(),
+            LspServerConfig {
+                command: "ra-nightly".to_string(),
+                args: vec![],
+                extensions: vec!["rs".to_string()],
+                language_id: None,
+                initialization_options: None,
+            },
+        );
+        let mgr = LspManager::new(config, PathBuf::from("/p"), BashEnvConfig::default());
+        assert_eq!(
+            mgr.server_for(Path::new("/p/a.rs")).unwrap().0,
+            "ra-nightly"
+        );
+        assert_eq!(mgr.server_for(Path::new("/p/a.py")).unwrap().0, "pyright");
+        assert!(mgr.server_for(Path::new("/p/README")).is_none());
+    }
+}
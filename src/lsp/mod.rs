@@ -0,0 +1,9 @@
+//! Language server integration for the Lsp tool.
+//!
+//! `client` speaks JSON-RPC to one server over stdio; `manager` picks servers
+//! by file extension, starts them lazily and keeps open documents in sync.
+
+pub mod client;
+pub mod manager;
+
+pub use manager::LspManager;
@@ -1,22 +1,31 @@
 mod agent;
 mod backend;
+mod child_env;
 mod cli;
 mod commands;
 mod compact;
 mod config;
 mod cost;
 mod hooks;
+mod limits;
 mod llm;
+mod lsp;
+mod mcp;
 mod model_routing;
 mod plan;
 mod policy;
+mod policy_check;
+mod repo_map;
+mod search_index;
 mod session;
+mod shell_parse;
 mod skillpacks;
 mod subagent;
 mod tool_display;
 mod tool_filter;
 mod tools;
 mod transcript;
+mod transcript_verify;
 mod vendors;
 
 use anyhow::Result;
@@ -103,6 +112,51 @@ pub struct Args {
 
     #[arg(long, help = "Resume a previous session by ID")]
     pub resume: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(clap::Subcommand)]
+pub enum Command {
+    /// Inspect the permission rules
+    Policy {
+        #[command(subcommand)]
+        action: PolicyCommand,
+    },
+    /// Inspect session transcripts
+    Transcript {
+        #[command(subcommand)]
+        action: TranscriptCommand,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum PolicyCommand {
+    /// Show how a tool call would be decided, e.g. `yo policy check Bash 'git push origin main'`
+    Check {
+        /// Tool name (Bash, Edit, Read, Git, mcp.server.tool, ...)
+        tool: String,
+        /// What a rule would match: the command, path, "source -> destination", or JSON arguments
+        arg: Option<String>,
+        /// Check as this subagent (its allowed_tools and clamped mode)
+        #[arg(long)]
+        agent: Option<String>,
+    },
+    /// Report malformed, duplicate and unreachable rules
+    Lint,
+}
+
+#[derive(clap::Subcommand)]
+pub enum TranscriptCommand {
+    /// Check a transcript's hash chain, HMACs and seal, e.g. `yo transcript verify .yo/sessions/<id>.jsonl`
+    Verify {
+        /// Transcript file (.jsonl)
+        file: PathBuf,
+        /// HMAC key file (default: transcript.hmac_key_file from config)
+        #[arg(long)]
+        key: Option<PathBuf>,
+    },
 }
 
 fn main() -> Result<()> {
@@ -165,6 +219,30 @@ fn main() -> Result<()> {
         return Ok(());
     }
 
+    // Apply CLI permission overrides
+    if let Some(mode_str) = &args.mode {
+        if let Some(mode) = config::PermissionMode::from_str(mode_str) {
+            cfg.permissions.mode = mode;
+        } else {
+            return Err(anyhow::anyhow!(
+                "Invalid permission mode: {}. Use: default, acceptEdits, bypassPermissions",
+                mode_str
+            ));
+        }
+    }
+
+    // Add CLI permission rules
+    cfg.permissions.allow.extend(args.allowed_tools.clone());
+    cfg.permissions.deny.extend(args.disallowed_tools.clone());
+    cfg.permissions.ask.extend(args.ask_tools.clone());
+
+    if let Some(Command::Policy { action }) = &args.command {
+        return policy_check::run(action, &cfg, &args);
+    }
+    if let Some(Command::Transcript { action }) = &args.command {
+        return transcript_verify::run(action, &cfg);
+    }
+
     // Ensure we have at least one backend configured
     if !cfg.has_backends() {
         return Err(anyhow::anyhow!(
@@ -183,23 +261,6 @@ fn main() -> Result<()> {
         ));
     }
 
-    // Apply CLI permission overrides
-    if let Some(mode_str) = &args.mode {
-        if let Some(mode) = config::PermissionMode::from_str(mode_str) {
-            cfg.permissions.mode = mode;
-        } else {
-            return Err(anyhow::anyhow!(
-                "Invalid permission mode: {}. Use: default, acceptEdits, bypassPermissions",
-                mode_str
-            ));
-        }
-    }
-
-    // Add CLI permission rules
-    cfg.permissions.allow.extend(args.allowed_tools.clone());
-    cfg.permissions.deny.extend(args.disallowed_tools.clone());
-    cfg.permissions.ask.extend(args.ask_tools.clone());
-
     // Debug output if requested
     if args.debug {
         eprintln!("[DEBUG] Permission mode: {}", cfg.permissions.mode.as_str());
@@ -218,14 +279,34 @@ fn main() -> Result<()> {
     let session_id = uuid::Uuid::new_v4().to_string();
     let transcript_path = transcripts_dir.join(format!("{}.jsonl", session_id));
     let mut transcript = transcript::Transcript::new(&transcript_path, &session_id, &root)?;
+    if let Some(key_path) = cfg.transcript.hmac_key_path(&root) {
+        transcript = transcript.with_hmac_key(transcript::load_or_create_key(&key_path)?);
+    }
+
+    // Keep backend API keys out of Bash/hook children and tell them who we are
+    cfg.bash.env.scrub = cfg.api_key_env_vars();
+    cfg.bash
+        .env
+        .set
+        .insert("YO_SESSION_ID".to_string(), session_id.clone());
+    cfg.bash.env.set.insert(
+        "YO_PROJECT_DIR".to_string(),
+        root.to_string_lossy().to_string(),
+    );
 
     let trace = args.trace;
     let backends = backend::BackendRegistry::new(&cfg);
 
     // Create policy engine from config
     let print_mode = args.prompt.is_some();
+
+    // Live Bash output is for interactive use; scripts get clean stderr by default
+    if cfg.bash.live_output.is_none() {
+        cfg.bash.live_output = Some(!print_mode);
+    }
     let auto_yes = args.yes;
-    let policy_engine = policy::PolicyEngine::new(cfg.permissions.clone(), print_mode, auto_yes);
+    let policy_engine = policy::PolicyEngine::new(cfg.permissions.clone(), print_mode, auto_yes)
+        .with_root(root.clone());
 
     // Build skill pack index
     let skill_index = skillpacks::SkillIndex::build(&root);
@@ -242,7 +323,9 @@ fn main() -> Result<()> {
     let model_router = model_routing::ModelRouter::new(cfg.model_routing.clone());
 
     // Create hook manager
-    let hook_manager = hooks::HookManager::new(cfg.hooks.clone(), session_id.clone(), root.clone());
+    let hook_manager = hooks::HookManager::new(cfg.hooks.clone(), session_id.clone(), root.clone())
+        .with_env(cfg.bash.env.clone())
+        .with_transcript_path(transcript_path.clone());
 
     // Create cost tracker with pricing from config + Venice API cache
     let mut pricing_table = cost::PricingTable::from_config(&cfg.model_pricing);
@@ -254,6 +337,29 @@ fn main() -> Result<()> {
     // Build command index
     let command_index = commands::CommandIndex::build(&root);
 
+    // Repository map for the system prompt (cached in .yo/)
+    let repo_map = repo_map::prompt_section(&root, &cfg.repo_map, false);
+
+    // Language servers start lazily on the first Lsp call
+    let lsp_manager = lsp::LspManager::new(cfg.lsp.clone(), root.clone(), cfg.bash.env.clone());
+
+    // MCP servers with auto_start connect now; others on /mcp connect
+    let mut mcp_manager = mcp::McpManager::new(cfg.mcp.clone(), root.clone(), cfg.bash.env.clone());
+    for (name, result) in mcp_manager.auto_start() {
+        match result {
+            Ok(tools) => {
+                let _ = transcript.mcp_server(&name, "connected", Some(tools), None);
+            }
+            Err(e) => {
+                eprintln!("Warning: {:#}", e);
+                let _ = transcript.mcp_server(&name, "failed", None, Some(&format!("{:#}", e)));
+            }
+        }
+    }
+
+    // Action budgets from [limits]
+    let limit_tracker = limits::LimitTracker::new(cfg.limits.clone());
+
     let ctx = cli::Context {
         args,
         root,
@@ -273,6 +379,11 @@ fn main() -> Result<()> {
         turn_counter: RefCell::new(0),
         command_index: RefCell::new(command_index),
         todo_state: RefCell::new(tools::todo::TodoState::new()),
+        lsp: RefCell::new(lsp_manager),
+        mcp: RefCell::new(mcp_manager),
+        repo_map: RefCell::new(repo_map),
+        limits: RefCell::new(limit_tracker),
+        session_context: RefCell::new(Vec::new()),
     };
 
     // Fire SessionStart hook
@@ -281,10 +392,21 @@ fn main() -> Result<()> {
     } else {
         "repl"
     };
-    ctx.hooks.borrow().on_session_start(session_mode);
+    let session_context = ctx.hooks.borrow().on_session_start(session_mode);
+    for context in &session_context {
+        let _ = ctx.transcript.borrow_mut().hook_modification(
+            "SessionStart",
+            None,
+            "additional_context",
+            &serde_json::json!(context),
+        );
+    }
+    *ctx.session_context.borrow_mut() = session_context;
 
     if let Some(prompt) = &ctx.args.prompt {
-        cli::run_once(&ctx, prompt)
+        let result = cli::run_once(&ctx, prompt);
+        cli::end_session(&ctx, "other");
+        result
     } else {
         // Handle session resume
         let initial_messages = if let Some(resume_id) = &ctx.args.resume {
@@ -0,0 +1,363 @@
+//! JSON-RPC client for a single MCP server.
+//!
+//! Requests block until their response arrives or the server's timeout
+//! passes. Server-to-client requests that show up in the meantime are
+//! answered (`ping` with an empty result, anything else with "method not
+//! found") so the server never stalls waiting on us. `list_changed`
+//! notifications are remembered until `refresh` fetches the list again.
+
+use super::transport::{NotSent, Transport};
+use anyhow::{anyhow, bail, Result};
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+/// Protocol revision we speak; servers may answer with an older one
+pub const PROTOCOL_VERSION: &str = "2025-03-26";
+
+/// A tool offered by a server
+#[derive(Debug, Clone)]
+pub struct McpTool {
+    pub name: String,
+    pub description: Option<String>,
+    /// JSON Schema for the tool's arguments
+    pub input_schema: Value,
+}
+
+/// A prompt template offered by a server
+#[derive(Debug, Clone)]
+pub struct McpPrompt {
+    pub name: String,
+    pub description: Option<String>,
+    pub arguments: Vec<McpPromptArgument>,
+}
+
+#[derive(Debug, Clone)]
+pub struct McpPromptArgument {
+    pub name: String,
+    pub required: bool,
+}
+
+impl McpPrompt {
+    /// Slash command that runs this prompt
+    pub fn command_name(&self, server: &str) -> String {
+        format!("mcp__{}__{}", server, self.name)
+    }
+
+    /// Usage hint listing the arguments, optional ones in brackets
+    pub fn usage(&self) -> String {
+        self.arguments
+            .iter()
+            .map(|arg| match arg.required {
+                true => format!("<{}>", arg.name),
+                false => format!("[{}]", arg.name),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Arguments for `prompts/get` from a command line: `name=value` sets an
+    /// argument by name, other words fill the remaining ones in order
+    pub fn parse_arguments(&self, input: &str) -> Result<Value> {
+        let words = shell_words::split(input).map_err(|e| anyhow!("invalid arguments: {}", e))?;
+        let mut values = serde_json::Map::new();
+        let mut positional = Vec::new();
+        for word in words {
+            match word.split_once('=') {
+                Some((name, value)) if self.arguments.iter().any(|a| a.name == name) => {
+                    values.insert(name.to_string(), json!(value));
+                }
+                _ => positional.push(word),
+            }
+        }
+        let mut positional = positional.into_iter();
+        for arg in &self.arguments {
+            if values.contains_key(&arg.name) {
+                continue;
+            }
+            match positional.next() {
+                Some(value) => {
+                    values.insert(arg.name.clone(), json!(value));
+                }
+                None if arg.required => bail!("missing argument '{}'", arg.name),
+                None => {}
+            }
+        }
+        if let Some(extra) = positional.next() {
+            bail!("unexpected argument '{}'", extra);
+        }
+        Ok(Value::Object(values))
+    }
+}
+
+/// A resource listed by a server
+#[derive(Debug, Clone)]
+pub struct McpResource {
+    pub uri: String,
+    pub name: String,
+    pub mime_type: Option<String>,
+}
+
+pub struct McpClient {
+    transport: Box<dyn Transport>,
+    next_id: i64,
+    timeout: Duration,
+    /// `serverInfo` from the initialize response
+    pub server_info: Value,
+    /// `capabilities` from the initialize response
+    pub capabilities: Value,
+    pub tools: Vec<McpTool>,
+    pub prompts: Vec<McpPrompt>,
+    tools_changed: bool,
+    prompts_changed: bool,
+}
+
+impl McpClient {
+    pub fn new(transport: Box<dyn Transport>, timeout: Duration) -> Self {
+        Self {
+            transport,
+            next_id: 1,
+            timeout,
+            server_info: Value::Null,
+            capabilities: Value::Null,
+            tools: Vec::new(),
+            prompts: Vec::new(),
+            tools_changed: false,
+            prompts_changed: false,
+        }
+    }
+
+    /// Send a notification (no response expected)
+    pub fn notify(&mut self, method: &str, params: Value) -> Result<()> {
+        self.transport
+            .send(&json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+    }
+
+    /// Send a request and wait for its result. If the transport lost the
+    /// session on the way, initialize again; the request is retried once if
+    /// it never reached the server or is safe to repeat (lists and reads),
+    /// since a tool call whose result was lost may already have run.
+    pub fn request(&mut self, method: &str, params: Value) -> Result<Value> {
+        let result = self.request_once(method, params.clone());
+        let Err(err) = &result else {
+            return result;
+        };
+        if method == "initialize" || !self.transport.reconnect_needed() {
+            return result;
+        }
+        let not_sent = err.downcast_ref::<NotSent>().is_some();
+        self.initialize()?;
+        if not_sent || method.ends_with("/list") || method.ends_with("/read") {
+            return self.request_once(method, params);
+        }
+        bail!(
+            "{}: the connection was lost before the result arrived; not retried since it may have run",
+            method
+        )
+    }
+
+    fn request_once(&mut self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.transport
+            .send(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))?;
+
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let Some(msg) = self.transport.recv(remaining)? else {
+                bail!("{} timed out after {}ms", method, self.timeout.as_millis());
+            };
+            let is_response = msg.get("method").is_none();
+            if is_response && msg["id"] == json!(id) {
+                if let Some(err) = msg.get("error") {
+                    let message = err["message"].as_str().unwrap_or("unknown error");
+                    return Err(anyhow!("{}: {}", method, message));
+                }
+                return Ok(msg.get("result").cloned().unwrap_or(Value::Null));
+            }
+            self.handle_incoming(msg)?;
+        }
+    }
+
+    /// Process server-initiated messages; stale responses are dropped
+    fn handle_incoming(&mut self, msg: Value) -> Result<()> {
+        let Some(method) = msg.get("method").and_then(|m| m.as_str()) else {
+            return Ok(());
+        };
+        match method {
+            "notifications/tools/list_changed" => self.tools_changed = true,
+            "notifications/prompts/list_changed" => self.prompts_changed = true,
+            _ => {}
+        }
+        if let Some(id) = msg.get("id").cloned() {
+            let reply = match method {
+                "ping" => json!({ "jsonrpc": "2.0", "id": id, "result": {} }),
+                _ => json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": { "code": -32601, "message": format!("Method not found: {}", method) }
+                }),
+            };
+            self.transport.send(&reply)?;
+        }
+        Ok(())
+    }
+
+    /// Perform the initialize handshake and fetch the tool list
+    pub fn initialize(&mut self) -> Result<()> {
+        let result = self.request(
+            "initialize",
+            json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": {},
+                "clientInfo": { "name": "yo", "version": env!("CARGO_PKG_VERSION") }
+            }),
+        )?;
+        self.server_info = result["serverInfo"].clone();
+        self.capabilities = result["capabilities"].clone();
+        self.notify("notifications/initialized", json!({}))?;
+        self.transport.initialized();
+        self.tools_changed = false;
+        self.tools = self.list_tools()?;
+        self.prompts_changed = false;
+        self.prompts = self.list_prompts()?;
+        Ok(())
+    }
+
+    /// Handle messages that arrived between requests, then fetch the tool and
+    /// prompt lists again if the server said they changed. Returns whether
+    /// the tool list was fetched.
+    pub fn refresh(&mut self) -> Result<bool> {
+        // A dead connection shows up on the next real request instead
+        while let Ok(Some(msg)) = self.transport.recv(Duration::ZERO) {
+            self.handle_incoming(msg)?;
+        }
+        if self.prompts_changed {
+            self.prompts_changed = false;
+            self.prompts = self.list_prompts()?;
+        }
+        if !self.tools_changed {
+            return Ok(false);
+        }
+        self.tools_changed = false;
+        self.tools = self.list_tools()?;
+        Ok(true)
+    }
+
+    /// Every page of a `*/list` method, following `nextCursor`
+    fn list_all(&mut self, method: &str, key: &str) -> Result<Vec<Value>> {
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let mut result = self.request(method, params)?;
+            if let Value::Array(page) = result[key].take() {
+                items.extend(page);
+            }
+            match result["nextCursor"].as_str() {
+                Some(next) if !next.is_empty() => cursor = Some(next.to_string()),
+                _ => return Ok(items),
+            }
+        }
+    }
+
+    /// All tools the server offers
+    pub fn list_tools(&mut self) -> Result<Vec<McpTool>> {
+        let tools = self.list_all("tools/list", "tools")?;
+        Ok(tools
+            .iter()
+            .filter_map(|tool| {
+                Some(McpTool {
+                    name: tool["name"].as_str()?.to_string(),
+                    description: tool["description"].as_str().map(String::from),
+                    input_schema: match &tool["inputSchema"] {
+                        Value::Object(_) => tool["inputSchema"].clone(),
+                        _ => json!({ "type": "object" }),
+                    },
+                })
+            })
+            .collect())
+    }
+
+    /// All prompts the server offers; none if it has no prompts capability
+    pub fn list_prompts(&mut self) -> Result<Vec<McpPrompt>> {
+        if self.capabilities.get("prompts").is_none() {
+            return Ok(Vec::new());
+        }
+        let prompts = self.list_all("prompts/list", "prompts")?;
+        Ok(prompts
+            .iter()
+            .filter_map(|prompt| {
+                let arguments = prompt["arguments"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|arg| {
+                        Some(McpPromptArgument {
+                            name: arg["name"].as_str()?.to_string(),
+                            required: arg["required"].as_bool().unwrap_or(false),
+                        })
+                    })
+                    .collect();
+                Some(McpPrompt {
+                    name: prompt["name"].as_str()?.to_string(),
+                    description: prompt["description"].as_str().map(String::from),
+                    arguments,
+                })
+            })
+            .collect())
+    }
+
+    /// Fill in a prompt; the result is the server's `GetPromptResult`
+    pub fn get_prompt(&mut self, name: &str, arguments: Value) -> Result<Value> {
+        self.request(
+            "prompts/get",
+            json!({ "name": name, "arguments": arguments }),
+        )
+    }
+
+    /// All resources the server lists; none if it has no resources capability
+    pub fn list_resources(&mut self) -> Result<Vec<McpResource>> {
+        if self.capabilities.get("resources").is_none() {
+            return Ok(Vec::new());
+        }
+        let resources = self.list_all("resources/list", "resources")?;
+        Ok(resources
+            .iter()
+            .filter_map(|resource| {
+                let uri = resource["uri"].as_str()?.to_string();
+                Some(McpResource {
+                    name: resource["name"].as_str().unwrap_or(&uri).to_string(),
+                    uri,
+                    mime_type: resource["mimeType"].as_str().map(String::from),
+                })
+            })
+            .collect())
+    }
+
+    /// Read a resource; the result is the server's `ReadResourceResult`
+    pub fn read_resource(&mut self, uri: &str) -> Result<Value> {
+        self.request("resources/read", json!({ "uri": uri }))
+    }
+
+    /// Call a tool; the result is the server's `CallToolResult`
+    pub fn call_tool(&mut self, name: &str, arguments: Value) -> Result<Value> {
+        self.request(
+            "tools/call",
+            json!({ "name": name, "arguments": arguments }),
+        )
+    }
+
+    pub fn close(&mut self) {
+        self.transport.close();
+    }
+}
+
+impl Drop for McpClient {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
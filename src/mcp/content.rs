@@ -0,0 +1,113 @@
+//! Turning MCP content into text for the model.
+//!
+//! Tool results, prompt messages and resource reads all carry content items
+//! (`text`, `image`, `audio`, `resource`, `resource_link`). Text is kept as
+//! is; anything else is summarized in brackets, since yo only sends text.
+//! This module also finds `@server:uri` resource mentions in user input.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::Value;
+
+/// Text for one content item
+pub fn content_text(item: &Value) -> String {
+    match item["type"].as_str() {
+        Some("text") => item["text"].as_str().unwrap_or("").to_string(),
+        Some("resource") => resource_contents_text(&item["resource"]),
+        Some("resource_link") => format!("[resource: {}]", item["uri"].as_str().unwrap_or("?")),
+        Some(kind) => format!("[{}: {}]", kind, item["mimeType"].as_str().unwrap_or("?")),
+        None => item.to_string(),
+    }
+}
+
+/// Text for one entry of a resource's contents
+fn resource_contents_text(contents: &Value) -> String {
+    match contents["text"].as_str() {
+        Some(text) => text.to_string(),
+        None => format!("[resource: {}]", contents["uri"].as_str().unwrap_or("?")),
+    }
+}
+
+/// Text of a `ReadResourceResult`
+pub fn resource_text(result: &Value) -> String {
+    result["contents"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(resource_contents_text)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Text of a `GetPromptResult`: its messages in order, separated by blank lines
+pub fn prompt_text(result: &Value) -> String {
+    result["messages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|msg| content_text(&msg["content"]))
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+static MENTION: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?:^|\s)@([A-Za-z0-9_-]+):(\S+)").unwrap());
+
+/// `@server:uri` mentions in `input`, for servers `is_server` accepts; each
+/// mention is returned once, in order
+pub fn mentions(input: &str, is_server: impl Fn(&str) -> bool) -> Vec<(String, String)> {
+    let mut found: Vec<(String, String)> = Vec::new();
+    for cap in MENTION.captures_iter(input) {
+        let server = &cap[1];
+        // Sentence punctuation after a mention isn't part of the URI
+        let uri = cap[2].trim_end_matches(['.', ',', ';', ':', '!', '?', ')', '"', '\'']);
+        let mention = (server.to_string(), uri.to_string());
+        if !uri.is_empty() && is_server(server) && !found.contains(&mention) {
+            found.push(mention);
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_mentions() {
+        let input = "compare @docs:file:///guide.md and @docs:file:///api.md, then @docs:file:///guide.md. Mail me@docs:x or @other:y";
+        let found = mentions(input, |s| s == "docs");
+        assert_eq!(
+            found,
+            [
+                ("docs".to_string(), "file:///guide.md".to_string()),
+                ("docs".to_string(), "file:///api.md".to_string()),
+            ]
+        );
+        assert!(mentions("@docs:", |_| true).is_empty());
+    }
+
+    #[test]
+    fn test_prompt_and_resource_text() {
+        let prompt = json!({
+            "messages": [
+                { "role": "user", "content": { "type": "text", "text": "Review this diff" } },
+                { "role": "user", "content": { "type": "resource", "resource": { "uri": "git://diff", "text": "+a" } } },
+                { "role": "user", "content": { "type": "image", "data": "..", "mimeType": "image/png" } }
+            ]
+        });
+        assert_eq!(
+            prompt_text(&prompt),
+            "Review this diff\n\n+a\n\n[image: image/png]"
+        );
+
+        let read = json!({
+            "contents": [
+                { "uri": "file:///a.txt", "text": "alpha" },
+                { "uri": "file:///b.png", "blob": "AAAA", "mimeType": "image/png" }
+            ]
+        });
+        assert_eq!(resource_text(&read), "alpha\n[resource: file:///b.png]");
+    }
+}
@@ -0,0 +1,1011 @@
+//! HTTP transports: Streamable HTTP and the older HTTP+SSE transport.
+//!
+//! With Streamable HTTP every message is POSTed to the server's URL. A
+//! request is answered either with a JSON body or with an event stream that
+//! carries the response (and anything the server sends before it). The
+//! server may hand out an `Mcp-Session-Id` on initialize, which is sent back
+//! on every later call; a 404 for that session means it expired and the
+//! client has to initialize again. A stream that drops before its response
+//! arrives is resumed with `Last-Event-ID`, and after initialize a GET stream
+//! is kept open for messages the server starts on its own.
+//!
+//! The legacy SSE transport opens one event stream up front; its first
+//! `endpoint` event names the URL to POST messages to, and every reply comes
+//! back as a `message` event on the stream. An endpoint on another origin
+//! is refused, since every POST carries the configured credentials. When
+//! the stream drops it is reopened, which starts a new session.
+//!
+//! Streams are read on background threads that feed a channel, like the
+//! stdio transport, so `recv` can time out. Connection failures are retried
+//! with exponential backoff; a POST is only retried when it failed before
+//! its body could have reached the server.
+
+use super::transport::{NotSent, Transport};
+use crate::config::McpServerConfig;
+use anyhow::{anyhow, bail, Context as _, Result};
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Retries for a failed connection before giving up
+const MAX_RETRIES: u32 = 5;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long closing a session may take
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+const SESSION_HEADER: &str = "Mcp-Session-Id";
+
+/// Delay before retry `attempt` (from 0): 250ms, doubling up to 8s
+fn backoff(attempt: u32) -> Duration {
+    Duration::from_millis(250 << attempt.min(5))
+}
+
+/// A message from the server, or why the connection failed
+type Incoming = Result<Value>;
+
+/// Request headers for `server`: the configured headers with `${VAR}`
+/// expanded, plus the bearer token
+fn request_headers(server: &McpServerConfig) -> Result<Vec<(String, String)>> {
+    let mut headers = Vec::new();
+    for (name, value) in &server.headers {
+        let value = expand_env(value).with_context(|| format!("header {}", name))?;
+        headers.push((name.clone(), value));
+    }
+    if let Some(var) = &server.bearer_token_env {
+        let token =
+            std::env::var(var).map_err(|_| anyhow!("environment variable {} is not set", var))?;
+        headers.push(("Authorization".to_string(), format!("Bearer {}", token)));
+    }
+    headers.sort();
+    Ok(headers)
+}
+
+/// Replace each `${VAR}` in `value` with the variable's value
+fn expand_env(value: &str) -> Result<String> {
+    let mut out = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        let var = &rest[start + 2..start + 2 + len];
+        let value =
+            std::env::var(var).map_err(|_| anyhow!("environment variable {} is not set", var))?;
+        out.push_str(&rest[..start]);
+        out.push_str(&value);
+        rest = &rest[start + 3 + len..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Resolve `reference` (absolute, host-relative or path-relative) against `base`
+fn resolve_url(base: &str, reference: &str) -> String {
+    if reference.starts_with("http://") || reference.starts_with("https://") {
+        return reference.to_string();
+    }
+    let authority_end = base
+        .find("://")
+        .and_then(|i| base[i + 3..].find('/').map(|j| i + 3 + j))
+        .unwrap_or(base.len());
+    if reference.starts_with('/') {
+        return format!("{}{}", &base[..authority_end], reference);
+    }
+    let path = &base[authority_end..];
+    let path = &path[..path.find(['?', '#']).unwrap_or(path.len())];
+    let dir = &path[..path.rfind('/').map(|i| i + 1).unwrap_or(0)];
+    let dir = if dir.is_empty() { "/" } else { dir };
+    format!("{}{}{}", &base[..authority_end], dir, reference)
+}
+
+/// Scheme, host and port of a URL, with the scheme's default port filled in
+fn origin(url: &str) -> Option<(String, String, u16)> {
+    let (scheme, rest) = url.split_once("://")?;
+    let scheme = scheme.to_ascii_lowercase();
+    let authority = &rest[..rest.find(['/', '?', '#']).unwrap_or(rest.len())];
+    let host_port = authority.rsplit_once('@').map_or(authority, |(_, h)| h);
+    let (host, port) = match host_port.rsplit_once(':') {
+        Some((host, port)) if !host_port.ends_with(']') => (host, port.parse().ok()?),
+        _ => {
+            let port = match scheme.as_str() {
+                "http" => 80,
+                "https" => 443,
+                _ => return None,
+            };
+            (host_port, port)
+        }
+    };
+    Some((scheme, host.to_ascii_lowercase(), port))
+}
+
+fn agent() -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout_connect(CONNECT_TIMEOUT)
+        .build()
+}
+
+/// Whether a failed call never reached the server (no connection was made),
+/// so it is safe to send again
+fn before_send(err: &ureq::Error) -> bool {
+    use ureq::ErrorKind::*;
+    match err {
+        ureq::Error::Transport(t) => matches!(
+            t.kind(),
+            Dns | ConnectionFailed | InvalidUrl | UnknownScheme | InvalidProxyUrl | ProxyConnect
+        ),
+        ureq::Error::Status(..) => false,
+    }
+}
+
+/// Describe a failed HTTP call; a failure before sending is `NotSent`
+fn http_error(err: ureq::Error) -> anyhow::Error {
+    let not_sent = before_send(&err);
+    let err = match err {
+        ureq::Error::Status(code, resp) => {
+            let body = resp.into_string().unwrap_or_default();
+            anyhow!("HTTP {}: {}", code, body.trim())
+        }
+        ureq::Error::Transport(t) => anyhow!("{}", t),
+    };
+    if not_sent {
+        return NotSent(err.to_string()).into();
+    }
+    err
+}
+
+/// One server-sent event
+#[derive(Debug, Default, PartialEq)]
+struct SseEvent {
+    event: String,
+    data: String,
+    id: Option<String>,
+}
+
+/// Read an event stream, passing each event to `on_event` until it returns
+/// false or the stream ends
+fn read_events(reader: impl Read, mut on_event: impl FnMut(SseEvent) -> bool) {
+    let mut event = SseEvent::default();
+    let mut has_data = false;
+    for line in BufReader::new(reader).lines() {
+        let Ok(line) = line else { return };
+        if line.is_empty() {
+            if has_data && !on_event(std::mem::take(&mut event)) {
+                return;
+            }
+            event = SseEvent::default();
+            has_data = false;
+            continue;
+        }
+        if line.starts_with(':') {
+            continue;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line.as_str(), ""),
+        };
+        match field {
+            "event" => event.event = value.to_string(),
+            "data" => {
+                if has_data {
+                    event.data.push('\n');
+                }
+                event.data.push_str(value);
+                has_data = true;
+            }
+            "id" => event.id = Some(value.to_string()),
+            _ => {}
+        }
+    }
+}
+
+/// Wait for the next incoming message; shared by both transports
+fn recv_incoming(rx: &Receiver<Incoming>, timeout: Duration) -> Result<Option<Value>> {
+    match rx.recv_timeout(timeout) {
+        Ok(Ok(msg)) => Ok(Some(msg)),
+        Ok(Err(e)) => Err(e),
+        Err(RecvTimeoutError::Timeout) => Ok(None),
+        Err(RecvTimeoutError::Disconnected) => bail!("connection closed"),
+    }
+}
+
+/// State shared between a Streamable HTTP transport and its stream readers
+struct HttpShared {
+    url: String,
+    agent: ureq::Agent,
+    headers: Vec<(String, String)>,
+    session: Mutex<Option<String>>,
+    tx: Mutex<Sender<Incoming>>,
+    closed: AtomicBool,
+    /// The server dropped our session; initialize again
+    session_lost: AtomicBool,
+    listening: AtomicBool,
+}
+
+impl HttpShared {
+    fn request(&self, method: &str) -> ureq::Request {
+        let mut req = self.agent.request(method, &self.url);
+        for (name, value) in &self.headers {
+            req = req.set(name, value);
+        }
+        if let Some(session) = self.session.lock().unwrap().as_ref() {
+            req = req.set(SESSION_HEADER, session);
+        }
+        req
+    }
+
+    fn deliver(&self, msg: Incoming) {
+        let _ = self.tx.lock().unwrap().send(msg);
+    }
+
+    /// POST a message, retrying failures to connect
+    fn post(&self, msg: &Value) -> Result<ureq::Response> {
+        let body = msg.to_string();
+        let mut attempt = 0;
+        loop {
+            let sent_session = self.session.lock().unwrap().is_some();
+            let result = self
+                .request("POST")
+                .set("Accept", "application/json, text/event-stream")
+                .set("Content-Type", "application/json")
+                .send_string(&body);
+            match result {
+                Ok(resp) => {
+                    if let Some(session) = resp.header(SESSION_HEADER) {
+                        *self.session.lock().unwrap() = Some(session.to_string());
+                    }
+                    return Ok(resp);
+                }
+                // The server refused the message, so it did not run
+                Err(ureq::Error::Status(404, _)) if sent_session => {
+                    *self.session.lock().unwrap() = None;
+                    self.session_lost.store(true, Ordering::SeqCst);
+                    return Err(NotSent("MCP session expired".to_string()).into());
+                }
+                Err(e) if before_send(&e) && attempt < MAX_RETRIES => {
+                    std::thread::sleep(backoff(attempt));
+                    attempt += 1;
+                }
+                Err(e) => return Err(http_error(e)),
+            }
+        }
+    }
+
+    /// POST a request and deliver everything the server answers with
+    fn post_request(&self, msg: &Value) -> Result<()> {
+        let resp = self.post(msg)?;
+        let id = &msg["id"];
+        if !resp.content_type().starts_with("text/event-stream") {
+            let body = resp.into_string()?;
+            if body.trim().is_empty() {
+                return Ok(());
+            }
+            match serde_json::from_str(&body).context("invalid JSON response")? {
+                Value::Array(batch) => batch.into_iter().for_each(|m| self.deliver(Ok(m))),
+                msg => self.deliver(Ok(msg)),
+            }
+            return Ok(());
+        }
+
+        // Resume a stream that ends before the response, from the last event seen
+        let mut last_id = None;
+        let mut answered = self.read_stream(resp, Some(id), &mut last_id);
+        let mut attempt = 0;
+        while !answered && !self.closed.load(Ordering::SeqCst) {
+            let Some(event_id) = last_id.clone() else {
+                bail!("stream ended before the response to request {}", id);
+            };
+            if attempt > MAX_RETRIES {
+                bail!("could not resume the stream for request {}", id);
+            }
+            std::thread::sleep(backoff(attempt));
+            attempt += 1;
+            let resp = match self
+                .request("GET")
+                .set("Accept", "text/event-stream")
+                .set("Last-Event-ID", &event_id)
+                .call()
+            {
+                Ok(resp) => resp,
+                Err(ureq::Error::Transport(_)) => continue,
+                Err(e) => return Err(http_error(e)),
+            };
+            answered = self.read_stream(resp, Some(id), &mut last_id);
+        }
+        Ok(())
+    }
+
+    /// Deliver the messages on an event stream; stops after the response to
+    /// `waiting_for` and returns whether it arrived
+    fn read_stream(
+        &self,
+        resp: ureq::Response,
+        waiting_for: Option<&Value>,
+        last_id: &mut Option<String>,
+    ) -> bool {
+        let mut answered = false;
+        read_events(resp.into_reader(), |event| {
+            if event.id.is_some() {
+                *last_id = event.id;
+            }
+            if event.event.is_empty() || event.event == "message" {
+                if let Ok(msg) = serde_json::from_str::<Value>(&event.data) {
+                    let is_response = msg.get("method").is_none();
+                    answered = is_response && Some(&msg["id"]) == waiting_for;
+                    self.deliver(Ok(msg));
+                }
+            }
+            !answered && !self.closed.load(Ordering::SeqCst)
+        });
+        answered
+    }
+
+    /// Keep a GET stream open for server-initiated messages, reconnecting
+    /// when it drops; returns if the server doesn't offer one
+    fn listen(&self) {
+        let mut last_id: Option<String> = None;
+        let mut attempt = 0;
+        while !self.closed.load(Ordering::SeqCst) {
+            let mut req = self.request("GET").set("Accept", "text/event-stream");
+            if let Some(id) = &last_id {
+                req = req.set("Last-Event-ID", id);
+            }
+            match req.call() {
+                Ok(resp) => {
+                    attempt = 0;
+                    self.read_stream(resp, None, &mut last_id);
+                }
+                Err(ureq::Error::Transport(_)) if attempt < MAX_RETRIES => {
+                    std::thread::sleep(backoff(attempt));
+                    attempt += 1;
+                }
+                Err(_) => break,
+            }
+        }
+        self.listening.store(false, Ordering::SeqCst);
+    }
+}
+
+/// The Streamable HTTP transport
+pub struct HttpTransport {
+    shared: Arc<HttpShared>,
+    rx: Receiver<Incoming>,
+}
+
+impl HttpTransport {
+    pub fn connect(server: &McpServerConfig) -> Result<Self> {
+        let url = server.url.clone().ok_or_else(|| anyhow!("no url"))?;
+        let (tx, rx) = mpsc::channel();
+        let shared = HttpShared {
+            url,
+            agent: agent(),
+            headers: request_headers(server)?,
+            session: Mutex::new(None),
+            tx: Mutex::new(tx),
+            closed: AtomicBool::new(false),
+            session_lost: AtomicBool::new(false),
+            listening: AtomicBool::new(false),
+        };
+        Ok(Self {
+            shared: Arc::new(shared),
+            rx,
+        })
+    }
+}
+
+impl Transport for HttpTransport {
+    fn send(&mut self, msg: &Value) -> Result<()> {
+        if self.shared.closed.load(Ordering::SeqCst) {
+            bail!("connection closed");
+        }
+        let is_request = msg.get("method").is_some() && msg.get("id").is_some();
+        if !is_request {
+            // Notifications and responses are acknowledged right away; sending
+            // them in order keeps `initialized` ahead of later requests
+            self.shared.post(msg)?;
+            return Ok(());
+        }
+        // The answer may take as long as the call, so wait for it off-thread
+        let shared = self.shared.clone();
+        let msg = msg.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = shared.post_request(&msg) {
+                shared.deliver(Err(e));
+            }
+        });
+        Ok(())
+    }
+
+    fn recv(&mut self, timeout: Duration) -> Result<Option<Value>> {
+        recv_incoming(&self.rx, timeout)
+    }
+
+    fn initialized(&mut self) {
+        if !self.shared.listening.swap(true, Ordering::SeqCst) {
+            let shared = self.shared.clone();
+            std::thread::spawn(move || shared.listen());
+        }
+    }
+
+    fn reconnect_needed(&mut self) -> bool {
+        self.shared.session_lost.swap(false, Ordering::SeqCst)
+    }
+
+    fn close(&mut self) {
+        if self.shared.closed.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        // Tell the server the session is over; it may not support this
+        if self.shared.session.lock().unwrap().is_some() {
+            let _ = self.shared.request("DELETE").timeout(CLOSE_TIMEOUT).call();
+        }
+    }
+}
+
+impl Drop for HttpTransport {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// State shared between a legacy SSE transport and its stream reader
+struct SseShared {
+    url: String,
+    agent: ureq::Agent,
+    headers: Vec<(String, String)>,
+    /// Where to POST messages, from the stream's `endpoint` event, or why
+    /// that endpoint was refused
+    endpoint: Mutex<Option<std::result::Result<String, String>>>,
+    tx: Mutex<Sender<Incoming>>,
+    closed: AtomicBool,
+    /// The stream dropped; the next one starts a new session
+    reconnected: AtomicBool,
+}
+
+impl SseShared {
+    fn deliver(&self, msg: Incoming) {
+        let _ = self.tx.lock().unwrap().send(msg);
+    }
+
+    /// Read the event stream, reopening it with backoff when it drops
+    fn run(&self) {
+        let mut attempt = 0;
+        while !self.closed.load(Ordering::SeqCst) {
+            let mut req = self.agent.get(&self.url).set("Accept", "text/event-stream");
+            for (name, value) in &self.headers {
+                req = req.set(name, value);
+            }
+            let resp = match req.call() {
+                Ok(resp) => resp,
+                Err(e) => {
+                    if attempt >= MAX_RETRIES {
+                        self.deliver(Err(http_error(e)));
+                        break;
+                    }
+                    std::thread::sleep(backoff(attempt));
+                    attempt += 1;
+                    continue;
+                }
+            };
+            attempt = 0;
+            read_events(resp.into_reader(), |event| {
+                match event.event.as_str() {
+                    "endpoint" => {
+                        // Our headers carry credentials; never send them elsewhere
+                        let endpoint = resolve_url(&self.url, event.data.trim());
+                        if origin(&endpoint) != origin(&self.url) {
+                            let refused = format!(
+                                "refusing endpoint {} on a different origin than {}",
+                                endpoint, self.url
+                            );
+                            *self.endpoint.lock().unwrap() = Some(Err(refused));
+                            return false;
+                        }
+                        *self.endpoint.lock().unwrap() = Some(Ok(endpoint));
+                    }
+                    "" | "message" => {
+                        if let Ok(msg) = serde_json::from_str(&event.data) {
+                            self.deliver(Ok(msg));
+                        }
+                    }
+                    _ => {}
+                }
+                !self.closed.load(Ordering::SeqCst)
+            });
+            let refused = matches!(*self.endpoint.lock().unwrap(), Some(Err(_)));
+            if refused || self.closed.load(Ordering::SeqCst) {
+                break;
+            }
+            // Anything in flight was lost with the stream; fail it so the
+            // client can start over on the new one
+            *self.endpoint.lock().unwrap() = None;
+            self.reconnected.store(true, Ordering::SeqCst);
+            self.deliver(Err(anyhow!("event stream closed by the server")));
+        }
+    }
+
+    /// The POST endpoint, waiting up to `timeout` for the stream to announce it
+    fn endpoint(&self, timeout: Duration) -> Result<String> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match self.endpoint.lock().unwrap().clone() {
+                Some(Ok(endpoint)) => return Ok(endpoint),
+                Some(Err(refused)) => return Err(NotSent(refused).into()),
+                None => {}
+            }
+            if Instant::now() >= deadline {
+                let err = NotSent("the server did not send an endpoint event".to_string());
+                return Err(err.into());
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+/// The legacy HTTP+SSE transport
+pub struct SseTransport {
+    shared: Arc<SseShared>,
+    rx: Receiver<Incoming>,
+    timeout: Duration,
+}
+
+impl SseTransport {
+    /// Open the event stream; `timeout` bounds the wait for its endpoint
+    pub fn connect(server: &McpServerConfig, timeout: Duration) -> Result<Self> {
+        let url = server.url.clone().ok_or_else(|| anyhow!("no url"))?;
+        let (tx, rx) = mpsc::channel();
+        let shared = Arc::new(SseShared {
+            url,
+            agent: agent(),
+            headers: request_headers(server)?,
+            endpoint: Mutex::new(None),
+            tx: Mutex::new(tx),
+            closed: AtomicBool::new(false),
+            reconnected: AtomicBool::new(false),
+        });
+        let reader = shared.clone();
+        std::thread::spawn(move || reader.run());
+        Ok(Self {
+            shared,
+            rx,
+            timeout,
+        })
+    }
+}
+
+impl Transport for SseTransport {
+    fn send(&mut self, msg: &Value) -> Result<()> {
+        if self.shared.closed.load(Ordering::SeqCst) {
+            bail!("connection closed");
+        }
+        let endpoint = self.shared.endpoint(self.timeout)?;
+        let body = msg.to_string();
+        let mut attempt = 0;
+        loop {
+            let mut req = self
+                .shared
+                .agent
+                .post(&endpoint)
+                .set("Content-Type", "application/json");
+            for (name, value) in &self.shared.headers {
+                req = req.set(name, value);
+            }
+            match req.send_string(&body) {
+                Ok(_) => return Ok(()),
+                Err(e) if before_send(&e) && attempt < MAX_RETRIES => {
+                    std::thread::sleep(backoff(attempt));
+                    attempt += 1;
+                }
+                Err(e) => return Err(http_error(e)),
+            }
+        }
+    }
+
+    fn recv(&mut self, timeout: Duration) -> Result<Option<Value>> {
+        recv_incoming(&self.rx, timeout)
+    }
+
+    fn reconnect_needed(&mut self) -> bool {
+        self.shared.reconnected.swap(false, Ordering::SeqCst)
+    }
+
+    fn close(&mut self) {
+        self.shared.closed.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Drop for SseTransport {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::client::McpClient;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::io::Write;
+    use std::net::{TcpListener, TcpStream};
+
+    /// A request received by the mock server; header names are lowercase
+    struct MockRequest {
+        method: String,
+        path: String,
+        headers: HashMap<String, String>,
+        body: Value,
+    }
+
+    /// Serve HTTP/1.1 on a local port, one request per connection; returns
+    /// the base URL
+    fn mock_server<F>(handle: F) -> String
+    where
+        F: Fn(MockRequest, &mut TcpStream) + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = Arc::new(handle);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { break };
+                let handle = handle.clone();
+                std::thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let mut parts = line.split_whitespace();
+                    let method = parts.next().unwrap_or("").to_string();
+                    let path = parts.next().unwrap_or("").to_string();
+                    let mut headers = HashMap::new();
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        let Some((name, value)) = line.trim_end().split_once(": ") else {
+                            break;
+                        };
+                        headers.insert(name.to_lowercase(), value.to_string());
+                    }
+                    let len = headers
+                        .get("content-length")
+                        .and_then(|l| l.parse().ok())
+                        .unwrap_or(0);
+                    let mut body = vec![0; len];
+                    reader.read_exact(&mut body).unwrap();
+                    let body = serde_json::from_slice(&body).unwrap_or(Value::Null);
+                    let req = MockRequest {
+                        method,
+                        path,
+                        headers,
+                        body,
+                    };
+                    handle(req, &mut stream);
+                });
+            }
+        });
+        url
+    }
+
+    fn respond(stream: &mut TcpStream, status: &str, headers: &[(&str, &str)], body: &str) {
+        let mut head = format!("HTTP/1.1 {}\r\n", status);
+        for (name, value) in headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        let _ = write!(
+            stream,
+            "{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            head,
+            body.len(),
+            body
+        );
+    }
+
+    fn start_events(stream: &mut TcpStream) {
+        let _ = write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n"
+        );
+    }
+
+    fn result(id: &Value, result: Value) -> String {
+        json!({ "jsonrpc": "2.0", "id": id, "result": result }).to_string()
+    }
+
+    fn initialize_result() -> Value {
+        json!({ "protocolVersion": "2025-03-26", "capabilities": {}, "serverInfo": { "name": "mock" } })
+    }
+
+    fn tools_result() -> Value {
+        json!({ "tools": [{ "name": "echo", "inputSchema": { "type": "object" } }] })
+    }
+
+    fn server_config(toml: &str) -> McpServerConfig {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn test_sse_parsing_and_urls() {
+        let stream = ": comment\nevent: endpoint\ndata: /messages\n\nid: 4\ndata: {\"a\":\ndata: 1}\n\ndata\n\n";
+        let mut events = Vec::new();
+        read_events(stream.as_bytes(), |e| {
+            events.push(e);
+            true
+        });
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].event, "endpoint");
+        assert_eq!(events[0].data, "/messages");
+        assert_eq!(events[1].id.as_deref(), Some("4"));
+        assert_eq!(events[1].data, "{\"a\":\n1}");
+        assert_eq!(events[2].data, "");
+
+        let base = "http://localhost:8080/mcp/sse?x=1";
+        assert_eq!(
+            resolve_url(base, "/messages?s=1"),
+            "http://localhost:8080/messages?s=1"
+        );
+        assert_eq!(
+            resolve_url(base, "messages"),
+            "http://localhost:8080/mcp/messages"
+        );
+        assert_eq!(resolve_url("http://h", "m"), "http://h/m");
+        assert_eq!(resolve_url(base, "https://other/m"), "https://other/m");
+
+        let origin_of = |url| origin(url).unwrap();
+        assert_eq!(
+            origin_of("HTTP://User@LocalHost/sse"),
+            origin_of("http://localhost:80/messages?s=1")
+        );
+        assert_eq!(origin_of("https://[::1]:8443/x").1, "[::1]");
+        assert_ne!(origin_of(base), origin_of("http://localhost:8081/m"));
+        assert_ne!(origin_of(base), origin_of("https://localhost:8080/m"));
+        assert_ne!(origin_of(base), origin_of("http://evil.example:8080/m"));
+        assert!(origin("localhost/m").is_none());
+
+        std::env::set_var("YO_TEST_MCP_HEADER", "abc");
+        assert_eq!(
+            expand_env("Key ${YO_TEST_MCP_HEADER}!").unwrap(),
+            "Key abc!"
+        );
+        assert!(expand_env("${YO_TEST_MCP_UNSET}").is_err());
+        assert_eq!(expand_env("${unterminated").unwrap(), "${unterminated");
+    }
+
+    #[test]
+    fn test_streamable_http_session_resume_and_reinitialize() {
+        #[derive(Default)]
+        struct State {
+            initializes: usize,
+            calls: usize,
+            ping_answered: bool,
+            resumed_from: Option<String>,
+        }
+        let state = Arc::new(Mutex::new(State::default()));
+        let server_state = state.clone();
+        let url = mock_server(move |req, stream| {
+            let mut state = server_state.lock().unwrap();
+            let session = req.headers.get("mcp-session-id").cloned();
+            assert_eq!(req.headers["authorization"], "Bearer secret-token");
+            assert_eq!(req.headers["x-team"], "core");
+            let id = &req.body["id"];
+            match (req.method.as_str(), req.body["method"].as_str()) {
+                ("POST", Some("initialize")) => {
+                    assert!(session.is_none());
+                    state.initializes += 1;
+                    let session = format!("s{}", state.initializes);
+                    let body = result(id, initialize_result());
+                    let headers = [
+                        ("Content-Type", "application/json"),
+                        (SESSION_HEADER, &session),
+                    ];
+                    respond(stream, "200 OK", &headers, &body);
+                }
+                ("POST", Some("tools/list")) => {
+                    // Ask the client something before answering
+                    start_events(stream);
+                    let ping = json!({ "jsonrpc": "2.0", "id": "p1", "method": "ping" });
+                    let _ = write!(stream, "id: 1\ndata: {}\n\n", ping);
+                    drop(state);
+                    let deadline = Instant::now() + Duration::from_secs(5);
+                    while !server_state.lock().unwrap().ping_answered && Instant::now() < deadline {
+                        std::thread::sleep(Duration::from_millis(10));
+                    }
+                    let _ = write!(stream, "id: 2\ndata: {}\n\n", result(id, tools_result()));
+                }
+                ("POST", Some("tools/call")) => {
+                    state.calls += 1;
+                    match state.calls {
+                        // Drop the stream before the response; it is resumed below
+                        1 => {
+                            start_events(stream);
+                            let note = json!({ "jsonrpc": "2.0", "method": "notifications/message", "params": {} });
+                            let _ = write!(stream, "id: call-{}\ndata: {}\n\n", id, note);
+                        }
+                        // The session expired
+                        2 => respond(stream, "404 Not Found", &[], ""),
+                        _ => {
+                            assert_eq!(session.as_deref(), Some("s2"));
+                            let body = result(
+                                id,
+                                json!({ "content": [{ "type": "text", "text": "fresh" }] }),
+                            );
+                            respond(
+                                stream,
+                                "200 OK",
+                                &[("Content-Type", "application/json")],
+                                &body,
+                            );
+                        }
+                    }
+                }
+                ("POST", _) => {
+                    // Notifications and our answer to the ping
+                    if req.body["id"] == "p1" {
+                        state.ping_answered = true;
+                    }
+                    assert!(session.is_some());
+                    respond(stream, "202 Accepted", &[], "");
+                }
+                ("GET", _) => match req.headers.get("last-event-id") {
+                    Some(last) => {
+                        state.resumed_from = Some(last.clone());
+                        let id: i64 = last.trim_start_matches("call-").parse().unwrap();
+                        let body = result(
+                            &json!(id),
+                            json!({ "content": [{ "type": "text", "text": "resumed" }] }),
+                        );
+                        start_events(stream);
+                        let _ = write!(stream, "id: 9\ndata: {}\n\n", body);
+                    }
+                    // No stream for server-initiated messages
+                    None => respond(stream, "405 Method Not Allowed", &[], ""),
+                },
+                ("DELETE", _) => respond(stream, "200 OK", &[], ""),
+                other => panic!("unexpected request {:?}", other),
+            }
+        });
+
+        std::env::set_var("YO_TEST_MCP_TOKEN", "secret-token");
+        let server = server_config(&format!(
+            "transport = \"http\"\nurl = \"{}/mcp\"\nbearer_token_env = \"YO_TEST_MCP_TOKEN\"\nheaders = {{ X-Team = \"core\" }}",
+            url
+        ));
+        let transport = HttpTransport::connect(&server).unwrap();
+        let mut client = McpClient::new(Box::new(transport), Duration::from_secs(10));
+        client.initialize().unwrap();
+        assert_eq!(client.server_info["name"], "mock");
+        assert_eq!(client.tools.len(), 1);
+        assert!(state.lock().unwrap().ping_answered);
+
+        let first = client.call_tool("echo", json!({})).unwrap();
+        assert_eq!(first["content"][0]["text"], "resumed");
+        assert_eq!(
+            state.lock().unwrap().resumed_from.as_deref(),
+            Some("call-3")
+        );
+
+        let second = client.call_tool("echo", json!({})).unwrap();
+        assert_eq!(second["content"][0]["text"], "fresh");
+        assert_eq!(state.lock().unwrap().initializes, 2);
+        client.close();
+    }
+
+    #[test]
+    fn test_legacy_sse_refuses_foreign_endpoint() {
+        let posted = Arc::new(AtomicBool::new(false));
+        let seen = posted.clone();
+        let other = mock_server(move |_, stream| {
+            seen.store(true, Ordering::SeqCst);
+            respond(stream, "202 Accepted", &[], "");
+        });
+        let url = mock_server(move |_, stream| {
+            start_events(stream);
+            let _ = write!(stream, "event: endpoint\ndata: {}/messages\n\n", other);
+            std::thread::sleep(Duration::from_millis(500));
+        });
+
+        let server = server_config(&format!(
+            "transport = \"sse\"\nurl = \"{}/sse\"\nheaders = {{ Authorization = \"Bearer secret\" }}",
+            url
+        ));
+        let transport = SseTransport::connect(&server, Duration::from_secs(5)).unwrap();
+        let mut client = McpClient::new(Box::new(transport), Duration::from_secs(5));
+        let err = client.initialize().unwrap_err();
+        assert!(err.to_string().contains("different origin"), "{}", err);
+        assert!(!posted.load(Ordering::SeqCst));
+        client.close();
+    }
+
+    #[test]
+    fn test_legacy_sse_transport_reconnects() {
+        #[derive(Default)]
+        struct State {
+            streams: usize,
+            initializes: usize,
+            calls: usize,
+            /// Messages to write to the open event stream
+            outbox: Vec<String>,
+            drop_stream: bool,
+        }
+        let state = Arc::new(Mutex::new(State::default()));
+        let server_state = state.clone();
+        let url = mock_server(move |req, stream| {
+            if req.method == "GET" {
+                let session = {
+                    let mut state = server_state.lock().unwrap();
+                    state.streams += 1;
+                    state.drop_stream = false;
+                    state.streams
+                };
+                start_events(stream);
+                let _ = write!(
+                    stream,
+                    "event: endpoint\ndata: messages?session={}\n\n",
+                    session
+                );
+                loop {
+                    let mut state = server_state.lock().unwrap();
+                    if state.drop_stream {
+                        return;
+                    }
+                    for msg in state.outbox.drain(..) {
+                        let _ = write!(stream, "event: message\ndata: {}\n\n", msg);
+                    }
+                    drop(state);
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+            }
+            let mut state = server_state.lock().unwrap();
+            let session = format!("/sse/messages?session={}", state.streams);
+            assert_eq!(req.path, session);
+            let id = &req.body["id"];
+            match req.body["method"].as_str() {
+                Some("initialize") => {
+                    state.initializes += 1;
+                    state.outbox.push(result(id, initialize_result()));
+                }
+                Some("tools/list") => state.outbox.push(result(id, tools_result())),
+                Some("tools/call") => {
+                    state.calls += 1;
+                    if state.calls == 2 {
+                        // Lose the stream instead of answering
+                        state.drop_stream = true;
+                    } else {
+                        let text = format!("call {}", state.calls);
+                        state.outbox.push(result(
+                            id,
+                            json!({ "content": [{ "type": "text", "text": text }] }),
+                        ));
+                    }
+                }
+                _ => {}
+            }
+            respond(stream, "202 Accepted", &[], "");
+        });
+
+        let server = server_config(&format!("transport = \"sse\"\nurl = \"{}/sse/\"", url));
+        let transport = SseTransport::connect(&server, Duration::from_secs(5)).unwrap();
+        let mut client = McpClient::new(Box::new(transport), Duration::from_secs(10));
+        client.initialize().unwrap();
+        assert_eq!(client.tools[0].name, "echo");
+
+        let first = client.call_tool("echo", json!({})).unwrap();
+        assert_eq!(first["content"][0]["text"], "call 1");
+
+        // The stream drops mid-call: the call may have run, so it fails
+        // rather than running again, and the client starts a new session
+        let err = client.call_tool("echo", json!({})).unwrap_err();
+        assert!(err.to_string().contains("not retried"), "{}", err);
+        let state_now = state.lock().unwrap();
+        assert_eq!(state_now.calls, 2);
+        assert_eq!((state_now.streams, state_now.initializes), (2, 2));
+        drop(state_now);
+
+        let third = client.call_tool("echo", json!({})).unwrap();
+        assert_eq!(third["content"][0]["text"], "call 3");
+        client.close();
+    }
+}
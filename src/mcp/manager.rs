@@ -0,0 +1,349 @@
+//! Connects to the MCP servers configured under `[mcp.servers]`.
+//!
+//! Servers are connected explicitly (`/mcp connect`) or at startup when
+//! `auto_start` is set, and stay connected until disconnected or the session
+//! ends. A server's tools and prompts are listed when it connects, and again
+//! when it reports that they changed. Tools are offered to the model as
+//! `mcp.<server>.<tool>`, prompts to the user as `/mcp__<server>__<prompt>`.
+
+use super::client::{McpClient, McpPrompt, McpResource, McpTool};
+use super::http::{HttpTransport, SseTransport};
+use super::transport::{StdioTransport, Transport};
+use crate::config::{BashEnvConfig, McpConfig, McpServerConfig, McpTransportKind};
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const DEFAULT_TIMEOUT_MS: u64 = 30_000;
+
+/// A configured server and whether it is connected, for `/mcp list`
+#[derive(Debug, Clone)]
+pub struct ServerStatus {
+    pub name: String,
+    pub transport: McpTransportKind,
+    pub enabled: bool,
+    pub auto_start: bool,
+    /// Number of tools, if connected
+    pub tools: Option<usize>,
+}
+
+pub struct McpManager {
+    root: PathBuf,
+    config: McpConfig,
+    env: BashEnvConfig,
+    clients: BTreeMap<String, McpClient>,
+}
+
+impl McpManager {
+    pub fn new(config: McpConfig, root: PathBuf, env: BashEnvConfig) -> Self {
+        Self {
+            root,
+            config,
+            env,
+            clients: BTreeMap::new(),
+        }
+    }
+
+    fn server(&self, name: &str) -> Result<&McpServerConfig> {
+        self.config
+            .servers
+            .get(name)
+            .ok_or_else(|| anyhow!("unknown MCP server '{}'", name))
+    }
+
+    fn timeout(server: &McpServerConfig) -> Duration {
+        Duration::from_millis(server.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS))
+    }
+
+    /// Configured servers by name
+    pub fn list(&self) -> Vec<ServerStatus> {
+        let mut servers: Vec<ServerStatus> = self
+            .config
+            .servers
+            .iter()
+            .map(|(name, server)| ServerStatus {
+                name: name.clone(),
+                transport: server.transport,
+                enabled: server.enabled,
+                auto_start: server.auto_start,
+                tools: self.clients.get(name).map(|c| c.tools.len()),
+            })
+            .collect();
+        servers.sort_by(|a, b| a.name.cmp(&b.name));
+        servers
+    }
+
+    pub fn is_connected(&self, name: &str) -> bool {
+        self.clients.contains_key(name)
+    }
+
+    /// Connect to a server and list its tools; returns the tool count
+    pub fn connect(&mut self, name: &str) -> Result<usize> {
+        if let Some(client) = self.clients.get(name) {
+            return Ok(client.tools.len());
+        }
+        let server = self.server(name)?;
+        if !server.enabled {
+            bail!("MCP server '{}' is disabled", name);
+        }
+        let transport: Box<dyn Transport> = match server.transport {
+            McpTransportKind::Stdio => {
+                Box::new(StdioTransport::spawn(server, &self.root, &self.env)?)
+            }
+            McpTransportKind::Http => Box::new(HttpTransport::connect(server)?),
+            McpTransportKind::Sse => {
+                Box::new(SseTransport::connect(server, Self::timeout(server))?)
+            }
+        };
+        let mut client = McpClient::new(transport, Self::timeout(server));
+        client
+            .initialize()
+            .map_err(|e| anyhow!("MCP server '{}' failed to initialize: {:#}", name, e))?;
+        let count = client.tools.len();
+        self.clients.insert(name.to_string(), client);
+
+        // Refuse a server whose tools would share a function name
+        let prefix = format!("mcp.{}.", name);
+        let clashes: Vec<String> = crate::tools::mcp_dispatch::collisions(self)
+            .into_iter()
+            .filter(|(_, tools)| tools.iter().any(|t| t.starts_with(&prefix)))
+            .map(|(api, tools)| format!("{} ({})", api, tools.join(", ")))
+            .collect();
+        if !clashes.is_empty() {
+            if let Some(mut client) = self.clients.remove(name) {
+                client.close();
+            }
+            bail!(
+                "MCP server '{}' has tool names that collide: {}",
+                name,
+                clashes.join("; ")
+            );
+        }
+        Ok(count)
+    }
+
+    /// Disconnect a server; its tools disappear from the next request on
+    pub fn disconnect(&mut self, name: &str) -> Result<()> {
+        self.server(name)?;
+        let mut client = self
+            .clients
+            .remove(name)
+            .ok_or_else(|| anyhow!("MCP server '{}' is not connected", name))?;
+        client.close();
+        Ok(())
+    }
+
+    /// Connect every enabled server with `auto_start`; returns each attempt
+    pub fn auto_start(&mut self) -> Vec<(String, Result<usize>)> {
+        let mut names: Vec<String> = self
+            .config
+            .servers
+            .iter()
+            .filter(|(_, s)| s.enabled && s.auto_start)
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+        names
+            .into_iter()
+            .map(|name| {
+                let result = self.connect(&name);
+                (name, result)
+            })
+            .collect()
+    }
+
+    /// Tools of a connected server
+    pub fn server_tools(&self, name: &str) -> Result<&[McpTool]> {
+        self.server(name)?;
+        self.clients
+            .get(name)
+            .map(|c| c.tools.as_slice())
+            .ok_or_else(|| anyhow!("MCP server '{}' is not connected", name))
+    }
+
+    /// Tools of every connected server, by server name
+    pub fn tools(&self) -> Vec<(&str, &McpTool)> {
+        self.clients
+            .iter()
+            .flat_map(|(name, client)| client.tools.iter().map(move |t| (name.as_str(), t)))
+            .collect()
+    }
+
+    fn client(&mut self, server: &str) -> Result<&mut McpClient> {
+        self.clients
+            .get_mut(server)
+            .ok_or_else(|| anyhow!("MCP server '{}' is not connected", server))
+    }
+
+    /// Re-list the tools of servers that said their tools changed; returns
+    /// each refreshed server with its new tool count
+    pub fn refresh(&mut self) -> Vec<(String, Result<usize>)> {
+        let mut refreshed = Vec::new();
+        for (name, client) in &mut self.clients {
+            match client.refresh() {
+                Ok(false) => {}
+                Ok(true) => refreshed.push((name.clone(), Ok(client.tools.len()))),
+                Err(e) => refreshed.push((name.clone(), Err(e))),
+            }
+        }
+        refreshed
+    }
+
+    /// Prompts of every connected server, by server name
+    pub fn prompts(&self) -> Vec<(&str, &McpPrompt)> {
+        self.clients
+            .iter()
+            .flat_map(|(name, client)| client.prompts.iter().map(move |p| (name.as_str(), p)))
+            .collect()
+    }
+
+    /// The server and prompt behind a `/mcp__<server>__<prompt>` command
+    pub fn find_prompt(&self, command: &str) -> Option<(String, McpPrompt)> {
+        self.prompts()
+            .into_iter()
+            .find(|(server, prompt)| prompt.command_name(server) == command)
+            .map(|(server, prompt)| (server.to_string(), prompt.clone()))
+    }
+
+    /// Fill in `prompt` on a connected server
+    pub fn get_prompt(&mut self, server: &str, prompt: &str, arguments: Value) -> Result<Value> {
+        let client = self.client(server)?;
+        if !client.prompts.iter().any(|p| p.name == prompt) {
+            bail!("MCP server '{}' has no prompt '{}'", server, prompt);
+        }
+        client.get_prompt(prompt, arguments)
+    }
+
+    /// Resources a connected server lists
+    pub fn resources(&mut self, server: &str) -> Result<Vec<McpResource>> {
+        self.client(server)?.list_resources()
+    }
+
+    /// Read a resource from a connected server
+    pub fn read_resource(&mut self, server: &str, uri: &str) -> Result<Value> {
+        self.client(server)?.read_resource(uri)
+    }
+
+    /// Call `tool` on a connected server
+    pub fn call(&mut self, server: &str, tool: &str, arguments: Value) -> Result<Value> {
+        let client = self.client(server)?;
+        if !client.tools.iter().any(|t| t.name == tool) {
+            bail!("MCP server '{}' has no tool '{}'", server, tool);
+        }
+        client.call_tool(tool, arguments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn echo_config(timeout_ms: u64) -> McpConfig {
+        let script = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/mcp_echo/server.sh");
+        let server: McpServerConfig = toml::from_str(&format!(
+            "command = \"sh\"\nargs = [\"{}\"]\ntimeout_ms = {}",
+            script, timeout_ms
+        ))
+        .unwrap();
+        let mut config = McpConfig::default();
+        config.servers.insert("echo".to_string(), server);
+        config
+    }
+
+    #[test]
+    fn test_connect_list_call_disconnect() {
+        let root = std::env::temp_dir();
+        let mut mgr = McpManager::new(echo_config(5000), root, BashEnvConfig::default());
+        assert!(mgr.call("echo", "echo", Value::Null).is_err());
+
+        // tools/list is paginated: echo on the first page, the rest on the second
+        assert_eq!(mgr.connect("echo").unwrap(), 4);
+        let names: Vec<&str> = mgr.tools().iter().map(|(_, t)| t.name.as_str()).collect();
+        assert_eq!(names, ["echo", "fail", "grow", "hang"]);
+        assert_eq!(mgr.list()[0].tools, Some(4));
+
+        let result = mgr
+            .call("echo", "echo", serde_json::json!({ "text": "hello" }))
+            .unwrap();
+        assert_eq!(result["content"][0]["text"], "hello");
+        assert!(mgr.call("echo", "nope", Value::Null).is_err());
+
+        mgr.disconnect("echo").unwrap();
+        assert!(!mgr.is_connected("echo"));
+        assert!(mgr.disconnect("echo").is_err());
+        assert!(mgr.connect("missing").is_err());
+    }
+
+    #[test]
+    fn test_colliding_tool_names_are_rejected() {
+        let root = std::env::temp_dir();
+        let mut config = echo_config(5000);
+        let server = config.servers.remove("echo").unwrap();
+        // Truncated to 64 characters, every tool name ends up the same
+        let long = "s".repeat(60);
+        config.servers.insert(long.clone(), server);
+        let mut mgr = McpManager::new(config, root, BashEnvConfig::default());
+
+        let err = mgr.connect(&long).unwrap_err();
+        assert!(err.to_string().contains("collide"), "{}", err);
+        assert!(!mgr.is_connected(&long));
+        assert!(mgr.tools().is_empty());
+    }
+
+    #[test]
+    fn test_prompts_resources_and_list_changed() {
+        let root = std::env::temp_dir();
+        let mut mgr = McpManager::new(echo_config(5000), root, BashEnvConfig::default());
+        mgr.connect("echo").unwrap();
+
+        let (server, prompt) = mgr.find_prompt("mcp__echo__review").unwrap();
+        assert_eq!(server, "echo");
+        assert_eq!(prompt.usage(), "<path> [focus]");
+        assert!(prompt.parse_arguments("").is_err());
+        assert!(prompt.parse_arguments("a b c").is_err());
+        let args = prompt
+            .parse_arguments("focus=errors 'src/main.rs'")
+            .unwrap();
+        assert_eq!(
+            args,
+            serde_json::json!({ "path": "src/main.rs", "focus": "errors" })
+        );
+        let result = mgr.get_prompt("echo", "review", args).unwrap();
+        assert_eq!(
+            result["messages"][0]["content"]["text"],
+            "Review src/main.rs"
+        );
+        assert!(mgr.find_prompt("mcp__echo__nope").is_none());
+
+        let resources = mgr.resources("echo").unwrap();
+        assert_eq!(resources[0].uri, "mem://notes");
+        let read = mgr.read_resource("echo", "mem://notes").unwrap();
+        assert_eq!(read["contents"][0]["text"], "remember the milk");
+        assert!(mgr.read_resource("echo", "mem://missing").is_err());
+
+        // Nothing changed yet; a tool call that changes the list is picked up
+        assert!(mgr.refresh().is_empty());
+        mgr.call("echo", "grow", serde_json::json!({})).unwrap();
+        let refreshed = mgr.refresh();
+        assert_eq!(refreshed.len(), 1);
+        assert_eq!(refreshed[0].1.as_ref().unwrap(), &5);
+        assert!(mgr.tools().iter().any(|(_, t)| t.name == "extra"));
+        assert!(mgr.refresh().is_empty());
+    }
+
+    #[test]
+    fn test_request_timeout() {
+        let root = std::env::temp_dir();
+        let mut mgr = McpManager::new(echo_config(300), root, BashEnvConfig::default());
+        mgr.connect("echo").unwrap();
+        let err = mgr.call("echo", "hang", serde_json::json!({})).unwrap_err();
+        assert_eq!(err.to_string(), "tools/call timed out after 300ms");
+        // The connection is still usable afterwards
+        let result = mgr
+            .call("echo", "echo", serde_json::json!({ "text": "still here" }))
+            .unwrap();
+        assert_eq!(result["content"][0]["text"], "still here");
+    }
+}
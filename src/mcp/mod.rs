@@ -0,0 +1,15 @@
+//! Model Context Protocol client for external tool servers.
+//!
+//! `transport` moves JSON-RPC messages to and from a server (over stdio, or
+//! over HTTP with the transports in `http`), `client` speaks the protocol to
+//! one server, and `manager` connects the servers configured under
+//! `[mcp.servers]` and routes tool calls, prompts and resource reads to them.
+//! `content` turns what servers return into text.
+
+pub mod client;
+pub mod content;
+pub mod http;
+pub mod manager;
+pub mod transport;
+
+pub use manager::McpManager;
@@ -0,0 +1,152 @@
+//! Transports carrying JSON-RPC messages between yo and one MCP server.
+//!
+//! A transport only moves whole messages: `send` writes one, `recv` waits for
+//! the next one the server sent. Matching responses to requests is the
+//! client's job. The stdio transport spawns the server and exchanges
+//! newline-delimited JSON over its stdin and stdout; a reader thread feeds
+//! incoming lines onto a channel so `recv` can time out. The HTTP transports
+//! live in `http`.
+
+use crate::config::{BashEnvConfig, McpServerConfig};
+use anyhow::{anyhow, bail, Context as _, Result};
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// How long a server gets to exit after its stdin is closed
+const EXIT_GRACE: Duration = Duration::from_millis(500);
+
+/// A send that failed before the message reached the server, so sending it
+/// again cannot run it twice
+#[derive(Debug)]
+pub struct NotSent(pub String);
+
+impl std::fmt::Display for NotSent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for NotSent {}
+
+pub trait Transport: Send {
+    /// Send one message; fails with `NotSent` when the server cannot have
+    /// seen it
+    fn send(&mut self, msg: &Value) -> Result<()>;
+
+    /// The next message from the server, waiting up to `timeout`; None if
+    /// nothing arrived in time
+    fn recv(&mut self, timeout: Duration) -> Result<Option<Value>>;
+
+    /// Called once the initialize handshake is done
+    fn initialized(&mut self) {}
+
+    /// Whether the server lost the session since the last check, so the
+    /// client must initialize again
+    fn reconnect_needed(&mut self) -> bool {
+        false
+    }
+
+    /// Disconnect and release the server
+    fn close(&mut self);
+}
+
+pub struct StdioTransport {
+    writer: Option<Box<dyn Write + Send>>,
+    rx: Receiver<Value>,
+    child: Option<Child>,
+}
+
+impl StdioTransport {
+    /// Wrap a server's output and input streams
+    pub fn new<R, W>(reader: R, writer: W, child: Option<Child>) -> Self
+    where
+        R: Read + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(reader).lines() {
+                let Ok(line) = line else { break };
+                // Servers may log to stdout; skip anything that isn't JSON
+                let Ok(msg) = serde_json::from_str::<Value>(&line) else {
+                    continue;
+                };
+                if tx.send(msg).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            writer: Some(Box::new(writer)),
+            rx,
+            child,
+        }
+    }
+
+    /// Start the server process for `server`
+    pub fn spawn(server: &McpServerConfig, root: &Path, env: &BashEnvConfig) -> Result<Self> {
+        let cwd = match &server.cwd {
+            Some(cwd) => root.join(cwd),
+            None => root.to_path_buf(),
+        };
+        let mut cmd = Command::new(&server.command);
+        cmd.args(&server.args)
+            .current_dir(&cwd)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null());
+        crate::child_env::apply(env, &mut cmd);
+        cmd.envs(&server.env);
+        let mut child = cmd
+            .spawn()
+            .with_context(|| format!("failed to run {}", server.command))?;
+        let stdin = child.stdin.take().ok_or_else(|| anyhow!("no stdin"))?;
+        let stdout = child.stdout.take().ok_or_else(|| anyhow!("no stdout"))?;
+        Ok(Self::new(stdout, stdin, Some(child)))
+    }
+}
+
+impl Transport for StdioTransport {
+    fn send(&mut self, msg: &Value) -> Result<()> {
+        let Some(writer) = self.writer.as_mut() else {
+            bail!("connection closed");
+        };
+        writeln!(writer, "{}", msg).context("server stopped reading")?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn recv(&mut self, timeout: Duration) -> Result<Option<Value>> {
+        match self.rx.recv_timeout(timeout) {
+            Ok(msg) => Ok(Some(msg)),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => bail!("server exited"),
+        }
+    }
+
+    fn close(&mut self) {
+        // Closing stdin asks the server to exit; kill it if it doesn't
+        self.writer = None;
+        if let Some(mut child) = self.child.take() {
+            let deadline = Instant::now() + EXIT_GRACE;
+            while Instant::now() < deadline {
+                if let Ok(Some(_)) = child.try_wait() {
+                    return;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+impl Drop for StdioTransport {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
@@ -368,7 +368,7 @@ pub fn list_plans(root: &Path) -> Result<Vec<PlanMetadata>> {
     }
 
     // Sort by creation date, newest first
-    plans.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    plans.sort_by_key(|p| std::cmp::Reverse(p.created_at));
     Ok(plans)
 }
 
@@ -491,7 +491,7 @@ fn extract_field(content: &str, prefix: &str) -> Option<String> {
 pub const PLAN_MODE_SYSTEM_PROMPT: &str = r#"You are in PLAN MODE. Create a detailed, executable implementation plan.
 
 ## Available Tools
-You have READ-ONLY access: Read, Glob, Search
+You have READ-ONLY access: Read, ReadMany, Glob, List, Search, CodeSearch
 Use these to explore the codebase and understand existing patterns.
 
 ## Plan Structure
@@ -1,11 +1,15 @@
 //! Policy engine for permission decisions on tool calls.
 //!
 //! This module implements the rule-based permission system with allow/ask/deny rules
-//! and three modes: Default, AcceptEdits, and BypassPermissions.
+//! and three modes: Default, AcceptEdits, and BypassPermissions. Bash command
+//! lines are split into their simple commands and each one is checked.
+//! Paths are normalized before matching, and files that look like secrets
+//! are denied ahead of every rule.
 
-use crate::config::{PermissionMode, PermissionsConfig};
+use crate::config::{PermissionMode, PermissionsConfig, ValidationError};
 use serde_json::Value;
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 
 /// Permission decision result
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -15,12 +19,76 @@ pub enum Decision {
     Ask,
 }
 
+/// Outcome of a permission check
+#[derive(Debug, Clone)]
+pub struct PermissionCheck {
+    pub allowed: bool,
+    pub decision: Decision,
+    pub rule: Option<String>,
+    /// Allow rules the user chose at the prompt ("always allow"); already
+    /// active in this engine, for the caller to persist
+    pub added_rules: Vec<String>,
+    /// What the user asked to tell the model when denying at the prompt
+    pub feedback: Option<String>,
+    /// A PermissionRequest hook answered in place of the prompt
+    pub by_hook: bool,
+}
+
+impl PermissionCheck {
+    /// How the call was decided, as logged to the transcript
+    pub fn outcome(&self) -> &'static str {
+        match (self.decision, self.by_hook, self.allowed) {
+            (Decision::Allow, _, _) => "allowed",
+            (Decision::Deny, _, _) => "denied",
+            (Decision::Ask, true, true) => "hook_allowed",
+            (Decision::Ask, true, false) => "hook_denied",
+            (Decision::Ask, false, true) => "prompted_yes",
+            (Decision::Ask, false, false) => "prompted_no",
+        }
+    }
+}
+
+/// A rule matching one part of a call
+#[derive(Debug, Clone)]
+pub struct RuleMatch {
+    /// The list holding the rule: "built-in", "deny", "ask" or "allow"
+    pub list: &'static str,
+    pub rule: String,
+}
+
+/// How one part of a call (a simple command, a redirection, a path) is decided
+#[derive(Debug, Clone)]
+pub struct PartExplanation {
+    pub tool: String,
+    pub arg: Option<String>,
+    pub decision: Decision,
+    /// Matching rules in evaluation order: the first decides, the rest are shadowed
+    pub matches: Vec<RuleMatch>,
+}
+
+/// Why a call gets the decision it does
+#[derive(Debug, Clone)]
+pub struct Explanation {
+    pub decision: Decision,
+    pub rule: Option<String>,
+    pub parts: Vec<PartExplanation>,
+    /// Adjustments made after the rules were matched
+    pub notes: Vec<String>,
+}
+
+/// The user's answer to a permission prompt
+enum PromptAnswer {
+    Allow,
+    AllowAlways(Vec<String>),
+    Deny(Option<String>),
+}
+
 /// Tool category for default behavior
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ToolCategory {
-    /// Read-only tools: Read, Grep, Glob
+    /// Read-only tools: Read, ReadMany, Grep, Glob, List, read-only Git subcommands
     ReadOnly,
-    /// Mutation tools: Write, Edit
+    /// Mutation tools: Write, Edit, Move, Copy, Delete, MakeDir, Git add/stash
     Mutation,
     /// Execution tools: Bash
     Execution,
@@ -30,23 +98,101 @@ impl ToolCategory {
     /// Determine the category of a tool by name
     pub fn from_tool_name(name: &str) -> Self {
         match name {
-            "Read" | "Grep" | "Glob" | "Search" => ToolCategory::ReadOnly,
-            "Write" | "Edit" => ToolCategory::Mutation,
+            "Read" | "ReadMany" | "Grep" | "Glob" | "List" | "Search" | "CodeSearch" | "Lsp" => {
+                ToolCategory::ReadOnly
+            }
+            "Write" | "Edit" | "Move" | "Copy" | "Delete" | "MakeDir" => ToolCategory::Mutation,
             "Bash" => ToolCategory::Execution,
             _ if name.starts_with("mcp.") => ToolCategory::Execution, // MCP tools require permission
             _ => ToolCategory::Execution, // Unknown tools require permission
         }
     }
+
+    /// Determine the category of a specific call. Git depends on its subcommand,
+    /// which is the first word of its rule argument.
+    pub fn from_tool_call(name: &str, arg: Option<&str>) -> Self {
+        if name != "Git" {
+            return Self::from_tool_name(name);
+        }
+        match arg.and_then(|a| a.split_whitespace().next()) {
+            Some("status" | "diff" | "log" | "show" | "blame") => ToolCategory::ReadOnly,
+            Some("add" | "stash") => ToolCategory::Mutation,
+            _ => ToolCategory::Execution,
+        }
+    }
 }
 
 /// Default deny rules that are always applied
 const DEFAULT_DENY_PATTERNS: &[&str] = &["Bash(curl:*)", "Bash(wget:*)"];
 
+/// Files denied to every tool unless `permissions.secret_files` replaces this
+/// list. Evaluated like .gitignore: the last matching glob wins, and `!glob`
+/// re-allows a file.
+const DEFAULT_SECRET_FILES: &[&str] = &[
+    "**/.env",
+    "**/.env.*",
+    "!**/.env.example",
+    "!**/.env.sample",
+    "!**/.env.template",
+    "**/*.pem",
+    "**/*.key",
+    "**/*.p12",
+    "**/*.pfx",
+    "**/id_rsa*",
+    "**/id_dsa*",
+    "**/id_ecdsa*",
+    "**/id_ed25519*",
+    "**/.ssh/**",
+    "**/.aws/credentials",
+    "**/.netrc",
+    "**/.git-credentials",
+];
+
+/// The secret-file globs in effect, shared with tools that walk the tree
+/// (Grep, Search, CodeSearch) so they skip what the policy would deny
+#[derive(Debug, Clone)]
+pub struct SecretFiles {
+    patterns: Vec<String>,
+}
+
+impl Default for SecretFiles {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl SecretFiles {
+    /// The configured list, or the built-in one
+    pub fn new(configured: Option<&Vec<String>>) -> Self {
+        let patterns = match configured {
+            Some(patterns) => patterns.clone(),
+            None => DEFAULT_SECRET_FILES.iter().map(|p| p.to_string()).collect(),
+        };
+        Self { patterns }
+    }
+
+    /// The glob that covers a normalized, root-relative path, if any
+    pub fn matches(&self, path: &str) -> Option<String> {
+        let mut matched = None;
+        for pattern in &self.patterns {
+            let (negated, glob) = match pattern.strip_prefix('!') {
+                Some(glob) => (true, glob),
+                None => (false, pattern.as_str()),
+            };
+            if crate::tool_filter::glob_matches(glob, path) == Some(true) {
+                matched = (!negated).then(|| pattern.clone());
+            }
+        }
+        matched
+    }
+}
+
 /// The policy engine that makes permission decisions
 pub struct PolicyEngine {
     config: PermissionsConfig,
     print_mode: bool,
     auto_yes: bool,
+    root: Option<PathBuf>,
 }
 
 impl PolicyEngine {
@@ -56,9 +202,17 @@ impl PolicyEngine {
             config,
             print_mode,
             auto_yes,
+            root: None,
         }
     }
 
+    /// Project root, used to relativize absolute paths before matching and to
+    /// preview file changes in permission prompts
+    pub fn with_root(mut self, root: PathBuf) -> Self {
+        self.root = Some(root);
+        self
+    }
+
     /// Get the current permission mode
     pub fn mode(&self) -> PermissionMode {
         self.config.mode
@@ -80,28 +234,141 @@ impl PolicyEngine {
     }
 
     /// Extract the primary argument for rule matching from tool args
-    /// For Bash: the command string
-    /// For Write/Edit/Read: the path
-    /// For Grep/Glob/Search: the pattern
-    fn extract_tool_arg(tool: &str, args: &Value) -> Option<String> {
+    /// For Bash: the command string (RunTests/Diagnostics: the resolved command)
+    /// For Write/Edit/Read/List/Lsp/Delete/MakeDir: the path
+    /// For Move/Copy: "source -> destination" (rules are checked per path in `decide`)
+    /// For ReadMany: the paths, space-separated (also checked per path)
+    /// For Grep/Glob/Search: the pattern (CodeSearch: the query)
+    /// For Git: the subcommand, then the stash action or paths (e.g. "add src/a.rs")
+    /// Paths are normalized (see `normalize_path`).
+    fn extract_tool_arg(&self, tool: &str, args: &Value) -> Option<String> {
+        let path = |key: &str| {
+            args.get(key)
+                .and_then(|v| v.as_str())
+                .map(|p| self.normalize_path(p))
+        };
         match tool {
-            "Bash" => args
+            "Git" => {
+                let sub = args.get("subcommand").and_then(|v| v.as_str())?;
+                let mut parts = vec![sub.to_string()];
+                if sub == "stash" {
+                    let action = args.get("action").and_then(|v| v.as_str());
+                    parts.push(action.unwrap_or("push").to_string());
+                }
+                if let Some(paths) = args.get("paths").and_then(|v| v.as_array()) {
+                    parts.extend(paths.iter().filter_map(|p| p.as_str()).map(String::from));
+                }
+                Some(parts.join(" "))
+            }
+            "Bash" | "RunTests" | "Diagnostics" => args
                 .get("command")
                 .and_then(|v| v.as_str())
                 .map(String::from),
-            "Write" | "Edit" | "Read" => {
-                args.get("path").and_then(|v| v.as_str()).map(String::from)
+            "Write" | "Edit" | "Read" | "List" | "Lsp" | "Delete" | "MakeDir" => path("path"),
+            "ReadMany" => {
+                let paths: Vec<String> = args
+                    .get("files")?
+                    .as_array()?
+                    .iter()
+                    .filter_map(|f| f.get("path").and_then(|v| v.as_str()))
+                    .map(|p| self.normalize_path(p))
+                    .collect();
+                Some(paths.join(" "))
             }
+            "Move" | "Copy" => Some(format!("{} -> {}", path("source")?, path("destination")?)),
             "Grep" | "Glob" | "Search" => args
                 .get("pattern")
                 .and_then(|v| v.as_str())
                 .map(String::from),
+            "CodeSearch" => args.get("query").and_then(|v| v.as_str()).map(String::from),
             _ => None,
         }
     }
 
+    /// Lexically normalize a path so rules see one spelling of it: "./src//a/../b.rs"
+    /// becomes "src/b.rs", and absolute paths inside the project become relative.
+    /// Symlinks are not resolved.
+    fn normalize_path(&self, path: &str) -> String {
+        let relative = self
+            .root
+            .as_ref()
+            .and_then(|root| Path::new(path).strip_prefix(root).ok())
+            .map(|rel| rel.to_string_lossy().to_string());
+        let path = relative.as_deref().unwrap_or(path);
+
+        let absolute = path.starts_with('/');
+        let mut parts: Vec<&str> = Vec::new();
+        for part in path.split('/') {
+            match part {
+                "" | "." => {}
+                ".." if parts.last().is_some_and(|p| *p != "..") => {
+                    parts.pop();
+                }
+                ".." if absolute => {}
+                _ => parts.push(part),
+            }
+        }
+        let joined = parts.join("/");
+        if absolute {
+            format!("/{}", joined)
+        } else if joined.is_empty() {
+            ".".to_string()
+        } else {
+            joined
+        }
+    }
+
+    /// The secret-file globs in effect
+    pub fn secret_files(&self) -> SecretFiles {
+        SecretFiles::new(self.config.secret_files.as_ref())
+    }
+
+    /// The secret-file glob that covers `path`, if any
+    fn secret_match(&self, path: &str) -> Option<String> {
+        let path = self.normalize_path(path);
+        self.secret_files().matches(path.trim_start_matches('/'))
+    }
+
+    /// Paths a call reads or writes directly, for the secret-file check
+    fn touched_paths<'a>(tool: &str, args: &'a Value) -> Vec<&'a str> {
+        let keys: &[&str] = match tool {
+            "Read" | "Write" | "Edit" | "Lsp" | "Delete" | "Search" => &["path"],
+            "Move" | "Copy" => &["source", "destination"],
+            "ReadMany" => {
+                let files = args["files"].as_array().into_iter().flatten();
+                return files.filter_map(|f| f["path"].as_str()).collect();
+            }
+            "Grep" => {
+                let paths = args["paths"].as_array().into_iter().flatten();
+                return paths.filter_map(|p| p.as_str()).collect();
+            }
+            "Git" => {
+                let paths = args["paths"].as_array().into_iter().flatten();
+                let mut touched: Vec<&str> = paths.filter_map(|p| p.as_str()).collect();
+                // `REV:path` and `:N:path` name a file's blob
+                if let Some((_, path)) = args["ref"].as_str().and_then(|r| r.split_once(':')) {
+                    touched.push(path);
+                    if let Some((stage, path)) = path.split_once(':') {
+                        if stage.len() == 1 && stage.chars().all(|c| c.is_ascii_digit()) {
+                            touched.push(path);
+                        }
+                    }
+                }
+                return touched;
+            }
+            _ => &[],
+        };
+        keys.iter().filter_map(|key| args[key].as_str()).collect()
+    }
+
+    /// Deny result for a secret file
+    fn secret_denial(pattern: String) -> (Decision, Option<String>) {
+        (Decision::Deny, Some(format!("secret_files: {}", pattern)))
+    }
+
     /// Check if a rule pattern matches a tool call
-    /// Pattern format: "ToolName" or "ToolName(prefix:*)" or "mcp.*" or "mcp.server.*"
+    /// Pattern format: "ToolName", "ToolName(arg)", "ToolName(prefix:*)", "ToolName(glob)",
+    /// "ToolName(re:regex)", "ToolName(!pattern)", "mcp.*" or "mcp.server.*"
     fn rule_matches(pattern: &str, tool: &str, arg: Option<&str>) -> bool {
         crate::tool_filter::tool_matches(tool, pattern, arg)
     }
@@ -109,46 +376,89 @@ impl PolicyEngine {
     /// Determine the permission decision for a tool call
     /// Returns (Decision, Option<matched_rule>)
     pub fn decide(&self, tool: &str, args: &Value) -> (Decision, Option<String>) {
-        let arg = Self::extract_tool_arg(tool, args);
-        let arg_ref = arg.as_deref();
+        // Secret files are off limits whatever the rules say
+        for path in Self::touched_paths(tool, args) {
+            if let Some(pattern) = self.secret_match(path) {
+                return Self::secret_denial(pattern);
+            }
+        }
+
+        let arg = self.extract_tool_arg(tool, args);
+        match (tool, arg.as_deref()) {
+            // RunTests and Diagnostics are checked against Bash rules for the command they run
+            ("Bash" | "RunTests" | "Diagnostics", Some(command)) => self.decide_command(command),
+            // Both ends of a transfer must pass, so "Move(src/*)" style rules see each path
+            ("Move" | "Copy", Some(_)) => {
+                let results = ["source", "destination"]
+                    .iter()
+                    .map(|key| args[key].as_str().map(|p| self.normalize_path(p)))
+                    .map(|path| self.decide_single(tool, &[path.as_deref()]))
+                    .collect();
+                Self::combine(results)
+            }
+            // Each file must pass both ReadMany and Read rules
+            ("ReadMany", Some(_)) => {
+                let paths = args["files"].as_array().into_iter().flatten();
+                let results = paths
+                    .map(|f| f["path"].as_str().map(|p| self.normalize_path(p)))
+                    .flat_map(|path| {
+                        [
+                            self.decide_single(tool, &[path.as_deref()]),
+                            self.decide_single("Read", &[path.as_deref()]),
+                        ]
+                    })
+                    .collect();
+                Self::combine(results)
+            }
+            _ => self.decide_single(tool, &[arg.as_deref()]),
+        }
+    }
+
+    /// Decide a single check; `args` lists alternative spellings of the argument.
+    /// Deny and ask rules apply if they match any of them, allow rules only
+    /// if they match the first
+    fn decide_single(&self, tool: &str, args: &[Option<&str>]) -> (Decision, Option<String>) {
+        let matches = |pattern: &str| args.iter().any(|a| Self::rule_matches(pattern, tool, *a));
 
         // 1. Check default deny rules first (highest priority)
         for pattern in DEFAULT_DENY_PATTERNS {
-            if Self::rule_matches(pattern, tool, arg_ref) {
+            if matches(pattern) {
                 return (Decision::Deny, Some(pattern.to_string()));
             }
         }
 
         // 2. Check user deny rules
         for rule in &self.config.deny {
-            if Self::rule_matches(rule, tool, arg_ref) {
+            if matches(rule) {
                 return (Decision::Deny, Some(rule.clone()));
             }
         }
 
         // 3. Check ask rules
         for rule in &self.config.ask {
-            if Self::rule_matches(rule, tool, arg_ref) {
+            if matches(rule) {
                 return (Decision::Ask, Some(rule.clone()));
             }
         }
 
         // 4. Check allow rules
+        let first = args.first().copied().flatten();
         for rule in &self.config.allow {
-            if Self::rule_matches(rule, tool, arg_ref) {
+            if Self::rule_matches(rule, tool, first) {
                 return (Decision::Allow, Some(rule.clone()));
             }
         }
 
         // 5. Apply mode-based defaults
+        let category = ToolCategory::from_tool_call(tool, args.first().copied().flatten());
         let decision = match self.config.mode {
             PermissionMode::BypassPermissions => Decision::Allow,
-            PermissionMode::AcceptEdits => match ToolCategory::from_tool_name(tool) {
+            PermissionMode::AcceptEdits => match category {
                 ToolCategory::ReadOnly => Decision::Allow,
                 ToolCategory::Mutation => Decision::Allow,
                 ToolCategory::Execution => Decision::Ask,
             },
-            PermissionMode::Default => match ToolCategory::from_tool_name(tool) {
+            PermissionMode::Default => match category {
                 ToolCategory::ReadOnly => Decision::Allow,
                 ToolCategory::Mutation => Decision::Ask,
                 ToolCategory::Execution => Decision::Ask,
@@ -158,15 +468,297 @@ impl PolicyEngine {
         (decision, None)
     }
 
+    /// Decide a Bash command line by evaluating every simple command it runs.
+    /// File redirections are checked as Read/Write of their target, and any
+    /// argument or redirection naming a secret file denies the line.
+    fn decide_command(&self, command: &str) -> (Decision, Option<String>) {
+        let commands = match crate::shell_parse::parse(command) {
+            Ok(commands) => commands,
+            Err(_) => {
+                // Can't see every sub-command, so an allow rule is not enough
+                return match self.decide_single("Bash", &[Some(command)]) {
+                    (Decision::Allow, Some(_)) => (Decision::Ask, None),
+                    other => other,
+                };
+            }
+        };
+
+        let mut results = Vec::new();
+        for cmd in &commands {
+            let operands = cmd.words.iter().skip(1).filter(|w| !w.starts_with('-'));
+            let targets = cmd.redirects.iter().filter(|r| r.is_file());
+            for path in operands.chain(targets.map(|r| &r.target)) {
+                if let Some(pattern) = self.secret_match(path) {
+                    return Self::secret_denial(pattern);
+                }
+            }
+
+            if !cmd.words.is_empty() {
+                let text = cmd.text();
+                // A lone command also matches on its original spelling
+                let raw = (commands.len() == 1).then(|| command.trim());
+                results.push(self.decide_single("Bash", &[Some(&text), raw]));
+            }
+            for redirect in cmd.redirects.iter().filter(|r| r.is_file()) {
+                let target = redirect.target.as_str();
+                let path = self.normalize_path(target);
+                if redirect.is_output() {
+                    let mut result = self.decide_single("Write", &[Some(&path)]);
+                    let outside = target.starts_with('/')
+                        || target.starts_with('~')
+                        || target.split('/').any(|c| c == "..");
+                    if outside && result.0 == Decision::Allow {
+                        result = (Decision::Ask, None);
+                    }
+                    results.push(result);
+                } else {
+                    results.push(self.decide_single("Read", &[Some(&path)]));
+                }
+            }
+        }
+
+        if results.is_empty() {
+            return self.decide_single("Bash", &[Some(command)]);
+        }
+        Self::combine(results)
+    }
+
+    /// Combine the decisions for the parts of one call: deny wins over ask,
+    /// and the call is only allowed if every part is
+    fn combine(results: Vec<(Decision, Option<String>)>) -> (Decision, Option<String>) {
+        for wanted in [Decision::Deny, Decision::Ask] {
+            if let Some(result) = results.iter().find(|(d, _)| *d == wanted) {
+                return result.clone();
+            }
+        }
+        let mut rules: Vec<String> = Vec::new();
+        for rule in results.into_iter().filter_map(|(_, r)| r) {
+            if !rules.contains(&rule) {
+                rules.push(rule);
+            }
+        }
+        let rule = (!rules.is_empty()).then(|| rules.join(", "));
+        (Decision::Allow, rule)
+    }
+
+    /// Explain a decision: every part of the call that is checked, with all
+    /// the rules matching it (not just the first)
+    pub fn explain(&self, tool: &str, args: &Value) -> Explanation {
+        let (decision, rule) = self.decide(tool, args);
+        let mut notes = Vec::new();
+        if rule
+            .as_deref()
+            .is_some_and(|r| r.starts_with("secret_files: "))
+        {
+            notes.push("Secret files are denied before any rule is checked".to_string());
+        }
+
+        let parts = self.call_parts(tool, args, &mut notes);
+
+        let parts = parts
+            .into_iter()
+            .map(|(part_tool, arg)| {
+                let decision = self.decide_single(&part_tool, &[arg.as_deref()]).0;
+                let matches = self
+                    .rule_lists()
+                    .into_iter()
+                    .flat_map(|(list, rules)| {
+                        rules
+                            .into_iter()
+                            .filter(|rule| Self::rule_matches(rule, &part_tool, arg.as_deref()))
+                            .map(move |rule| RuleMatch { list, rule })
+                    })
+                    .collect();
+                PartExplanation {
+                    tool: part_tool,
+                    arg,
+                    decision,
+                    matches,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let combined = Self::combine(parts.iter().map(|p| (p.decision, None)).collect()).0;
+        if combined == Decision::Allow
+            && decision == Decision::Ask
+            && notes.is_empty()
+            && !parts.is_empty()
+        {
+            notes.push("Redirections outside the project always ask".to_string());
+        }
+
+        Explanation {
+            decision,
+            rule,
+            parts,
+            notes,
+        }
+    }
+
+    /// The parts of a call that rules are checked against, as (tool, arg):
+    /// each simple command and file redirect of a Bash line, each path of a
+    /// Move/Copy/ReadMany, or the call itself. Adds a note if a command
+    /// cannot be split.
+    fn call_parts(
+        &self,
+        tool: &str,
+        args: &Value,
+        notes: &mut Vec<String>,
+    ) -> Vec<(String, Option<String>)> {
+        let mut parts: Vec<(String, Option<String>)> = Vec::new();
+        match tool {
+            "Bash" | "RunTests" | "Diagnostics" => {
+                let command = self.extract_tool_arg(tool, args);
+                match command.as_deref().map(crate::shell_parse::parse) {
+                    Some(Ok(commands)) => {
+                        for cmd in commands {
+                            if !cmd.words.is_empty() {
+                                parts.push(("Bash".to_string(), Some(cmd.text())));
+                            }
+                            for redirect in cmd.redirects.iter().filter(|r| r.is_file()) {
+                                let part_tool = if redirect.is_output() {
+                                    "Write"
+                                } else {
+                                    "Read"
+                                };
+                                let path = self.normalize_path(&redirect.target);
+                                parts.push((part_tool.to_string(), Some(path)));
+                            }
+                        }
+                    }
+                    Some(Err(_)) => {
+                        notes.push(
+                            "The command could not be split into simple commands, so allow rules only lead to a prompt"
+                                .to_string(),
+                        );
+                        parts.push(("Bash".to_string(), command));
+                    }
+                    None => parts.push((tool.to_string(), None)),
+                }
+            }
+            "Move" | "Copy" => {
+                for key in ["source", "destination"] {
+                    let path = args[key].as_str().map(|p| self.normalize_path(p));
+                    parts.push((tool.to_string(), path));
+                }
+            }
+            "ReadMany" => {
+                for file in args["files"].as_array().into_iter().flatten() {
+                    let path = file["path"].as_str().map(|p| self.normalize_path(p));
+                    parts.push((tool.to_string(), path.clone()));
+                    parts.push(("Read".to_string(), path));
+                }
+            }
+            _ => parts.push((tool.to_string(), self.extract_tool_arg(tool, args))),
+        }
+        parts
+    }
+
+    /// Whether `rule` matches any part of a call, as a permission rule would
+    pub fn matches_call(&self, rule: &str, tool: &str, args: &Value) -> bool {
+        self.call_parts(tool, args, &mut Vec::new())
+            .iter()
+            .any(|(part_tool, arg)| Self::rule_matches(rule, part_tool, arg.as_deref()))
+    }
+
+    /// Rule lists in evaluation order
+    fn rule_lists(&self) -> [(&'static str, Vec<String>); 4] {
+        let builtin = DEFAULT_DENY_PATTERNS
+            .iter()
+            .map(|p| p.to_string())
+            .collect();
+        [
+            ("built-in", builtin),
+            ("deny", self.config.deny.clone()),
+            ("ask", self.config.ask.clone()),
+            ("allow", self.config.allow.clone()),
+        ]
+    }
+
+    /// Find rules that can never decide a call: duplicates, rules a broader
+    /// rule in the same list already covers, and rules an earlier list
+    /// (built-in, deny, ask, then allow) or the secret-file list always beats.
+    /// Malformed rules are skipped; `Config::validate` reports those.
+    pub fn lint(&self) -> Vec<ValidationError> {
+        let lists = self.rule_lists();
+        let mut problems = Vec::new();
+        for (index, (list, rules)) in lists.iter().enumerate().skip(1) {
+            for (i, rule) in rules.iter().enumerate() {
+                if crate::tool_filter::validate_rule(rule).is_err() {
+                    continue;
+                }
+                let mut report = |message: String| {
+                    problems.push(ValidationError {
+                        field: format!("permissions.{}[{}]", list, i),
+                        message,
+                    })
+                };
+
+                if let Some(j) = rules[..i].iter().position(|r| r == rule) {
+                    report(format!("Duplicate of {}[{}] '{}'", list, j, rule));
+                    continue;
+                }
+                let earlier = lists[..index]
+                    .iter()
+                    .flat_map(|(l, rs)| rs.iter().map(move |r| (*l, r)));
+                if let Some((l, r)) = earlier
+                    .into_iter()
+                    .find(|(_, r)| crate::tool_filter::rule_covers(r, rule))
+                {
+                    report(format!(
+                        "Unreachable: {} rule '{}' is checked first and matches every call this does",
+                        l, r
+                    ));
+                    continue;
+                }
+                if let Some(r) = rules
+                    .iter()
+                    .find(|r| *r != rule && crate::tool_filter::rule_covers(r, rule))
+                {
+                    report(format!(
+                        "Redundant: '{}' already matches every call this does",
+                        r
+                    ));
+                    continue;
+                }
+                if let Some(pattern) = self.secret_rule_target(rule) {
+                    report(format!(
+                        "Unreachable: secret_files entry '{}' denies this path",
+                        pattern
+                    ));
+                }
+            }
+        }
+        problems
+    }
+
+    /// For a rule naming one literal path of a file tool, the secret-file
+    /// glob covering that path
+    fn secret_rule_target(&self, rule: &str) -> Option<String> {
+        let open = rule.find('(')?;
+        let tool = &rule[..open];
+        let arg = rule[open + 1..].strip_suffix(')')?;
+        let literal = !arg.contains(['*', '?', '[', '!']) && !arg.starts_with("re:");
+        let file_tool = matches!(
+            tool,
+            "Read" | "ReadMany" | "Write" | "Edit" | "Lsp" | "Delete" | "Move" | "Copy"
+        );
+        if !literal || !file_tool {
+            return None;
+        }
+        self.secret_match(arg)
+    }
+
     /// Check permission and prompt if needed
-    /// Returns true if the action is allowed
-    pub fn check_permission(&self, tool: &str, args: &Value) -> (bool, Decision, Option<String>) {
+    pub fn check_permission(&mut self, tool: &str, args: &Value) -> PermissionCheck {
         let (decision, rule) = self.decide(tool, args);
+        let mut added_rules = Vec::new();
+        let mut feedback = None;
 
         let allowed = match decision {
             Decision::Allow => true,
             Decision::Deny => {
-                let arg = Self::extract_tool_arg(tool, args).unwrap_or_default();
+                let arg = self.extract_tool_arg(tool, args).unwrap_or_default();
                 eprintln!(
                     "Permission denied: {}({}) - denied by policy{}",
                     tool,
@@ -177,15 +769,132 @@ impl PolicyEngine {
                 );
                 false
             }
-            Decision::Ask => self.prompt_user(tool, args),
+            Decision::Ask => match self.prompt_user(tool, args) {
+                PromptAnswer::Allow => true,
+                PromptAnswer::AllowAlways(rules) => {
+                    for rule in &rules {
+                        if !self.config.allow.contains(rule) {
+                            self.config.allow.push(rule.clone());
+                        }
+                    }
+                    added_rules = rules;
+                    true
+                }
+                PromptAnswer::Deny(reason) => {
+                    feedback = reason;
+                    false
+                }
+            },
         };
 
-        (allowed, decision, rule)
+        PermissionCheck {
+            allowed,
+            decision,
+            rule,
+            added_rules,
+            feedback,
+            by_hook: false,
+        }
+    }
+
+    /// Whether an Ask decision shows an interactive prompt (not in -p mode,
+    /// not with --yes)
+    pub fn prompts(&self) -> bool {
+        !self.print_mode && !self.auto_yes
+    }
+
+    /// Allow rules that would let this call through without asking: one per
+    /// part that is not already allowed (each simple command of a Bash line,
+    /// each path of a Move/Copy/ReadMany). With `prefix`, rules cover the
+    /// command prefix or parent directory instead of the exact argument.
+    /// None if no such rules exist, e.g. because an ask rule applies.
+    fn always_rules(&self, tool: &str, args: &Value, prefix: bool) -> Option<Vec<String>> {
+        let mut parts: Vec<(&str, String)> = Vec::new();
+        match tool {
+            "Bash" | "RunTests" | "Diagnostics" => {
+                let command = args.get("command")?.as_str()?;
+                for cmd in crate::shell_parse::parse(command).ok()? {
+                    if !cmd.words.is_empty() {
+                        parts.push(("Bash", cmd.text()));
+                    }
+                    for redirect in cmd.redirects.iter().filter(|r| r.is_file()) {
+                        if redirect.is_output() {
+                            parts.push(("Write", self.normalize_path(&redirect.target)));
+                        }
+                    }
+                }
+            }
+            "Move" | "Copy" => {
+                for key in ["source", "destination"] {
+                    parts.push((tool, self.normalize_path(args.get(key)?.as_str()?)));
+                }
+            }
+            "ReadMany" => {
+                for file in args.get("files")?.as_array()? {
+                    parts.push((tool, self.normalize_path(file.get("path")?.as_str()?)));
+                }
+            }
+            _ => match self.extract_tool_arg(tool, args) {
+                Some(arg) => parts.push((tool, arg)),
+                None => return Some(vec![tool.to_string()]),
+            },
+        }
+
+        let mut rules: Vec<String> = Vec::new();
+        for (part_tool, arg) in parts {
+            if self.decide_single(part_tool, &[Some(&arg)]).0 == Decision::Allow {
+                continue;
+            }
+            let rule = match Self::arg_prefix(part_tool, &arg).filter(|_| prefix) {
+                Some(p) => format!("{}({}:*)", part_tool, p),
+                None => format!("{}({})", part_tool, arg),
+            };
+            if !rules.contains(&rule) {
+                rules.push(rule);
+            }
+        }
+        if rules.is_empty() {
+            return None;
+        }
+
+        // Only offer rules that really allow the call (ask rules win over allow)
+        let mut config = self.config.clone();
+        config.allow.extend(rules.iter().cloned());
+        let mut engine = PolicyEngine::new(config, true, false);
+        engine.root = self.root.clone();
+        (engine.decide(tool, args).0 == Decision::Allow).then_some(rules)
+    }
+
+    /// The part of an argument an "always allow prefix" rule keeps: the
+    /// program and subcommand of a command, the Git subcommand, or the
+    /// parent directory of a path
+    fn arg_prefix(tool: &str, arg: &str) -> Option<String> {
+        match tool {
+            "Bash" => {
+                let words = shell_words::split(arg).ok()?;
+                let program = words.first()?;
+                match words.get(1) {
+                    Some(sub)
+                        if sub
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+                            && !sub.starts_with('-') =>
+                    {
+                        Some(format!("{} {}", program, sub))
+                    }
+                    _ => Some(program.clone()),
+                }
+            }
+            "Git" => arg.split_whitespace().next().map(String::from),
+            _ => arg.rfind('/').map(|i| arg[..=i].to_string()),
+        }
     }
 
     /// Prompt the user for permission
-    fn prompt_user(&self, tool: &str, args: &Value) -> bool {
-        let arg = Self::extract_tool_arg(tool, args).unwrap_or_else(|| "?".to_string());
+    fn prompt_user(&self, tool: &str, args: &Value) -> PromptAnswer {
+        let arg = self
+            .extract_tool_arg(tool, args)
+            .unwrap_or_else(|| "?".to_string());
 
         // In print mode without --yes, deny
         if self.print_mode && !self.auto_yes {
@@ -193,51 +902,87 @@ impl PolicyEngine {
                 "Permission denied: {}({}) - use --yes in -p mode",
                 tool, arg
             );
-            return false;
+            return PromptAnswer::Deny(None);
         }
 
         // With --yes, auto-approve
         if self.auto_yes {
-            return true;
+            return PromptAnswer::Allow;
         }
 
         // Interactive prompt
         println!("Permission required: {}(\"{}\")", tool, arg);
 
-        // Show summary for specific tools
+        // Show what the call will do
         match tool {
-            "Edit" => {
-                if let Some(old) = args.get("old_string").and_then(|v| v.as_str()) {
-                    let preview = if old.len() > 60 {
-                        format!("{}...", &old[..60])
-                    } else {
-                        old.to_string()
-                    };
-                    println!("  Replacing: \"{}\"", preview);
+            "Edit" | "Write" | "Patch" => {
+                let color = io::stdout().is_terminal();
+                let preview = self.root.as_ref().and_then(|root| {
+                    crate::tool_display::format_change_preview(tool, args, root, color)
+                });
+                if let Some(preview) = preview {
+                    println!("{}", preview);
                 }
             }
-            "Bash" => {
+            "Bash" | "RunTests" | "Diagnostics" => {
                 println!("  Command: {}", arg);
             }
-            "Write" => {
-                println!("  Action: create/overwrite file");
+            "Delete" => {
+                if args.get("recursive").and_then(|v| v.as_bool()) == Some(true) {
+                    println!("  Action: delete recursively");
+                } else {
+                    println!("  Action: delete");
+                }
             }
             _ => {}
         }
 
-        print!("Allow? [y/N]: ");
+        let exact = self.always_rules(tool, args, false);
+        let prefix = self
+            .always_rules(tool, args, true)
+            .filter(|rules| Some(rules) != exact.as_ref());
+        let mut choices = String::from("y/N");
+        println!("  y) yes   n) no");
+        if let Some(rules) = &exact {
+            println!("  a) always allow {}", rules.join(", "));
+            choices.push_str("/a");
+        }
+        if let Some(rules) = &prefix {
+            println!("  p) always allow {}", rules.join(", "));
+            choices.push_str("/p");
+        }
+        println!("  d) no, and tell the model why");
+        choices.push_str("/d");
+
+        print!("Allow? [{}]: ", choices);
         io::stdout().flush().ok();
+        let answer = read_line().to_lowercase();
 
-        let mut input = String::new();
-        if io::stdin().read_line(&mut input).is_ok() {
-            let input = input.trim().to_lowercase();
-            input == "y" || input == "yes"
-        } else {
-            false
+        match (answer.as_str(), exact, prefix) {
+            ("y" | "yes", _, _) => PromptAnswer::Allow,
+            ("a" | "always", Some(rules), _) | ("p" | "prefix", _, Some(rules)) => {
+                PromptAnswer::AllowAlways(rules)
+            }
+            ("d", _, _) => {
+                print!("Tell the model: ");
+                io::stdout().flush().ok();
+                let feedback = read_line();
+                PromptAnswer::Deny((!feedback.is_empty()).then_some(feedback))
+            }
+            _ => PromptAnswer::Deny(None),
         }
     }
 }
 
+/// One trimmed line from stdin (empty on error)
+fn read_line() -> String {
+    let mut input = String::new();
+    match io::stdin().read_line(&mut input) {
+        Ok(_) => input.trim().to_string(),
+        Err(_) => String::new(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -343,8 +1088,10 @@ mod tests {
 
     #[test]
     fn test_accept_edits_mode() {
-        let mut config = PermissionsConfig::default();
-        config.mode = PermissionMode::AcceptEdits;
+        let config = PermissionsConfig {
+            mode: PermissionMode::AcceptEdits,
+            ..Default::default()
+        };
         let engine = PolicyEngine::new(config, false, false);
 
         let (decision, _) = engine.decide("Write", &json!({"path": "foo.txt"}));
@@ -401,6 +1148,451 @@ mod tests {
         assert_eq!(decision, Decision::Deny);
     }
 
+    #[test]
+    fn test_compound_command_checks_every_part() {
+        let mut config = PermissionsConfig::default();
+        config.allow.push("Bash(git status:*)".to_string());
+        config.deny.push("Bash(rm -rf:*)".to_string());
+        let engine = PolicyEngine::new(config, false, false);
+
+        let (decision, _) = engine.decide("Bash", &json!({"command": "git status"}));
+        assert_eq!(decision, Decision::Allow);
+
+        let (decision, rule) = engine.decide("Bash", &json!({"command": "git status; rm -rf x"}));
+        assert_eq!(decision, Decision::Deny);
+        assert_eq!(rule.as_deref(), Some("Bash(rm -rf:*)"));
+
+        // Unmatched sub-commands fall back to the mode default
+        let (decision, _) = engine.decide("Bash", &json!({"command": "git status && make"}));
+        assert_eq!(decision, Decision::Ask);
+    }
+
+    #[test]
+    fn test_compound_command_all_allowed() {
+        let mut config = PermissionsConfig::default();
+        config.allow.push("Bash(cargo test:*)".to_string());
+        config.allow.push("Bash(tail:*)".to_string());
+        let engine = PolicyEngine::new(config, false, false);
+
+        let (decision, rule) =
+            engine.decide("Bash", &json!({"command": "cargo test 2>&1 | tail -50"}));
+        assert_eq!(decision, Decision::Allow);
+        assert_eq!(rule.as_deref(), Some("Bash(cargo test:*), Bash(tail:*)"));
+
+        let (decision, _) =
+            engine.decide("Bash", &json!({"command": "RUST_BACKTRACE=1 cargo test"}));
+        assert_eq!(decision, Decision::Allow);
+    }
+
+    #[test]
+    fn test_substitution_and_pipe_checked() {
+        let mut config = PermissionsConfig::default();
+        config.allow.push("Bash(echo:*)".to_string());
+        let engine = PolicyEngine::new(config, false, false);
+
+        let (decision, _) = engine.decide("Bash", &json!({"command": "echo $(curl http://x)"}));
+        assert_eq!(decision, Decision::Deny);
+
+        let (decision, _) = engine.decide("Bash", &json!({"command": "echo hi | wget -i -"}));
+        assert_eq!(decision, Decision::Deny);
+
+        let args = json!({"command": "echo $(( $(rm -rf x) + 1 ))"});
+        assert_eq!(engine.decide("Bash", &args).0, Decision::Ask);
+
+        // Process substitutions run their body; expanded program names ask
+        let mut config = PermissionsConfig::default();
+        config.allow.push("Bash(cat:*)".to_string());
+        config.deny.push("Bash(rm:*)".to_string());
+        let engine = PolicyEngine::new(config, false, false);
+        let args = json!({"command": "cat <(rm -rf x)"});
+        assert_eq!(engine.decide("Bash", &args).0, Decision::Deny);
+        for command in [
+            "<(rm -rf x)",
+            "cat a; <(rm -rf x)",
+            "$'rm' -rf x",
+            "{rm,-rf,x}",
+        ] {
+            let args = json!({ "command": command });
+            assert_eq!(engine.decide("Bash", &args).0, Decision::Ask, "{}", command);
+        }
+    }
+
+    #[test]
+    fn test_output_redirect_checked_as_write() {
+        let mut config = PermissionsConfig::default();
+        config.allow.push("Bash(echo:*)".to_string());
+        let engine = PolicyEngine::new(config, false, false);
+
+        let (decision, _) = engine.decide("Bash", &json!({"command": "echo hi > /dev/null"}));
+        assert_eq!(decision, Decision::Allow);
+
+        // Write asks in Default mode
+        let (decision, _) = engine.decide("Bash", &json!({"command": "echo hi > out.txt"}));
+        assert_eq!(decision, Decision::Ask);
+
+        let mut config = PermissionsConfig {
+            mode: PermissionMode::AcceptEdits,
+            ..Default::default()
+        };
+        config.allow.push("Bash(echo:*)".to_string());
+        let engine = PolicyEngine::new(config, false, false);
+        let (decision, _) = engine.decide("Bash", &json!({"command": "echo hi > out.txt"}));
+        assert_eq!(decision, Decision::Allow);
+        // Targets outside the project always ask
+        let (decision, _) = engine.decide("Bash", &json!({"command": "echo hi >> ~/.bashrc"}));
+        assert_eq!(decision, Decision::Ask);
+        let (decision, _) = engine.decide("Bash", &json!({"command": "echo hi >& /etc/passwd"}));
+        assert_eq!(decision, Decision::Ask);
+    }
+
+    #[test]
+    fn test_unparseable_command_asks() {
+        let mut config = PermissionsConfig::default();
+        config.allow.push("Bash(cat:*)".to_string());
+        let engine = PolicyEngine::new(config, false, false);
+
+        let (decision, _) = engine.decide("Bash", &json!({"command": "cat <<EOF\nhi\nEOF"}));
+        assert_eq!(decision, Decision::Ask);
+    }
+
+    #[test]
+    fn test_git_categories() {
+        let engine = default_engine();
+        let (decision, _) = engine.decide("Git", &json!({"subcommand": "status"}));
+        assert_eq!(decision, Decision::Allow);
+        let (decision, _) = engine.decide("Git", &json!({"subcommand": "diff", "staged": true}));
+        assert_eq!(decision, Decision::Allow);
+        let (decision, _) = engine.decide("Git", &json!({"subcommand": "add", "paths": ["a"]}));
+        assert_eq!(decision, Decision::Ask);
+
+        let config = PermissionsConfig {
+            mode: PermissionMode::AcceptEdits,
+            ..Default::default()
+        };
+        let engine = PolicyEngine::new(config, false, false);
+        let (decision, _) = engine.decide("Git", &json!({"subcommand": "add", "paths": ["a"]}));
+        assert_eq!(decision, Decision::Allow);
+        // Commits still ask in AcceptEdits mode
+        let (decision, _) = engine.decide("Git", &json!({"subcommand": "commit", "message": "m"}));
+        assert_eq!(decision, Decision::Ask);
+    }
+
+    #[test]
+    fn test_git_rules() {
+        let mut config = PermissionsConfig::default();
+        config.allow.push("Git(commit)".to_string());
+        config.allow.push("Git(add src/:*)".to_string());
+        config.deny.push("Git(stash pop)".to_string());
+        let engine = PolicyEngine::new(config, false, false);
+
+        let (decision, rule) =
+            engine.decide("Git", &json!({"subcommand": "commit", "message": "m"}));
+        assert_eq!(decision, Decision::Allow);
+        assert_eq!(rule.as_deref(), Some("Git(commit)"));
+
+        let (decision, _) =
+            engine.decide("Git", &json!({"subcommand": "add", "paths": ["src/a.rs"]}));
+        assert_eq!(decision, Decision::Allow);
+        let (decision, _) = engine.decide(
+            "Git",
+            &json!({"subcommand": "add", "paths": ["Cargo.toml"]}),
+        );
+        assert_eq!(decision, Decision::Ask);
+
+        let (decision, _) = engine.decide("Git", &json!({"subcommand": "stash", "action": "pop"}));
+        assert_eq!(decision, Decision::Deny);
+        let (decision, _) = engine.decide("Git", &json!({"subcommand": "stash"}));
+        assert_eq!(decision, Decision::Ask);
+    }
+
+    #[test]
+    fn test_run_tests_uses_bash_rules() {
+        let mut config = PermissionsConfig::default();
+        config.allow.push("Bash(cargo test:*)".to_string());
+        let engine = PolicyEngine::new(config, false, false);
+
+        let args = json!({"runner": "cargo", "command": "cargo test --no-fail-fast"});
+        let (decision, rule) = engine.decide("RunTests", &args);
+        assert_eq!(decision, Decision::Allow);
+        assert_eq!(rule.as_deref(), Some("Bash(cargo test:*)"));
+
+        let args = json!({"runner": "go", "command": "go test -json ./..."});
+        let (decision, _) = engine.decide("RunTests", &args);
+        assert_eq!(decision, Decision::Ask);
+    }
+
+    #[test]
+    fn test_always_rules() {
+        let engine = default_engine();
+        let args = json!({"command": "cargo test --lib && ls src > out.txt"});
+        assert_eq!(
+            engine.always_rules("Bash", &args, false).unwrap(),
+            ["Bash(cargo test --lib)", "Bash(ls src)", "Write(out.txt)"]
+        );
+        assert_eq!(
+            engine.always_rules("Bash", &args, true).unwrap(),
+            ["Bash(cargo test:*)", "Bash(ls src:*)", "Write(out.txt)"]
+        );
+
+        let args = json!({"path": "src/tools/a.rs", "edits": []});
+        assert_eq!(
+            engine.always_rules("Edit", &args, true).unwrap(),
+            ["Edit(src/tools/:*)"]
+        );
+        let args = json!({"subcommand": "add", "paths": ["src/a.rs"]});
+        assert_eq!(
+            engine.always_rules("Git", &args, true).unwrap(),
+            ["Git(add:*)"]
+        );
+
+        // Parts that are already allowed need no rule
+        let mut config = PermissionsConfig::default();
+        config.allow.push("Move(src/:*)".to_string());
+        config.ask.push("Bash(rm:*)".to_string());
+        let engine = PolicyEngine::new(config, false, false);
+        let args = json!({"source": "src/a.rs", "destination": "lib/a.rs"});
+        assert_eq!(
+            engine.always_rules("Move", &args, false).unwrap(),
+            ["Move(lib/a.rs)"]
+        );
+
+        // An ask rule can't be overridden by allow rules, so nothing is offered
+        let args = json!({"command": "rm -rf build"});
+        assert!(engine.always_rules("Bash", &args, false).is_none());
+    }
+
+    #[test]
+    fn test_check_permission_auto_yes() {
+        let mut engine = PolicyEngine::new(PermissionsConfig::default(), true, true);
+        let check = engine.check_permission("Write", &json!({"path": "a.txt"}));
+        assert!(check.allowed);
+        assert_eq!(check.decision, Decision::Ask);
+        assert!(check.added_rules.is_empty());
+        assert!(check.feedback.is_none());
+    }
+
+    #[test]
+    fn test_read_many_checks_each_path() {
+        let mut config = PermissionsConfig::default();
+        config.deny.push("Read(notes/private.md)".to_string());
+        config.ask.push("ReadMany(secrets/:*)".to_string());
+        let engine = PolicyEngine::new(config, false, false);
+
+        let args = json!({"files": [{"path": "src/main.rs"}, {"path": "README.md"}]});
+        assert_eq!(engine.decide("ReadMany", &args).0, Decision::Allow);
+        let args = json!({"files": [{"path": "src/main.rs"}, {"path": "notes/private.md"}]});
+        let (decision, rule) = engine.decide("ReadMany", &args);
+        assert_eq!(decision, Decision::Deny);
+        assert_eq!(rule.as_deref(), Some("Read(notes/private.md)"));
+        let args = json!({"files": [{"path": "secrets/token.txt"}]});
+        assert_eq!(engine.decide("ReadMany", &args).0, Decision::Ask);
+    }
+
+    #[test]
+    fn test_file_management_rules_check_each_path() {
+        let mut config = PermissionsConfig::default();
+        config.allow.push("Move(src/:*)".to_string());
+        config.deny.push("Delete(Cargo.toml)".to_string());
+        let engine = PolicyEngine::new(config, false, false);
+
+        let args = json!({"source": "src/a.rs", "destination": "src/b.rs"});
+        let (decision, rule) = engine.decide("Move", &args);
+        assert_eq!(decision, Decision::Allow);
+        assert_eq!(rule.as_deref(), Some("Move(src/:*)"));
+
+        // Moving out of src/ is not covered by the rule
+        let args = json!({"source": "src/a.rs", "destination": "a.rs"});
+        let (decision, _) = engine.decide("Move", &args);
+        assert_eq!(decision, Decision::Ask);
+
+        let (decision, _) = engine.decide("Delete", &json!({"path": "Cargo.toml"}));
+        assert_eq!(decision, Decision::Deny);
+        let (decision, _) = engine.decide("MakeDir", &json!({"path": "docs"}));
+        assert_eq!(decision, Decision::Ask);
+        assert_eq!(ToolCategory::from_tool_name("Copy"), ToolCategory::Mutation);
+    }
+
+    #[test]
+    fn test_glob_regex_and_negated_rules() {
+        let mut config = PermissionsConfig::default();
+        config.allow.push("Edit(src/**)".to_string());
+        config
+            .allow
+            .push("Bash(re:^cargo (build|test)( |$))".to_string());
+        config.deny.push("Write(!tests/**)".to_string());
+        let engine = PolicyEngine::new(config, false, false);
+
+        let (decision, rule) = engine.decide("Edit", &json!({"path": "src/tools/a.rs"}));
+        assert_eq!(decision, Decision::Allow);
+        assert_eq!(rule.as_deref(), Some("Edit(src/**)"));
+        let (decision, _) = engine.decide("Edit", &json!({"path": "Cargo.toml"}));
+        assert_eq!(decision, Decision::Ask);
+
+        let (decision, _) = engine.decide("Bash", &json!({"command": "cargo test --lib"}));
+        assert_eq!(decision, Decision::Allow);
+        let (decision, _) = engine.decide("Bash", &json!({"command": "cargo publish"}));
+        assert_eq!(decision, Decision::Ask);
+
+        // Writes are only possible under tests/
+        let (decision, _) = engine.decide("Write", &json!({"path": "tests/cli.rs"}));
+        assert_eq!(decision, Decision::Ask);
+        let (decision, rule) = engine.decide("Write", &json!({"path": "src/a.rs"}));
+        assert_eq!(decision, Decision::Deny);
+        assert_eq!(rule.as_deref(), Some("Write(!tests/**)"));
+
+        // Allow rules only see the parsed command, not its original spelling
+        let mut config = PermissionsConfig::default();
+        config.allow.push("Bash(!rm:*)".to_string());
+        let engine = PolicyEngine::new(config, false, false);
+        let (decision, _) = engine.decide("Bash", &json!({"command": "ls -la"}));
+        assert_eq!(decision, Decision::Allow);
+        for command in ["rm -rf x", "'rm' -rf x", "r\\m -rf x"] {
+            let (decision, _) = engine.decide("Bash", &json!({ "command": command }));
+            assert_eq!(decision, Decision::Ask, "{}", command);
+        }
+    }
+
+    #[test]
+    fn test_paths_normalized_before_matching() {
+        let mut config = PermissionsConfig::default();
+        config.deny.push("Edit(Cargo.toml)".to_string());
+        config.allow.push("Move(src/**)".to_string());
+        let engine = PolicyEngine::new(config, false, false).with_root(PathBuf::from("/proj"));
+
+        for path in [
+            "./Cargo.toml",
+            "src/../Cargo.toml",
+            "/proj/Cargo.toml",
+            ".//Cargo.toml",
+        ] {
+            let (decision, _) = engine.decide("Edit", &json!({"path": path}));
+            assert_eq!(decision, Decision::Deny, "{}", path);
+        }
+        let (decision, _) = engine.decide("Edit", &json!({"path": "/other/Cargo.toml"}));
+        assert_eq!(decision, Decision::Ask);
+
+        // "src/../a.rs" leaves src/, so the Move rule doesn't cover it
+        let args = json!({"source": "./src/a.rs", "destination": "src/../a.rs"});
+        assert_eq!(engine.decide("Move", &args).0, Decision::Ask);
+        let args = json!({"source": "./src/a.rs", "destination": "/proj/src/b.rs"});
+        assert_eq!(engine.decide("Move", &args).0, Decision::Allow);
+        assert_eq!(
+            engine
+                .always_rules("Edit", &json!({"path": "./src//a.rs"}), false)
+                .unwrap(),
+            ["Edit(src/a.rs)"]
+        );
+    }
+
+    #[test]
+    fn test_secret_files_denied() {
+        let mut config = PermissionsConfig::default();
+        config.allow.push("Read(.env)".to_string());
+        config.allow.push("Bash(cat:*)".to_string());
+        let engine = PolicyEngine::new(config, false, false);
+
+        // Allow rules can't reach secret files
+        let (decision, rule) = engine.decide("Read", &json!({"path": ".env"}));
+        assert_eq!(decision, Decision::Deny);
+        assert_eq!(rule.as_deref(), Some("secret_files: **/.env"));
+        for (tool, args) in [
+            ("Read", json!({"path": "config/.env.production"})),
+            ("Read", json!({"path": "src/../deploy/server.key"})),
+            ("Edit", json!({"path": "home/.ssh/config"})),
+            (
+                "Copy",
+                json!({"source": "certs/tls.pem", "destination": "tls.txt"}),
+            ),
+            (
+                "ReadMany",
+                json!({"files": [{"path": "a.rs"}, {"path": "id_ed25519"}]}),
+            ),
+            ("Grep", json!({"pattern": "KEY", "paths": [".env"]})),
+            ("Git", json!({"subcommand": "diff", "paths": [".env"]})),
+            ("Git", json!({"subcommand": "show", "ref": "HEAD:.env"})),
+            (
+                "Git",
+                json!({"subcommand": "show", "ref": "HEAD~2:config/.env"}),
+            ),
+            ("Git", json!({"subcommand": "show", "ref": ":0:.env"})),
+            ("Bash", json!({"command": "cat .env"})),
+            ("Bash", json!({"command": "wc -l < ./.env.local"})),
+        ] {
+            assert_eq!(engine.decide(tool, &args).0, Decision::Deny, "{}", args);
+        }
+        let (decision, _) = engine.decide("Read", &json!({"path": ".env.example"}));
+        assert_eq!(decision, Decision::Allow);
+        let args = json!({"subcommand": "show", "ref": "HEAD:src/main.rs"});
+        assert_ne!(engine.decide("Git", &args).0, Decision::Deny);
+        let (decision, _) = engine.decide("Bash", &json!({"command": "cat README.md"}));
+        assert_eq!(decision, Decision::Allow);
+
+        // A configured list replaces the built-in one
+        let config = PermissionsConfig {
+            secret_files: Some(vec!["vault/**".to_string()]),
+            ..Default::default()
+        };
+        let engine = PolicyEngine::new(config, false, false);
+        let (decision, _) = engine.decide("Read", &json!({"path": ".env"}));
+        assert_eq!(decision, Decision::Allow);
+        let (decision, rule) = engine.decide("Read", &json!({"path": "vault/token"}));
+        assert_eq!(decision, Decision::Deny);
+        assert_eq!(rule.as_deref(), Some("secret_files: vault/**"));
+    }
+
+    #[test]
+    fn test_lint_flags_rules_that_never_apply() {
+        let mut config = PermissionsConfig::default();
+        config.deny.push("Bash(git push:*)".to_string());
+        config.allow.push("Bash(curl -s:*)".to_string());
+        config.allow.push("Bash(cargo:*)".to_string());
+        config.allow.push("Bash(cargo test)".to_string());
+        config.allow.push("Bash(git push origin main)".to_string());
+        config.allow.push("Read(.env)".to_string());
+        config.allow.push("Edit(src/**)".to_string());
+        config.allow.push("Bash(oops".to_string());
+        let engine = PolicyEngine::new(config, false, false);
+
+        let problems: Vec<String> = engine.lint().iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            problems,
+            [
+                "[permissions.allow[0]]: Unreachable: built-in rule 'Bash(curl:*)' is checked first and matches every call this does",
+                "[permissions.allow[2]]: Redundant: 'Bash(cargo:*)' already matches every call this does",
+                "[permissions.allow[3]]: Unreachable: deny rule 'Bash(git push:*)' is checked first and matches every call this does",
+                "[permissions.allow[4]]: Unreachable: secret_files entry '**/.env' denies this path",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_explain_lists_every_matching_rule() {
+        let mut config = PermissionsConfig {
+            mode: PermissionMode::AcceptEdits,
+            ..Default::default()
+        };
+        config.allow.push("Bash(echo:*)".to_string());
+        config.allow.push("Bash(re:^echo )".to_string());
+        let engine = PolicyEngine::new(config, false, false);
+
+        let explanation = engine.explain("Bash", &json!({"command": "echo hi > /tmp/out"}));
+        assert_eq!(explanation.decision, Decision::Ask);
+        assert_eq!(explanation.parts.len(), 2);
+        let rules: Vec<&str> = explanation.parts[0]
+            .matches
+            .iter()
+            .map(|m| m.rule.as_str())
+            .collect();
+        assert_eq!(rules, ["Bash(echo:*)", "Bash(re:^echo )"]);
+        // The write itself is allowed in AcceptEdits mode; its target is not
+        assert_eq!(explanation.parts[1].tool, "Write");
+        assert_eq!(explanation.parts[1].decision, Decision::Allow);
+        assert_eq!(
+            explanation.notes,
+            ["Redirections outside the project always ask"]
+        );
+    }
+
     #[test]
     fn test_mcp_wildcard_all() {
         // Pattern "mcp.*" should match any MCP tool
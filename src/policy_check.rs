@@ -0,0 +1,372 @@
+//! `yo policy check|lint` and `/permissions test|lint`.
+//!
+//! Explains how the permission rules decide a tool call, naming the config
+//! layer (user, project, local, CLI) each matching rule comes from and the
+//! rules it shadows, and lints the rule lists for entries that never apply.
+
+use crate::config::{Config, HookConfig, HookEvent, PermissionsConfig};
+use crate::policy::{Decision, PolicyEngine, ToolCategory};
+use crate::{Args, PolicyCommand};
+use serde_json::{json, Value};
+use std::fmt::Write;
+
+/// The permission rules each config layer contributes, in merge order
+pub type Layers = Vec<(String, PermissionsConfig)>;
+
+/// Rule layers for the config files `Config::load` reads plus the CLI flags
+pub fn layers(args: &Args) -> Layers {
+    let mut layers = Config::permission_layers(args.config.as_deref());
+    let cli = PermissionsConfig {
+        allow: args.allowed_tools.clone(),
+        ask: args.ask_tools.clone(),
+        deny: args.disallowed_tools.clone(),
+        ..Default::default()
+    };
+    if !(cli.allow.is_empty() && cli.ask.is_empty() && cli.deny.is_empty()) {
+        layers.push(("cli flags".to_string(), cli));
+    }
+    layers
+}
+
+/// Tool arguments for the argument a rule would match: the command for Bash,
+/// the path for file tools, "source -> destination" for Move/Copy, and so on.
+/// A JSON object is used as-is.
+pub fn call_args(tool: &str, arg: Option<&str>) -> Value {
+    let Some(arg) = arg else {
+        return json!({});
+    };
+    if let Ok(value @ Value::Object(_)) = serde_json::from_str(arg) {
+        return value;
+    }
+    match tool {
+        "Bash" | "RunTests" | "Diagnostics" => json!({ "command": arg }),
+        "Grep" | "Glob" | "Search" => json!({ "pattern": arg }),
+        "CodeSearch" => json!({ "query": arg }),
+        "ReadMany" => {
+            let files: Vec<Value> = arg
+                .split_whitespace()
+                .map(|path| json!({ "path": path }))
+                .collect();
+            json!({ "files": files })
+        }
+        "Move" | "Copy" => {
+            let (source, destination) = arg.split_once("->").unwrap_or((arg, ""));
+            json!({ "source": source.trim(), "destination": destination.trim() })
+        }
+        "Git" => {
+            let mut words = arg.split_whitespace();
+            let subcommand = words.next().unwrap_or("");
+            let rest: Vec<&str> = words.collect();
+            if subcommand == "stash" {
+                json!({ "subcommand": subcommand, "action": rest.first().copied().unwrap_or("push") })
+            } else if rest.is_empty() {
+                json!({ "subcommand": subcommand })
+            } else {
+                json!({ "subcommand": subcommand, "paths": rest })
+            }
+        }
+        _ => json!({ "path": arg }),
+    }
+}
+
+/// Where the `nth` occurrence of `rule` in a merged rule list was configured
+fn source(layers: &Layers, list: &str, rule: &str, nth: usize) -> String {
+    if list == "built-in" {
+        return "built-in".to_string();
+    }
+    layers
+        .iter()
+        .flat_map(|(label, permissions)| {
+            let rules = match list {
+                "deny" => &permissions.deny,
+                "ask" => &permissions.ask,
+                _ => &permissions.allow,
+            };
+            rules.iter().filter(|r| *r == rule).map(move |_| label)
+        })
+        .nth(nth)
+        .cloned()
+        .unwrap_or_else(|| "this session".to_string())
+}
+
+fn decision_name(decision: Decision) -> &'static str {
+    match decision {
+        Decision::Allow => "allow",
+        Decision::Ask => "ask",
+        Decision::Deny => "deny",
+    }
+}
+
+/// Describe how `engine` decides a call: the final decision, then each part
+/// with the rule deciding it and the rules it shadows
+pub fn explain(
+    engine: &PolicyEngine,
+    tool: &str,
+    args: &Value,
+    layers: &Layers,
+    hooks: &[&HookConfig],
+) -> String {
+    let explanation = engine.explain(tool, args);
+    let mut out = String::new();
+    let _ = write!(out, "Decision: {}", decision_name(explanation.decision));
+    match &explanation.rule {
+        Some(rule) => {
+            let _ = writeln!(out, " (rule: {})", rule);
+        }
+        None => out.push('\n'),
+    }
+
+    for part in &explanation.parts {
+        let _ = writeln!(
+            out,
+            "  {}({}): {}",
+            part.tool,
+            part.arg.as_deref().unwrap_or(""),
+            decision_name(part.decision)
+        );
+        if part.matches.is_empty() {
+            let category = match ToolCategory::from_tool_call(&part.tool, part.arg.as_deref()) {
+                ToolCategory::ReadOnly => "read-only tools",
+                ToolCategory::Mutation => "edits",
+                ToolCategory::Execution => "commands and other tools",
+            };
+            let verb = match part.decision {
+                Decision::Allow => "allows",
+                Decision::Ask => "asks for",
+                Decision::Deny => "denies",
+            };
+            let _ = writeln!(
+                out,
+                "    no rule matches; {} mode {} {}",
+                engine.mode().as_str(),
+                verb,
+                category
+            );
+        }
+        for (i, m) in part.matches.iter().enumerate() {
+            let status = if i == 0 { "decides " } else { "shadowed" };
+            let nth = part.matches[..i]
+                .iter()
+                .filter(|earlier| earlier.list == m.list && earlier.rule == m.rule)
+                .count();
+            let _ = writeln!(
+                out,
+                "    {} {:<5} {}  [{}]",
+                status,
+                m.list,
+                m.rule,
+                source(layers, m.list, &m.rule, nth)
+            );
+        }
+    }
+
+    for note in &explanation.notes {
+        let _ = writeln!(out, "  Note: {}", note);
+    }
+    for hook in hooks {
+        let _ = writeln!(
+            out,
+            "  Note: PreToolUse hook `{}` runs before the call and can still block it",
+            shell_words::join(&hook.command)
+        );
+    }
+    out
+}
+
+/// Report config errors and rules that never apply; returns the report and
+/// the number of problems found
+pub fn lint(config: &Config, engine: &PolicyEngine, layers: &Layers) -> (String, usize) {
+    let errors = config.validate().err().unwrap_or_default();
+    let warnings = engine.lint();
+    let permissions = engine.config();
+
+    let mut out = String::new();
+    for (level, problems) in [("error", &errors), ("warning", &warnings)] {
+        for problem in problems {
+            let _ = write!(out, "{} {}", level, problem);
+            if let Some((list, rule, nth)) = locate(permissions, &problem.field) {
+                let _ = write!(out, "  [{}]", source(layers, list, rule, nth));
+            }
+            out.push('\n');
+        }
+    }
+    let count = errors.len() + warnings.len();
+    if count == 0 {
+        out.push_str("No problems found\n");
+    }
+    (out, count)
+}
+
+/// The rule a "permissions.<list>[<i>]" field names, with how many times the
+/// same rule appears before it in that list
+fn locate<'a>(
+    permissions: &'a PermissionsConfig,
+    field: &str,
+) -> Option<(&'static str, &'a str, usize)> {
+    let rest = field.strip_prefix("permissions.")?;
+    let (list, index) = rest.strip_suffix(']')?.split_once('[')?;
+    let index: usize = index.parse().ok()?;
+    let (list, rules) = match list {
+        "allow" => ("allow", &permissions.allow),
+        "ask" => ("ask", &permissions.ask),
+        "deny" => ("deny", &permissions.deny),
+        _ => return None,
+    };
+    let rule = rules.get(index)?;
+    let nth = rules[..index].iter().filter(|r| *r == rule).count();
+    Some((list, rule, nth))
+}
+
+/// Run `yo policy ...` against the loaded config (CLI rules and --mode applied)
+pub fn run(command: &PolicyCommand, config: &Config, args: &Args) -> anyhow::Result<()> {
+    let root = std::env::current_dir()?;
+    let layers = layers(args);
+
+    match command {
+        PolicyCommand::Check { tool, arg, agent } => {
+            let mut permissions = config.permissions.clone();
+            if let Some(name) = agent {
+                let spec = config
+                    .agents
+                    .get(name)
+                    .ok_or_else(|| anyhow::anyhow!("Unknown agent '{}'", name))?;
+                if !crate::subagent::is_tool_allowed(tool, &spec.allowed_tools) {
+                    println!(
+                        "Decision: unavailable ({} is not in agent '{}' allowed_tools: {})",
+                        tool,
+                        name,
+                        spec.allowed_tools.join(", ")
+                    );
+                    return Ok(());
+                }
+                let requested = spec.get_permission_mode();
+                permissions.mode = crate::subagent::clamp_mode(requested, permissions.mode);
+                println!(
+                    "Agent '{}': mode {} (requested {}, parent {})",
+                    name,
+                    permissions.mode.as_str(),
+                    requested.as_str(),
+                    config.permissions.mode.as_str()
+                );
+            }
+
+            let engine = PolicyEngine::new(permissions, false, false).with_root(root.clone());
+            let call = call_args(tool, arg.as_deref());
+            let hooks = crate::hooks::HookManager::new(config.hooks.clone(), String::new(), root);
+            let pre_hooks = hooks.tool_hooks(HookEvent::PreToolUse, tool, Some(&call));
+            print!("{}", explain(&engine, tool, &call, &layers, &pre_hooks));
+            if agent.is_some() && engine.decide(tool, &call).0 == Decision::Ask {
+                println!("  Note: subagents cannot prompt, so the call is denied");
+            }
+            Ok(())
+        }
+        PolicyCommand::Lint => {
+            let engine = PolicyEngine::new(config.permissions.clone(), false, false);
+            let (report, count) = lint(config, &engine, &layers);
+            print!("{}", report);
+            if count > 0 {
+                println!("{} problem(s) found", count);
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layers() -> Layers {
+        let user = PermissionsConfig {
+            allow: vec!["Bash(git:*)".to_string()],
+            ..Default::default()
+        };
+        let project = PermissionsConfig {
+            allow: vec!["Bash(git:*)".to_string()],
+            ask: vec!["Bash(git push:*)".to_string()],
+            ..Default::default()
+        };
+        vec![
+            ("user ~/.yo/config.toml".to_string(), user),
+            ("project .yo/config.toml".to_string(), project),
+        ]
+    }
+
+    fn merged(layers: &Layers) -> PermissionsConfig {
+        let mut permissions = PermissionsConfig::default();
+        for (_, layer) in layers {
+            permissions.allow.extend(layer.allow.clone());
+            permissions.ask.extend(layer.ask.clone());
+            permissions.deny.extend(layer.deny.clone());
+        }
+        permissions
+    }
+
+    #[test]
+    fn test_call_args() {
+        assert_eq!(
+            call_args("Bash", Some("ls -la")),
+            json!({ "command": "ls -la" })
+        );
+        assert_eq!(
+            call_args("Edit", Some("src/a.rs")),
+            json!({ "path": "src/a.rs" })
+        );
+        assert_eq!(
+            call_args("Move", Some("a.rs -> b.rs")),
+            json!({ "source": "a.rs", "destination": "b.rs" })
+        );
+        assert_eq!(
+            call_args("Git", Some("add src/a.rs")),
+            json!({ "subcommand": "add", "paths": ["src/a.rs"] })
+        );
+        assert_eq!(
+            call_args("Write", Some(r#"{"path": "x", "content": ""}"#)),
+            json!({ "path": "x", "content": "" })
+        );
+        assert_eq!(call_args("mcp.echo.add", None), json!({}));
+    }
+
+    #[test]
+    fn test_explain_names_sources_and_shadowed_rules() {
+        let layers = layers();
+        let engine = PolicyEngine::new(merged(&layers), false, false);
+        let call = call_args("Bash", Some("git push origin main > log.txt"));
+        let report = explain(&engine, "Bash", &call, &layers, &[]);
+        assert_eq!(
+            report,
+            "Decision: ask (rule: Bash(git push:*))\n\
+             \x20 Bash(git push origin main): ask\n\
+             \x20   decides  ask   Bash(git push:*)  [project .yo/config.toml]\n\
+             \x20   shadowed allow Bash(git:*)  [user ~/.yo/config.toml]\n\
+             \x20   shadowed allow Bash(git:*)  [project .yo/config.toml]\n\
+             \x20 Write(log.txt): ask\n\
+             \x20   no rule matches; default mode asks for edits\n"
+        );
+    }
+
+    #[test]
+    fn test_lint_reports_source_of_each_problem() {
+        let mut layers = layers();
+        layers[1].1.allow.push("Bash(git push --force)".to_string());
+        let engine = PolicyEngine::new(merged(&layers), false, false);
+        let mut config = Config::default();
+        config.permissions = engine.config().clone();
+        config.permissions.deny.push("Bash(re:(".to_string());
+
+        let engine = PolicyEngine::new(config.permissions.clone(), false, false);
+        let (report, count) = lint(&config, &engine, &layers);
+        assert_eq!(count, 3);
+        let lines: Vec<&str> = report.lines().collect();
+        assert!(lines[0].starts_with("error [permissions.deny[0]]: Invalid rule"));
+        assert!(lines[0].ends_with("[this session]"));
+        assert_eq!(
+            lines[1],
+            "warning [permissions.allow[1]]: Duplicate of allow[0] 'Bash(git:*)'  [project .yo/config.toml]"
+        );
+        assert!(lines[2].starts_with(
+            "warning [permissions.allow[2]]: Unreachable: ask rule 'Bash(git push:*)'"
+        ));
+    }
+}
@@ -0,0 +1,518 @@
+//! Repository map for the system prompt.
+//!
+//! At session start the project's files are listed (via `git ls-files` when
+//! available) and top-level symbols are extracted from Rust, Python, TS/JS and
+//! Go sources with per-language patterns. Each symbol is ranked by how often
+//! its name appears across the project, and the best-ranked files are rendered
+//! as a directory tree under a token budget. The extracted map is cached in
+//! `.yo/repo_map.json`, keyed by the git tree hash plus the working-tree state,
+//! so unchanged repositories don't pay for a rescan.
+
+use crate::config::RepoMapConfig;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use std::process::Command;
+use walkdir::WalkDir;
+
+/// Bumped whenever extraction changes, to invalidate old caches
+const CACHE_VERSION: u32 = 1;
+const CACHE_FILE: &str = "repo_map.json";
+
+/// Files beyond this are listed but not scanned for symbols
+const MAX_SCANNED_FILES: usize = 20_000;
+const MAX_FILE_BYTES: u64 = 512 * 1024;
+const MAX_SYMBOLS_PER_FILE: usize = 10;
+
+/// Rough chars-per-token ratio used for the budget
+const CHARS_PER_TOKEN: usize = 4;
+
+static IDENT_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"[A-Za-z_$][A-Za-z0-9_$]*").unwrap());
+
+static RUST_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"^(?:pub(?:\([^)]*\))?\s+)?(?:(?:async|const|unsafe|extern(?:\s+"[^"]*")?)\s+)*(fn|struct|enum|trait|type|const|static|union|macro_rules!)\s*([A-Za-z_][A-Za-z0-9_]*)"#,
+    )
+    .unwrap()
+});
+static PYTHON_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?:async\s+)?(def|class)\s+([A-Za-z_][A-Za-z0-9_]*)").unwrap());
+static JS_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(export\s+)?(?:default\s+)?(?:declare\s+)?(?:abstract\s+)?(?:async\s+)?(function\*?|class|interface|type|enum|const|let|var)\s+([A-Za-z_$][A-Za-z0-9_$]*)",
+    )
+    .unwrap()
+});
+static GO_FUNC_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^func\s+(\([^)]*\)\s*)?([A-Za-z_][A-Za-z0-9_]*)").unwrap());
+static GO_DECL_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(type|const|var)\s+([A-Za-z_][A-Za-z0-9_]*)").unwrap());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: String,
+    pub line: usize,
+    /// Occurrences of the name elsewhere in the project
+    pub refs: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileEntry {
+    pub path: String,
+    pub symbols: Vec<Symbol>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepoMap {
+    version: u32,
+    key: String,
+    pub files: Vec<FileEntry>,
+}
+
+impl RepoMap {
+    /// Load the cached map if it is still current, otherwise rebuild and cache it
+    pub fn load_or_build(root: &Path, refresh: bool) -> Self {
+        let key = cache_key(root);
+        let cache_path = root.join(".yo").join(CACHE_FILE);
+        if !refresh {
+            let cached = std::fs::read_to_string(&cache_path)
+                .ok()
+                .and_then(|s| serde_json::from_str::<RepoMap>(&s).ok());
+            if let Some(map) = cached {
+                if map.version == CACHE_VERSION && map.key == key {
+                    return map;
+                }
+            }
+        }
+
+        let map = Self::build(root, key);
+        if let Some(parent) = cache_path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if let Ok(json) = serde_json::to_string(&map) {
+            let _ = std::fs::write(&cache_path, json);
+        }
+        map
+    }
+
+    fn build(root: &Path, key: String) -> Self {
+        let paths = list_files(root);
+        let mut ident_counts: HashMap<String, usize> = HashMap::new();
+        let mut files: Vec<FileEntry> = Vec::with_capacity(paths.len());
+
+        for (i, path) in paths.into_iter().enumerate() {
+            let lang = language_of(&path);
+            let content = if lang.is_some() && i < MAX_SCANNED_FILES {
+                read_source(&root.join(&path))
+            } else {
+                None
+            };
+            let symbols = match (lang, &content) {
+                (Some(lang), Some(content)) => {
+                    for m in IDENT_RE.find_iter(content) {
+                        *ident_counts.entry(m.as_str().to_string()).or_insert(0) += 1;
+                    }
+                    extract_symbols(lang, content)
+                }
+                _ => Vec::new(),
+            };
+            files.push(FileEntry { path, symbols });
+        }
+
+        // The definition itself is one of the occurrences
+        for file in &mut files {
+            for sym in &mut file.symbols {
+                sym.refs = ident_counts
+                    .get(&sym.name)
+                    .copied()
+                    .unwrap_or(0)
+                    .saturating_sub(1);
+            }
+            sym_sort(&mut file.symbols);
+        }
+
+        Self {
+            version: CACHE_VERSION,
+            key,
+            files,
+        }
+    }
+
+    /// Render the best-ranked files as a tree within roughly `max_tokens`
+    pub fn render(&self, max_tokens: usize) -> String {
+        let budget = max_tokens * CHARS_PER_TOKEN;
+
+        let mut ranked: Vec<&FileEntry> = self.files.iter().collect();
+        ranked.sort_by(|a, b| {
+            score(b)
+                .cmp(&score(a))
+                .then_with(|| {
+                    a.path
+                        .matches('/')
+                        .count()
+                        .cmp(&b.path.matches('/').count())
+                })
+                .then_with(|| a.path.cmp(&b.path))
+        });
+
+        // Leave room for directory lines and the header
+        let mut used = budget / 5;
+        let mut selected: Vec<&FileEntry> = Vec::new();
+        for file in ranked {
+            let cost = file_line(file).len() + 2 * file.path.matches('/').count() + 1;
+            if used + cost > budget {
+                continue;
+            }
+            used += cost;
+            selected.push(file);
+        }
+        selected.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut out = format!(
+            "## Repository map\nTop-level symbols per file, most referenced first ({} of {} files shown).\n",
+            selected.len(),
+            self.files.len()
+        );
+        let mut open_dirs: Vec<&str> = Vec::new();
+        for file in selected {
+            let parts: Vec<&str> = file.path.split('/').collect();
+            let dirs = &parts[..parts.len() - 1];
+            let common = open_dirs
+                .iter()
+                .zip(dirs)
+                .take_while(|(a, b)| a == b)
+                .count();
+            open_dirs.truncate(common);
+            for dir in &dirs[common..] {
+                out.push_str(&format!("{}{}/\n", "  ".repeat(open_dirs.len()), dir));
+                open_dirs.push(dir);
+            }
+            out.push_str(&format!("{}{}\n", "  ".repeat(dirs.len()), file_line(file)));
+        }
+        out.trim_end().to_string()
+    }
+}
+
+/// The system prompt section for a project, or None when disabled
+pub fn prompt_section(root: &Path, config: &RepoMapConfig, refresh: bool) -> Option<String> {
+    if !config.enabled() {
+        return None;
+    }
+    let map = RepoMap::load_or_build(root, refresh);
+    if map.files.is_empty() {
+        return None;
+    }
+    Some(map.render(config.max_tokens()))
+}
+
+fn score(file: &FileEntry) -> usize {
+    file.symbols.iter().map(|s| s.refs + 1).sum()
+}
+
+fn sym_sort(symbols: &mut [Symbol]) {
+    symbols.sort_by(|a, b| b.refs.cmp(&a.refs).then(a.line.cmp(&b.line)));
+}
+
+/// `name.rs: top, symbols` for one file
+fn file_line(file: &FileEntry) -> String {
+    let name = file.path.rsplit('/').next().unwrap_or(&file.path);
+    if file.symbols.is_empty() {
+        return name.to_string();
+    }
+    let mut seen = BTreeSet::new();
+    let names: Vec<&str> = file
+        .symbols
+        .iter()
+        .map(|s| s.name.as_str())
+        .filter(|n| seen.insert(*n))
+        .take(MAX_SYMBOLS_PER_FILE)
+        .collect();
+    format!("{}: {}", name, names.join(", "))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Lang {
+    Rust,
+    Python,
+    Js,
+    Go,
+}
+
+fn language_of(path: &str) -> Option<Lang> {
+    match path.rsplit('.').next()? {
+        "rs" => Some(Lang::Rust),
+        "py" | "pyi" => Some(Lang::Python),
+        "ts" | "tsx" | "js" | "jsx" | "mjs" | "cjs" => Some(Lang::Js),
+        "go" => Some(Lang::Go),
+        _ => None,
+    }
+}
+
+fn read_source(path: &Path) -> Option<String> {
+    let meta = std::fs::metadata(path).ok()?;
+    if meta.len() > MAX_FILE_BYTES {
+        return None;
+    }
+    std::fs::read_to_string(path).ok()
+}
+
+/// Top-level definitions: only lines starting in column 0 are considered
+fn extract_symbols(lang: Lang, content: &str) -> Vec<Symbol> {
+    let mut symbols = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        if line.starts_with(char::is_whitespace) {
+            continue;
+        }
+        let found = match lang {
+            Lang::Rust => RUST_RE.captures(line).map(|c| {
+                let kind = match &c[1] {
+                    "macro_rules!" => "macro",
+                    k => k,
+                };
+                (kind.to_string(), c[2].to_string())
+            }),
+            Lang::Python => PYTHON_RE
+                .captures(line)
+                .map(|c| (c[1].to_string(), c[2].to_string())),
+            // Unexported top-level variables are mostly imports and locals
+            Lang::Js => JS_RE.captures(line).and_then(|c| {
+                let kind = c[2].trim_end_matches('*');
+                let exported = c.get(1).is_some();
+                if matches!(kind, "const" | "let" | "var") && !exported {
+                    return None;
+                }
+                Some((kind.to_string(), c[3].to_string()))
+            }),
+            Lang::Go => GO_FUNC_RE
+                .captures(line)
+                .map(|c| {
+                    let kind = if c.get(1).is_some() { "method" } else { "func" };
+                    (kind.to_string(), c[2].to_string())
+                })
+                .or_else(|| {
+                    GO_DECL_RE
+                        .captures(line)
+                        .map(|c| (c[1].to_string(), c[2].to_string()))
+                }),
+        };
+        if let Some((kind, name)) = found {
+            symbols.push(Symbol {
+                name,
+                kind,
+                line: i + 1,
+                refs: 0,
+            });
+        }
+    }
+    symbols
+}
+
+fn git(root: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+/// Project files relative to root, honouring .gitignore when in a git repo
+pub(crate) fn list_files(root: &Path) -> Vec<String> {
+    if let Some(out) = git(
+        root,
+        &[
+            "ls-files",
+            "-z",
+            "--cached",
+            "--others",
+            "--exclude-standard",
+        ],
+    ) {
+        let mut files: Vec<String> = out
+            .split('\0')
+            .filter(|p| !p.is_empty() && !is_excluded(p))
+            .filter(|p| root.join(p).is_file())
+            .map(String::from)
+            .collect();
+        files.sort();
+        files.dedup();
+        return files;
+    }
+
+    let mut files: Vec<String> = WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|e| {
+            let rel = e.path().strip_prefix(root).unwrap_or(e.path());
+            !is_excluded(&rel.to_string_lossy())
+        })
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            e.path()
+                .strip_prefix(root)
+                .ok()
+                .map(|p| p.to_string_lossy().replace('\\', "/"))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+fn is_excluded(rel: &str) -> bool {
+    rel.split('/')
+        .any(|c| matches!(c, ".git" | "target" | ".yo" | "node_modules"))
+}
+
+/// Identifies the current file contents: the HEAD tree hash plus every
+/// uncommitted file's size and mtime, or (outside git) all files' metadata
+fn cache_key(root: &Path) -> String {
+    let mut hasher = Sha256::new();
+    let tree = git(root, &["rev-parse", "HEAD^{tree}"]);
+    let status = git(
+        root,
+        &["status", "--porcelain=v1", "-z", "--untracked-files=all"],
+    );
+    let dirty: Vec<String> = match (&tree, &status) {
+        (Some(tree), Some(status)) => {
+            hasher.update(tree.trim().as_bytes());
+            let mut paths = Vec::new();
+            let mut entries = status.split('\0');
+            while let Some(entry) = entries.next() {
+                // Renames and copies are followed by their source path
+                if entry.starts_with(['R', 'C']) {
+                    entries.next();
+                }
+                if let Some(path) = entry.get(3..).filter(|p| !is_excluded(p)) {
+                    paths.push(path.to_string());
+                }
+            }
+            paths
+        }
+        _ => list_files(root),
+    };
+    for path in dirty {
+        hasher.update(path.as_bytes());
+        if let Ok(meta) = std::fs::metadata(root.join(&path)) {
+            let mtime = meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_nanos())
+                .unwrap_or(0);
+            hasher.update(format!(":{}:{}\n", meta.len(), mtime).as_bytes());
+        }
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_extract_symbols_per_language() {
+        let rust = "pub fn run() {}\npub(crate) struct Config;\nimpl Config {\n    fn inner() {}\n}\nconst fn build() {}\nmacro_rules! m { () => {} }\n";
+        let names: Vec<_> = extract_symbols(Lang::Rust, rust)
+            .into_iter()
+            .map(|s| (s.kind, s.name))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("fn".into(), "run".into()),
+                ("struct".into(), "Config".into()),
+                ("fn".into(), "build".into()),
+                ("macro".into(), "m".into()),
+            ]
+        );
+
+        let py = "class Foo:\n    def method(self): pass\nasync def main():\n    pass\n";
+        let names: Vec<_> = extract_symbols(Lang::Python, py)
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        assert_eq!(names, vec!["Foo", "main"]);
+
+        let js = "import x from 'y';\nconst local = 1;\nexport const API = 2;\nexport default class App {}\nexport async function load() {}\ninterface Props {}\n";
+        let names: Vec<_> = extract_symbols(Lang::Js, js)
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        assert_eq!(names, vec!["API", "App", "load", "Props"]);
+
+        let go = "package main\nfunc main() {}\nfunc (s *Server) Start() error {}\ntype Server struct {}\n";
+        let kinds: Vec<_> = extract_symbols(Lang::Go, go)
+            .into_iter()
+            .map(|s| (s.kind, s.name))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("func".into(), "main".into()),
+                ("method".into(), "Start".into()),
+                ("type".into(), "Server".into()),
+            ]
+        );
+    }
+
+    fn setup() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("src/util")).unwrap();
+        fs::write(
+            dir.path().join("src/lib.rs"),
+            "pub fn popular() {}\npub fn lonely() {}\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("src/util/a.rs"),
+            "fn caller() { popular(); popular(); }\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("README.md"), "# hi\n").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_ranking_and_tree_render() {
+        let dir = setup();
+        let map = RepoMap::load_or_build(dir.path(), false);
+        let lib = map.files.iter().find(|f| f.path == "src/lib.rs").unwrap();
+        assert_eq!(lib.symbols[0].name, "popular");
+        assert_eq!(lib.symbols[0].refs, 2);
+        assert_eq!(lib.symbols[1].refs, 0);
+
+        let text = map.render(1000);
+        assert!(text.contains("(3 of 3 files shown)"), "{}", text);
+        assert!(text.contains("src/\n  lib.rs: popular, lonely\n  util/\n    a.rs: caller"));
+        assert!(text.contains("\nREADME.md"));
+
+        // A tiny budget keeps the best-ranked file only
+        let text = map.render(8);
+        assert!(text.contains("lib.rs"), "{}", text);
+        assert!(!text.contains("README.md"), "{}", text);
+    }
+
+    #[test]
+    fn test_cache_reused_until_files_change() {
+        let dir = setup();
+        let first = RepoMap::load_or_build(dir.path(), false);
+        assert!(dir.path().join(".yo").join(CACHE_FILE).exists());
+        let second = RepoMap::load_or_build(dir.path(), false);
+        assert_eq!(first.key, second.key);
+
+        fs::write(dir.path().join("src/new.rs"), "pub struct Added;\n").unwrap();
+        let third = RepoMap::load_or_build(dir.path(), false);
+        assert_ne!(first.key, third.key);
+        assert!(third.files.iter().any(|f| f.path == "src/new.rs"));
+    }
+}
@@ -0,0 +1,533 @@
+//! On-disk inverted index for lexical code search.
+//!
+//! The index lives in `.yo/index/` and covers the same files the Search tool
+//! walks. Each refresh stats every file and re-reads only those whose size or
+//! mtime changed (a content hash avoids reindexing files that were merely
+//! touched). Text is split into identifier-aware terms: every word is indexed
+//! whole and, when it is camelCase or snake_case, also by its parts, so
+//! `parseHttpRequest` matches queries for "http request".
+//!
+//! Queries are ranked with BM25. Because whole words are kept, the vocabulary
+//! can also tell which files may contain a literal substring, which lets regex
+//! Search skip files that cannot match.
+
+use crate::policy::SecretFiles;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use walkdir::WalkDir;
+
+const INDEX_VERSION: u32 = 1;
+const INDEX_DIR: &str = "index";
+const INDEX_FILE: &str = "index.json";
+
+/// Larger files are not tokenized; they are always Search candidates
+const MAX_INDEXED_BYTES: u64 = 1024 * 1024;
+
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum DocState {
+    Indexed,
+    TooLarge,
+    /// Not UTF-8, which Search skips as well
+    Binary,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Doc {
+    path: String,
+    size: u64,
+    mtime_ns: u64,
+    hash: String,
+    /// Number of terms, for BM25 length normalization
+    len: u32,
+    state: DocState,
+}
+
+/// What a refresh changed
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RefreshStats {
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+}
+
+impl RefreshStats {
+    fn changed(&self) -> bool {
+        self.added + self.updated + self.removed > 0
+    }
+}
+
+/// A ranked file from a BM25 query
+#[derive(Debug, Clone)]
+pub struct Hit {
+    pub path: String,
+    pub score: f64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SearchIndex {
+    version: u32,
+    /// Document slots; removed documents leave a hole that is reused
+    docs: Vec<Option<Doc>>,
+    /// term -> (doc id, term frequency)
+    terms: HashMap<String, Vec<(u32, u32)>>,
+    #[serde(skip)]
+    by_path: HashMap<String, u32>,
+}
+
+impl SearchIndex {
+    fn index_path(root: &Path) -> std::path::PathBuf {
+        root.join(".yo").join(INDEX_DIR).join(INDEX_FILE)
+    }
+
+    /// Whether an index has been built for this project
+    pub fn exists(root: &Path) -> bool {
+        Self::index_path(root).exists()
+    }
+
+    /// Load the index (or start an empty one) and bring it up to date with disk;
+    /// secret files are never indexed
+    pub fn open(root: &Path, secrets: &SecretFiles) -> Result<(Self, RefreshStats)> {
+        let mut index = std::fs::read_to_string(Self::index_path(root))
+            .ok()
+            .and_then(|s| serde_json::from_str::<SearchIndex>(&s).ok())
+            .filter(|i| i.version == INDEX_VERSION)
+            .unwrap_or_else(|| SearchIndex {
+                version: INDEX_VERSION,
+                ..Default::default()
+            });
+        index.by_path = index
+            .docs
+            .iter()
+            .enumerate()
+            .filter_map(|(id, d)| d.as_ref().map(|d| (d.path.clone(), id as u32)))
+            .collect();
+
+        let stats = index.refresh(root, secrets);
+        if stats.changed() || !Self::exists(root) {
+            index.save(root)?;
+        }
+        Ok((index, stats))
+    }
+
+    fn save(&self, root: &Path) -> Result<()> {
+        let path = Self::index_path(root);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Write then rename, so a concurrent reader never sees a partial file
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string(self)?)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.by_path.len()
+    }
+
+    fn refresh(&mut self, root: &Path, secrets: &SecretFiles) -> RefreshStats {
+        let mut stats = RefreshStats::default();
+        let mut seen: HashSet<String> = HashSet::new();
+        // Documents whose postings must be dropped, and new contents to add
+        let mut stale: HashSet<u32> = HashSet::new();
+        let mut fresh: Vec<(Doc, Option<String>)> = Vec::new();
+
+        for (rel, size, mtime_ns) in walk(root, secrets) {
+            seen.insert(rel.clone());
+            let existing = self
+                .by_path
+                .get(&rel)
+                .and_then(|&id| self.docs[id as usize].as_ref().map(|d| (id, d)));
+            if let Some((_, doc)) = existing {
+                if doc.size == size && doc.mtime_ns == mtime_ns {
+                    continue;
+                }
+            }
+            let Ok(bytes) = std::fs::read(root.join(&rel)) else {
+                continue;
+            };
+            let hash = format!("{:x}", Sha256::digest(&bytes));
+            if let Some((id, doc)) = existing {
+                if doc.hash == hash {
+                    // Touched but unchanged
+                    if let Some(doc) = self.docs[id as usize].as_mut() {
+                        doc.mtime_ns = mtime_ns;
+                        doc.size = size;
+                    }
+                    stats.updated += 1;
+                    continue;
+                }
+                stale.insert(id);
+                stats.updated += 1;
+            } else {
+                stats.added += 1;
+            }
+
+            let (state, text) = if size > MAX_INDEXED_BYTES {
+                (DocState::TooLarge, None)
+            } else {
+                match String::from_utf8(bytes) {
+                    Ok(text) => (DocState::Indexed, Some(text)),
+                    Err(_) => (DocState::Binary, None),
+                }
+            };
+            let doc = Doc {
+                path: rel,
+                size,
+                mtime_ns,
+                hash,
+                len: 0,
+                state,
+            };
+            fresh.push((doc, text));
+        }
+
+        for (path, &id) in &self.by_path {
+            if !seen.contains(path) {
+                stale.insert(id);
+                stats.removed += 1;
+            }
+        }
+
+        if !stale.is_empty() {
+            self.terms.retain(|_, postings| {
+                postings.retain(|(id, _)| !stale.contains(id));
+                !postings.is_empty()
+            });
+            for &id in &stale {
+                if let Some(doc) = self.docs[id as usize].take() {
+                    self.by_path.remove(&doc.path);
+                }
+            }
+        }
+
+        let mut free: Vec<u32> = self
+            .docs
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| d.is_none())
+            .map(|(id, _)| id as u32)
+            .rev()
+            .collect();
+        for (mut doc, text) in fresh {
+            let id = free.pop().unwrap_or_else(|| {
+                self.docs.push(None);
+                (self.docs.len() - 1) as u32
+            });
+            if let Some(text) = text {
+                let mut counts: HashMap<String, u32> = HashMap::new();
+                let mut len = 0;
+                // The path counts too, so "config loader" finds config/loader.rs
+                for source in [doc.path.as_str(), text.as_str()] {
+                    tokenize(source, |term| {
+                        *counts.entry(term.to_string()).or_insert(0) += 1;
+                        len += 1;
+                    });
+                }
+                for (term, tf) in counts {
+                    self.terms.entry(term).or_default().push((id, tf));
+                }
+                doc.len = len;
+            }
+            self.by_path.insert(doc.path.clone(), id);
+            self.docs[id as usize] = Some(doc);
+        }
+
+        stats
+    }
+
+    /// Rank indexed files against a free-text or identifier query
+    pub fn search(&self, query: &str, filter: impl Fn(&str) -> bool) -> Vec<Hit> {
+        let mut query_terms: Vec<String> = Vec::new();
+        tokenize(query, |t| {
+            if !query_terms.iter().any(|q| q == t) {
+                query_terms.push(t.to_string());
+            }
+        });
+
+        let indexed: Vec<&Doc> = self
+            .docs
+            .iter()
+            .flatten()
+            .filter(|d| d.state == DocState::Indexed)
+            .collect();
+        let n = indexed.len() as f64;
+        if n == 0.0 {
+            return Vec::new();
+        }
+        let avg_len = indexed.iter().map(|d| d.len as f64).sum::<f64>() / n;
+
+        let mut scores: HashMap<u32, f64> = HashMap::new();
+        for term in &query_terms {
+            let Some(postings) = self.terms.get(term) else {
+                continue;
+            };
+            let df = postings.len() as f64;
+            let idf = (1.0 + (n - df + 0.5) / (df + 0.5)).ln();
+            for &(id, tf) in postings {
+                let Some(doc) = self.docs[id as usize].as_ref() else {
+                    continue;
+                };
+                let tf = tf as f64;
+                let norm = 1.0 - BM25_B + BM25_B * doc.len as f64 / avg_len.max(1.0);
+                *scores.entry(id).or_insert(0.0) +=
+                    idf * tf * (BM25_K1 + 1.0) / (tf + BM25_K1 * norm);
+            }
+        }
+
+        let mut hits: Vec<Hit> = scores
+            .into_iter()
+            .filter_map(|(id, score)| {
+                let doc = self.docs[id as usize].as_ref()?;
+                filter(&doc.path).then(|| Hit {
+                    path: doc.path.clone(),
+                    score,
+                })
+            })
+            .collect();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.path.cmp(&b.path)));
+        hits
+    }
+
+    /// Files that may contain `literal` (case-insensitively): those with a
+    /// word containing it, plus files too large to index. `literal` must
+    /// consist of ASCII word characters.
+    pub fn candidates(&self, literal: &str) -> HashSet<String> {
+        let needle = literal.to_ascii_lowercase();
+        let mut ids: HashSet<u32> = HashSet::new();
+        for (term, postings) in &self.terms {
+            if term.contains(&needle) {
+                ids.extend(postings.iter().map(|(id, _)| *id));
+            }
+        }
+        self.docs
+            .iter()
+            .enumerate()
+            .filter_map(|(id, d)| d.as_ref().map(|d| (id as u32, d)))
+            .filter(|(id, d)| d.state == DocState::TooLarge || ids.contains(id))
+            .map(|(_, d)| d.path.clone())
+            .collect()
+    }
+}
+
+/// Files under root (relative, with size and mtime), skipping the same
+/// directories and secret files as the Search tool
+fn walk(root: &Path, secrets: &SecretFiles) -> Vec<(String, u64, u64)> {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|e| {
+            let rel = e.path().strip_prefix(root).unwrap_or(e.path());
+            !rel.components().any(|c| {
+                matches!(
+                    c.as_os_str().to_str(),
+                    Some(".git" | "target" | ".yo" | "node_modules")
+                )
+            })
+        })
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let meta = e.metadata().ok()?;
+            let mtime_ns = meta
+                .modified()
+                .ok()?
+                .duration_since(std::time::UNIX_EPOCH)
+                .ok()?
+                .as_nanos() as u64;
+            let rel = e
+                .path()
+                .strip_prefix(root)
+                .ok()?
+                .to_string_lossy()
+                .to_string();
+            if secrets.matches(&rel).is_some() {
+                return None;
+            }
+            Some((rel, meta.len(), mtime_ns))
+        })
+        .collect()
+}
+
+/// Emit lowercase terms: each ASCII word whole, then its camelCase/snake_case
+/// parts when there is more than one. Single characters are skipped.
+pub fn tokenize(text: &str, mut emit: impl FnMut(&str)) {
+    for word in text
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .filter(|w| !w.is_empty())
+    {
+        let lower = word.to_ascii_lowercase();
+        if lower.len() >= 2 {
+            emit(&lower);
+        }
+        let parts = split_identifier(word);
+        if parts.len() > 1 {
+            for part in parts {
+                if part.len() >= 2 {
+                    emit(&part.to_ascii_lowercase());
+                }
+            }
+        }
+    }
+}
+
+/// `parseHTTPRequest_v2` -> ["parse", "HTTP", "Request", "v2"]
+fn split_identifier(word: &str) -> Vec<&str> {
+    let bytes = word.as_bytes();
+    let mut parts = Vec::new();
+    let mut start = 0;
+    for i in 0..bytes.len() {
+        if bytes[i] == b'_' {
+            if i > start {
+                parts.push(&word[start..i]);
+            }
+            start = i + 1;
+            continue;
+        }
+        if i == start {
+            continue;
+        }
+        let prev = bytes[i - 1];
+        let cur = bytes[i];
+        let next_lower = bytes.get(i + 1).is_some_and(|b| b.is_ascii_lowercase());
+        // aB, or the B in ABc (end of an acronym)
+        let boundary = (prev.is_ascii_lowercase() || prev.is_ascii_digit())
+            && cur.is_ascii_uppercase()
+            || prev.is_ascii_uppercase() && cur.is_ascii_uppercase() && next_lower;
+        if boundary {
+            parts.push(&word[start..i]);
+            start = i;
+        }
+    }
+    if start < word.len() {
+        parts.push(&word[start..]);
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn terms(text: &str) -> Vec<String> {
+        let mut out = Vec::new();
+        tokenize(text, |t| out.push(t.to_string()));
+        out
+    }
+
+    #[test]
+    fn test_tokenize_identifiers() {
+        assert_eq!(
+            terms("parseHTTPRequest"),
+            vec!["parsehttprequest", "parse", "http", "request"]
+        );
+        assert_eq!(
+            terms("MAX_FILE_BYTES = 1"),
+            vec!["max_file_bytes", "max", "file", "bytes"]
+        );
+        assert_eq!(terms("a.b utf8"), vec!["utf8"]);
+    }
+
+    fn setup() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(
+            dir.path().join("src/http.rs"),
+            "fn parse_http_request(raw: &str) -> Request {\n    // parse the request line\n    todo!()\n}\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("src/config.rs"),
+            "struct ConfigLoader;\nimpl ConfigLoader { fn load() {} }\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("blob.bin"), [0xff, 0xfe, 0x00]).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_bm25_ranking() {
+        let dir = setup();
+        let (index, stats) = SearchIndex::open(dir.path(), &SecretFiles::default()).unwrap();
+        assert_eq!(stats.added, 3);
+        assert!(SearchIndex::exists(dir.path()));
+
+        let hits = index.search("http request parsing", |_| true);
+        assert_eq!(hits[0].path, "src/http.rs");
+
+        let hits = index.search("configLoader", |_| true);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].path, "src/config.rs");
+
+        let hits = index.search("load", |p| p.ends_with("http.rs"));
+        assert!(hits.is_empty());
+    }
+
+    #[test]
+    fn test_incremental_refresh() {
+        let dir = setup();
+        SearchIndex::open(dir.path(), &SecretFiles::default()).unwrap();
+
+        let (_, stats) = SearchIndex::open(dir.path(), &SecretFiles::default()).unwrap();
+        assert_eq!(stats, RefreshStats::default());
+
+        fs::write(
+            dir.path().join("src/config.rs"),
+            "fn reload_settings() {}\n",
+        )
+        .unwrap();
+        fs::remove_file(dir.path().join("src/http.rs")).unwrap();
+        fs::write(dir.path().join("src/new.rs"), "fn fresh() {}\n").unwrap();
+        let (index, stats) = SearchIndex::open(dir.path(), &SecretFiles::default()).unwrap();
+        assert_eq!(
+            stats,
+            RefreshStats {
+                added: 1,
+                updated: 1,
+                removed: 1
+            }
+        );
+        assert_eq!(index.file_count(), 3);
+        assert!(index.search("loader", |_| true).is_empty());
+        assert_eq!(index.search("settings", |_| true)[0].path, "src/config.rs");
+        assert!(index.search("http", |_| true).is_empty());
+    }
+
+    #[test]
+    fn test_literal_candidates() {
+        let dir = setup();
+        let (index, _) = SearchIndex::open(dir.path(), &SecretFiles::default()).unwrap();
+        let c = index.candidates("http_req");
+        assert_eq!(c, HashSet::from(["src/http.rs".to_string()]));
+        let c = index.candidates("Loader");
+        assert_eq!(c, HashSet::from(["src/config.rs".to_string()]));
+        assert!(index.candidates("nowhere").is_empty());
+    }
+
+    #[test]
+    fn test_secret_files_not_indexed() {
+        let dir = setup();
+        fs::write(dir.path().join(".env"), "API_TOKEN=hunter2\n").unwrap();
+        fs::create_dir_all(dir.path().join("certs")).unwrap();
+        fs::write(dir.path().join("certs/tls.pem"), "PRIVATE KEY hunter2\n").unwrap();
+        let (index, stats) = SearchIndex::open(dir.path(), &SecretFiles::default()).unwrap();
+        assert_eq!(stats.added, 3);
+        assert!(index.search("hunter2", |_| true).is_empty());
+        assert!(index.candidates("hunter2").is_empty());
+
+        // A configured list replaces the built-in one
+        let secrets = SecretFiles::new(Some(&vec!["src/config.rs".to_string()]));
+        let (index, stats) = SearchIndex::open(dir.path(), &secrets).unwrap();
+        assert_eq!((stats.added, stats.removed), (2, 1));
+        assert_eq!(index.search("hunter2", |_| true).len(), 2);
+        assert!(index.search("configLoader", |_| true).is_empty());
+    }
+}
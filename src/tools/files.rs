@@ -0,0 +1,531 @@
+//! File management tools: Move, Copy, Delete and MakeDir.
+//!
+//! Every path is scoped with `validate_path`. The project root itself and
+//! anything inside a `.git` directory are refused, and directory trees
+//! containing a nested `.git` are never moved or deleted. Symlinks are acted on
+//! as links, never followed to their target.
+
+use super::{normalize_path, sha256, validate_path, SchemaOptions};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+pub fn move_schema(opts: &SchemaOptions) -> Value {
+    if opts.optimize {
+        json!({
+            "type": "function",
+            "function": {
+                "name": "Move",
+                "description": "Move/rename file or dir",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "source": { "type": "string" },
+                        "destination": { "type": "string" },
+                        "overwrite": { "type": "boolean" }
+                    },
+                    "required": ["source", "destination"]
+                }
+            }
+        })
+    } else {
+        json!({
+            "type": "function",
+            "function": {
+                "name": "Move",
+                "description": "Move or rename a file or directory. Parent directories of the destination are created. Requires permission.",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "source": { "type": "string", "description": "Existing file or directory, relative to root" },
+                        "destination": { "type": "string", "description": "New path, relative to root" },
+                        "overwrite": { "type": "boolean", "description": "Replace an existing destination file (default false)" }
+                    },
+                    "required": ["source", "destination"]
+                }
+            }
+        })
+    }
+}
+
+pub fn copy_schema(opts: &SchemaOptions) -> Value {
+    if opts.optimize {
+        json!({
+            "type": "function",
+            "function": {
+                "name": "Copy",
+                "description": "Copy file or dir",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "source": { "type": "string" },
+                        "destination": { "type": "string" },
+                        "overwrite": { "type": "boolean" }
+                    },
+                    "required": ["source", "destination"]
+                }
+            }
+        })
+    } else {
+        json!({
+            "type": "function",
+            "function": {
+                "name": "Copy",
+                "description": "Copy a file, or a directory recursively (symlinks and .git entries are skipped). Requires permission.",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "source": { "type": "string", "description": "Existing file or directory, relative to root" },
+                        "destination": { "type": "string", "description": "Path of the copy, relative to root" },
+                        "overwrite": { "type": "boolean", "description": "Replace an existing destination file (default false)" }
+                    },
+                    "required": ["source", "destination"]
+                }
+            }
+        })
+    }
+}
+
+pub fn delete_schema(opts: &SchemaOptions) -> Value {
+    if opts.optimize {
+        json!({
+            "type": "function",
+            "function": {
+                "name": "Delete",
+                "description": "Delete file or dir",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "path": { "type": "string" },
+                        "recursive": { "type": "boolean" }
+                    },
+                    "required": ["path"]
+                }
+            }
+        })
+    } else {
+        json!({
+            "type": "function",
+            "function": {
+                "name": "Delete",
+                "description": "Delete a file or directory. Non-empty directories need recursive=true. Requires permission.",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "path": { "type": "string", "description": "File or directory relative to root" },
+                        "recursive": { "type": "boolean", "description": "Delete a non-empty directory and its contents (default false)" }
+                    },
+                    "required": ["path"]
+                }
+            }
+        })
+    }
+}
+
+pub fn make_dir_schema(opts: &SchemaOptions) -> Value {
+    if opts.optimize {
+        json!({
+            "type": "function",
+            "function": {
+                "name": "MakeDir",
+                "description": "Create directory",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "path": { "type": "string" }
+                    },
+                    "required": ["path"]
+                }
+            }
+        })
+    } else {
+        json!({
+            "type": "function",
+            "function": {
+                "name": "MakeDir",
+                "description": "Create a directory and any missing parents. Succeeds if it already exists. Requires permission.",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "path": { "type": "string", "description": "Directory path relative to root" }
+                    },
+                    "required": ["path"]
+                }
+            }
+        })
+    }
+}
+
+pub fn execute_move(args: Value, root: &Path) -> anyhow::Result<Value> {
+    let (source, destination) = match transfer_paths(&args, root) {
+        Ok(p) => p,
+        Err(e) => return Ok(e),
+    };
+    let (src, dst) = (&source.1, &destination.1);
+    if src.is_dir() && contains_git(src) {
+        return Ok(protected(&source.0));
+    }
+
+    let summary = describe(src);
+    if fs::rename(src, dst).is_err() {
+        // Renames fail across filesystems; fall back to copy and remove
+        let moved = copy_entry(src, dst).and_then(|_| remove_entry(src));
+        if let Err(e) = moved {
+            return Ok(json!({ "error": { "code": "move_error", "message": e.to_string() } }));
+        }
+    }
+
+    let mut result = json!({ "source": source.0, "destination": destination.0 });
+    merge(&mut result, summary);
+    Ok(result)
+}
+
+pub fn execute_copy(args: Value, root: &Path) -> anyhow::Result<Value> {
+    let (source, destination) = match transfer_paths(&args, root) {
+        Ok(p) => p,
+        Err(e) => return Ok(e),
+    };
+    let (src, dst) = (&source.1, &destination.1);
+
+    let skipped = match copy_entry(src, dst) {
+        Ok(skipped) => skipped,
+        Err(e) => {
+            return Ok(json!({ "error": { "code": "copy_error", "message": e.to_string() } }))
+        }
+    };
+
+    let mut result = json!({ "source": source.0, "destination": destination.0 });
+    merge(&mut result, describe(dst));
+    if skipped > 0 {
+        result["skipped"] = json!(skipped);
+    }
+    Ok(result)
+}
+
+pub fn execute_delete(args: Value, root: &Path) -> anyhow::Result<Value> {
+    let path = args["path"].as_str().unwrap_or("");
+    let recursive = args["recursive"].as_bool().unwrap_or(false);
+
+    let full = match resolve(path, root) {
+        Ok(p) => p,
+        Err(e) => return Ok(e),
+    };
+    if full.symlink_metadata().is_err() {
+        return Ok(not_found(path));
+    }
+    if full.is_dir() && !full.is_symlink() {
+        if contains_git(&full) {
+            return Ok(protected(path));
+        }
+        let empty = fs::read_dir(&full)
+            .map(|mut d| d.next().is_none())
+            .unwrap_or(false);
+        if !empty && !recursive {
+            return Ok(json!({
+                "error": {
+                    "code": "directory_not_empty",
+                    "message": format!("{} is not empty; set recursive=true to delete it", path)
+                }
+            }));
+        }
+    }
+
+    let summary = describe(&full);
+    if let Err(e) = remove_entry(&full) {
+        return Ok(json!({ "error": { "code": "delete_error", "message": e.to_string() } }));
+    }
+
+    let mut result = json!({ "path": path });
+    merge(&mut result, summary);
+    Ok(result)
+}
+
+pub fn execute_make_dir(args: Value, root: &Path) -> anyhow::Result<Value> {
+    let path = args["path"].as_str().unwrap_or("");
+
+    let full = match resolve(path, root) {
+        Ok(p) => p,
+        Err(e) => return Ok(e),
+    };
+    if full.is_dir() {
+        return Ok(json!({ "path": path, "created": false }));
+    }
+    if full.symlink_metadata().is_ok() {
+        return Ok(
+            json!({ "error": { "code": "file_exists", "message": format!("{} exists and is not a directory", path) } }),
+        );
+    }
+    if let Err(e) = fs::create_dir_all(&full) {
+        return Ok(json!({ "error": { "code": "mkdir_error", "message": e.to_string() } }));
+    }
+
+    Ok(json!({ "path": path, "created": true }))
+}
+
+/// Scope a path to the project without following a final symlink, so the
+/// operation applies to the entry itself rather than what it points at
+fn resolve(path: &str, root: &Path) -> Result<PathBuf, Value> {
+    validate_path(path, root)?;
+
+    let full = normalize_path(&root.join(path));
+    let (Some(parent), Some(name)) = (full.parent(), full.file_name()) else {
+        return Err(protected(path));
+    };
+    let entry = parent
+        .canonicalize()
+        .unwrap_or_else(|_| parent.to_path_buf())
+        .join(name);
+
+    let Ok(relative) = entry.strip_prefix(root) else {
+        return Err(
+            json!({ "error": { "code": "path_out_of_scope", "message": "Path escapes project root" } }),
+        );
+    };
+    if relative.as_os_str().is_empty() || relative.components().any(|c| c.as_os_str() == ".git") {
+        return Err(protected(path));
+    }
+    Ok(entry)
+}
+
+/// Resolve and check the source and destination of a Move or Copy
+#[allow(clippy::type_complexity)]
+fn transfer_paths(
+    args: &Value,
+    root: &Path,
+) -> Result<((String, PathBuf), (String, PathBuf)), Value> {
+    let source = args["source"].as_str().unwrap_or("");
+    let destination = args["destination"].as_str().unwrap_or("");
+    let overwrite = args["overwrite"].as_bool().unwrap_or(false);
+
+    let src = resolve(source, root)?;
+    let dst = resolve(destination, root)?;
+
+    if src.symlink_metadata().is_err() {
+        return Err(not_found(source));
+    }
+    if dst.starts_with(&src) {
+        return Err(json!({
+            "error": {
+                "code": "invalid_destination",
+                "message": "Destination must not be the source or inside it"
+            }
+        }));
+    }
+    if dst.symlink_metadata().is_ok() {
+        if dst.is_dir() && !dst.is_symlink() {
+            return Err(
+                json!({ "error": { "code": "file_exists", "message": format!("{} is an existing directory", destination) } }),
+            );
+        }
+        if !overwrite {
+            return Err(
+                json!({ "error": { "code": "file_exists", "message": "Destination exists and overwrite=false" } }),
+            );
+        }
+    }
+    if let Some(parent) = dst.parent() {
+        fs::create_dir_all(parent).ok();
+    }
+
+    Ok(((source.to_string(), src), (destination.to_string(), dst)))
+}
+
+/// Kind of entry plus its file count (directories) or content hash (files)
+fn describe(path: &Path) -> Value {
+    if path.is_dir() && !path.is_symlink() {
+        let files = WalkDir::new(path)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| !e.file_type().is_dir())
+            .count();
+        json!({ "kind": "directory", "files": files })
+    } else if path.is_symlink() {
+        json!({ "kind": "symlink" })
+    } else {
+        let hash = fs::read(path).map(|data| sha256(&data)).unwrap_or_default();
+        json!({ "kind": "file", "sha256": hash })
+    }
+}
+
+fn merge(target: &mut Value, extra: Value) {
+    if let (Some(target), Value::Object(extra)) = (target.as_object_mut(), extra) {
+        target.extend(extra);
+    }
+}
+
+/// Copy a file, symlink or directory tree; returns how many entries were skipped
+fn copy_entry(src: &Path, dst: &Path) -> std::io::Result<usize> {
+    if src.is_symlink() {
+        let target = fs::read_link(src)?;
+        if dst.symlink_metadata().is_ok() {
+            fs::remove_file(dst)?;
+        }
+        symlink(&target, dst)?;
+        return Ok(0);
+    }
+    if !src.is_dir() {
+        fs::copy(src, dst)?;
+        return Ok(0);
+    }
+
+    let mut skipped = 0;
+    let mut walker = WalkDir::new(src).into_iter();
+    while let Some(entry) = walker.next() {
+        let entry = entry.map_err(std::io::Error::other)?;
+        if entry.file_name() == ".git" {
+            skipped += 1;
+            if entry.file_type().is_dir() {
+                walker.skip_current_dir();
+            }
+            continue;
+        }
+        let target = dst.join(entry.path().strip_prefix(src).unwrap_or(entry.path()));
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)?;
+        } else if entry.file_type().is_symlink() {
+            skipped += 1;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(skipped)
+}
+
+fn remove_entry(path: &Path) -> std::io::Result<()> {
+    if path.is_dir() && !path.is_symlink() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(not(unix))]
+fn symlink(_target: &Path, _link: &Path) -> std::io::Result<()> {
+    Err(std::io::Error::other(
+        "copying symlinks is not supported on this platform",
+    ))
+}
+
+/// Whether a directory tree holds a nested repository
+fn contains_git(dir: &Path) -> bool {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .any(|e| e.file_name() == ".git")
+}
+
+fn protected(path: &str) -> Value {
+    json!({
+        "error": {
+            "code": "protected_path",
+            "message": format!("Refusing to modify {}: the project root and .git are protected", path)
+        }
+    })
+}
+
+fn not_found(path: &str) -> Value {
+    json!({ "error": { "code": "not_found", "message": format!("Not found: {}", path) } })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn project() -> (TempDir, PathBuf) {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("src/net")).unwrap();
+        fs::write(root.join("src/net/http.rs"), "fn get() {}\n").unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join(".git/HEAD"), "ref: refs/heads/main\n").unwrap();
+        (dir, root)
+    }
+
+    #[test]
+    fn test_move_and_copy() {
+        let (_dir, root) = project();
+
+        let result = execute_move(
+            json!({ "source": "src/net", "destination": "src/transport/net" }),
+            &root,
+        )
+        .unwrap();
+        assert_eq!(result["kind"], "directory");
+        assert_eq!(result["files"], 1);
+        assert!(root.join("src/transport/net/http.rs").is_file());
+        assert!(!root.join("src/net").exists());
+
+        let result = execute_copy(
+            json!({ "source": "src/main.rs", "destination": "src/bin/tool.rs" }),
+            &root,
+        )
+        .unwrap();
+        assert_eq!(result["kind"], "file");
+        assert_eq!(result["sha256"], sha256(b"fn main() {}\n"));
+        assert!(root.join("src/main.rs").is_file());
+
+        let result = execute_copy(
+            json!({ "source": "src/main.rs", "destination": "src/bin/tool.rs" }),
+            &root,
+        )
+        .unwrap();
+        assert_eq!(result["error"]["code"], "file_exists");
+
+        let result = execute_move(
+            json!({ "source": "src", "destination": "src/inner" }),
+            &root,
+        )
+        .unwrap();
+        assert_eq!(result["error"]["code"], "invalid_destination");
+    }
+
+    #[test]
+    fn test_delete_and_make_dir() {
+        let (_dir, root) = project();
+
+        let result = execute_delete(json!({ "path": "src" }), &root).unwrap();
+        assert_eq!(result["error"]["code"], "directory_not_empty");
+
+        let result = execute_delete(json!({ "path": "src", "recursive": true }), &root).unwrap();
+        assert_eq!(result["kind"], "directory");
+        assert_eq!(result["files"], 2);
+        assert!(!root.join("src").exists());
+
+        let result = execute_make_dir(json!({ "path": "docs/api" }), &root).unwrap();
+        assert_eq!(result["created"], true);
+        let result = execute_make_dir(json!({ "path": "docs/api" }), &root).unwrap();
+        assert_eq!(result["created"], false);
+    }
+
+    #[test]
+    fn test_refuses_git_and_root() {
+        let (_dir, root) = project();
+
+        for path in [".git", ".git/HEAD", ".", "src/..", ""] {
+            let result = execute_delete(json!({ "path": path, "recursive": true }), &root).unwrap();
+            assert_eq!(result["error"]["code"], "protected_path", "{}", path);
+        }
+        let result = execute_copy(
+            json!({ "source": "src/main.rs", "destination": ".git/hooks/pre-commit" }),
+            &root,
+        )
+        .unwrap();
+        assert_eq!(result["error"]["code"], "protected_path");
+
+        fs::create_dir_all(root.join("vendor/lib/.git")).unwrap();
+        let result = execute_delete(json!({ "path": "vendor", "recursive": true }), &root).unwrap();
+        assert_eq!(result["error"]["code"], "protected_path");
+
+        let result = execute_move(json!({ "source": "../x", "destination": "y" }), &root).unwrap();
+        assert_eq!(result["error"]["code"], "path_out_of_scope");
+        assert!(root.join(".git/HEAD").is_file());
+    }
+}
@@ -0,0 +1,269 @@
+//! Post-mutation formatting.
+//!
+//! After a successful Write, Edit or Patch, every `[format]` command whose glob
+//! matches a changed file is run on it. The tool result gets the post-format
+//! hash in place of the pre-format one, plus a `format` list describing each
+//! run. A failing formatter is reported there; the edit itself stays applied.
+
+use super::{bash, sha256};
+use crate::config::{BashConfig, FormatConfig};
+use glob::Pattern;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+const DEFAULT_TIMEOUT_MS: u64 = 30000;
+const MAX_ERROR_CHARS: usize = 500;
+
+/// Format the files changed by a successful mutation and update `result`
+pub fn after_mutation(
+    tool: &str,
+    result: &mut Value,
+    root: &Path,
+    config: &FormatConfig,
+    bash_config: &BashConfig,
+) {
+    // A dry run changed nothing on disk, so there is nothing to format
+    if config.commands.is_empty() || result.get("error").is_some() || result["dry_run"] == true {
+        return;
+    }
+    match tool {
+        "Write" => format_entry(result, "sha256", root, config, bash_config),
+        "Edit" => format_entry(result, "after_sha256", root, config, bash_config),
+        "Patch" => {
+            if let Some(files) = result.get_mut("files").and_then(|f| f.as_array_mut()) {
+                for entry in files {
+                    format_entry(entry, "after_sha256", root, config, bash_config);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Run the matching formatters on the file an entry describes; `hash_key`
+/// names the field holding the file's current sha256
+fn format_entry(
+    entry: &mut Value,
+    hash_key: &str,
+    root: &Path,
+    config: &FormatConfig,
+    bash_config: &BashConfig,
+) {
+    let Some(path) = entry.get("path").and_then(|p| p.as_str()).map(String::from) else {
+        return;
+    };
+    let commands = matching_commands(config, &path);
+    if commands.is_empty() {
+        return;
+    }
+
+    let timeout = Duration::from_millis(config.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS));
+    let mut runs = Vec::new();
+    for command in commands {
+        let before = std::fs::read(root.join(&path)).ok();
+        let mut run = match run_formatter(command, &path, root, bash_config, timeout) {
+            Ok(argv) => json!({ "command": argv, "ok": true }),
+            Err((argv, error)) => json!({ "command": argv, "ok": false, "error": error }),
+        };
+        let after = std::fs::read(root.join(&path)).ok();
+        run["changed"] = json!(before != after);
+        let failed = run["ok"] == false;
+        runs.push(run);
+        if failed {
+            break;
+        }
+    }
+
+    if let Ok(data) = std::fs::read(root.join(&path)) {
+        entry[hash_key] = json!(sha256(&data));
+    }
+    entry["format"] = json!(runs);
+}
+
+/// Commands whose glob matches `path`, in glob order
+fn matching_commands<'a>(config: &'a FormatConfig, path: &str) -> Vec<&'a str> {
+    config
+        .commands
+        .iter()
+        .filter(|(glob, _)| {
+            Pattern::new(glob).is_ok_and(|p| p.matches(path) || p.matches_path(Path::new(path)))
+        })
+        .map(|(_, command)| command.as_str())
+        .collect()
+}
+
+/// Run one formatter; returns the command line that ran, and on failure a
+/// short description of what went wrong
+fn run_formatter(
+    command: &str,
+    path: &str,
+    root: &Path,
+    bash_config: &BashConfig,
+    timeout: Duration,
+) -> Result<String, (String, String)> {
+    let mut words = match shell_words::split(command) {
+        Ok(words) if !words.is_empty() => words,
+        _ => return Err((command.to_string(), "invalid command".to_string())),
+    };
+    if words.iter().any(|w| w.contains("{file}")) {
+        for word in &mut words {
+            *word = word.replace("{file}", path);
+        }
+    } else {
+        words.push(path.to_string());
+    }
+    let argv = shell_words::join(&words);
+
+    let mut cmd = Command::new(&words[0]);
+    cmd.args(&words[1..]).current_dir(root);
+    let quiet = BashConfig {
+        live_output: Some(false),
+        ..bash_config.clone()
+    };
+    let captured = match bash::run_captured(cmd, root, &quiet, timeout) {
+        Ok(c) => c,
+        Err(e) => return Err((argv, e.to_string())),
+    };
+
+    match captured.status {
+        Ok(Some(status)) if status.success() => Ok(argv),
+        Ok(Some(status)) => {
+            let (stderr, stdout) = (captured.stderr.full(), captured.stdout.full());
+            let (stderr, stdout) = (
+                String::from_utf8_lossy(&stderr),
+                String::from_utf8_lossy(&stdout),
+            );
+            let output = if stderr.trim().is_empty() {
+                stdout
+            } else {
+                stderr
+            };
+            let detail: String = output.trim().chars().take(MAX_ERROR_CHARS).collect();
+            let code = status
+                .code()
+                .map_or("signal".to_string(), |c| c.to_string());
+            Err((argv, format!("exit {}: {}", code, detail)))
+        }
+        Ok(None) => Err((argv, format!("timed out after {}ms", timeout.as_millis()))),
+        Err(e) => Err((argv, e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn config(commands: &[(&str, &str)]) -> FormatConfig {
+        FormatConfig {
+            commands: commands
+                .iter()
+                .map(|(g, c)| (g.to_string(), c.to_string()))
+                .collect(),
+            timeout_ms: None,
+        }
+    }
+
+    #[test]
+    fn test_format_after_write_updates_hash() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/a.rs"), "fn  a() {}\n").unwrap();
+        let config = config(&[("**/*.rs", "sed -i s/fn\\ \\ a/fn\\ a/ {file}")]);
+
+        let mut result = json!({ "path": "src/a.rs", "sha256": "stale" });
+        after_mutation(
+            "Write",
+            &mut result,
+            dir.path(),
+            &config,
+            &BashConfig::default(),
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("src/a.rs")).unwrap(),
+            "fn a() {}\n"
+        );
+        assert_eq!(result["sha256"], sha256(b"fn a() {}\n"));
+        assert_eq!(result["format"][0]["ok"], true);
+        assert_eq!(result["format"][0]["changed"], true);
+        assert_eq!(
+            result["format"][0]["command"],
+            "sed -i 's/fn  a/fn a/' src/a.rs"
+        );
+
+        // Non-matching files and failed calls are left alone
+        let mut result = json!({ "path": "notes.md", "sha256": "x" });
+        after_mutation(
+            "Write",
+            &mut result,
+            dir.path(),
+            &config,
+            &BashConfig::default(),
+        );
+        assert!(result.get("format").is_none());
+        let mut result = json!({ "error": { "code": "write_error", "message": "denied" } });
+        after_mutation(
+            "Write",
+            &mut result,
+            dir.path(),
+            &config,
+            &BashConfig::default(),
+        );
+        assert!(result.get("format").is_none());
+    }
+
+    #[test]
+    fn test_format_failure_is_reported() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a.rs"), "fn a() {}\n").unwrap();
+        let config = config(&[("*.rs", "sh -c 'echo bad syntax >&2; exit 3'")]);
+
+        let mut result = json!({
+            "files": [{ "path": "a.rs", "after_sha256": "h" }]
+        });
+        after_mutation(
+            "Patch",
+            &mut result,
+            dir.path(),
+            &config,
+            &BashConfig::default(),
+        );
+        let run = &result["files"][0]["format"][0];
+        assert_eq!(run["ok"], false);
+        assert_eq!(run["error"], "exit 3: bad syntax");
+        assert_eq!(run["changed"], false);
+        assert_eq!(result["files"][0]["after_sha256"], sha256(b"fn a() {}\n"));
+    }
+
+    #[test]
+    fn test_dry_run_is_not_formatted() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a.rs"), "fn  a() {}\n").unwrap();
+        let config = config(&[("*.rs", "sed -i s/fn\\ \\ a/fn\\ a/ {file}")]);
+
+        let mut result = json!({
+            "dry_run": true,
+            "files": [
+                { "path": "a.rs", "after_sha256": "h" },
+                { "path": "new.rs", "after_sha256": "n" }
+            ]
+        });
+        after_mutation(
+            "Patch",
+            &mut result,
+            dir.path(),
+            &config,
+            &BashConfig::default(),
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("a.rs")).unwrap(),
+            "fn  a() {}\n"
+        );
+        assert!(!dir.path().join("new.rs").exists());
+        assert_eq!(result["files"][0]["after_sha256"], "h");
+        assert!(result["files"][1].get("format").is_none());
+    }
+}
@@ -0,0 +1,924 @@
+//! Git tool with structured output.
+//!
+//! Runs `git` directly (no shell) and parses porcelain formats into JSON so the
+//! agent doesn't have to scrape human-readable output. `status`, `diff`, `log`,
+//! `show` and `blame` are read-only; `add`, `commit` and `stash` change the
+//! repository and are permission-checked separately (see `policy`).
+
+use super::bash::{effective_timeout_ms, run_captured};
+use super::{validate_path, SchemaOptions};
+use crate::config::BashConfig;
+use crate::policy::SecretFiles;
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::cell::Cell;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+const DEFAULT_LOG_COUNT: usize = 20;
+const DEFAULT_MAX_DIFF_LINES: usize = 2000;
+
+#[derive(Debug, Deserialize)]
+struct GitArgs {
+    subcommand: String,
+    #[serde(default)]
+    paths: Vec<String>,
+    #[serde(default, rename = "ref")]
+    reference: Option<String>,
+    #[serde(default)]
+    staged: bool,
+    #[serde(default)]
+    max_count: Option<usize>,
+    #[serde(default)]
+    max_lines: Option<usize>,
+    #[serde(default)]
+    start_line: Option<usize>,
+    #[serde(default)]
+    end_line: Option<usize>,
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    action: Option<String>,
+}
+
+pub fn schema(opts: &SchemaOptions) -> Value {
+    if opts.optimize {
+        json!({
+            "type": "function",
+            "function": {
+                "name": "Git",
+                "description": "Structured git: status|diff|log|show|blame|add|commit|stash",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "subcommand": { "type": "string" },
+                        "paths": { "type": "array", "items": { "type": "string" } },
+                        "ref": { "type": "string" },
+                        "staged": { "type": "boolean" },
+                        "max_count": { "type": "integer" },
+                        "start_line": { "type": "integer" },
+                        "end_line": { "type": "integer" },
+                        "message": { "type": "string" },
+                        "action": { "type": "string" }
+                    },
+                    "required": ["subcommand"]
+                }
+            }
+        })
+    } else {
+        json!({
+            "type": "function",
+            "function": {
+                "name": "Git",
+                "description": "Run git and get structured JSON. Read-only: status, diff (unstaged, staged, or against ref; optional paths), log (optional ref and paths), show (commit metadata and diff), blame (one path, optional line range). Mutating (requires permission): add (paths), commit (message), stash (action push|pop|list).",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "subcommand": {
+                            "type": "string",
+                            "enum": ["status", "diff", "log", "show", "blame", "add", "commit", "stash"]
+                        },
+                        "paths": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Paths relative to root (diff, log, show, blame, add)"
+                        },
+                        "ref": { "type": "string", "description": "Commit, branch or range (diff, log, show, blame)" },
+                        "staged": { "type": "boolean", "description": "diff: compare index to HEAD instead of worktree to index" },
+                        "max_count": { "type": "integer", "description": "log: max commits (default 20)" },
+                        "max_lines": { "type": "integer", "description": "diff/show: max hunk lines returned (default 2000)" },
+                        "start_line": { "type": "integer", "description": "blame: first line (1-based)" },
+                        "end_line": { "type": "integer", "description": "blame: last line (inclusive)" },
+                        "message": { "type": "string", "description": "commit message, or stash message for push" },
+                        "action": { "type": "string", "enum": ["push", "pop", "list"], "description": "stash action (default push)" }
+                    },
+                    "required": ["subcommand"]
+                }
+            }
+        })
+    }
+}
+
+pub fn execute(
+    args: Value,
+    root: &Path,
+    bash_config: &BashConfig,
+    secrets: &SecretFiles,
+) -> Result<Value> {
+    let git_args: GitArgs = match serde_json::from_value(args) {
+        Ok(a) => a,
+        Err(e) => {
+            return Ok(
+                json!({ "error": { "code": "invalid_args", "message": format!("Invalid Git args: {}", e) } }),
+            )
+        }
+    };
+
+    if let Some(r) = &git_args.reference {
+        if r.starts_with('-') {
+            return Ok(
+                json!({ "error": { "code": "invalid_ref", "message": "ref must not start with '-'" } }),
+            );
+        }
+    }
+    for path in &git_args.paths {
+        if let Err(e) = validate_path(path, root) {
+            return Ok(e);
+        }
+    }
+
+    // Output is parsed, so keep its start whole rather than head and tail
+    let config = BashConfig {
+        head_bytes: Some(usize::MAX),
+        live_output: Some(false),
+        ..bash_config.clone()
+    };
+    let git = Git {
+        root,
+        config: &config,
+        secrets,
+        truncated: Cell::new(false),
+    };
+    let result = match git_args.subcommand.as_str() {
+        "status" => git.status(),
+        "diff" => git.diff(&git_args),
+        "log" => git.log(&git_args),
+        "show" => git.show(&git_args),
+        "blame" => git.blame(&git_args),
+        "add" => git.add(&git_args),
+        "commit" => git.commit(&git_args),
+        "stash" => git.stash(&git_args),
+        other => Err(json!({
+            "error": {
+                "code": "unknown_subcommand",
+                "message": format!("Unknown Git subcommand: {}", other)
+            }
+        })),
+    };
+
+    let mut result = result.unwrap_or_else(|e| e);
+    if git.truncated.get() {
+        if let Some(obj) = result.as_object_mut() {
+            obj.insert("output_truncated".into(), json!(true));
+        }
+    }
+    Ok(result)
+}
+
+struct Git<'a> {
+    root: &'a Path,
+    config: &'a BashConfig,
+    /// Files whose hunks diff and show leave out
+    secrets: &'a SecretFiles,
+    /// Set when git printed more than the Bash output limit
+    truncated: Cell<bool>,
+}
+
+impl Git<'_> {
+    /// Run git with the given arguments and return stdout, within the Bash
+    /// timeout. Output past the Bash output limit is dropped at the last
+    /// complete record and the result is marked `output_truncated`.
+    fn run(&self, args: &[&str]) -> Result<String, Value> {
+        let mut cmd = Command::new("git");
+        cmd.args([
+            "--no-pager",
+            "-c",
+            "color.ui=never",
+            "-c",
+            "core.quotepath=off",
+        ])
+        .args(args)
+        .current_dir(self.root);
+
+        let timeout_ms = effective_timeout_ms(self.config, None);
+        let timeout = Duration::from_millis(timeout_ms);
+        let captured = run_captured(cmd, self.root, self.config, timeout).map_err(|e| {
+            json!({ "error": { "code": "spawn_error", "message": format!("Failed to run git: {}", e) } })
+        })?;
+        let status = match captured.status {
+            Ok(Some(status)) => status,
+            Ok(None) => {
+                return Err(json!({
+                    "error": {
+                        "code": "timeout",
+                        "message": format!("git timed out after {}ms", timeout_ms)
+                    }
+                }))
+            }
+            Err(e) => {
+                return Err(json!({
+                    "error": { "code": "spawn_error", "message": format!("Failed to wait for git: {}", e) }
+                }))
+            }
+        };
+        if !status.success() {
+            let stderr = String::from_utf8_lossy(captured.stderr.head());
+            return Err(json!({
+                "error": {
+                    "code": "git_error",
+                    "message": stderr.trim(),
+                    "exit_code": status.code()
+                }
+            }));
+        }
+
+        let mut stdout = captured.stdout.head();
+        if captured.stdout.is_cut() {
+            self.truncated.set(true);
+            let end = stdout.iter().rposition(|&b| b == b'\n' || b == 0);
+            stdout = &stdout[..end.map_or(0, |i| i + 1)];
+        }
+        Ok(String::from_utf8_lossy(stdout).into_owned())
+    }
+
+    fn status(&self) -> Result<Value, Value> {
+        let out = self.run(&["status", "--porcelain=v2", "--branch", "-z"])?;
+        Ok(parse_status(&out))
+    }
+
+    fn diff(&self, args: &GitArgs) -> Result<Value, Value> {
+        let mut cmd = vec!["diff", "--no-ext-diff", "--no-textconv", "--find-renames"];
+        if args.staged {
+            cmd.push("--cached");
+        }
+        if let Some(r) = &args.reference {
+            cmd.push(r);
+        }
+        cmd.push("--");
+        cmd.extend(args.paths.iter().map(|p| p.as_str()));
+        let out = self.run(&cmd)?;
+        let max_lines = args.max_lines.unwrap_or(DEFAULT_MAX_DIFF_LINES);
+        Ok(parse_diff(&out, max_lines, self.secrets))
+    }
+
+    fn log(&self, args: &GitArgs) -> Result<Value, Value> {
+        let count = format!("-n{}", args.max_count.unwrap_or(DEFAULT_LOG_COUNT));
+        let mut cmd = vec![
+            "log",
+            "--format=%H%x1f%an%x1f%ae%x1f%aI%x1f%s%x1e",
+            count.as_str(),
+        ];
+        if let Some(r) = &args.reference {
+            cmd.push(r);
+        }
+        cmd.push("--");
+        cmd.extend(args.paths.iter().map(|p| p.as_str()));
+        let out = self.run(&cmd)?;
+
+        let commits: Vec<Value> = out
+            .split('\x1e')
+            .map(|rec| rec.trim_start_matches('\n'))
+            .filter(|rec| !rec.is_empty())
+            .map(|rec| {
+                let f: Vec<&str> = rec.splitn(5, '\x1f').collect();
+                let field = |i: usize| f.get(i).copied().unwrap_or("");
+                json!({
+                    "hash": field(0),
+                    "author": field(1),
+                    "email": field(2),
+                    "date": field(3),
+                    "subject": field(4)
+                })
+            })
+            .collect();
+        Ok(json!({ "count": commits.len(), "commits": commits }))
+    }
+
+    fn show(&self, args: &GitArgs) -> Result<Value, Value> {
+        let reference = args.reference.as_deref().unwrap_or("HEAD");
+        let meta = self.run(&[
+            "show",
+            "-s",
+            "--format=%H%x1f%an%x1f%ae%x1f%aI%x1f%P%x1f%B",
+            reference,
+        ])?;
+        let f: Vec<&str> = meta.splitn(6, '\x1f').collect();
+        let field = |i: usize| f.get(i).copied().unwrap_or("");
+        let parents: Vec<&str> = field(4).split_whitespace().collect();
+
+        let mut cmd = vec![
+            "show",
+            "--format=",
+            "--no-ext-diff",
+            "--no-textconv",
+            reference,
+            "--",
+        ];
+        cmd.extend(args.paths.iter().map(|p| p.as_str()));
+        let out = self.run(&cmd)?;
+        let max_lines = args.max_lines.unwrap_or(DEFAULT_MAX_DIFF_LINES);
+        let diff = parse_diff(&out, max_lines, self.secrets);
+
+        Ok(json!({
+            "hash": field(0),
+            "author": field(1),
+            "email": field(2),
+            "date": field(3),
+            "parents": parents,
+            "message": field(5).trim_end(),
+            "files": diff["files"],
+            "truncated": diff["truncated"]
+        }))
+    }
+
+    fn blame(&self, args: &GitArgs) -> Result<Value, Value> {
+        let [path] = args.paths.as_slice() else {
+            return Err(
+                json!({ "error": { "code": "missing_path", "message": "blame requires exactly one path" } }),
+            );
+        };
+        let range = match (args.start_line, args.end_line) {
+            (None, None) => None,
+            (start, end) => Some(format!(
+                "{},{}",
+                start.unwrap_or(1).max(1),
+                end.map(|e| e.to_string()).unwrap_or_default()
+            )),
+        };
+
+        let mut cmd = vec!["blame", "--porcelain"];
+        if let Some(range) = &range {
+            cmd.push("-L");
+            cmd.push(range);
+        }
+        if let Some(r) = &args.reference {
+            cmd.push(r);
+        }
+        cmd.push("--");
+        cmd.push(path);
+        let out = self.run(&cmd)?;
+        let lines = parse_blame(&out);
+        Ok(json!({ "path": path, "lines": lines }))
+    }
+
+    fn add(&self, args: &GitArgs) -> Result<Value, Value> {
+        if args.paths.is_empty() {
+            return Err(
+                json!({ "error": { "code": "missing_path", "message": "add requires at least one path" } }),
+            );
+        }
+        let mut cmd = vec!["add", "--"];
+        cmd.extend(args.paths.iter().map(|p| p.as_str()));
+        self.run(&cmd)?;
+        let status = self.status()?;
+        Ok(json!({ "added": args.paths, "staged": status["staged"] }))
+    }
+
+    fn commit(&self, args: &GitArgs) -> Result<Value, Value> {
+        let message = match args.message.as_deref() {
+            Some(m) if !m.trim().is_empty() => m,
+            _ => {
+                return Err(
+                    json!({ "error": { "code": "missing_message", "message": "commit requires a message" } }),
+                )
+            }
+        };
+        self.run(&["commit", "-m", message])?;
+        let hash = self.run(&["rev-parse", "HEAD"])?;
+        let stat = self.run(&["show", "--format=", "--numstat", "HEAD"])?;
+        let files: Vec<&str> = stat
+            .lines()
+            .filter_map(|l| l.splitn(3, '\t').nth(2))
+            .collect();
+        Ok(json!({
+            "hash": hash.trim(),
+            "subject": message.lines().next().unwrap_or(""),
+            "files": files
+        }))
+    }
+
+    fn stash(&self, args: &GitArgs) -> Result<Value, Value> {
+        match args.action.as_deref().unwrap_or("push") {
+            "push" => {
+                let mut cmd = vec!["stash", "push"];
+                if let Some(m) = &args.message {
+                    cmd.push("-m");
+                    cmd.push(m);
+                }
+                let out = self.run(&cmd)?;
+                Ok(json!({ "action": "push", "output": out.trim() }))
+            }
+            "pop" => {
+                let out = self.run(&["stash", "pop"])?;
+                Ok(json!({ "action": "pop", "output": out.trim() }))
+            }
+            "list" => {
+                let out = self.run(&["stash", "list", "--format=%gd%x1f%s"])?;
+                let entries: Vec<Value> = out
+                    .lines()
+                    .filter_map(|l| l.split_once('\x1f'))
+                    .map(|(r, m)| json!({ "ref": r, "message": m }))
+                    .collect();
+                Ok(json!({ "action": "list", "entries": entries }))
+            }
+            other => Err(json!({
+                "error": {
+                    "code": "invalid_args",
+                    "message": format!("Unknown stash action: {}", other)
+                }
+            })),
+        }
+    }
+}
+
+fn status_word(c: char) -> Option<&'static str> {
+    match c {
+        'M' => Some("modified"),
+        'T' => Some("type_changed"),
+        'A' => Some("added"),
+        'D' => Some("deleted"),
+        'R' => Some("renamed"),
+        'C' => Some("copied"),
+        'U' => Some("unmerged"),
+        _ => None,
+    }
+}
+
+/// Parse `git status --porcelain=v2 --branch -z`
+fn parse_status(out: &str) -> Value {
+    let mut branch = json!({});
+    let mut staged = Vec::new();
+    let mut unstaged = Vec::new();
+    let mut untracked = Vec::new();
+    let mut conflicted = Vec::new();
+
+    let mut records = out.split('\0').filter(|r| !r.is_empty());
+    while let Some(rec) = records.next() {
+        if let Some(header) = rec.strip_prefix("# ") {
+            let (key, value) = header.split_once(' ').unwrap_or((header, ""));
+            match key {
+                "branch.oid" => branch["oid"] = json!(value),
+                "branch.head" => branch["head"] = json!(value),
+                "branch.upstream" => branch["upstream"] = json!(value),
+                "branch.ab" => {
+                    for part in value.split_whitespace() {
+                        let n: i64 = part[1..].parse().unwrap_or(0);
+                        if part.starts_with('+') {
+                            branch["ahead"] = json!(n);
+                        } else {
+                            branch["behind"] = json!(n);
+                        }
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        let kind = rec.chars().next().unwrap_or(' ');
+        match kind {
+            '1' | '2' => {
+                let fields = if kind == '1' { 9 } else { 10 };
+                let parts: Vec<&str> = rec.splitn(fields, ' ').collect();
+                let xy: Vec<char> = parts.get(1).unwrap_or(&"..").chars().collect();
+                let path = parts.last().copied().unwrap_or("");
+                let from = if kind == '2' { records.next() } else { None };
+
+                if let Some(status) = xy.first().and_then(|&c| status_word(c)) {
+                    let mut entry = json!({ "path": path, "status": status });
+                    if let Some(from) = from {
+                        entry["from"] = json!(from);
+                    }
+                    staged.push(entry);
+                }
+                if let Some(status) = xy.get(1).and_then(|&c| status_word(c)) {
+                    unstaged.push(json!({ "path": path, "status": status }));
+                }
+            }
+            'u' => {
+                let parts: Vec<&str> = rec.splitn(11, ' ').collect();
+                conflicted.push(json!(parts.last().copied().unwrap_or("")));
+            }
+            '?' => untracked.push(json!(&rec[2..])),
+            _ => {}
+        }
+    }
+
+    let clean =
+        staged.is_empty() && unstaged.is_empty() && untracked.is_empty() && conflicted.is_empty();
+    json!({
+        "branch": branch,
+        "staged": staged,
+        "unstaged": unstaged,
+        "untracked": untracked,
+        "conflicted": conflicted,
+        "clean": clean
+    })
+}
+
+/// Parse `@@ -a,b +c,d @@ section` into its numbers and section text
+fn parse_hunk_header(line: &str) -> Option<Value> {
+    let rest = line.strip_prefix("@@ ")?;
+    let (ranges, section) = rest.split_once(" @@").unwrap_or((rest, ""));
+    let mut parts = ranges.split_whitespace();
+    let range = |s: &str| -> (u64, u64) {
+        let (start, len) = s.split_once(',').unwrap_or((s, "1"));
+        (start.parse().unwrap_or(0), len.parse().unwrap_or(0))
+    };
+    let (old_start, old_lines) = range(parts.next()?.strip_prefix('-')?);
+    let (new_start, new_lines) = range(parts.next()?.strip_prefix('+')?);
+    Some(json!({
+        "old_start": old_start,
+        "old_lines": old_lines,
+        "new_start": new_start,
+        "new_lines": new_lines,
+        "section": section.trim(),
+        "lines": []
+    }))
+}
+
+/// Take the path from a `diff --git a/X b/Y` header
+fn header_path(rest: &str) -> (String, String) {
+    // Unrenamed files have identical halves, which handles paths with spaces
+    // (`core.quotepath=off` leaves non-ASCII bytes raw, so slice with `get`)
+    let n = rest.len();
+    if n % 2 == 1 {
+        let halves = (rest.get(..n / 2), rest.get(n / 2 + 1..));
+        if let (Some(left), Some(right)) = halves {
+            if let (Some(a), Some(b)) = (left.get(2..), right.get(2..)) {
+                if !a.is_empty() && a == b {
+                    return (a.to_string(), b.to_string());
+                }
+            }
+        }
+    }
+    match rest.split_once(" b/") {
+        Some((a, b)) => (a.trim_start_matches("a/").to_string(), b.to_string()),
+        None => (rest.to_string(), rest.to_string()),
+    }
+}
+
+/// Parse unified diff output into per-file hunks, keeping at most `max_lines`
+/// hunk lines. Secret files are listed without their hunks.
+fn parse_diff(out: &str, max_lines: usize, secrets: &SecretFiles) -> Value {
+    let mut files: Vec<Value> = Vec::new();
+    let mut kept = 0usize;
+    let mut truncated = false;
+    let mut in_hunk = false;
+    let mut withheld = false;
+
+    for line in out.lines() {
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            let (old_path, path) = header_path(rest);
+            files.push(json!({
+                "path": path,
+                "old_path": old_path,
+                "status": "modified",
+                "binary": false,
+                "additions": 0,
+                "deletions": 0,
+                "hunks": []
+            }));
+            in_hunk = false;
+            withheld = false;
+            continue;
+        }
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+
+        if !in_hunk {
+            if line.starts_with("new file mode") {
+                file["status"] = json!("added");
+            } else if line.starts_with("deleted file mode") {
+                file["status"] = json!("deleted");
+            } else if let Some(from) = line.strip_prefix("rename from ") {
+                file["status"] = json!("renamed");
+                file["old_path"] = json!(from);
+            } else if let Some(to) = line.strip_prefix("rename to ") {
+                file["path"] = json!(to);
+            } else if line.starts_with("Binary files ") {
+                file["binary"] = json!(true);
+            } else if let Some(p) = line.strip_prefix("--- a/") {
+                file["old_path"] = json!(p);
+            } else if let Some(p) = line.strip_prefix("+++ b/") {
+                file["path"] = json!(p);
+            }
+        }
+
+        if line.starts_with("@@") {
+            let secret = ["path", "old_path"]
+                .iter()
+                .find_map(|key| file[key].as_str().and_then(|p| secrets.matches(p)));
+            if let Some(pattern) = secret {
+                file["withheld"] = json!(format!("secret_files: {}", pattern));
+                withheld = true;
+                in_hunk = true;
+                continue;
+            }
+            withheld = false;
+            if let Some(hunk) = parse_hunk_header(line) {
+                file["hunks"].as_array_mut().unwrap().push(hunk);
+                in_hunk = true;
+            }
+            continue;
+        }
+        if !in_hunk || withheld {
+            continue;
+        }
+
+        match line.chars().next() {
+            Some('+') => file["additions"] = json!(file["additions"].as_u64().unwrap_or(0) + 1),
+            Some('-') => file["deletions"] = json!(file["deletions"].as_u64().unwrap_or(0) + 1),
+            _ => {}
+        }
+        if kept < max_lines {
+            if let Some(hunk) = file["hunks"].as_array_mut().and_then(|h| h.last_mut()) {
+                hunk["lines"].as_array_mut().unwrap().push(json!(line));
+                kept += 1;
+            }
+        } else {
+            truncated = true;
+        }
+    }
+
+    json!({ "files": files, "truncated": truncated })
+}
+
+/// Parse `git blame --porcelain`
+fn parse_blame(out: &str) -> Vec<Value> {
+    use std::collections::HashMap;
+
+    let mut commits: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut lines = Vec::new();
+    let mut current: Option<(String, u64)> = None;
+
+    for line in out.lines() {
+        if let Some(content) = line.strip_prefix('\t') {
+            if let Some((hash, line_no)) = current.take() {
+                let info = commits.get(&hash);
+                let get = |k: &str| info.and_then(|i| i.get(k)).cloned().unwrap_or_default();
+                let date = get("author-time")
+                    .parse::<i64>()
+                    .ok()
+                    .and_then(|t| chrono::DateTime::from_timestamp(t, 0))
+                    .map(|d| d.to_rfc3339())
+                    .unwrap_or_default();
+                lines.push(json!({
+                    "line": line_no,
+                    "hash": hash,
+                    "author": get("author"),
+                    "date": date,
+                    "summary": get("summary"),
+                    "content": content
+                }));
+            }
+            continue;
+        }
+
+        let mut parts = line.split(' ');
+        let first = parts.next().unwrap_or("");
+        if first.len() == 40 && first.chars().all(|c| c.is_ascii_hexdigit()) {
+            let final_line = parts.nth(1).and_then(|n| n.parse().ok()).unwrap_or(0);
+            commits.entry(first.to_string()).or_default();
+            current = Some((first.to_string(), final_line));
+        } else if let Some((hash, _)) = &current {
+            if let Some((key, value)) = line.split_once(' ') {
+                commits
+                    .entry(hash.clone())
+                    .or_default()
+                    .insert(key.to_string(), value.to_string());
+            }
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .env("GIT_AUTHOR_NAME", "Test")
+            .env("GIT_AUTHOR_EMAIL", "test@example.com")
+            .env("GIT_COMMITTER_NAME", "Test")
+            .env("GIT_COMMITTER_EMAIL", "test@example.com")
+            .output()
+            .unwrap();
+        assert!(status.status.success(), "git {:?} failed", args);
+    }
+
+    fn repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        git(dir.path(), &["init", "-q"]);
+        git(dir.path(), &["config", "user.name", "Test"]);
+        git(dir.path(), &["config", "user.email", "test@example.com"]);
+        std::fs::write(dir.path().join("a.txt"), "one\ntwo\nthree\n").unwrap();
+        git(dir.path(), &["add", "a.txt"]);
+        git(dir.path(), &["commit", "-q", "-m", "initial"]);
+        dir
+    }
+
+    fn run(dir: &TempDir, args: Value) -> Value {
+        let root = dir.path().canonicalize().unwrap();
+        execute(args, &root, &BashConfig::default(), &SecretFiles::default()).unwrap()
+    }
+
+    #[test]
+    fn test_parse_status() {
+        let out = "# branch.oid abc\0# branch.head main\0# branch.ab +2 -1\0\
+                   1 M. N... 100644 100644 100644 h1 h2 src/a.rs\0\
+                   1 .M N... 100644 100644 100644 h1 h2 my file.rs\0\
+                   2 R. N... 100644 100644 100644 h1 h2 R100 new.rs\0old.rs\0\
+                   ? notes.txt\0";
+        let status = parse_status(out);
+        assert_eq!(status["branch"]["head"], "main");
+        assert_eq!(status["branch"]["ahead"], 2);
+        assert_eq!(status["branch"]["behind"], 1);
+        assert_eq!(status["staged"][0]["path"], "src/a.rs");
+        assert_eq!(status["unstaged"][0]["path"], "my file.rs");
+        assert_eq!(status["staged"][1]["status"], "renamed");
+        assert_eq!(status["staged"][1]["from"], "old.rs");
+        assert_eq!(status["untracked"][0], "notes.txt");
+        assert_eq!(status["clean"], false);
+    }
+
+    #[test]
+    fn test_parse_diff() {
+        let out = "diff --git a/src/a.rs b/src/a.rs\n\
+                   index 1..2 100644\n\
+                   --- a/src/a.rs\n\
+                   +++ b/src/a.rs\n\
+                   @@ -1,2 +1,2 @@ fn main() {\n\
+                   -old\n\
+                   +new\n\
+                    same\n\
+                   diff --git a/new.txt b/new.txt\n\
+                   new file mode 100644\n\
+                   --- /dev/null\n\
+                   +++ b/new.txt\n\
+                   @@ -0,0 +1 @@\n\
+                   +hello\n";
+        let diff = parse_diff(out, 100, &SecretFiles::default());
+        let files = diff["files"].as_array().unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0]["path"], "src/a.rs");
+        assert_eq!(files[0]["additions"], 1);
+        assert_eq!(files[0]["deletions"], 1);
+        assert_eq!(files[0]["hunks"][0]["section"], "fn main() {");
+        assert_eq!(files[0]["hunks"][0]["lines"].as_array().unwrap().len(), 3);
+        assert_eq!(files[1]["status"], "added");
+        assert_eq!(files[1]["hunks"][0]["new_lines"], 1);
+        assert_eq!(diff["truncated"], false);
+
+        let diff = parse_diff(out, 2, &SecretFiles::default());
+        assert_eq!(diff["truncated"], true);
+        assert_eq!(diff["files"][1]["additions"], 1);
+    }
+
+    #[test]
+    fn test_parse_diff_non_ascii_paths() {
+        assert_eq!(
+            header_path("a/xy b/éééé"),
+            ("xy".to_string(), "éééé".to_string())
+        );
+        assert_eq!(header_path("a/é b/é"), ("é".to_string(), "é".to_string()));
+
+        let out = "diff --git a/docs/café menu.png b/docs/café menu.png\n\
+                   index 1..2 100644\n\
+                   Binary files a/docs/café menu.png and b/docs/café menu.png differ\n\
+                   diff --git a/ü.txt b/ü.txt\n\
+                   --- a/ü.txt\n\
+                   +++ b/ü.txt\n\
+                   @@ -1 +1 @@\n\
+                   -ä\n\
+                   +ö\n";
+        let diff = parse_diff(out, 100, &SecretFiles::default());
+        assert_eq!(diff["files"][0]["path"], "docs/café menu.png");
+        assert_eq!(diff["files"][0]["binary"], true);
+        assert_eq!(diff["files"][1]["path"], "ü.txt");
+        assert_eq!(diff["files"][1]["additions"], 1);
+    }
+
+    #[test]
+    fn test_status_and_diff_in_repo() {
+        let dir = repo();
+        std::fs::write(dir.path().join("a.txt"), "one\nTWO\nthree\n").unwrap();
+        std::fs::write(dir.path().join("b.txt"), "new\n").unwrap();
+
+        let status = run(&dir, json!({"subcommand": "status"}));
+        assert_eq!(status["unstaged"][0]["path"], "a.txt");
+        assert_eq!(status["untracked"][0], "b.txt");
+
+        let diff = run(&dir, json!({"subcommand": "diff", "paths": ["a.txt"]}));
+        assert_eq!(diff["files"][0]["additions"], 1);
+
+        let staged = run(&dir, json!({"subcommand": "diff", "staged": true}));
+        assert_eq!(staged["files"].as_array().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_output_limit_and_no_textconv() {
+        let dir = repo();
+        git(
+            dir.path(),
+            &["config", "diff.conv.textconv", "echo CONVERTED"],
+        );
+        std::fs::write(dir.path().join(".gitattributes"), "*.txt diff=conv\n").unwrap();
+        let lines: String = (0..200).map(|i| format!("line {}\n", i)).collect();
+        std::fs::write(dir.path().join("a.txt"), &lines).unwrap();
+
+        let full = run(&dir, json!({"subcommand": "diff"}));
+        assert!(full.get("output_truncated").is_none());
+        assert!(full.to_string().contains("line 199"));
+        assert!(!full.to_string().contains("CONVERTED"));
+
+        let root = dir.path().canonicalize().unwrap();
+        let config = BashConfig {
+            max_output_bytes: Some(1000),
+            ..Default::default()
+        };
+        let args = json!({"subcommand": "diff"});
+        let cut = execute(args, &root, &config, &SecretFiles::default()).unwrap();
+        assert_eq!(cut["output_truncated"], true);
+        let hunk_lines = cut["files"][0]["hunks"][0]["lines"].as_array().unwrap();
+        assert!(!hunk_lines.is_empty() && hunk_lines.len() < 200);
+        let last = hunk_lines.last().unwrap().as_str().unwrap();
+        assert!(last.strip_prefix("+line ").unwrap().parse::<u32>().is_ok());
+    }
+
+    #[test]
+    fn test_log_show_blame() {
+        let dir = repo();
+        let log = run(&dir, json!({"subcommand": "log"}));
+        assert_eq!(log["count"], 1);
+        assert_eq!(log["commits"][0]["subject"], "initial");
+
+        let show = run(&dir, json!({"subcommand": "show"}));
+        assert_eq!(show["message"], "initial");
+        assert_eq!(show["files"][0]["status"], "added");
+
+        let blame = run(
+            &dir,
+            json!({"subcommand": "blame", "paths": ["a.txt"], "start_line": 2, "end_line": 3}),
+        );
+        let lines = blame["lines"].as_array().unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["line"], 2);
+        assert_eq!(lines[0]["content"], "two");
+        assert_eq!(lines[0]["author"], "Test");
+    }
+
+    #[test]
+    fn test_diff_and_show_withhold_secret_files() {
+        let dir = repo();
+        std::fs::create_dir_all(dir.path().join("certs")).unwrap();
+        std::fs::write(dir.path().join(".env"), "TOKEN=hunter2\n").unwrap();
+        std::fs::write(dir.path().join("certs/tls.pem"), "KEY hunter2\n").unwrap();
+        std::fs::write(dir.path().join("b.txt"), "hunter2 is fine here\n").unwrap();
+        git(dir.path(), &["add", "."]);
+
+        let staged = run(&dir, json!({"subcommand": "diff", "staged": true}));
+        git(dir.path(), &["commit", "-q", "-m", "secrets"]);
+        std::fs::write(dir.path().join(".env"), "TOKEN=hunter3\n").unwrap();
+        let unstaged = run(&dir, json!({"subcommand": "diff"}));
+        let show = run(&dir, json!({"subcommand": "show"}));
+
+        for result in [&staged, &unstaged, &show] {
+            let text = result.to_string();
+            assert!(
+                !text.contains("hunter3") && !text.contains("TOKEN"),
+                "{}",
+                text
+            );
+            for file in result["files"].as_array().unwrap() {
+                let secret = file["path"] != "b.txt";
+                assert_eq!(file.get("withheld").is_some(), secret, "{}", file);
+            }
+        }
+        assert_eq!(show["files"].as_array().unwrap().len(), 3);
+        assert!(show.to_string().contains("hunter2 is fine here"));
+    }
+
+    #[test]
+    fn test_add_and_commit() {
+        let dir = repo();
+        std::fs::write(dir.path().join("b.txt"), "new\n").unwrap();
+
+        let added = run(&dir, json!({"subcommand": "add", "paths": ["b.txt"]}));
+        assert_eq!(added["staged"][0]["path"], "b.txt");
+
+        let commit = run(&dir, json!({"subcommand": "commit", "message": "add b"}));
+        assert_eq!(commit["subject"], "add b");
+        assert_eq!(commit["files"][0], "b.txt");
+    }
+
+    #[test]
+    fn test_rejects_bad_args() {
+        let dir = repo();
+        let result = run(&dir, json!({"subcommand": "log", "ref": "--output=x"}));
+        assert_eq!(result["error"]["code"], "invalid_ref");
+
+        let result = run(&dir, json!({"subcommand": "add", "paths": ["../escape"]}));
+        assert_eq!(result["error"]["code"], "path_out_of_scope");
+
+        let result = run(&dir, json!({"subcommand": "push"}));
+        assert_eq!(result["error"]["code"], "unknown_subcommand");
+    }
+}
@@ -1,9 +1,10 @@
+use crate::policy::SecretFiles;
 use regex::Regex;
 use serde_json::{json, Value};
 use std::path::Path;
 use walkdir::WalkDir;
 
-pub fn execute(args: Value, root: &Path) -> anyhow::Result<Value> {
+pub fn execute(args: Value, root: &Path, secrets: &SecretFiles) -> anyhow::Result<Value> {
     let pattern = args["pattern"].as_str().unwrap_or("");
     let max_results = args["max_results"].as_u64().unwrap_or(100) as usize;
 
@@ -39,6 +40,9 @@ pub fn execute(args: Value, root: &Path) -> anyhow::Result<Value> {
 
         let path = entry.path();
         let rel_path = path.strip_prefix(root).unwrap_or(path);
+        if secrets.matches(&rel_path.to_string_lossy()).is_some() {
+            continue;
+        }
 
         if !search_paths.is_empty() {
             let rel_str = rel_path.to_string_lossy();
@@ -91,3 +95,33 @@ fn is_excluded(path: &Path, root: &Path) -> bool {
     }
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_grep_skips_secret_files() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("config")).unwrap();
+        fs::write(dir.path().join(".env"), "API_TOKEN=hunter2\n").unwrap();
+        fs::write(dir.path().join("config/.env.local"), "API_TOKEN=x\n").unwrap();
+        fs::write(
+            dir.path().join("config/app.toml"),
+            "token = \"API_TOKEN\"\n",
+        )
+        .unwrap();
+
+        let args = json!({ "pattern": "API_TOKEN" });
+        let result = execute(args, dir.path(), &SecretFiles::default()).unwrap();
+        assert_eq!(result["matches_found"], 1);
+        assert_eq!(result["matches"][0]["path"], "config/app.toml");
+
+        let secrets = SecretFiles::new(Some(&vec![]));
+        let args = json!({ "pattern": "API_TOKEN" });
+        let result = execute(args, dir.path(), &secrets).unwrap();
+        assert_eq!(result["matches_found"], 3);
+    }
+}
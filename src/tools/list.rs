@@ -0,0 +1,404 @@
+//! List tool: a compact, depth-limited directory tree.
+//!
+//! Files come from `repo_map::list_files`, so .gitignore is honoured in git
+//! repositories and .git/target/.yo/node_modules are always skipped. Each
+//! directory shows its recursive file count and size; text files show their
+//! line count. Directories with many entries are collapsed with "+N more".
+
+use super::{validate_path, SchemaOptions};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::Path;
+use std::time::SystemTime;
+
+const DEFAULT_DEPTH: usize = 3;
+const DEFAULT_MAX_ENTRIES: usize = 40;
+const MAX_LINE_COUNT_BYTES: u64 = 1024 * 1024;
+
+pub fn schema(opts: &SchemaOptions) -> Value {
+    if opts.optimize {
+        json!({
+            "type": "function",
+            "function": {
+                "name": "List",
+                "description": "Directory tree with sizes",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "path": { "type": "string" },
+                        "depth": { "type": "integer" },
+                        "sort": { "type": "string", "enum": ["name", "size", "mtime"] },
+                        "max_entries": { "type": "integer" }
+                    }
+                }
+            }
+        })
+    } else {
+        json!({
+            "type": "function",
+            "function": {
+                "name": "List",
+                "description": "List a directory as a tree with per-directory file counts, file sizes and line counts. Honours .gitignore. Prefer this over `ls -R` or `find`.",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "path": { "type": "string", "description": "Directory relative to root (default: root)" },
+                        "depth": { "type": "integer", "description": "Levels to expand (default 3)" },
+                        "sort": { "type": "string", "enum": ["name", "size", "mtime"], "description": "Entry order: name (directories first), size or mtime (largest/newest first). Default name" },
+                        "max_entries": { "type": "integer", "description": "Entries shown per directory before collapsing into '+N more' (default 40)" }
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortBy {
+    Name,
+    Size,
+    Mtime,
+}
+
+struct Options {
+    depth: usize,
+    sort: SortBy,
+    max_entries: usize,
+}
+
+struct FileEntry {
+    name: String,
+    size: u64,
+    mtime: SystemTime,
+}
+
+struct Dir {
+    files: usize,
+    size: u64,
+    mtime: SystemTime,
+    dirs: BTreeMap<String, Dir>,
+    entries: Vec<FileEntry>,
+}
+
+impl Dir {
+    fn new() -> Self {
+        Self {
+            files: 0,
+            size: 0,
+            mtime: SystemTime::UNIX_EPOCH,
+            dirs: BTreeMap::new(),
+            entries: Vec::new(),
+        }
+    }
+
+    fn insert(&mut self, parts: &[&str], size: u64, mtime: SystemTime) {
+        self.files += 1;
+        self.size += size;
+        self.mtime = self.mtime.max(mtime);
+        match parts {
+            [name] => self.entries.push(FileEntry {
+                name: name.to_string(),
+                size,
+                mtime,
+            }),
+            [dir, rest @ ..] => self
+                .dirs
+                .entry(dir.to_string())
+                .or_insert_with(Dir::new)
+                .insert(rest, size, mtime),
+            [] => {}
+        }
+    }
+
+    fn dir_count(&self) -> usize {
+        self.dirs.values().map(|d| 1 + d.dir_count()).sum()
+    }
+}
+
+enum Entry<'a> {
+    Dir(&'a str, &'a Dir),
+    File(&'a FileEntry),
+}
+
+impl Entry<'_> {
+    fn name(&self) -> &str {
+        match self {
+            Entry::Dir(name, _) => name,
+            Entry::File(f) => &f.name,
+        }
+    }
+
+    fn size(&self) -> u64 {
+        match self {
+            Entry::Dir(_, d) => d.size,
+            Entry::File(f) => f.size,
+        }
+    }
+
+    fn mtime(&self) -> SystemTime {
+        match self {
+            Entry::Dir(_, d) => d.mtime,
+            Entry::File(f) => f.mtime,
+        }
+    }
+
+    fn files(&self) -> usize {
+        match self {
+            Entry::Dir(_, d) => d.files,
+            Entry::File(_) => 1,
+        }
+    }
+}
+
+pub fn execute(args: Value, root: &Path) -> anyhow::Result<Value> {
+    let path = args["path"].as_str().unwrap_or(".");
+    let sort = match args["sort"].as_str().unwrap_or("name") {
+        "name" => SortBy::Name,
+        "size" => SortBy::Size,
+        "mtime" => SortBy::Mtime,
+        other => {
+            return Ok(json!({
+                "error": {
+                    "code": "invalid_args",
+                    "message": format!("Unknown sort '{}': use name, size or mtime", other)
+                }
+            }))
+        }
+    };
+    let opts = Options {
+        depth: args["depth"]
+            .as_u64()
+            .map(|d| d.max(1) as usize)
+            .unwrap_or(DEFAULT_DEPTH),
+        sort,
+        max_entries: args["max_entries"]
+            .as_u64()
+            .map(|n| n.max(1) as usize)
+            .unwrap_or(DEFAULT_MAX_ENTRIES),
+    };
+
+    let full = match validate_path(path, root) {
+        Ok(p) => p,
+        Err(e) => return Ok(e),
+    };
+    if !full.exists() {
+        return Ok(
+            json!({ "error": { "code": "not_found", "message": format!("Not found: {}", path) } }),
+        );
+    }
+    if !full.is_dir() {
+        return Ok(
+            json!({ "error": { "code": "not_a_directory", "message": format!("{} is a file; use Read", path) } }),
+        );
+    }
+
+    let prefix = full
+        .strip_prefix(root)
+        .map(|p| p.to_string_lossy().replace('\\', "/"))
+        .unwrap_or_default();
+    let mut tree = Dir::new();
+    for file in crate::repo_map::list_files(root) {
+        let rel = if prefix.is_empty() {
+            file.as_str()
+        } else {
+            match file.strip_prefix(&prefix).and_then(|r| r.strip_prefix('/')) {
+                Some(rel) => rel,
+                None => continue,
+            }
+        };
+        let Ok(meta) = std::fs::metadata(root.join(&file)) else {
+            continue;
+        };
+        let mtime = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        let parts: Vec<&str> = rel.split('/').collect();
+        tree.insert(&parts, meta.len(), mtime);
+    }
+
+    let label = if prefix.is_empty() { "." } else { &prefix };
+    let mut out = format!(
+        "{}/ ({}, {})\n",
+        label,
+        plural(tree.files, "file"),
+        format_size(tree.size)
+    );
+    let mut collapsed = 0;
+    render(&tree, &full, 1, &opts, &mut out, &mut collapsed);
+
+    Ok(json!({
+        "path": label,
+        "tree": out,
+        "files": tree.files,
+        "dirs": tree.dir_count(),
+        "size": tree.size,
+        "truncated": collapsed > 0,
+    }))
+}
+
+fn render(
+    dir: &Dir,
+    path: &Path,
+    level: usize,
+    opts: &Options,
+    out: &mut String,
+    collapsed: &mut usize,
+) {
+    let mut entries: Vec<Entry> = dir
+        .dirs
+        .iter()
+        .map(|(name, d)| Entry::Dir(name, d))
+        .chain(dir.entries.iter().map(Entry::File))
+        .collect();
+    match opts.sort {
+        // BTreeMap order for directories, then files by name
+        SortBy::Name => entries.sort_by(|a, b| {
+            let is_file = |e: &Entry| matches!(e, Entry::File(_));
+            is_file(a).cmp(&is_file(b)).then(a.name().cmp(b.name()))
+        }),
+        SortBy::Size => {
+            entries.sort_by(|a, b| b.size().cmp(&a.size()).then(a.name().cmp(b.name())))
+        }
+        SortBy::Mtime => {
+            entries.sort_by(|a, b| b.mtime().cmp(&a.mtime()).then(a.name().cmp(b.name())))
+        }
+    }
+
+    let indent = "  ".repeat(level);
+    for entry in entries.iter().take(opts.max_entries) {
+        match entry {
+            Entry::Dir(name, d) => {
+                out.push_str(&format!(
+                    "{}{}/ ({}, {})\n",
+                    indent,
+                    name,
+                    plural(d.files, "file"),
+                    format_size(d.size)
+                ));
+                if level < opts.depth {
+                    render(d, &path.join(name), level + 1, opts, out, collapsed);
+                }
+            }
+            Entry::File(f) => {
+                out.push_str(&format!("{}{}  {}", indent, f.name, format_size(f.size)));
+                if let Some(lines) = line_count(&path.join(&f.name), f.size) {
+                    out.push_str(&format!(", {}", plural(lines, "line")));
+                }
+                out.push('\n');
+            }
+        }
+    }
+
+    if entries.len() > opts.max_entries {
+        let rest = &entries[opts.max_entries..];
+        *collapsed += rest.len();
+        out.push_str(&format!(
+            "{}+{} more ({}, {})\n",
+            indent,
+            rest.len(),
+            plural(rest.iter().map(|e| e.files()).sum(), "file"),
+            format_size(rest.iter().map(|e| e.size()).sum())
+        ));
+    }
+}
+
+/// Lines in a text file; None for binary or very large files
+fn line_count(path: &Path, size: u64) -> Option<usize> {
+    if size > MAX_LINE_COUNT_BYTES {
+        return None;
+    }
+    let mut data = Vec::with_capacity(size as usize);
+    std::fs::File::open(path)
+        .ok()?
+        .read_to_end(&mut data)
+        .ok()?;
+    if data.iter().take(8192).any(|&b| b == 0) {
+        return None;
+    }
+    let newlines = data.iter().filter(|&&b| b == b'\n').count();
+    let unterminated = data.last().is_some_and(|&b| b != b'\n');
+    Some(newlines + unterminated as usize)
+}
+
+fn plural(n: usize, word: &str) -> String {
+    if n == 1 {
+        format!("1 {}", word)
+    } else {
+        format!("{} {}s", n, word)
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KB", "MB", "GB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn setup() -> (TempDir, std::path::PathBuf) {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("src/tools")).unwrap();
+        fs::create_dir_all(root.join("target/debug")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {\n}\n").unwrap();
+        fs::write(root.join("src/lib.rs"), "pub mod tools;").unwrap();
+        for i in 0..5 {
+            fs::write(
+                root.join(format!("src/tools/t{}.rs", i)),
+                "x".repeat(i * 100),
+            )
+            .unwrap();
+        }
+        fs::write(root.join("logo.png"), [0x89, b'P', b'N', b'G', 0, 0]).unwrap();
+        fs::write(root.join("target/debug/yo"), "binary").unwrap();
+        (dir, root)
+    }
+
+    #[test]
+    fn test_list_tree() {
+        let (_dir, root) = setup();
+        let result = execute(json!({}), &root).unwrap();
+        assert_eq!(result["files"], 8);
+        assert_eq!(result["dirs"], 2);
+        assert_eq!(result["truncated"], false);
+        let tree = result["tree"].as_str().unwrap();
+        assert!(tree.starts_with("./ (8 files, "), "{}", tree);
+        assert!(tree.contains("\n  src/ (7 files, 1.0 KB)\n"), "{}", tree);
+        assert!(tree.contains("\n    main.rs  14 B, 2 lines\n"), "{}", tree);
+        assert!(tree.contains("\n    lib.rs  14 B, 1 line\n"), "{}", tree);
+        assert!(tree.contains("\n  logo.png  6 B\n"), "{}", tree);
+        assert!(!tree.contains("target"));
+        // Directories come before files
+        assert!(tree.find("src/").unwrap() < tree.find("logo.png").unwrap());
+    }
+
+    #[test]
+    fn test_list_depth_sort_and_collapse() {
+        let (_dir, root) = setup();
+        let args = json!({ "path": "src", "depth": 1, "sort": "size", "max_entries": 2 });
+        let result = execute(args, &root).unwrap();
+        let tree = result["tree"].as_str().unwrap();
+        assert_eq!(
+            tree,
+            "src/ (7 files, 1.0 KB)\n  tools/ (5 files, 1000 B)\n  lib.rs  14 B, 1 line\n  +1 more (1 file, 14 B)\n"
+        );
+        assert_eq!(result["truncated"], true);
+
+        let result = execute(json!({ "path": "src/main.rs" }), &root).unwrap();
+        assert_eq!(result["error"]["code"], "not_a_directory");
+        let result = execute(json!({ "sort": "age" }), &root).unwrap();
+        assert_eq!(result["error"]["code"], "invalid_args");
+    }
+}
@@ -0,0 +1,840 @@
+//! Lsp tool: semantic navigation through a language server.
+//!
+//! Positions are 1-based lines and columns (in characters); the conversion to
+//! LSP's 0-based UTF-16 positions happens here. `rename` never writes files:
+//! it returns one set of `Edit` arguments per file, so applying a rename goes
+//! through the normal Edit permission check.
+
+use super::{edit_args, validate_path, SchemaOptions, TextReplacement};
+use crate::lsp::manager::{path_to_uri, uri_to_path};
+use crate::lsp::LspManager;
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const MAX_LOCATIONS: usize = 200;
+const MAX_SYMBOLS: usize = 200;
+
+/// How long to wait for a server to publish diagnostics for a file
+const DIAGNOSTICS_WAIT: Duration = Duration::from_secs(5);
+
+const ACTIONS: &[&str] = &[
+    "definition",
+    "references",
+    "hover",
+    "document_symbols",
+    "workspace_symbols",
+    "diagnostics",
+    "rename",
+];
+
+pub fn schema(opts: &SchemaOptions) -> Value {
+    if opts.optimize {
+        json!({
+            "type": "function",
+            "function": {
+                "name": "Lsp",
+                "description": "Language server queries",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "action": { "type": "string", "enum": ACTIONS },
+                        "path": { "type": "string" },
+                        "line": { "type": "integer" },
+                        "column": { "type": "integer" },
+                        "symbol": { "type": "string" },
+                        "query": { "type": "string" },
+                        "new_name": { "type": "string" }
+                    },
+                    "required": ["action"]
+                }
+            }
+        })
+    } else {
+        json!({
+            "type": "function",
+            "function": {
+                "name": "Lsp",
+                "description": "Ask the project's language server (rust-analyzer, pyright, typescript-language-server or a configured one) about code. Unlike Grep it resolves definitions, real references, types and docs. Point at a symbol with path + line and either column or the symbol's name on that line. `rename` returns Edit arguments per file; apply them with the Edit tool.",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "action": {
+                            "type": "string",
+                            "enum": ACTIONS,
+                            "description": "definition, references, hover and rename need a position; document_symbols and diagnostics need a path; workspace_symbols needs a query"
+                        },
+                        "path": { "type": "string", "description": "File path relative to project root" },
+                        "line": { "type": "integer", "description": "1-based line number" },
+                        "column": { "type": "integer", "description": "1-based column (characters)" },
+                        "symbol": { "type": "string", "description": "Name on `line` to point at, instead of column" },
+                        "query": { "type": "string", "description": "Symbol name to search for (workspace_symbols)" },
+                        "new_name": { "type": "string", "description": "New name (rename)" }
+                    },
+                    "required": ["action"]
+                }
+            }
+        })
+    }
+}
+
+fn error(code: &str, message: impl Into<String>) -> Value {
+    json!({ "error": { "code": code, "message": message.into() } })
+}
+
+pub fn execute(args: Value, lsp: &mut LspManager) -> Result<Value> {
+    let action = args.get("action").and_then(|v| v.as_str()).unwrap_or("");
+    let root = lsp.root().to_path_buf();
+
+    if action == "workspace_symbols" {
+        let query = args.get("query").and_then(|v| v.as_str()).unwrap_or("");
+        if query.is_empty() {
+            return Ok(error("missing_query", "workspace_symbols needs a query"));
+        }
+        return Ok(workspace_symbols(&args, query, lsp, &root)
+            .unwrap_or_else(|e| error("lsp_error", format!("{:#}", e))));
+    }
+    if !ACTIONS.contains(&action) {
+        return Ok(error(
+            "invalid_action",
+            format!(
+                "Unknown action '{}'; expected one of: {}",
+                action,
+                ACTIONS.join(", ")
+            ),
+        ));
+    }
+
+    let Some(path_str) = args.get("path").and_then(|v| v.as_str()) else {
+        return Ok(error("missing_path", "Missing required 'path' parameter"));
+    };
+    let path = match validate_path(path_str, &root) {
+        Ok(p) => p,
+        Err(e) => return Ok(e),
+    };
+    if !path.is_file() {
+        return Ok(error("not_found", format!("File not found: {}", path_str)));
+    }
+
+    let result = match action {
+        "document_symbols" => document_symbols(&path, lsp, &root),
+        "diagnostics" => diagnostics(&path, lsp),
+        _ => {
+            let content = std::fs::read_to_string(&path)?;
+            let position = match position(&args, &content) {
+                Ok(p) => p,
+                Err(e) => return Ok(e),
+            };
+            match action {
+                "definition" => locations(&path, position, "textDocument/definition", lsp, &root),
+                "references" => locations(&path, position, "textDocument/references", lsp, &root),
+                "hover" => hover(&path, position, lsp),
+                _ => {
+                    let new_name = args.get("new_name").and_then(|v| v.as_str()).unwrap_or("");
+                    if new_name.is_empty() {
+                        return Ok(error("missing_new_name", "rename needs 'new_name'"));
+                    }
+                    rename(&path, position, new_name, lsp, &root)
+                }
+            }
+        }
+    };
+    Ok(result.unwrap_or_else(|e| error("lsp_error", format!("{:#}", e))))
+}
+
+/// LSP position (0-based line, UTF-16 character) for the requested line and column/symbol
+fn position(args: &Value, content: &str) -> Result<(u64, u64), Value> {
+    let Some(line) = args.get("line").and_then(|v| v.as_u64()).filter(|l| *l > 0) else {
+        return Err(error("missing_line", "This action needs a 1-based 'line'"));
+    };
+    let Some(text) = content.lines().nth(line as usize - 1) else {
+        return Err(error(
+            "invalid_position",
+            format!("Line {} is past the end of the file", line),
+        ));
+    };
+    let char_col = if let Some(symbol) = args.get("symbol").and_then(|v| v.as_str()) {
+        match find_symbol(text, symbol) {
+            Some(byte) => text[..byte].chars().count(),
+            None => {
+                return Err(error(
+                    "symbol_not_found",
+                    format!("'{}' does not occur on line {}", symbol, line),
+                ))
+            }
+        }
+    } else if let Some(col) = args.get("column").and_then(|v| v.as_u64()) {
+        (col.max(1) - 1) as usize
+    } else {
+        return Err(error("missing_column", "Give either 'column' or 'symbol'"));
+    };
+    Ok((line - 1, utf16_len(text.chars().take(char_col))))
+}
+
+/// Byte offset of `symbol` on a line, preferring a whole-word match
+fn find_symbol(text: &str, symbol: &str) -> Option<usize> {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let whole_word = text.match_indices(symbol).find(|(i, _)| {
+        let before = text[..*i].chars().next_back();
+        let after = text[i + symbol.len()..].chars().next();
+        !before.is_some_and(is_ident) && !after.is_some_and(is_ident)
+    });
+    whole_word.map(|(i, _)| i).or_else(|| text.find(symbol))
+}
+
+fn utf16_len(chars: impl Iterator<Item = char>) -> u64 {
+    chars.map(|c| c.len_utf16() as u64).sum()
+}
+
+/// 1-based character column for a UTF-16 offset into `line`
+fn char_column(line: &str, utf16: u64) -> usize {
+    let mut units = 0;
+    for (i, c) in line.chars().enumerate() {
+        if units >= utf16 {
+            return i + 1;
+        }
+        units += c.len_utf16() as u64;
+    }
+    line.chars().count() + 1
+}
+
+/// Converts LSP locations to project-relative paths and 1-based columns,
+/// reading each file at most once
+struct Resolver<'a> {
+    root: &'a Path,
+    files: HashMap<PathBuf, Option<Vec<String>>>,
+}
+
+impl<'a> Resolver<'a> {
+    fn new(root: &'a Path) -> Self {
+        Self {
+            root,
+            files: HashMap::new(),
+        }
+    }
+
+    fn display_path(&self, path: &Path) -> String {
+        path.strip_prefix(self.root)
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|_| path.to_string_lossy().to_string())
+    }
+
+    /// (line, column), both 1-based, for an LSP position in `path`
+    fn position(&mut self, path: &Path, pos: &Value) -> (u64, usize) {
+        let line = pos["line"].as_u64().unwrap_or(0);
+        let character = pos["character"].as_u64().unwrap_or(0);
+        let lines = self.files.entry(path.to_path_buf()).or_insert_with(|| {
+            std::fs::read_to_string(path)
+                .ok()
+                .map(|c| c.lines().map(String::from).collect())
+        });
+        let column = match lines.as_ref().and_then(|l| l.get(line as usize)) {
+            Some(text) => char_column(text, character),
+            None => character as usize + 1,
+        };
+        (line + 1, column)
+    }
+
+    /// Normalize a Location or LocationLink
+    fn location(&mut self, loc: &Value) -> Option<Value> {
+        let uri = loc.get("uri").or_else(|| loc.get("targetUri"))?.as_str()?;
+        let range = loc
+            .get("range")
+            .or_else(|| loc.get("targetSelectionRange"))
+            .or_else(|| loc.get("targetRange"))?;
+        let path = uri_to_path(uri)?;
+        let (line, column) = self.position(&path, &range["start"]);
+        let (end_line, _) = self.position(&path, &range["end"]);
+        let text = self
+            .files
+            .get(&path)
+            .and_then(|f| f.as_ref())
+            .and_then(|l| l.get(line as usize - 1))
+            .map(|t| t.trim().to_string());
+        let mut out = json!({ "path": self.display_path(&path), "line": line, "column": column });
+        if end_line > line {
+            out["end_line"] = json!(end_line);
+        }
+        if let Some(text) = text {
+            out["text"] = json!(text);
+        }
+        Some(out)
+    }
+}
+
+fn text_document_position(path: &Path, (line, character): (u64, u64)) -> Value {
+    json!({
+        "textDocument": { "uri": path_to_uri(path) },
+        "position": { "line": line, "character": character }
+    })
+}
+
+fn locations(
+    path: &Path,
+    position: (u64, u64),
+    method: &str,
+    lsp: &mut LspManager,
+    root: &Path,
+) -> Result<Value> {
+    let (server, _) = lsp.open(path)?;
+    let mut params = text_document_position(path, position);
+    if method == "textDocument/references" {
+        params["context"] = json!({ "includeDeclaration": true });
+    }
+    let result = lsp.client(&server)?.request(method, params)?;
+    let items = match result {
+        Value::Array(items) => items,
+        Value::Null => vec![],
+        single => vec![single],
+    };
+
+    let mut resolver = Resolver::new(root);
+    let total = items.len();
+    let locations: Vec<Value> = items
+        .iter()
+        .take(MAX_LOCATIONS)
+        .filter_map(|l| resolver.location(l))
+        .collect();
+    let mut out = json!({ "server": server, "count": total, "locations": locations });
+    if total > MAX_LOCATIONS {
+        out["truncated"] = json!(true);
+    }
+    Ok(out)
+}
+
+/// Flatten MarkupContent, MarkedString or an array of MarkedStrings
+fn hover_text(contents: &Value) -> String {
+    match contents {
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items
+            .iter()
+            .map(hover_text)
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        Value::Object(obj) => {
+            let value = obj.get("value").and_then(|v| v.as_str()).unwrap_or("");
+            match obj.get("language").and_then(|l| l.as_str()) {
+                Some(lang) => format!("```{}\n{}\n```", lang, value),
+                None => value.to_string(),
+            }
+        }
+        _ => String::new(),
+    }
+}
+
+fn hover(path: &Path, position: (u64, u64), lsp: &mut LspManager) -> Result<Value> {
+    let (server, _) = lsp.open(path)?;
+    let result = lsp
+        .client(&server)?
+        .request("textDocument/hover", text_document_position(path, position))?;
+    let contents = hover_text(&result["contents"]);
+    if contents.is_empty() {
+        return Ok(json!({ "server": server, "found": false }));
+    }
+    Ok(json!({ "server": server, "found": true, "contents": contents }))
+}
+
+fn symbol_kind(kind: &Value) -> &'static str {
+    const KINDS: &[&str] = &[
+        "file",
+        "module",
+        "namespace",
+        "package",
+        "class",
+        "method",
+        "property",
+        "field",
+        "constructor",
+        "enum",
+        "interface",
+        "function",
+        "variable",
+        "constant",
+        "string",
+        "number",
+        "boolean",
+        "array",
+        "object",
+        "key",
+        "null",
+        "enum_member",
+        "struct",
+        "event",
+        "operator",
+        "type_parameter",
+    ];
+    kind.as_u64()
+        .and_then(|k| KINDS.get((k as usize).wrapping_sub(1)))
+        .copied()
+        .unwrap_or("unknown")
+}
+
+/// Flatten a DocumentSymbol tree, recording each symbol's parent
+fn flatten_symbols(
+    symbols: &[Value],
+    container: Option<&str>,
+    path: &Path,
+    resolver: &mut Resolver,
+    out: &mut Vec<Value>,
+) {
+    for sym in symbols {
+        let name = sym["name"].as_str().unwrap_or("");
+        let range = sym.get("selectionRange").unwrap_or(&sym["range"]);
+        let (line, column) = resolver.position(path, &range["start"]);
+        let mut entry = json!({ "name": name, "kind": symbol_kind(&sym["kind"]), "line": line, "column": column });
+        if let Some(c) = container {
+            entry["container"] = json!(c);
+        }
+        out.push(entry);
+        if let Some(children) = sym["children"].as_array() {
+            flatten_symbols(children, Some(name), path, resolver, out);
+        }
+    }
+}
+
+fn document_symbols(path: &Path, lsp: &mut LspManager, root: &Path) -> Result<Value> {
+    let (server, uri) = lsp.open(path)?;
+    let result = lsp.client(&server)?.request(
+        "textDocument/documentSymbol",
+        json!({ "textDocument": { "uri": uri } }),
+    )?;
+    let items = result.as_array().cloned().unwrap_or_default();
+
+    let mut resolver = Resolver::new(root);
+    let mut symbols = Vec::new();
+    if items.first().is_some_and(|s| s.get("location").is_some()) {
+        // Flat SymbolInformation[]
+        for sym in &items {
+            let (line, column) = resolver.position(path, &sym["location"]["range"]["start"]);
+            let mut entry = json!({ "name": sym["name"], "kind": symbol_kind(&sym["kind"]), "line": line, "column": column });
+            if let Some(c) = sym["containerName"].as_str().filter(|c| !c.is_empty()) {
+                entry["container"] = json!(c);
+            }
+            symbols.push(entry);
+        }
+    } else {
+        flatten_symbols(&items, None, path, &mut resolver, &mut symbols);
+    }
+    let total = symbols.len();
+    symbols.truncate(MAX_SYMBOLS);
+    let mut out = json!({ "server": server, "path": resolver.display_path(path), "count": total, "symbols": symbols });
+    if total > MAX_SYMBOLS {
+        out["truncated"] = json!(true);
+    }
+    Ok(out)
+}
+
+fn workspace_symbols(
+    args: &Value,
+    query: &str,
+    lsp: &mut LspManager,
+    root: &Path,
+) -> Result<Value> {
+    // The path (if any) only selects which server to ask
+    let server = match args.get("path").and_then(|v| v.as_str()) {
+        Some(p) => match validate_path(p, root) {
+            Ok(path) => lsp.server_name_for(&path)?,
+            Err(e) => return Ok(e),
+        },
+        None => match lsp.any_running() {
+            Some(name) => name,
+            None => return Ok(error(
+                "missing_path",
+                "No language server is running yet; pass 'path' of a file in the target language",
+            )),
+        },
+    };
+    let result = lsp
+        .client(&server)?
+        .request("workspace/symbol", json!({ "query": query }))?;
+    let items = result.as_array().cloned().unwrap_or_default();
+
+    let mut resolver = Resolver::new(root);
+    let total = items.len();
+    let symbols: Vec<Value> = items
+        .iter()
+        .take(MAX_SYMBOLS)
+        .filter_map(|sym| {
+            let location = &sym["location"];
+            let path = uri_to_path(location["uri"].as_str()?)?;
+            let mut entry = json!({
+                "name": sym["name"],
+                "kind": symbol_kind(&sym["kind"]),
+                "path": resolver.display_path(&path),
+            });
+            // WorkspaceSymbol may omit the range until resolved
+            if location.get("range").is_some() {
+                let (line, column) = resolver.position(&path, &location["range"]["start"]);
+                entry["line"] = json!(line);
+                entry["column"] = json!(column);
+            }
+            if let Some(c) = sym["containerName"].as_str().filter(|c| !c.is_empty()) {
+                entry["container"] = json!(c);
+            }
+            Some(entry)
+        })
+        .collect();
+    let mut out = json!({ "server": server, "count": total, "symbols": symbols });
+    if total > MAX_SYMBOLS {
+        out["truncated"] = json!(true);
+    }
+    Ok(out)
+}
+
+fn diagnostics(path: &Path, lsp: &mut LspManager) -> Result<Value> {
+    let root = lsp.root().to_path_buf();
+    let (server, uri) = lsp.open(path)?;
+    let items = lsp
+        .client(&server)?
+        .diagnostics_for(&uri, DIAGNOSTICS_WAIT)?;
+
+    let mut resolver = Resolver::new(&root);
+    let diagnostics: Vec<Value> = items
+        .iter()
+        .map(|d| {
+            let (line, column) = resolver.position(path, &d["range"]["start"]);
+            let severity = match d["severity"].as_u64() {
+                Some(1) => "error",
+                Some(2) => "warning",
+                Some(3) => "info",
+                Some(4) => "hint",
+                _ => "error",
+            };
+            let mut entry = json!({
+                "severity": severity,
+                "line": line,
+                "column": column,
+                "message": d["message"],
+            });
+            if let Some(source) = d["source"].as_str() {
+                entry["source"] = json!(source);
+            }
+            match &d["code"] {
+                Value::Null => {}
+                code => entry["code"] = code.clone(),
+            }
+            entry
+        })
+        .collect();
+    let count = |sev: &str| diagnostics.iter().filter(|d| d["severity"] == sev).count();
+    Ok(json!({
+        "server": server,
+        "path": resolver.display_path(path),
+        "errors": count("error"),
+        "warnings": count("warning"),
+        "diagnostics": diagnostics,
+    }))
+}
+
+/// TextEdits per URI from a WorkspaceEdit (`changes` or `documentChanges`)
+fn workspace_edit_files(edit: &Value) -> Result<BTreeMap<String, Vec<Value>>, String> {
+    let mut files: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    if let Some(changes) = edit["changes"].as_object() {
+        for (uri, edits) in changes {
+            files
+                .entry(uri.clone())
+                .or_default()
+                .extend(edits.as_array().cloned().unwrap_or_default());
+        }
+    }
+    if let Some(doc_changes) = edit["documentChanges"].as_array() {
+        for change in doc_changes {
+            if let Some(kind) = change["kind"].as_str() {
+                return Err(format!(
+                    "rename needs a file {} operation, which Lsp does not apply",
+                    kind
+                ));
+            }
+            let Some(uri) = change["textDocument"]["uri"].as_str() else {
+                continue;
+            };
+            files
+                .entry(uri.to_string())
+                .or_default()
+                .extend(change["edits"].as_array().cloned().unwrap_or_default());
+        }
+    }
+    Ok(files)
+}
+
+fn rename(
+    path: &Path,
+    position: (u64, u64),
+    new_name: &str,
+    lsp: &mut LspManager,
+    root: &Path,
+) -> Result<Value> {
+    let (server, _) = lsp.open(path)?;
+    let mut params = text_document_position(path, position);
+    params["newName"] = json!(new_name);
+    let result = lsp
+        .client(&server)?
+        .request("textDocument/rename", params)?;
+    if result.is_null() {
+        return Ok(error(
+            "not_renameable",
+            "The server found nothing to rename here",
+        ));
+    }
+    let files = match workspace_edit_files(&result) {
+        Ok(f) => f,
+        Err(msg) => return Ok(error("unsupported_edit", msg)),
+    };
+
+    let mut resolver = Resolver::new(root);
+    let mut edits = Vec::new();
+    let mut changes = 0;
+    for (uri, text_edits) in &files {
+        let Some(file) = uri_to_path(uri) else {
+            continue;
+        };
+        let rel = resolver.display_path(&file);
+        if !file.starts_with(root) {
+            return Ok(error(
+                "path_out_of_scope",
+                format!("rename touches {} outside the project", rel),
+            ));
+        }
+        let replacements: Vec<TextReplacement> = text_edits
+            .iter()
+            .map(|e| {
+                let start = resolver.position(&file, &e["range"]["start"]);
+                let end = resolver.position(&file, &e["range"]["end"]);
+                TextReplacement {
+                    start: (start.0 as usize, start.1),
+                    end: (end.0 as usize, end.1),
+                    text: e["newText"].as_str().unwrap_or("").to_string(),
+                }
+            })
+            .collect();
+        if replacements.is_empty() {
+            continue;
+        }
+        changes += replacements.len();
+        match edit_args(root, &rel, &replacements) {
+            Some(args) => edits.push(args),
+            None => {
+                return Ok(error(
+                    "edit_conflict",
+                    format!(
+                        "Could not turn the server's edits for {} into Edit arguments",
+                        rel
+                    ),
+                ))
+            }
+        }
+    }
+
+    Ok(json!({
+        "server": server,
+        "new_name": new_name,
+        "files": edits.len(),
+        "changes": changes,
+        "edits": edits,
+        "message": "Nothing was written. Apply each entry of `edits` with the Edit tool.",
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{BashEnvConfig, LspConfig, LspServerConfig};
+    use crate::lsp::client::{read_message, write_message, LspClient};
+    use std::io::BufReader;
+    use tempfile::TempDir;
+
+    /// A tiny language server: `target` is defined on line 1 of a.rs and used
+    /// on line 3 of a.rs and line 1 of b.rs
+    fn fake_server(root: PathBuf) -> LspClient {
+        let (server_read, client_write) = std::io::pipe().unwrap();
+        let (client_read, mut server_write) = std::io::pipe().unwrap();
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(server_read);
+            let a = path_to_uri(&root.join("a.rs"));
+            let b = path_to_uri(&root.join("b.rs"));
+            let range = |line: u64, start: u64, len: u64| {
+                json!({ "start": { "line": line, "character": start },
+                        "end": { "line": line, "character": start + len } })
+            };
+            while let Some(msg) = read_message(&mut reader) {
+                let method = msg["method"].as_str().unwrap_or("").to_string();
+                let result = match method.as_str() {
+                    "initialize" => json!({ "capabilities": {} }),
+                    "textDocument/didOpen" => {
+                        let uri = &msg["params"]["textDocument"]["uri"];
+                        let note = json!({
+                            "jsonrpc": "2.0",
+                            "method": "textDocument/publishDiagnostics",
+                            "params": { "uri": uri, "diagnostics": [{
+                                "range": range(2, 4, 6), "severity": 2,
+                                "message": "unused result", "source": "fake"
+                            }]}
+                        });
+                        let _ = write_message(&mut server_write, &note);
+                        continue;
+                    }
+                    "textDocument/definition" => json!([{ "uri": a, "range": range(0, 3, 6) }]),
+                    "textDocument/references" => json!([
+                        { "uri": a, "range": range(0, 3, 6) },
+                        { "uri": a, "range": range(2, 4, 6) },
+                        { "uri": b, "range": range(0, 13, 6) }
+                    ]),
+                    "textDocument/hover" => {
+                        json!({ "contents": { "kind": "markdown", "value": "fn target() -> u32" } })
+                    }
+                    "textDocument/documentSymbol" => json!([{
+                        "name": "target", "kind": 12, "range": range(0, 0, 20),
+                        "selectionRange": range(0, 3, 6)
+                    }, {
+                        "name": "Holder", "kind": 23, "range": range(4, 0, 20),
+                        "selectionRange": range(4, 7, 6),
+                        "children": [{ "name": "value", "kind": 8, "range": range(5, 4, 10),
+                                       "selectionRange": range(5, 4, 5) }]
+                    }]),
+                    "textDocument/rename" => {
+                        let name = msg["params"]["newName"].clone();
+                        json!({ "changes": {
+                            a.clone(): [
+                                { "range": range(0, 3, 6), "newText": name },
+                                { "range": range(2, 4, 6), "newText": name }
+                            ],
+                            b.clone(): [{ "range": range(0, 13, 6), "newText": name }]
+                        }})
+                    }
+                    _ => Value::Null,
+                };
+                if msg.get("id").is_some() {
+                    let reply = json!({ "jsonrpc": "2.0", "id": msg["id"], "result": result });
+                    if write_message(&mut server_write, &reply).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        LspClient::new(client_read, client_write, None, Duration::from_secs(5))
+    }
+
+    fn setup() -> (TempDir, LspManager) {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(
+            root.join("a.rs"),
+            "fn target() -> u32 { 1 }\nfn main() {\n    target();\n}\nstruct Holder {\n    value: u32,\n}\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("b.rs"), "fn other() { target(); }\n").unwrap();
+
+        let mut config = LspConfig::default();
+        config.servers.insert(
+            "fake".to_string(),
+            LspServerConfig {
+                command: "fake-ls".to_string(),
+                args: vec![],
+                extensions: vec!["rs".to_string()],
+                language_id: None,
+                initialization_options: None,
+            },
+        );
+        let mut mgr = LspManager::new(config, root.clone(), BashEnvConfig::default());
+        mgr.insert_client("fake", fake_server(root)).unwrap();
+        (dir, mgr)
+    }
+
+    #[test]
+    fn test_definition_and_references() {
+        let (_dir, mut mgr) = setup();
+        let args = json!({ "action": "definition", "path": "a.rs", "line": 3, "symbol": "target" });
+        let result = execute(args, &mut mgr).unwrap();
+        assert_eq!(result["count"], 1);
+        let loc = &result["locations"][0];
+        assert_eq!(loc["path"], "a.rs");
+        assert_eq!(loc["line"], 1);
+        assert_eq!(loc["column"], 4);
+        assert_eq!(loc["text"], "fn target() -> u32 { 1 }");
+
+        let args = json!({ "action": "references", "path": "a.rs", "line": 1, "column": 4 });
+        let result = execute(args, &mut mgr).unwrap();
+        assert_eq!(result["count"], 3);
+        assert_eq!(result["locations"][2]["path"], "b.rs");
+        assert_eq!(result["locations"][2]["column"], 14);
+    }
+
+    #[test]
+    fn test_hover_symbols_and_diagnostics() {
+        let (_dir, mut mgr) = setup();
+        let result = execute(
+            json!({ "action": "hover", "path": "a.rs", "line": 1, "symbol": "target" }),
+            &mut mgr,
+        )
+        .unwrap();
+        assert_eq!(result["contents"], "fn target() -> u32");
+
+        let result = execute(
+            json!({ "action": "document_symbols", "path": "a.rs" }),
+            &mut mgr,
+        )
+        .unwrap();
+        let symbols = result["symbols"].as_array().unwrap();
+        assert_eq!(symbols.len(), 3);
+        assert_eq!(symbols[0]["kind"], "function");
+        assert_eq!(symbols[2]["name"], "value");
+        assert_eq!(symbols[2]["container"], "Holder");
+
+        let result = execute(json!({ "action": "diagnostics", "path": "a.rs" }), &mut mgr).unwrap();
+        assert_eq!(result["warnings"], 1);
+        assert_eq!(result["diagnostics"][0]["line"], 3);
+        assert_eq!(result["diagnostics"][0]["message"], "unused result");
+    }
+
+    #[test]
+    fn test_rename_returns_edit_args() {
+        let (dir, mut mgr) = setup();
+        let args = json!({ "action": "rename", "path": "a.rs", "line": 1, "symbol": "target", "new_name": "goal" });
+        let result = execute(args, &mut mgr).unwrap();
+        assert_eq!(result["files"], 2);
+        assert_eq!(result["changes"], 3);
+
+        // Nothing written until the edits go through the Edit tool
+        let a = dir.path().join("a.rs");
+        assert!(std::fs::read_to_string(&a).unwrap().contains("target"));
+        let root = dir.path().canonicalize().unwrap();
+        for edit in result["edits"].as_array().unwrap() {
+            let applied = crate::tools::edit::execute(edit.clone(), &root).unwrap();
+            assert!(applied.get("error").is_none(), "{}", applied);
+        }
+        assert!(!std::fs::read_to_string(&a).unwrap().contains("target"));
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("b.rs")).unwrap(),
+            "fn other() { goal(); }\n"
+        );
+    }
+
+    #[test]
+    fn test_position_errors() {
+        let (_dir, mut mgr) = setup();
+        let result = execute(
+            json!({ "action": "hover", "path": "a.rs", "line": 2, "symbol": "nope" }),
+            &mut mgr,
+        )
+        .unwrap();
+        assert_eq!(result["error"]["code"], "symbol_not_found");
+
+        let result = execute(
+            json!({ "action": "definition", "path": "c.py", "line": 1 }),
+            &mut mgr,
+        )
+        .unwrap();
+        assert_eq!(result["error"]["code"], "not_found");
+    }
+
+    #[test]
+    fn test_utf16_columns() {
+        // "é" is one UTF-16 unit, "😀" is two
+        assert_eq!(char_column("😀é x", 2), 2);
+        assert_eq!(char_column("😀é x", 4), 4);
+        assert_eq!(utf16_len("😀é ".chars()), 4);
+    }
+}
@@ -0,0 +1,221 @@
+//! Route tool calls to connected MCP servers.
+//!
+//! Inside yo an MCP tool is named `mcp.<server>.<tool>`, which is what
+//! permission rules, hooks, limits and the transcript see. Model APIs only
+//! accept `[a-zA-Z0-9_-]` in function names, so schemas call it
+//! `mcp__<server>__<tool>` and `tool_name` maps the model's calls back.
+//! Function names that collide after sanitizing or truncation are never
+//! routed: the manager refuses to connect such a server, and tools that
+//! collide after a list refresh are left out of the schemas.
+
+use super::SchemaOptions;
+use crate::mcp::client::McpTool;
+use crate::mcp::content::content_text;
+use crate::mcp::McpManager;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// Longest function name the model APIs accept
+const MAX_NAME_LEN: usize = 64;
+
+/// Name of an MCP tool in the schemas sent to the model
+fn api_name(server: &str, tool: &str) -> String {
+    let tool: String = tool
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' => c,
+            _ => '_',
+        })
+        .collect();
+    let mut name = format!("mcp__{}__{}", server, tool);
+    name.truncate(MAX_NAME_LEN);
+    name
+}
+
+/// Tools grouped by their function name; more than one entry is a collision
+fn by_api_name<'a>(
+    tools: &[(&'a str, &'a McpTool)],
+) -> BTreeMap<String, Vec<(&'a str, &'a McpTool)>> {
+    let mut names: BTreeMap<String, Vec<(&str, &McpTool)>> = BTreeMap::new();
+    for &(server, tool) in tools {
+        names
+            .entry(api_name(server, &tool.name))
+            .or_default()
+            .push((server, tool));
+    }
+    names
+}
+
+/// Function names shared by several tools, with yo's names for each
+pub fn collisions(mcp: &McpManager) -> Vec<(String, Vec<String>)> {
+    by_api_name(&mcp.tools())
+        .into_iter()
+        .filter(|(_, tools)| tools.len() > 1)
+        .map(|(name, tools)| {
+            let tools = tools
+                .iter()
+                .map(|(server, tool)| format!("mcp.{}.{}", server, tool.name))
+                .collect();
+            (name, tools)
+        })
+        .collect()
+}
+
+/// Function schema for one MCP tool
+fn schema(server: &str, tool: &McpTool, opts: &SchemaOptions) -> Value {
+    let mut description = tool.description.clone().unwrap_or_default();
+    if opts.optimize {
+        description = description.lines().next().unwrap_or("").to_string();
+    }
+    json!({
+        "type": "function",
+        "function": {
+            "name": api_name(server, &tool.name),
+            "description": format!("[MCP {}] {}", server, description).trim_end(),
+            "parameters": tool.input_schema
+        }
+    })
+}
+
+/// Schemas for the tools of every connected server, except colliding ones
+pub fn schemas(mcp: &McpManager, opts: &SchemaOptions) -> Vec<Value> {
+    by_api_name(&mcp.tools())
+        .into_values()
+        .filter_map(|tools| match tools[..] {
+            [(server, tool)] => Some(schema(server, tool, opts)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// yo's name (`mcp.<server>.<tool>`) for a tool name the model called;
+/// other names are returned unchanged
+pub fn tool_name(mcp: &McpManager, called: &str) -> String {
+    if called.starts_with("mcp__") {
+        if let Some([(server, tool)]) = by_api_name(&mcp.tools()).get(called).map(|t| &t[..]) {
+            return format!("mcp.{}.{}", server, tool.name);
+        }
+    }
+    called.to_string()
+}
+
+/// Execute `mcp.<server>.<tool>`
+pub fn execute(name: &str, args: Value, mcp: &mut McpManager) -> Value {
+    let Some((server, tool)) = name
+        .strip_prefix("mcp.")
+        .and_then(|rest| rest.split_once('.'))
+    else {
+        return error("unknown_tool", format!("Unknown tool: {}", name));
+    };
+    let arguments = match args {
+        Value::Null => json!({}),
+        args => args,
+    };
+    match mcp.call(server, tool, arguments) {
+        Ok(result) => to_tool_result(&result),
+        Err(e) => error("mcp_error", format!("{:#}", e)),
+    }
+}
+
+/// Turn a `CallToolResult` into a tool result: text content is joined, other
+/// content is summarized, and `isError` becomes an error
+fn to_tool_result(result: &Value) -> Value {
+    let parts: Vec<String> = result["content"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(content_text)
+        .collect();
+    let text = parts.join("\n");
+
+    if result["isError"].as_bool() == Some(true) {
+        let message = if text.is_empty() {
+            "Tool reported an error".to_string()
+        } else {
+            text
+        };
+        return error("mcp_tool_error", message);
+    }
+    let mut out = json!({ "content": text });
+    if let Some(structured) = result.get("structuredContent") {
+        out["structured_content"] = structured.clone();
+    }
+    out
+}
+
+fn error(code: &str, message: impl Into<String>) -> Value {
+    json!({ "error": { "code": code, "message": message.into() } })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_names_are_valid_function_names() {
+        assert_eq!(api_name("git", "status"), "mcp__git__status");
+        assert_eq!(api_name("fs", "read.file/v2"), "mcp__fs__read_file_v2");
+        assert_eq!(api_name("s", &"x".repeat(100)).len(), MAX_NAME_LEN);
+
+        let tool = McpTool {
+            name: "add".to_string(),
+            description: Some("Add two numbers\nReturns the sum".to_string()),
+            input_schema: json!({ "type": "object", "properties": { "a": { "type": "number" } } }),
+        };
+        let schema = schema("calc", &tool, &SchemaOptions::new(true));
+        assert_eq!(schema["function"]["name"], "mcp__calc__add");
+        assert_eq!(
+            schema["function"]["description"],
+            "[MCP calc] Add two numbers"
+        );
+        assert_eq!(schema["function"]["parameters"], tool.input_schema);
+    }
+
+    #[test]
+    fn test_colliding_api_names() {
+        let tool = |name: &str| McpTool {
+            name: name.to_string(),
+            description: None,
+            input_schema: json!({ "type": "object" }),
+        };
+        let (dotted, plain, other) = (tool("read.file"), tool("read_file"), tool("list"));
+        let long = "s".repeat(60);
+        let longer = format!("{}2", long);
+        let tools = [
+            ("fs", &dotted),
+            ("fs", &plain),
+            ("fs", &other),
+            (long.as_str(), &other),
+            (longer.as_str(), &other),
+        ];
+        let names = by_api_name(&tools);
+        assert_eq!(names["mcp__fs__read_file"].len(), 2);
+        assert_eq!(names["mcp__fs__list"].len(), 1);
+        assert_eq!(names[&format!("mcp__{}", &long[..59])].len(), 2);
+    }
+
+    #[test]
+    fn test_call_results() {
+        let result = to_tool_result(&json!({
+            "content": [
+                { "type": "text", "text": "3 files" },
+                { "type": "image", "data": "...", "mimeType": "image/png" }
+            ],
+            "structuredContent": { "count": 3 }
+        }));
+        assert_eq!(result["content"], "3 files\n[image: image/png]");
+        assert_eq!(result["structured_content"]["count"], 3);
+
+        let result = to_tool_result(&json!({
+            "content": [{ "type": "text", "text": "no such repo" }],
+            "isError": true
+        }));
+        assert_eq!(result["error"]["code"], "mcp_tool_error");
+        assert_eq!(result["error"]["message"], "no such repo");
+
+        let mut mcp = McpManager::new(Default::default(), std::env::temp_dir(), Default::default());
+        let result = execute("mcp.calc.add", json!({}), &mut mcp);
+        assert_eq!(result["error"]["code"], "mcp_error");
+        assert_eq!(tool_name(&mcp, "mcp__calc__add"), "mcp__calc__add");
+    }
+}
@@ -1,18 +1,30 @@
 pub mod activate_skill;
 pub mod ask_user;
 pub mod bash;
+mod code_search;
+mod diagnostics;
 pub mod edit;
+mod files;
+pub mod format;
+mod git;
 mod glob;
 mod grep;
+mod list;
+pub mod lsp;
+pub mod mcp_dispatch;
 mod patch;
 pub mod plan_mode;
 mod read;
+mod read_many;
+mod run_tests;
 mod search;
 pub mod task;
 pub mod todo;
 mod write;
 
 use crate::config::BashConfig;
+use crate::mcp::McpManager;
+use crate::policy::SecretFiles;
 use anyhow::Result;
 use serde_json::{json, Value};
 use std::path::Path;
@@ -22,11 +34,21 @@ use std::path::Path;
 pub struct SchemaOptions {
     /// Generate terse schemas optimized for token efficiency
     pub optimize: bool,
+    /// Bash runs commands through a real shell (`bash.shell` is set)
+    pub shell: bool,
 }
 
 impl SchemaOptions {
     pub fn new(optimize: bool) -> Self {
-        Self { optimize }
+        Self {
+            optimize,
+            shell: false,
+        }
+    }
+
+    pub fn with_shell(mut self, shell: bool) -> Self {
+        self.shell = shell;
+        self
     }
 }
 
@@ -34,46 +56,100 @@ impl SchemaOptions {
 pub fn schemas(opts: &SchemaOptions) -> Vec<Value> {
     vec![
         read::schema(opts),
+        read_many::schema(opts),
         write::schema(opts),
         edit::schema(opts),
         patch::schema(opts),
+        files::move_schema(opts),
+        files::copy_schema(opts),
+        files::delete_schema(opts),
+        files::make_dir_schema(opts),
         glob::schema(opts),
+        list::schema(opts),
         search::schema(opts),
+        code_search::schema(opts),
         bash::schema(opts),
+        git::schema(opts),
+        run_tests::schema(opts),
+        diagnostics::schema(opts),
+        lsp::schema(opts),
     ]
 }
 
-/// Get all tool schemas including Task and ActivateSkill (used by main agent)
-pub fn schemas_with_task(opts: &SchemaOptions) -> Vec<Value> {
-    vec![
+/// Get all tool schemas including Task and ActivateSkill, plus the tools of
+/// connected MCP servers (used by main agent)
+pub fn schemas_with_task(opts: &SchemaOptions, mcp: &McpManager) -> Vec<Value> {
+    let mut schemas = vec![
         read::schema(opts),
+        read_many::schema(opts),
         write::schema(opts),
         edit::schema(opts),
         patch::schema(opts),
+        files::move_schema(opts),
+        files::copy_schema(opts),
+        files::delete_schema(opts),
+        files::make_dir_schema(opts),
         glob::schema(opts),
+        list::schema(opts),
         search::schema(opts),
+        code_search::schema(opts),
         bash::schema(opts),
+        git::schema(opts),
+        run_tests::schema(opts),
+        diagnostics::schema(opts),
+        lsp::schema(opts),
         task::schema(opts),
         activate_skill::schema(opts),
         todo::schema(opts),
         ask_user::schema(opts),
         plan_mode::enter_schema(opts),
         plan_mode::exit_schema(opts),
-    ]
+    ];
+    schemas.extend(mcp_dispatch::schemas(mcp, opts));
+    schemas
+}
+
+/// Fill in arguments derived from the project before the permission check,
+/// so policy sees what will actually run (e.g. the RunTests command line)
+pub fn prepare_args(name: &str, args: Value, root: &Path) -> Value {
+    match name {
+        "ReadMany" => read_many::resolve(args, root),
+        "RunTests" => run_tests::resolve(args, root),
+        "Diagnostics" => diagnostics::resolve(args, root),
+        _ => args,
+    }
 }
 
 /// Execute a tool by name
-/// For Bash tool, uses the provided BashConfig; other tools ignore it
-pub fn execute(name: &str, args: Value, root: &Path, bash_config: &BashConfig) -> Result<Value> {
+/// Bash, RunTests, Diagnostics and Git use the provided BashConfig; other tools ignore it
+/// Grep, Search and CodeSearch skip the secret files in `secrets`; Git diff and show omit their hunks
+/// Tools that keep state across calls (Lsp, `mcp.*`) are dispatched by the agent
+pub fn execute(
+    name: &str,
+    args: Value,
+    root: &Path,
+    bash_config: &BashConfig,
+    secrets: &SecretFiles,
+) -> Result<Value> {
     match name {
         "Read" => read::execute(args, root),
+        "ReadMany" => read_many::execute(args, root),
         "Write" => write::execute(args, root),
         "Edit" => edit::execute(args, root),
         "Patch" => patch::execute(args, root),
-        "Grep" => grep::execute(args, root),
+        "Move" => files::execute_move(args, root),
+        "Copy" => files::execute_copy(args, root),
+        "Delete" => files::execute_delete(args, root),
+        "MakeDir" => files::execute_make_dir(args, root),
+        "Grep" => grep::execute(args, root, secrets),
         "Glob" => glob::execute(args, root),
-        "Search" => search::execute(args, root),
+        "List" => list::execute(args, root),
+        "Search" => search::execute(args, root, secrets),
+        "CodeSearch" => code_search::execute(args, root, secrets),
         "Bash" => bash::execute(args, root, bash_config),
+        "Git" => git::execute(args, root, bash_config, secrets),
+        "RunTests" => run_tests::execute(args, root, bash_config),
+        "Diagnostics" => diagnostics::execute(args, root, bash_config),
         _ => Ok(
             json!({ "error": { "code": "unknown_tool", "message": format!("Unknown tool: {}", name) } }),
         ),
@@ -130,6 +206,111 @@ fn normalize_path(path: &Path) -> std::path::PathBuf {
     result
 }
 
+/// A replacement of the text between two 1-based (line, character) positions
+#[derive(Debug, Clone)]
+pub(crate) struct TextReplacement {
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+    pub text: String,
+}
+
+/// Byte offset of a 1-based (line, character) position
+fn offset_of(content: &str, (line, column): (usize, usize)) -> Option<usize> {
+    let mut start = 0;
+    for _ in 1..line {
+        start += content[start..].find('\n')? + 1;
+    }
+    let line_text = &content[start..];
+    let col = line_text
+        .char_indices()
+        .nth(column.saturating_sub(1))
+        .map(|(i, _)| i)
+        .unwrap_or_else(|| line_text.find('\n').unwrap_or(line_text.len()));
+    Some(start + col)
+}
+
+/// Turn replacements in `file` into Edit tool arguments. Each group of
+/// affected lines becomes one `find` text, widened with neighbouring lines
+/// until it is unique in the file, so the edit can go through the normal
+/// Edit permission check and apply exactly where intended.
+pub(crate) fn edit_args(
+    root: &Path,
+    file: &str,
+    replacements: &[TextReplacement],
+) -> Option<Value> {
+    let content = std::fs::read_to_string(root.join(file)).ok()?;
+
+    let mut spans: Vec<(usize, usize, &str)> = replacements
+        .iter()
+        .map(|r| {
+            Some((
+                offset_of(&content, r.start)?,
+                offset_of(&content, r.end)?,
+                r.text.as_str(),
+            ))
+        })
+        .collect::<Option<_>>()?;
+    spans.sort_by_key(|s| s.0);
+    if spans.is_empty() || spans.windows(2).any(|w| w[0].1 > w[1].0) {
+        return None;
+    }
+
+    // Whole-line region around each replacement, widened until unique
+    let mut regions: Vec<(usize, usize)> = spans
+        .iter()
+        .map(|&(s, e, _)| {
+            let mut start = content[..s].rfind('\n').map(|i| i + 1).unwrap_or(0);
+            let mut end = content[e..]
+                .find('\n')
+                .map(|i| e + i)
+                .unwrap_or(content.len());
+            while content.matches(&content[start..end]).count() > 1 {
+                let before = (start, end);
+                if start > 0 {
+                    start = content[..start - 1].rfind('\n').map(|i| i + 1).unwrap_or(0);
+                }
+                if end < content.len() {
+                    end = content[end + 1..]
+                        .find('\n')
+                        .map(|i| end + 1 + i)
+                        .unwrap_or(content.len());
+                }
+                if (start, end) == before {
+                    break;
+                }
+            }
+            (start, end)
+        })
+        .collect();
+
+    // Merge overlapping or touching regions; a region containing a unique one is unique
+    regions.sort();
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in regions {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 + 1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+
+    let edits: Vec<Value> = merged
+        .iter()
+        .map(|&(start, end)| {
+            let mut replace = String::new();
+            let mut cursor = start;
+            for &(s, e, text) in spans.iter().filter(|sp| sp.0 >= start && sp.1 <= end) {
+                replace.push_str(&content[cursor..s]);
+                replace.push_str(text);
+                cursor = e;
+            }
+            replace.push_str(&content[cursor..end]);
+            json!({ "find": &content[start..end], "replace": replace })
+        })
+        .collect();
+
+    Some(json!({ "path": file, "edits": edits }))
+}
+
 fn sha256(data: &[u8]) -> String {
     use sha2::{Digest, Sha256};
     let mut hasher = Sha256::new();
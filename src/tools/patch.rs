@@ -74,7 +74,9 @@ pub fn execute(args: Value, root: &Path) -> anyhow::Result<Value> {
         } else if !original.is_empty() && original != "/dev/null" {
             original
         } else {
-            return Ok(json!({ "error": { "code": "invalid_patch", "message": "No target path in patch headers and no path provided" } }));
+            return Ok(
+                json!({ "error": { "code": "invalid_patch", "message": "No target path in patch headers and no path provided" } }),
+            );
         };
 
         // Strip a/ or b/ prefix (git diff format)
@@ -97,9 +99,7 @@ pub fn execute(args: Value, root: &Path) -> anyhow::Result<Value> {
         match std::fs::read_to_string(&full_path) {
             Ok(s) => s,
             Err(e) => {
-                return Ok(
-                    json!({ "error": { "code": "read_error", "message": e.to_string() } }),
-                )
+                return Ok(json!({ "error": { "code": "read_error", "message": e.to_string() } }))
             }
         }
     };
@@ -166,17 +166,13 @@ mod tests {
 
     fn setup_test_dir() -> TempDir {
         let dir = TempDir::new().unwrap();
-        fs::write(
-            dir.path().join("test.txt"),
-            "line 1\nline 2\nline 3\n",
-        )
-        .unwrap();
+        fs::write(dir.path().join("test.txt"), "line 1\nline 2\nline 3\n").unwrap();
         dir
     }
 
     #[test]
     fn test_schema() {
-        let opts = SchemaOptions { optimize: false };
+        let opts = SchemaOptions::new(false);
         let schema = schema(&opts);
         assert_eq!(schema["function"]["name"].as_str().unwrap(), "Patch");
         assert!(schema["function"]["parameters"]["properties"]
@@ -186,7 +182,7 @@ mod tests {
 
     #[test]
     fn test_schema_optimized() {
-        let opts = SchemaOptions { optimize: true };
+        let opts = SchemaOptions::new(true);
         let schema = schema(&opts);
         assert_eq!(schema["function"]["name"].as_str().unwrap(), "Patch");
     }
@@ -205,7 +201,7 @@ mod tests {
         let args = json!({ "patch": patch });
         let result = execute(args, dir.path()).unwrap();
 
-        assert_eq!(result["success"].as_bool().unwrap(), true);
+        assert!(result["success"].as_bool().unwrap());
         assert_eq!(result["files_modified"].as_i64().unwrap(), 1);
         assert_eq!(result["files"][0]["hunks_applied"].as_i64().unwrap(), 1);
 
@@ -226,7 +222,7 @@ mod tests {
         let args = json!({ "patch": patch, "path": "test.txt" });
         let result = execute(args, dir.path()).unwrap();
 
-        assert_eq!(result["success"].as_bool().unwrap(), true);
+        assert!(result["success"].as_bool().unwrap());
 
         let content = fs::read_to_string(dir.path().join("test.txt")).unwrap();
         assert!(content.contains("inserted"));
@@ -248,8 +244,8 @@ mod tests {
         let args = json!({ "patch": patch, "dry_run": true });
         let result = execute(args, dir.path()).unwrap();
 
-        assert_eq!(result["success"].as_bool().unwrap(), true);
-        assert_eq!(result["dry_run"].as_bool().unwrap(), true);
+        assert!(result["success"].as_bool().unwrap());
+        assert!(result["dry_run"].as_bool().unwrap());
         assert_eq!(result["files_modified"].as_i64().unwrap(), 0);
 
         // File should be unchanged
@@ -86,5 +86,10 @@ pub fn execute(args: Value, root: &Path) -> anyhow::Result<Value> {
         result["encoding"] = json!(enc);
     }
 
+    if truncated {
+        result["next_offset"] = json!(end);
+        result["size"] = json!(data.len());
+    }
+
     Ok(result)
 }
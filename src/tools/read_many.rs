@@ -0,0 +1,322 @@
+//! ReadMany tool: read several files or line ranges in one call.
+//!
+//! Entries are paths or globs with optional line ranges. Globs are expanded
+//! by `resolve` before the permission check, so policy sees every concrete
+//! path. Files are read in order against a shared byte budget; whatever does
+//! not fit is listed under `skipped` with the range to request next.
+
+use super::{sha256, validate_path, SchemaOptions};
+use serde_json::{json, Value};
+use std::path::Path;
+
+const DEFAULT_MAX_BYTES: usize = 131072;
+const MAX_FILES: usize = 50;
+
+pub fn schema(opts: &SchemaOptions) -> Value {
+    if opts.optimize {
+        json!({
+            "type": "function",
+            "function": {
+                "name": "ReadMany",
+                "description": "Read several files/ranges",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "files": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "path": { "type": "string" },
+                                    "start_line": { "type": "integer" },
+                                    "end_line": { "type": "integer" }
+                                },
+                                "required": ["path"]
+                            }
+                        },
+                        "max_bytes": { "type": "integer" }
+                    },
+                    "required": ["files"]
+                }
+            }
+        })
+    } else {
+        json!({
+            "type": "function",
+            "function": {
+                "name": "ReadMany",
+                "description": "Read several files, globs or line ranges in one call, sharing one byte budget. Files that do not fit are listed in `skipped` with the range to request next. Use Read for binary files.",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "files": {
+                            "type": "array",
+                            "description": "Files to read, in order",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "path": { "type": "string", "description": "File path or glob (e.g. src/tools/*.rs) relative to root" },
+                                    "start_line": { "type": "integer", "description": "First line, 1-based (default 1)" },
+                                    "end_line": { "type": "integer", "description": "Last line, inclusive (default: end of file)" }
+                                },
+                                "required": ["path"]
+                            }
+                        },
+                        "max_bytes": { "type": "integer", "description": "Total content budget across all files (default 131072)" }
+                    },
+                    "required": ["files"]
+                }
+            }
+        })
+    }
+}
+
+/// Expand globs in `files` into one entry per matching file (keeping the
+/// entry's line range), so the policy engine can check every path
+pub fn resolve(args: Value, root: &Path) -> Value {
+    let mut args = if args.is_object() { args } else { json!({}) };
+    let Some(entries) = args["files"].as_array() else {
+        return args;
+    };
+
+    let mut files = Vec::new();
+    for entry in entries {
+        // Accept bare strings as well as objects
+        let mut entry = match entry {
+            Value::String(path) => json!({ "path": path }),
+            other => other.clone(),
+        };
+        let path = entry["path"].as_str().unwrap_or("").to_string();
+        if !is_glob(&path) || path.starts_with('/') {
+            files.push(entry);
+            continue;
+        }
+        let matches = expand(&path, root);
+        if matches.is_empty() {
+            files.push(entry);
+            continue;
+        }
+        for rel in matches {
+            entry["path"] = json!(rel);
+            files.push(entry.clone());
+        }
+    }
+    args["files"] = json!(files);
+    args
+}
+
+pub fn execute(args: Value, root: &Path) -> anyhow::Result<Value> {
+    let args = resolve(args, root);
+    let max_bytes = args["max_bytes"]
+        .as_u64()
+        .map(|n| n as usize)
+        .unwrap_or(DEFAULT_MAX_BYTES);
+    let entries = args["files"].as_array().cloned().unwrap_or_default();
+    if entries.is_empty() {
+        return Ok(
+            json!({ "error": { "code": "invalid_args", "message": "files must list at least one path" } }),
+        );
+    }
+
+    let mut files = Vec::new();
+    let mut skipped = Vec::new();
+    let mut errors = Vec::new();
+    let mut used = 0;
+
+    for (i, entry) in entries.iter().enumerate() {
+        let path = entry["path"].as_str().unwrap_or("");
+        let start_line = entry["start_line"].as_u64().unwrap_or(1).max(1) as usize;
+        let end_line = entry["end_line"].as_u64().map(|n| n as usize);
+
+        if i >= MAX_FILES {
+            skipped.push(json!({ "path": path, "reason": "file_limit" }));
+            continue;
+        }
+        if is_glob(path) {
+            errors.push(json!({
+                "path": path,
+                "error": { "code": "no_match", "message": "Glob matched no files" }
+            }));
+            continue;
+        }
+        let full_path = match validate_path(path, root) {
+            Ok(p) => p,
+            Err(e) => {
+                errors.push(json!({ "path": path, "error": e["error"] }));
+                continue;
+            }
+        };
+        let data = match std::fs::read(&full_path) {
+            Ok(d) => d,
+            Err(e) => {
+                errors.push(json!({
+                    "path": path,
+                    "error": { "code": "read_error", "message": e.to_string() }
+                }));
+                continue;
+            }
+        };
+        let Ok(text) = std::str::from_utf8(&data) else {
+            skipped.push(json!({ "path": path, "reason": "binary", "size": data.len() }));
+            continue;
+        };
+
+        let lines: Vec<&str> = text.split_inclusive('\n').collect();
+        let total_lines = lines.len();
+        let last = end_line.unwrap_or(total_lines).min(total_lines);
+        let range = lines.get(start_line - 1..last).unwrap_or(&[]);
+        let needed: usize = range.iter().map(|l| l.len()).sum();
+        let remaining = max_bytes.saturating_sub(used);
+
+        // An over-budget first file is cut at a line boundary rather than skipped
+        let take = if needed <= remaining {
+            range.len()
+        } else if files.is_empty() {
+            let mut bytes = 0;
+            range
+                .iter()
+                .take_while(|l| {
+                    bytes += l.len();
+                    bytes <= remaining
+                })
+                .count()
+        } else {
+            0
+        };
+        if take == 0 && !range.is_empty() {
+            skipped.push(json!({
+                "path": path,
+                "reason": "budget",
+                "start_line": start_line,
+                "end_line": last,
+                "bytes": needed,
+            }));
+            continue;
+        }
+
+        let content: String = range[..take].concat();
+        used += content.len();
+        let mut result = json!({
+            "path": path,
+            "content": content,
+            "sha256": sha256(&data),
+            "start_line": start_line,
+            "end_line": start_line + take - 1,
+            "total_lines": total_lines,
+            "truncated": take < range.len(),
+        });
+        if take < range.len() {
+            result["next_line"] = json!(start_line + take);
+        }
+        files.push(result);
+    }
+
+    Ok(json!({
+        "files": files,
+        "skipped": skipped,
+        "errors": errors,
+        "bytes": used,
+        "max_bytes": max_bytes,
+    }))
+}
+
+fn is_glob(path: &str) -> bool {
+    path.contains(['*', '?', '['])
+}
+
+/// Files matching a glob, relative to root, skipping the usual build and VCS dirs
+fn expand(pattern: &str, root: &Path) -> Vec<String> {
+    let full_pattern = root.join(pattern).to_string_lossy().to_string();
+    let Ok(paths) = glob::glob(&full_pattern) else {
+        return Vec::new();
+    };
+    paths
+        .filter_map(|p| p.ok())
+        .filter(|p| p.is_file())
+        .filter_map(|p| {
+            let rel = p
+                .strip_prefix(root)
+                .ok()?
+                .to_string_lossy()
+                .replace('\\', "/");
+            let excluded = rel
+                .split('/')
+                .any(|c| matches!(c, ".git" | "target" | ".yo" | "node_modules"));
+            (!excluded).then_some(rel)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn setup() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("src/tools")).unwrap();
+        fs::write(
+            dir.path().join("src/main.rs"),
+            "fn main() {\n    run();\n}\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("src/tools/a.rs"), "// a\n").unwrap();
+        fs::write(dir.path().join("src/tools/b.rs"), "// b\n").unwrap();
+        fs::write(dir.path().join("logo.png"), [0x89, 0xff, 0x00]).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_resolve_expands_globs() {
+        let dir = setup();
+        let args = json!({ "files": ["src/main.rs", { "path": "src/tools/*.rs", "end_line": 1 }] });
+        let args = resolve(args, dir.path());
+        let paths: Vec<&str> = args["files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| f["path"].as_str().unwrap())
+            .collect();
+        assert_eq!(paths, ["src/main.rs", "src/tools/a.rs", "src/tools/b.rs"]);
+        assert_eq!(args["files"][2]["end_line"], 1);
+    }
+
+    #[test]
+    fn test_read_many_ranges_and_errors() {
+        let dir = setup();
+        let args = json!({ "files": [
+            { "path": "src/main.rs", "start_line": 2, "end_line": 3 },
+            "src/tools/*.rs",
+            "logo.png",
+            "missing.rs",
+            "docs/*.md"
+        ] });
+        let result = execute(args, dir.path()).unwrap();
+        let files = result["files"].as_array().unwrap();
+        assert_eq!(files.len(), 3);
+        assert_eq!(files[0]["content"], "    run();\n}\n");
+        assert_eq!(files[0]["total_lines"], 3);
+        assert_eq!(files[0]["truncated"], false);
+        assert_eq!(files[2]["path"], "src/tools/b.rs");
+        assert_eq!(result["skipped"][0]["reason"], "binary");
+        assert_eq!(result["errors"][0]["error"]["code"], "read_error");
+        assert_eq!(result["errors"][1]["error"]["code"], "no_match");
+    }
+
+    #[test]
+    fn test_read_many_budget() {
+        let dir = setup();
+        let args = json!({ "files": ["src/main.rs", "src/tools/a.rs"], "max_bytes": 14 });
+        let result = execute(args, dir.path()).unwrap();
+        // The first file is cut at a line boundary, the second is deferred
+        let first = &result["files"][0];
+        assert_eq!(first["content"], "fn main() {\n");
+        assert_eq!(first["truncated"], true);
+        assert_eq!(first["next_line"], 2);
+        assert_eq!(result["files"].as_array().unwrap().len(), 1);
+        assert_eq!(result["skipped"][0]["path"], "src/tools/a.rs");
+        assert_eq!(result["skipped"][0]["reason"], "budget");
+        assert_eq!(result["bytes"], 12);
+    }
+}
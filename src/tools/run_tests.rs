@@ -0,0 +1,889 @@
+//! RunTests tool: run the project's test suite and return structured results.
+//!
+//! The runner is detected from the project root (Cargo, pytest, Jest, Vitest,
+//! Go) and invoked with machine-readable output where the runner has one
+//! (JUnit XML, `--json`, `go test -json`); Cargo's stable libtest output is
+//! parsed directly. The resolved command line is filled into the call's
+//! arguments before the permission check so `Bash(...)` rules apply to it.
+
+use super::bash::{effective_timeout_ms, limit_output, run_captured};
+use super::SchemaOptions;
+use crate::config::BashConfig;
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+const REPORT_DIR: &str = ".yo/tests";
+const FAILURES_FILE: &str = ".yo/tests/last_failures.json";
+const MAX_FAILURES: usize = 50;
+const MAX_MESSAGE_CHARS: usize = 2000;
+
+static ANSI_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\x1b\[[0-9;]*[A-Za-z]").unwrap());
+static ATTR_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"([\w:-]+)="([^"]*)""#).unwrap());
+static PY_LOCATION_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^(\S+\.py):(\d+): ").unwrap());
+static GO_LOCATION_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s+(\S+\.go):(\d+): (.*)").unwrap());
+static JS_LOCATION_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\(?([^\s()]+\.[cm]?[jt]sx?):(\d+):\d+\)?").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Runner {
+    Cargo,
+    Pytest,
+    Jest,
+    Vitest,
+    Go,
+}
+
+impl Runner {
+    fn name(self) -> &'static str {
+        match self {
+            Runner::Cargo => "cargo",
+            Runner::Pytest => "pytest",
+            Runner::Jest => "jest",
+            Runner::Vitest => "vitest",
+            Runner::Go => "go",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "cargo" => Some(Runner::Cargo),
+            "pytest" => Some(Runner::Pytest),
+            "jest" => Some(Runner::Jest),
+            "vitest" => Some(Runner::Vitest),
+            "go" => Some(Runner::Go),
+            _ => None,
+        }
+    }
+
+    /// Detect the runner from marker files in the project root
+    fn detect(root: &Path) -> Option<Self> {
+        if root.join("Cargo.toml").exists() {
+            return Some(Runner::Cargo);
+        }
+        if root.join("go.mod").exists() {
+            return Some(Runner::Go);
+        }
+        if let Ok(text) = std::fs::read_to_string(root.join("package.json")) {
+            let pkg: Value = serde_json::from_str(&text).unwrap_or_default();
+            let uses = |dep: &str| {
+                ["dependencies", "devDependencies"]
+                    .iter()
+                    .any(|k| pkg[k].get(dep).is_some())
+                    || pkg["scripts"]["test"]
+                        .as_str()
+                        .is_some_and(|t| t.contains(dep))
+            };
+            if uses("vitest") {
+                return Some(Runner::Vitest);
+            }
+            if uses("jest") {
+                return Some(Runner::Jest);
+            }
+        }
+        let python_markers = [
+            "pytest.ini",
+            "pyproject.toml",
+            "setup.cfg",
+            "tox.ini",
+            "conftest.py",
+        ];
+        if python_markers.iter().any(|m| root.join(m).exists()) {
+            return Some(Runner::Pytest);
+        }
+        None
+    }
+
+    fn report_path(self) -> Option<String> {
+        match self {
+            Runner::Pytest => Some(format!("{}/pytest.xml", REPORT_DIR)),
+            Runner::Jest => Some(format!("{}/jest.json", REPORT_DIR)),
+            Runner::Vitest => Some(format!("{}/vitest.json", REPORT_DIR)),
+            Runner::Cargo | Runner::Go => None,
+        }
+    }
+
+    /// Build the argv for a run, optionally restricted to a filter or exact test names
+    fn argv(self, filter: Option<&str>, only: &[String]) -> Vec<String> {
+        let mut argv: Vec<String> = match self {
+            Runner::Cargo => vec!["cargo", "test", "--no-fail-fast"],
+            Runner::Pytest => vec!["python", "-m", "pytest", "-q"],
+            Runner::Jest => vec!["npx", "jest", "--json"],
+            Runner::Vitest => vec!["npx", "vitest", "run", "--reporter=json"],
+            Runner::Go => vec!["go", "test", "-json"],
+        }
+        .into_iter()
+        .map(String::from)
+        .collect();
+
+        match self {
+            Runner::Pytest => argv.push(format!("--junitxml={}/pytest.xml", REPORT_DIR)),
+            Runner::Jest | Runner::Vitest => {
+                argv.push(format!("--outputFile={}", self.report_path().unwrap()))
+            }
+            Runner::Go => argv.push("./...".to_string()),
+            Runner::Cargo => {}
+        }
+
+        let regex_any = |names: &[String]| {
+            names
+                .iter()
+                .map(|n| regex::escape(n))
+                .collect::<Vec<_>>()
+                .join("|")
+        };
+        if !only.is_empty() {
+            match self {
+                Runner::Cargo => {
+                    argv.extend(["--".to_string(), "--exact".to_string()]);
+                    argv.extend(only.iter().cloned());
+                }
+                Runner::Pytest => {
+                    argv.extend(["-k".to_string(), only.join(" or ")]);
+                }
+                Runner::Jest | Runner::Vitest => {
+                    argv.extend(["-t".to_string(), format!("^({})$", regex_any(only))]);
+                }
+                Runner::Go => {
+                    argv.extend(["-run".to_string(), format!("^({})$", regex_any(only))]);
+                }
+            }
+        } else if let Some(filter) = filter {
+            match self {
+                Runner::Cargo => argv.push(filter.to_string()),
+                Runner::Pytest => argv.extend(["-k".to_string(), filter.to_string()]),
+                Runner::Jest | Runner::Vitest => {
+                    argv.extend(["-t".to_string(), filter.to_string()])
+                }
+                Runner::Go => argv.extend(["-run".to_string(), filter.to_string()]),
+            }
+        }
+        argv
+    }
+}
+
+pub fn schema(opts: &SchemaOptions) -> Value {
+    if opts.optimize {
+        json!({
+            "type": "function",
+            "function": {
+                "name": "RunTests",
+                "description": "Run tests, get structured results",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "runner": { "type": "string" },
+                        "filter": { "type": "string" },
+                        "failed_only": { "type": "boolean" },
+                        "timeout_ms": { "type": "integer" }
+                    }
+                }
+            }
+        })
+    } else {
+        json!({
+            "type": "function",
+            "function": {
+                "name": "RunTests",
+                "description": "Run the project's tests and return pass/fail/skip counts, failing test names, assertion messages and file:line locations. The runner (cargo, pytest, jest, vitest, go) is detected from the project root. Prefer this over Bash for running tests.",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "runner": {
+                            "type": "string",
+                            "enum": ["cargo", "pytest", "jest", "vitest", "go"],
+                            "description": "Override runner detection"
+                        },
+                        "filter": { "type": "string", "description": "Only run tests matching this name filter" },
+                        "failed_only": { "type": "boolean", "description": "Re-run only the tests that failed in the previous run" },
+                        "timeout_ms": { "type": "integer", "description": "Timeout in milliseconds (default: Bash timeout)" }
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Names of the tests that failed last time, if they were run with `runner`
+fn load_failures(root: &Path, runner: Runner) -> Vec<String> {
+    let Ok(text) = std::fs::read_to_string(root.join(FAILURES_FILE)) else {
+        return Vec::new();
+    };
+    let saved: Value = serde_json::from_str(&text).unwrap_or_default();
+    if saved["runner"] != runner.name() {
+        return Vec::new();
+    }
+    saved["tests"]
+        .as_array()
+        .map(|a| {
+            a.iter()
+                .filter_map(|t| t.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn save_failures(root: &Path, runner: Runner, tests: &[String]) {
+    let _ = std::fs::create_dir_all(root.join(REPORT_DIR));
+    let saved = json!({ "runner": runner.name(), "tests": tests });
+    let _ = std::fs::write(root.join(FAILURES_FILE), saved.to_string());
+}
+
+fn error(code: &str, message: &str) -> Value {
+    json!({ "error": { "code": code, "message": message } })
+}
+
+/// Fill in `runner` and the exact `command` a call will run, so the policy
+/// engine can check it against Bash rules before execution
+pub fn resolve(args: Value, root: &Path) -> Value {
+    let mut args = if args.is_object() { args } else { json!({}) };
+    let runner = match args.get("runner").and_then(|v| v.as_str()) {
+        Some(name) => Runner::from_name(name),
+        None => Runner::detect(root),
+    };
+    let Some(runner) = runner else {
+        return args;
+    };
+
+    let only = if args["failed_only"].as_bool().unwrap_or(false) {
+        load_failures(root, runner)
+    } else {
+        Vec::new()
+    };
+    let argv = runner.argv(args["filter"].as_str(), &only);
+    args["runner"] = json!(runner.name());
+    args["command"] = json!(shell_words::join(&argv));
+    args
+}
+
+pub fn execute(args: Value, root: &Path, config: &BashConfig) -> Result<Value> {
+    let args = resolve(args, root);
+    let Some(runner) = args["runner"].as_str().and_then(Runner::from_name) else {
+        return Ok(error(
+            "unknown_runner",
+            "Could not detect a test runner (looked for Cargo.toml, go.mod, package.json with jest/vitest, pytest config)",
+        ));
+    };
+    if args["failed_only"].as_bool().unwrap_or(false) && load_failures(root, runner).is_empty() {
+        return Ok(error(
+            "no_failures",
+            "No failing tests recorded from a previous run with this runner",
+        ));
+    }
+    let command = args["command"].as_str().unwrap_or_default().to_string();
+
+    let argv = match &config.shell {
+        Some(shell) => vec![shell.clone(), "-c".to_string(), command.clone()],
+        None => match shell_words::split(&command) {
+            Ok(v) if !v.is_empty() => v,
+            _ => return Ok(error("parse_error", "Failed to parse test command")),
+        },
+    };
+
+    let report = runner.report_path().map(|p| root.join(p));
+    if let Some(report) = &report {
+        let _ = std::fs::create_dir_all(root.join(REPORT_DIR));
+        let _ = std::fs::remove_file(report);
+    }
+
+    let timeout_ms = effective_timeout_ms(config, args["timeout_ms"].as_u64());
+    let mut cmd = Command::new(&argv[0]);
+    cmd.args(&argv[1..]).current_dir(root);
+    let captured = match run_captured(cmd, root, config, Duration::from_millis(timeout_ms)) {
+        Ok(c) => c,
+        Err(e) => return Ok(error("spawn_error", &e.to_string())),
+    };
+
+    let stdout = captured.stdout.full();
+    let stdout = String::from_utf8_lossy(&stdout);
+    let summary = match runner {
+        Runner::Cargo => parse_libtest(&stdout),
+        Runner::Go => parse_go_json(&stdout),
+        Runner::Pytest => report
+            .as_ref()
+            .and_then(|r| std::fs::read_to_string(r).ok())
+            .map(|xml| parse_junit(&xml))
+            .unwrap_or_default(),
+        Runner::Jest | Runner::Vitest => report
+            .as_ref()
+            .and_then(|r| std::fs::read_to_string(r).ok())
+            .and_then(|text| serde_json::from_str::<Value>(&text).ok())
+            .map(|report| parse_jest_json(&report, root))
+            .unwrap_or_default(),
+    };
+
+    let exit_code = captured
+        .status
+        .as_ref()
+        .ok()
+        .and_then(|s| s.and_then(|s| s.code()));
+    let timed_out = matches!(captured.status, Ok(None));
+
+    // Remember failures for failed_only; a clean full run clears them
+    let rerun: Vec<String> = summary
+        .failures
+        .iter()
+        .filter_map(|f| f.rerun.clone())
+        .collect();
+    if !timed_out && (!rerun.is_empty() || summary.total() > 0) {
+        save_failures(root, runner, &rerun);
+    }
+
+    let failures: Vec<Value> = summary
+        .failures
+        .iter()
+        .take(MAX_FAILURES)
+        .map(|f| {
+            json!({
+                "name": f.name,
+                "message": truncate_chars(&f.message, MAX_MESSAGE_CHARS),
+                "file": f.file,
+                "line": f.line
+            })
+        })
+        .collect();
+
+    let mut result = json!({
+        "runner": runner.name(),
+        "command": command,
+        "exit_code": exit_code,
+        "ok": exit_code == Some(0) && summary.failed == 0,
+        "passed": summary.passed,
+        "failed": summary.failed,
+        "skipped": summary.skipped,
+        "total": summary.total(),
+        "failures": failures,
+        "failures_truncated": summary.failures.len() > MAX_FAILURES,
+        "duration_ms": captured.duration_ms
+    });
+
+    if timed_out {
+        result["error"] = json!({
+            "code": "timeout",
+            "message": format!("Tests timed out after {}ms", timeout_ms)
+        });
+    }
+
+    // Without parsed results (build errors, crashed runner) the raw output is the only signal
+    if timed_out || (exit_code != Some(0) && summary.failures.is_empty()) {
+        let output = limit_output(root, &captured);
+        result["stdout"] = json!(output.stdout);
+        result["stderr"] = json!(output.stderr);
+        result["truncated"] = json!(output.truncated);
+        if let Some(path) = output.spill_path {
+            result["spill_path"] = json!(path);
+        }
+    }
+
+    Ok(result)
+}
+
+#[derive(Debug, Default)]
+struct Failure {
+    name: String,
+    message: String,
+    file: Option<String>,
+    line: Option<u64>,
+    /// Name to pass back to the runner when re-running failures
+    rerun: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct Summary {
+    passed: u64,
+    failed: u64,
+    skipped: u64,
+    failures: Vec<Failure>,
+}
+
+impl Summary {
+    fn total(&self) -> u64 {
+        self.passed + self.failed + self.skipped
+    }
+}
+
+fn truncate_chars(s: &str, max: usize) -> String {
+    match s.char_indices().nth(max) {
+        Some((idx, _)) => format!("{}...", &s[..idx]),
+        None => s.to_string(),
+    }
+}
+
+/// Split `path:line[:col]` into its parts
+fn split_location(loc: &str) -> (Option<String>, Option<u64>) {
+    let mut parts = loc.trim().trim_end_matches(':').splitn(3, ':');
+    let file = parts.next().filter(|f| !f.is_empty()).map(String::from);
+    let line = parts.next().and_then(|l| l.parse().ok());
+    match line {
+        Some(_) => (file, line),
+        None => (None, None),
+    }
+}
+
+/// Parse libtest's human-readable output (`cargo test`)
+fn parse_libtest(out: &str) -> Summary {
+    let mut summary = Summary::default();
+    let mut failed_names = Vec::new();
+    let mut sections: Vec<(String, Vec<&str>)> = Vec::new();
+    let mut in_section = false;
+
+    for line in out.lines() {
+        if let Some(rest) = line.strip_prefix("test ") {
+            if let Some((name, outcome)) = rest.rsplit_once(" ... ") {
+                // "ignored, <reason>" carries the #[ignore] reason
+                match outcome.split([' ', ',']).next() {
+                    Some("ok") => summary.passed += 1,
+                    Some("FAILED") => {
+                        summary.failed += 1;
+                        failed_names.push(name.to_string());
+                    }
+                    Some("ignored") => summary.skipped += 1,
+                    _ => {}
+                }
+                in_section = false;
+                continue;
+            }
+        }
+        if let Some(name) = line
+            .strip_prefix("---- ")
+            .and_then(|l| l.strip_suffix(" stdout ----"))
+        {
+            sections.push((name.to_string(), Vec::new()));
+            in_section = true;
+            continue;
+        }
+        if line == "failures:" || line.starts_with("test result:") {
+            in_section = false;
+            continue;
+        }
+        if in_section {
+            if let Some((_, lines)) = sections.last_mut() {
+                lines.push(line);
+            }
+        }
+    }
+
+    for name in failed_names {
+        let output = sections
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, lines)| lines.as_slice())
+            .unwrap_or_default();
+        let mut failure = Failure {
+            name: name.clone(),
+            rerun: Some(name.clone()),
+            ..Default::default()
+        };
+
+        if let Some(idx) = output.iter().position(|l| l.contains("panicked at ")) {
+            let after = output[idx].split("panicked at ").nth(1).unwrap_or("");
+            if let Some(old) = after.strip_prefix('\'') {
+                // Older format: panicked at 'message', src/lib.rs:10:5
+                if let Some((msg, loc)) = old.rsplit_once("', ") {
+                    failure.message = msg.to_string();
+                    (failure.file, failure.line) = split_location(loc);
+                }
+            } else {
+                (failure.file, failure.line) = split_location(after);
+                failure.message = output[idx + 1..]
+                    .iter()
+                    .take_while(|l| {
+                        !l.is_empty() && !l.starts_with("note:") && **l != "stack backtrace:"
+                    })
+                    .copied()
+                    .collect::<Vec<_>>()
+                    .join("\n");
+            }
+        } else {
+            failure.message = output.join("\n").trim().to_string();
+        }
+        summary.failures.push(failure);
+    }
+    summary
+}
+
+/// Parse `go test -json` event stream
+fn parse_go_json(out: &str) -> Summary {
+    use std::collections::HashMap;
+
+    let mut summary = Summary::default();
+    let mut outputs: HashMap<(String, String), Vec<String>> = HashMap::new();
+
+    for line in out.lines() {
+        let Ok(event) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        let package = event["Package"].as_str().unwrap_or("").to_string();
+        let test = event["Test"].as_str().unwrap_or("").to_string();
+        let key = (package.clone(), test.clone());
+
+        match event["Action"].as_str().unwrap_or("") {
+            "output" => outputs
+                .entry(key)
+                .or_default()
+                .push(event["Output"].as_str().unwrap_or("").to_string()),
+            "pass" if !test.is_empty() => summary.passed += 1,
+            "skip" if !test.is_empty() => summary.skipped += 1,
+            "fail" => {
+                let lines = outputs.remove(&key).unwrap_or_default();
+                if !test.is_empty() {
+                    summary.failed += 1;
+                }
+                // A package failing with no failed tests is a build or setup error
+                if test.is_empty()
+                    && summary
+                        .failures
+                        .iter()
+                        .any(|f| f.name.starts_with(&format!("{}.", package)))
+                {
+                    continue;
+                }
+
+                let mut failure = Failure {
+                    name: if test.is_empty() {
+                        package.clone()
+                    } else {
+                        format!("{}.{}", package, test)
+                    },
+                    rerun: (!test.is_empty())
+                        .then(|| test.split('/').next().unwrap_or(&test).to_string()),
+                    ..Default::default()
+                };
+                let mut message = Vec::new();
+                for l in &lines {
+                    if l.starts_with("=== ") || l.trim_start().starts_with("--- FAIL") {
+                        continue;
+                    }
+                    if let Some(caps) = GO_LOCATION_RE.captures(l) {
+                        if failure.file.is_none() {
+                            failure.file = Some(caps[1].to_string());
+                            failure.line = caps[2].parse().ok();
+                        }
+                        message.push(caps[3].trim().to_string());
+                    } else if test.is_empty() {
+                        message.push(l.trim_end().to_string());
+                    }
+                }
+                failure.message = message.join("\n").trim().to_string();
+                summary.failures.push(failure);
+            }
+            _ => {}
+        }
+    }
+    summary
+}
+
+fn xml_unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#10;", "\n")
+        .replace("&amp;", "&")
+}
+
+/// Parse a JUnit XML report (pytest `--junitxml`)
+fn parse_junit(xml: &str) -> Summary {
+    let mut summary = Summary::default();
+    let mut rest = xml;
+
+    while let Some(start) = rest.find("<testcase") {
+        rest = &rest[start..];
+        let Some(tag_end) = rest.find('>') else {
+            break;
+        };
+        let tag = &rest[..tag_end];
+        let self_closing = tag.ends_with('/');
+        let (body, next) = if self_closing {
+            ("", &rest[tag_end + 1..])
+        } else {
+            match rest.find("</testcase>") {
+                Some(close) => (&rest[tag_end + 1..close], &rest[close..]),
+                None => ("", &rest[tag_end + 1..]),
+            }
+        };
+        rest = next;
+
+        let attr = |name: &str| {
+            ATTR_RE
+                .captures_iter(tag)
+                .find(|c| &c[1] == name)
+                .map(|c| xml_unescape(&c[2]))
+        };
+        let name = attr("name").unwrap_or_default();
+        let full_name = match attr("classname") {
+            Some(class) if !class.is_empty() => format!("{}.{}", class, name),
+            _ => name.clone(),
+        };
+
+        let failure_tag = ["<failure", "<error"]
+            .iter()
+            .find_map(|t| body.find(t).map(|i| &body[i..]));
+        if let Some(f) = failure_tag {
+            summary.failed += 1;
+            let open_end = f.find('>').unwrap_or(f.len());
+            let open = &f[..open_end];
+            let text = f[open_end.saturating_add(1).min(f.len())..]
+                .split("</")
+                .next()
+                .unwrap_or("");
+            let text = xml_unescape(text);
+            let message = ATTR_RE
+                .captures_iter(open)
+                .find(|c| &c[1] == "message")
+                .map(|c| xml_unescape(&c[2]))
+                .unwrap_or_else(|| text.trim().to_string());
+
+            let mut failure = Failure {
+                name: full_name,
+                message,
+                rerun: Some(name.split('[').next().unwrap_or(&name).to_string()),
+                ..Default::default()
+            };
+            if let Some(caps) = PY_LOCATION_RE.captures_iter(&text).last() {
+                failure.file = Some(caps[1].to_string());
+                failure.line = caps[2].parse().ok();
+            } else if let Some(file) = attr("file") {
+                failure.file = Some(file);
+                failure.line = attr("line")
+                    .and_then(|l| l.parse::<u64>().ok())
+                    .map(|l| l + 1);
+            }
+            summary.failures.push(failure);
+        } else if body.contains("<skipped") {
+            summary.skipped += 1;
+        } else {
+            summary.passed += 1;
+        }
+    }
+    summary
+}
+
+/// Parse a Jest/Vitest JSON report
+fn parse_jest_json(report: &Value, root: &Path) -> Summary {
+    let mut summary = Summary::default();
+    let empty = Vec::new();
+
+    for suite in report["testResults"].as_array().unwrap_or(&empty) {
+        let file = suite["name"].as_str().unwrap_or("");
+        let rel = Path::new(file)
+            .strip_prefix(root)
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|_| file.to_string());
+        let assertions = suite["assertionResults"].as_array().unwrap_or(&empty);
+
+        // Suite failed to load (syntax error, missing import)
+        if assertions.is_empty() && suite["status"] == "failed" {
+            summary.failures.push(Failure {
+                name: rel.clone(),
+                message: ANSI_RE
+                    .replace_all(suite["message"].as_str().unwrap_or(""), "")
+                    .trim()
+                    .to_string(),
+                file: Some(rel.clone()),
+                ..Default::default()
+            });
+            continue;
+        }
+
+        for test in assertions {
+            let full_name = test["fullName"]
+                .as_str()
+                .or_else(|| test["title"].as_str())
+                .unwrap_or("")
+                .to_string();
+            match test["status"].as_str().unwrap_or("") {
+                "passed" => summary.passed += 1,
+                "failed" => {
+                    summary.failed += 1;
+                    let message = test["failureMessages"]
+                        .as_array()
+                        .map(|m| {
+                            m.iter()
+                                .filter_map(|v| v.as_str())
+                                .collect::<Vec<_>>()
+                                .join("\n")
+                        })
+                        .unwrap_or_default();
+                    let message = ANSI_RE.replace_all(&message, "").to_string();
+
+                    let mut line = test["location"]["line"].as_u64();
+                    if line.is_none() {
+                        let base = Path::new(&rel).file_name().map(|f| f.to_string_lossy());
+                        line = JS_LOCATION_RE
+                            .captures_iter(&message)
+                            .find(|c| base.as_deref().is_some_and(|b| c[1].ends_with(b)))
+                            .and_then(|c| c[2].parse().ok());
+                    }
+                    // The first line of a failure message is the assertion summary
+                    let short = message
+                        .split("\n    at ")
+                        .next()
+                        .unwrap_or("")
+                        .trim()
+                        .to_string();
+                    summary.failures.push(Failure {
+                        name: full_name.clone(),
+                        message: short,
+                        file: Some(rel.clone()),
+                        line,
+                        rerun: Some(full_name),
+                    });
+                }
+                _ => summary.skipped += 1,
+            }
+        }
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_detect_runner() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(Runner::detect(dir.path()), None);
+
+        std::fs::write(
+            dir.path().join("package.json"),
+            r#"{"devDependencies": {"vitest": "^1.0.0"}}"#,
+        )
+        .unwrap();
+        assert_eq!(Runner::detect(dir.path()), Some(Runner::Vitest));
+
+        std::fs::write(dir.path().join("Cargo.toml"), "[package]").unwrap();
+        assert_eq!(Runner::detect(dir.path()), Some(Runner::Cargo));
+    }
+
+    #[test]
+    fn test_resolve_fills_command() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("go.mod"), "module x").unwrap();
+        let args = resolve(json!({"filter": "TestFoo"}), dir.path());
+        assert_eq!(args["runner"], "go");
+        assert_eq!(args["command"], "go test -json ./... -run TestFoo");
+    }
+
+    #[test]
+    fn test_failed_only_uses_saved_names() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("Cargo.toml"), "[package]").unwrap();
+        save_failures(dir.path(), Runner::Cargo, &["a::b".to_string()]);
+        let args = resolve(json!({"failed_only": true}), dir.path());
+        assert_eq!(args["command"], "cargo test --no-fail-fast -- --exact a::b");
+    }
+
+    #[test]
+    fn test_parse_libtest() {
+        let out = "\
+running 3 tests
+test tests::ok_one ... ok
+test tests::skipped ... ignored, slow
+test tests::bad ... FAILED
+
+failures:
+
+---- tests::bad stdout ----
+
+thread 'tests::bad' panicked at src/lib.rs:42:9:
+assertion `left == right` failed
+  left: 1
+ right: 2
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+
+
+failures:
+    tests::bad
+
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out
+";
+        let s = parse_libtest(out);
+        assert_eq!((s.passed, s.failed, s.skipped), (1, 1, 1));
+        let f = &s.failures[0];
+        assert_eq!(f.name, "tests::bad");
+        assert_eq!(f.file.as_deref(), Some("src/lib.rs"));
+        assert_eq!(f.line, Some(42));
+        assert!(f.message.starts_with("assertion `left == right` failed"));
+        assert!(f.message.contains("right: 2"));
+    }
+
+    #[test]
+    fn test_parse_go_json() {
+        let out = r#"{"Action":"run","Package":"ex/pkg","Test":"TestAdd"}
+{"Action":"output","Package":"ex/pkg","Test":"TestAdd","Output":"=== RUN   TestAdd\n"}
+{"Action":"output","Package":"ex/pkg","Test":"TestAdd","Output":"    add_test.go:12: got 3, want 4\n"}
+{"Action":"output","Package":"ex/pkg","Test":"TestAdd","Output":"--- FAIL: TestAdd (0.00s)\n"}
+{"Action":"fail","Package":"ex/pkg","Test":"TestAdd"}
+{"Action":"pass","Package":"ex/pkg","Test":"TestSub"}
+{"Action":"skip","Package":"ex/pkg","Test":"TestSlow"}
+{"Action":"fail","Package":"ex/pkg"}"#;
+        let s = parse_go_json(out);
+        assert_eq!((s.passed, s.failed, s.skipped), (1, 1, 1));
+        assert_eq!(s.failures.len(), 1);
+        let f = &s.failures[0];
+        assert_eq!(f.name, "ex/pkg.TestAdd");
+        assert_eq!(f.rerun.as_deref(), Some("TestAdd"));
+        assert_eq!(f.file.as_deref(), Some("add_test.go"));
+        assert_eq!(f.line, Some(12));
+        assert_eq!(f.message, "got 3, want 4");
+    }
+
+    #[test]
+    fn test_parse_junit() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<testsuites><testsuite name="pytest" errors="0" failures="1" skipped="1" tests="3">
+<testcase classname="tests.test_math" name="test_ok" time="0.001" />
+<testcase classname="tests.test_math" name="test_bad[2]" time="0.001"><failure message="assert 1 == 2">def test_bad():
+&gt;       assert 1 == 2
+E       assert 1 == 2
+
+tests/test_math.py:7: AssertionError</failure></testcase>
+<testcase classname="tests.test_math" name="test_skip" time="0.0"><skipped type="pytest.skip" message="later">skip</skipped></testcase>
+</testsuite></testsuites>"#;
+        let s = parse_junit(xml);
+        assert_eq!((s.passed, s.failed, s.skipped), (1, 1, 1));
+        let f = &s.failures[0];
+        assert_eq!(f.name, "tests.test_math.test_bad[2]");
+        assert_eq!(f.message, "assert 1 == 2");
+        assert_eq!(f.file.as_deref(), Some("tests/test_math.py"));
+        assert_eq!(f.line, Some(7));
+        assert_eq!(f.rerun.as_deref(), Some("test_bad"));
+    }
+
+    #[test]
+    fn test_parse_jest_json() {
+        let root = Path::new("/proj");
+        let report = json!({
+            "testResults": [{
+                "name": "/proj/src/sum.test.js",
+                "status": "failed",
+                "assertionResults": [
+                    { "fullName": "sum adds", "status": "passed", "failureMessages": [] },
+                    {
+                        "fullName": "sum subtracts",
+                        "status": "failed",
+                        "location": null,
+                        "failureMessages": ["Error: expect(received).toBe(expected)\n\nExpected: 1\nReceived: 2\n    at Object.<anonymous> (/proj/src/sum.test.js:9:17)"]
+                    },
+                    { "fullName": "sum later", "status": "pending", "failureMessages": [] }
+                ]
+            }]
+        });
+        let s = parse_jest_json(&report, root);
+        assert_eq!((s.passed, s.failed, s.skipped), (1, 1, 1));
+        let f = &s.failures[0];
+        assert_eq!(f.name, "sum subtracts");
+        assert_eq!(f.file.as_deref(), Some("src/sum.test.js"));
+        assert_eq!(f.line, Some(9));
+        assert!(f.message.contains("Received: 2"));
+        assert!(!f.message.contains("at Object"));
+    }
+}
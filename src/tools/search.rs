@@ -1,8 +1,10 @@
 use super::SchemaOptions;
+use crate::policy::SecretFiles;
+use crate::search_index::SearchIndex;
 use glob::Pattern;
 use regex::{Regex, RegexBuilder};
 use serde_json::{json, Value};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 use walkdir::WalkDir;
 
@@ -34,7 +36,7 @@ pub fn schema(opts: &SchemaOptions) -> Value {
             "type": "function",
             "function": {
                 "name": "Search",
-                "description": "Search file contents for regex pattern. Recursively searches directory. Skips .git, target, .yo, node_modules.",
+                "description": "Search file contents for regex pattern. Recursively searches directory. Skips .git, target, .yo, node_modules. Uses the CodeSearch index, when built, to skip files that cannot match.",
                 "parameters": {
                     "type": "object",
                     "properties": {
@@ -54,7 +56,7 @@ pub fn schema(opts: &SchemaOptions) -> Value {
     }
 }
 
-pub fn execute(args: Value, root: &Path) -> anyhow::Result<Value> {
+pub fn execute(args: Value, root: &Path, secrets: &SecretFiles) -> anyhow::Result<Value> {
     let pattern = args["pattern"].as_str().unwrap_or("");
     let search_path = args["path"].as_str();
     let output_mode = args["output_mode"].as_str().unwrap_or("files_with_matches");
@@ -100,27 +102,234 @@ pub fn execute(args: Value, root: &Path) -> anyhow::Result<Value> {
         root.to_path_buf()
     };
 
+    let filter = Filter {
+        glob: glob_matcher.as_ref(),
+        secrets,
+        candidates: index_candidates(root, pattern, case_insensitive, secrets),
+    };
+
     // Execute search based on output mode
     match output_mode {
         "content" => search_content(
             &search_root,
             root,
             &re,
-            glob_matcher.as_ref(),
+            &filter,
             context_before,
             context_after,
             max_results,
         ),
-        "count" => search_count(&search_root, root, &re, glob_matcher.as_ref(), max_results),
-        _ => search_files_with_matches(&search_root, root, &re, glob_matcher.as_ref(), max_results),
+        "count" => search_count(&search_root, root, &re, &filter, max_results),
+        _ => search_files_with_matches(&search_root, root, &re, &filter, max_results),
+    }
+}
+
+/// Which files to scan: the glob, minus secret files, plus index candidates
+/// when available
+struct Filter<'a> {
+    glob: Option<&'a Pattern>,
+    secrets: &'a SecretFiles,
+    candidates: Option<HashSet<String>>,
+}
+
+impl Filter<'_> {
+    fn skips(&self, rel_path: &Path) -> bool {
+        let rel_str = rel_path.to_string_lossy();
+        if self.secrets.matches(&rel_str).is_some() {
+            return true;
+        }
+        // Apply glob filter - match against relative path for patterns like **/*.rs
+        if let Some(g) = self.glob {
+            if !g.matches(&rel_str) && !g.matches_path(rel_path) {
+                return true;
+            }
+        }
+        // Index paths are normalized; a path through ".." is always scanned
+        let normalized = !rel_path
+            .components()
+            .any(|c| matches!(c, std::path::Component::ParentDir));
+        normalized
+            && self
+                .candidates
+                .as_ref()
+                .is_some_and(|c| !c.contains(rel_str.as_ref()))
     }
 }
 
+/// Files that may match, from the CodeSearch index if one has been built and
+/// the pattern has a literal every match must contain
+fn index_candidates(
+    root: &Path,
+    pattern: &str,
+    case_insensitive: bool,
+    secrets: &SecretFiles,
+) -> Option<HashSet<String>> {
+    if !SearchIndex::exists(root) {
+        return None;
+    }
+    let literal = required_literal(pattern, case_insensitive)?;
+    let (index, _) = SearchIndex::open(root, secrets).ok()?;
+    Some(index.candidates(&literal))
+}
+
+/// The longest run of word characters (3+) that every match must contain,
+/// or None when the pattern has top-level alternation or verbose mode.
+/// Groups and character classes are skipped rather than analysed.
+fn required_literal(pattern: &str, case_insensitive: bool) -> Option<String> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut runs: Vec<String> = Vec::new();
+    let mut cur = String::new();
+    let mut i = 0;
+
+    // Skip from an opening bracket to just past its closing one
+    fn skip_group(chars: &[char], mut i: usize) -> Option<(usize, String)> {
+        let mut depth = 0;
+        let mut in_class = false;
+        let start = i;
+        while i < chars.len() {
+            match chars[i] {
+                '\\' => i += 1,
+                '[' if !in_class => in_class = true,
+                ']' if in_class => in_class = false,
+                '(' if !in_class => depth += 1,
+                ')' if !in_class => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some((i + 1, chars[start..=i].iter().collect()));
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        None
+    }
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\\' => {
+                runs.push(std::mem::take(&mut cur));
+                // Escapes with arguments: \x41 A \U00000041 \x{..} \pL \p{..}
+                let digits = match chars.get(i + 1) {
+                    Some('x') => 2,
+                    Some('u') => 4,
+                    Some('U') => 8,
+                    Some('p' | 'P') => 1,
+                    _ => 0,
+                };
+                i += 2;
+                if digits > 0 {
+                    if chars.get(i) == Some(&'{') {
+                        while i < chars.len() && chars[i] != '}' {
+                            i += 1;
+                        }
+                        i += 1;
+                    } else {
+                        i += digits;
+                    }
+                }
+            }
+            '[' => {
+                runs.push(std::mem::take(&mut cur));
+                i += 1;
+                // A leading ']' (or '^]') is literal
+                if chars.get(i) == Some(&'^') {
+                    i += 1;
+                }
+                if chars.get(i) == Some(&']') {
+                    i += 1;
+                }
+                while i < chars.len() && chars[i] != ']' {
+                    if chars[i] == '\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                i += 1;
+            }
+            '(' => {
+                runs.push(std::mem::take(&mut cur));
+                let (next, group) = skip_group(&chars, i)?;
+                // Inline flags: verbose mode changes what whitespace and '#' mean
+                if let Some(flags) = group.strip_prefix("(?") {
+                    let flags: String = flags
+                        .chars()
+                        .take_while(|c| *c != ':' && *c != ')')
+                        .collect();
+                    if flags.contains('x') && !flags.starts_with('-') {
+                        return None;
+                    }
+                }
+                i = next;
+            }
+            '|' => return None,
+            '?' | '*' | '+' | '{' => {
+                // The preceding character is optional unless the minimum is 1+
+                let optional = match c {
+                    '?' | '*' => true,
+                    '+' => false,
+                    _ => {
+                        let min: String = chars[i + 1..]
+                            .iter()
+                            .take_while(|c| c.is_ascii_digit())
+                            .collect();
+                        if min.is_empty() {
+                            // Not a repetition, just a literal '{'
+                            runs.push(std::mem::take(&mut cur));
+                            i += 1;
+                            continue;
+                        }
+                        min.parse::<u64>().unwrap_or(0) == 0
+                    }
+                };
+                if optional {
+                    cur.pop();
+                }
+                runs.push(std::mem::take(&mut cur));
+                if c == '{' {
+                    while i < chars.len() && chars[i] != '}' {
+                        i += 1;
+                    }
+                }
+                i += 1;
+                // Lazy or possessive suffix
+                if matches!(chars.get(i), Some('?' | '+')) {
+                    i += 1;
+                }
+            }
+            c if c.is_ascii_alphanumeric() || c == '_' => {
+                cur.push(c);
+                i += 1;
+            }
+            _ => {
+                runs.push(std::mem::take(&mut cur));
+                i += 1;
+            }
+        }
+    }
+    runs.push(cur);
+
+    // Unicode case folding maps k and s to non-ASCII letters (KELVIN SIGN, LONG S)
+    let pieces: Vec<String> = if case_insensitive {
+        runs.iter()
+            .flat_map(|r| r.split(['k', 'K', 's', 'S']))
+            .map(String::from)
+            .collect()
+    } else {
+        runs
+    };
+    pieces
+        .into_iter()
+        .filter(|r| r.len() >= 3)
+        .max_by_key(|r| r.len())
+}
+
 fn search_files_with_matches(
     search_root: &Path,
     project_root: &Path,
     re: &Regex,
-    glob_matcher: Option<&Pattern>,
+    filter: &Filter,
     max_results: usize,
 ) -> anyhow::Result<Value> {
     let mut paths = Vec::new();
@@ -142,12 +351,8 @@ fn search_files_with_matches(
         let path = entry.path();
         let rel_path = path.strip_prefix(project_root).unwrap_or(path);
 
-        // Apply glob filter - match against relative path for patterns like **/*.rs
-        if let Some(g) = glob_matcher {
-            let rel_str = rel_path.to_string_lossy();
-            if !g.matches(&rel_str) && !g.matches_path(rel_path) {
-                continue;
-            }
+        if filter.skips(rel_path) {
+            continue;
         }
 
         // Read and search
@@ -178,7 +383,7 @@ fn search_content(
     search_root: &Path,
     project_root: &Path,
     re: &Regex,
-    glob_matcher: Option<&Pattern>,
+    filter: &Filter,
     context_before: usize,
     context_after: usize,
     max_results: usize,
@@ -202,12 +407,8 @@ fn search_content(
         let path = entry.path();
         let rel_path = path.strip_prefix(project_root).unwrap_or(path);
 
-        // Apply glob filter - match against relative path for patterns like **/*.rs
-        if let Some(g) = glob_matcher {
-            let rel_str = rel_path.to_string_lossy();
-            if !g.matches(&rel_str) && !g.matches_path(rel_path) {
-                continue;
-            }
+        if filter.skips(rel_path) {
+            continue;
         }
 
         // Read file
@@ -261,7 +462,7 @@ fn search_count(
     search_root: &Path,
     project_root: &Path,
     re: &Regex,
-    glob_matcher: Option<&Pattern>,
+    filter: &Filter,
     max_results: usize,
 ) -> anyhow::Result<Value> {
     let mut by_file: HashMap<String, usize> = HashMap::new();
@@ -284,12 +485,8 @@ fn search_count(
         let path = entry.path();
         let rel_path = path.strip_prefix(project_root).unwrap_or(path);
 
-        // Apply glob filter - match against relative path for patterns like **/*.rs
-        if let Some(g) = glob_matcher {
-            let rel_str = rel_path.to_string_lossy();
-            if !g.matches(&rel_str) && !g.matches_path(rel_path) {
-                continue;
-            }
+        if filter.skips(rel_path) {
+            continue;
         }
 
         // Read and count
@@ -360,7 +557,7 @@ mod tests {
     fn test_search_files_with_matches() {
         let dir = setup_test_dir();
         let args = json!({ "pattern": "fn" });
-        let result = execute(args, dir.path()).unwrap();
+        let result = execute(args, dir.path(), &SecretFiles::default()).unwrap();
 
         assert!(result.get("paths").is_some());
         let paths = result["paths"].as_array().unwrap();
@@ -372,7 +569,7 @@ mod tests {
     fn test_search_content_mode() {
         let dir = setup_test_dir();
         let args = json!({ "pattern": "fn main", "output_mode": "content" });
-        let result = execute(args, dir.path()).unwrap();
+        let result = execute(args, dir.path(), &SecretFiles::default()).unwrap();
 
         assert!(result.get("matches").is_some());
         let matches = result["matches"].as_array().unwrap();
@@ -384,7 +581,7 @@ mod tests {
     fn test_search_count_mode() {
         let dir = setup_test_dir();
         let args = json!({ "pattern": "fn", "output_mode": "count" });
-        let result = execute(args, dir.path()).unwrap();
+        let result = execute(args, dir.path(), &SecretFiles::default()).unwrap();
 
         assert!(result.get("count").is_some());
         assert!(result["count"].as_u64().unwrap() >= 2);
@@ -395,7 +592,7 @@ mod tests {
     fn test_search_with_glob_filter() {
         let dir = setup_test_dir();
         let args = json!({ "pattern": "fn", "glob": "*.rs" });
-        let result = execute(args, dir.path()).unwrap();
+        let result = execute(args, dir.path(), &SecretFiles::default()).unwrap();
 
         let paths = result["paths"].as_array().unwrap();
         for path in paths {
@@ -408,7 +605,7 @@ mod tests {
         let dir = setup_test_dir();
         let args =
             json!({ "pattern": "FN MAIN", "case_insensitive": true, "output_mode": "content" });
-        let result = execute(args, dir.path()).unwrap();
+        let result = execute(args, dir.path(), &SecretFiles::default()).unwrap();
 
         let matches = result["matches"].as_array().unwrap();
         assert_eq!(matches.len(), 1);
@@ -418,7 +615,7 @@ mod tests {
     fn test_search_with_path() {
         let dir = setup_test_dir();
         let args = json!({ "pattern": "fn", "path": "src" });
-        let result = execute(args, dir.path()).unwrap();
+        let result = execute(args, dir.path(), &SecretFiles::default()).unwrap();
 
         let paths = result["paths"].as_array().unwrap();
         assert_eq!(paths.len(), 2);
@@ -428,7 +625,7 @@ mod tests {
     fn test_search_invalid_regex() {
         let dir = setup_test_dir();
         let args = json!({ "pattern": "[invalid" });
-        let result = execute(args, dir.path()).unwrap();
+        let result = execute(args, dir.path(), &SecretFiles::default()).unwrap();
 
         assert!(result.get("error").is_some());
         assert_eq!(result["error"]["code"].as_str().unwrap(), "invalid_regex");
@@ -438,7 +635,7 @@ mod tests {
     fn test_search_path_not_found() {
         let dir = setup_test_dir();
         let args = json!({ "pattern": "fn", "path": "nonexistent" });
-        let result = execute(args, dir.path()).unwrap();
+        let result = execute(args, dir.path(), &SecretFiles::default()).unwrap();
 
         assert!(result.get("error").is_some());
         assert_eq!(result["error"]["code"].as_str().unwrap(), "path_not_found");
@@ -446,7 +643,7 @@ mod tests {
 
     #[test]
     fn test_schema() {
-        let opts = SchemaOptions { optimize: false };
+        let opts = SchemaOptions::new(false);
         let schema = schema(&opts);
 
         assert_eq!(schema["function"]["name"].as_str().unwrap(), "Search");
@@ -463,7 +660,7 @@ mod tests {
 
     #[test]
     fn test_schema_optimized() {
-        let opts = SchemaOptions { optimize: true };
+        let opts = SchemaOptions::new(true);
         let schema = schema(&opts);
 
         assert_eq!(schema["function"]["name"].as_str().unwrap(), "Search");
@@ -472,4 +669,69 @@ mod tests {
             .get("description")
             .is_none());
     }
+
+    #[test]
+    fn test_required_literal() {
+        let lit = |p: &str| required_literal(p, false);
+        assert_eq!(lit(r"fn\s+parse_\w+"), Some("parse_".to_string()));
+        assert_eq!(lit("colou?r_name"), Some("r_name".to_string()));
+        assert_eq!(lit("handler{0,2}Config"), Some("Config".to_string()));
+        assert_eq!(lit(r"\x41bc_def"), Some("bc_def".to_string()));
+        assert_eq!(lit("(foo|bar)_baz"), Some("_baz".to_string()));
+        assert_eq!(lit("foo|bar"), None);
+        assert_eq!(lit("(?x) foo # comment"), None);
+        assert_eq!(lit("[a-z]+"), None);
+        // k and s have non-ASCII case folds
+        assert_eq!(
+            required_literal("task_runner", true),
+            Some("_runner".to_string())
+        );
+    }
+
+    #[test]
+    fn test_search_uses_index_prefilter() {
+        let dir = setup_test_dir();
+        crate::search_index::SearchIndex::open(dir.path(), &SecretFiles::default()).unwrap();
+        assert!(
+            index_candidates(dir.path(), "add", true, &SecretFiles::default())
+                .unwrap()
+                .contains("src/lib.rs")
+        );
+
+        // New files are picked up by the incremental refresh
+        fs::write(dir.path().join("src/extra.rs"), "fn add_more() {}\n").unwrap();
+        let args = json!({ "pattern": "fn add" });
+        let result = execute(args, dir.path(), &SecretFiles::default()).unwrap();
+        assert_eq!(result["count"], 2);
+
+        // A literal found nowhere skips every file but still returns cleanly
+        let result = execute(
+            json!({ "pattern": "nowhere_at_all" }),
+            dir.path(),
+            &SecretFiles::default(),
+        )
+        .unwrap();
+        assert_eq!(result["count"], 0);
+    }
+
+    #[test]
+    fn test_search_skips_secret_files() {
+        let dir = setup_test_dir();
+        fs::write(dir.path().join(".env"), "API_TOKEN=hunter2\n").unwrap();
+        fs::write(dir.path().join("src/server.key"), "hunter2\n").unwrap();
+        fs::write(dir.path().join(".env.example"), "API_TOKEN=hunter2\n").unwrap();
+        let secrets = SecretFiles::default();
+
+        for mode in ["files_with_matches", "content", "count"] {
+            let args = json!({ "pattern": "hunter2", "output_mode": mode });
+            let result = execute(args, dir.path(), &secrets).unwrap();
+            assert_eq!(result["count"], 1, "{}", mode);
+        }
+        let args = json!({ "pattern": "hunter2", "output_mode": "content" });
+        let result = execute(args, dir.path(), &secrets).unwrap();
+        assert_eq!(result["matches"][0]["path"], ".env.example");
+        let args = json!({ "pattern": "hunter2", "path": "src" });
+        let result = execute(args, dir.path(), &secrets).unwrap();
+        assert_eq!(result["count"], 0);
+    }
 }
@@ -1,6 +1,15 @@
+//! Session transcripts: one JSON event per line in `.yo/sessions/<id>.jsonl`.
+//!
+//! Events are hash-chained: each carries a `seq` number and the sha256 of the
+//! previous line as `prev_hash`, and with a key configured an `hmac` of the
+//! line itself, written last. A `seal` event closes the session. Readers that
+//! ignore unknown fields see the same events as before; `yo transcript verify`
+//! checks the chain.
+
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::Serialize;
+use sha2::{Digest, Sha256};
 use std::fs::{File, OpenOptions};
 use std::io::Write;
 use std::path::{Path, PathBuf};
@@ -10,6 +19,12 @@ pub struct Transcript {
     session_id: String,
     cwd: PathBuf,
     file: File,
+    /// Sequence number of the next event
+    seq: u64,
+    /// sha256 of the last line written ("" before the first event)
+    prev_hash: String,
+    hmac_key: Option<Vec<u8>>,
+    sealed: bool,
 }
 
 #[derive(Serialize)]
@@ -19,12 +34,29 @@ struct Event<'a> {
     cwd: &'a Path,
     #[serde(rename = "type")]
     event_type: &'a str,
+    seq: u64,
+    prev_hash: &'a str,
     #[serde(flatten)]
     data: serde_json::Value,
 }
 
 impl Transcript {
     pub fn new(path: &Path, session_id: &str, cwd: &Path) -> Result<Self> {
+        // Appending to an existing transcript continues its chain
+        let existing = std::fs::read_to_string(path).unwrap_or_default();
+        let (seq, prev_hash) = match existing.lines().rfind(|l| !l.trim().is_empty()) {
+            Some(last) => {
+                let last_seq = serde_json::from_str::<serde_json::Value>(last)
+                    .ok()
+                    .and_then(|v| v["seq"].as_u64());
+                let count = existing.lines().filter(|l| !l.trim().is_empty()).count() as u64;
+                (
+                    last_seq.map_or(count, |s| s + 1),
+                    sha256_hex(last.as_bytes()),
+                )
+            }
+            None => (0, String::new()),
+        };
         let file = OpenOptions::new().create(true).append(true).open(path)?;
 
         Ok(Self {
@@ -32,20 +64,50 @@ impl Transcript {
             session_id: session_id.to_string(),
             cwd: cwd.to_path_buf(),
             file,
+            seq,
+            prev_hash,
+            hmac_key: None,
+            sealed: false,
         })
     }
 
+    /// Sign every event with an HMAC-SHA256 under `key`
+    pub fn with_hmac_key(mut self, key: Vec<u8>) -> Self {
+        self.hmac_key = Some(key);
+        self
+    }
+
     pub fn log(&mut self, event_type: &str, data: serde_json::Value) -> Result<()> {
         let event = Event {
             ts: Utc::now(),
             session_id: &self.session_id,
             cwd: &self.cwd,
             event_type,
+            seq: self.seq,
+            prev_hash: &self.prev_hash,
             data,
         };
-        let line = serde_json::to_string(&event)?;
+        let mut line = serde_json::to_string(&event)?;
+        if let Some(key) = &self.hmac_key {
+            // The MAC covers the line as written up to its own field
+            let mac = hmac_sha256(key, line.as_bytes());
+            line.pop();
+            line.push_str(&format!(",\"hmac\":\"{}\"}}", mac));
+        }
         writeln!(self.file, "{}", line)?;
         self.file.flush()?;
+        self.seq += 1;
+        self.prev_hash = sha256_hex(line.as_bytes());
+        self.sealed = false;
+        Ok(())
+    }
+
+    /// Close the session with a `seal` event recording how many events came
+    /// before it, so a truncated transcript can be told from a complete one
+    pub fn seal(&mut self) -> Result<()> {
+        let events = self.seq;
+        self.log("seal", serde_json::json!({ "events": events }))?;
+        self.sealed = true;
         Ok(())
     }
 
@@ -91,6 +153,56 @@ impl Transcript {
         )
     }
 
+    /// Log a tool call or turn stopped by a `[limits]` budget
+    pub fn limit_exceeded(&mut self, limit: &str, tool: Option<&str>, message: &str) -> Result<()> {
+        self.log(
+            "limit_exceeded",
+            serde_json::json!({
+                "limit": limit,
+                "tool": tool,
+                "message": message,
+            }),
+        )
+    }
+
+    /// Log a change a hook made to a prompt, tool call or result
+    pub fn hook_modification(
+        &mut self,
+        hook_event: &str,
+        tool: Option<&str>,
+        change: &str,
+        value: &serde_json::Value,
+    ) -> Result<()> {
+        self.log(
+            "hook_modification",
+            serde_json::json!({
+                "hook_event": hook_event,
+                "tool": tool,
+                "change": change,
+                "value": value,
+            }),
+        )
+    }
+
+    /// Log an MCP server connecting, disconnecting or failing to connect
+    pub fn mcp_server(
+        &mut self,
+        name: &str,
+        event: &str,
+        tools: Option<usize>,
+        error: Option<&str>,
+    ) -> Result<()> {
+        self.log(
+            "mcp_server",
+            serde_json::json!({
+                "name": name,
+                "event": event,
+                "tools": tools,
+                "error": error,
+            }),
+        )
+    }
+
     /// Log subagent start
     pub fn subagent_start(
         &mut self,
@@ -273,3 +385,67 @@ impl Transcript {
         )
     }
 }
+
+impl Drop for Transcript {
+    fn drop(&mut self) {
+        if !self.sealed {
+            let _ = self.seal();
+        }
+    }
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// HMAC-SHA256 (RFC 2104) as lowercase hex
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> String {
+    const BLOCK: usize = 64;
+    let mut block = [0u8; BLOCK];
+    if key.len() > BLOCK {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| block.iter().map(|b| b ^ byte).collect::<Vec<u8>>();
+    let inner = Sha256::new()
+        .chain_update(pad(0x36))
+        .chain_update(message)
+        .finalize();
+    let outer = Sha256::new()
+        .chain_update(pad(0x5c))
+        .chain_update(inner)
+        .finalize();
+    format!("{:x}", outer)
+}
+
+/// Read the HMAC key from `path`, creating it with a random key if missing
+pub fn load_or_create_key(path: &Path) -> Result<Vec<u8>> {
+    if !path.exists() {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let key = format!(
+            "{}{}\n",
+            uuid::Uuid::new_v4().simple(),
+            uuid::Uuid::new_v4().simple()
+        );
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options.open(path)?.write_all(key.as_bytes())?;
+    }
+    load_key(path)
+}
+
+/// Read an HMAC key file; surrounding whitespace is ignored
+pub fn load_key(path: &Path) -> Result<Vec<u8>> {
+    let key = std::fs::read(path)
+        .map_err(|e| anyhow::anyhow!("Cannot read key file {}: {}", path.display(), e))?;
+    let key = String::from_utf8_lossy(&key).trim().as_bytes().to_vec();
+    if key.is_empty() {
+        return Err(anyhow::anyhow!("Key file {} is empty", path.display()));
+    }
+    Ok(key)
+}
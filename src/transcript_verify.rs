@@ -0,0 +1,269 @@
+//! `yo transcript verify`: check a transcript's hash chain.
+//!
+//! Every line must carry the next `seq` and the sha256 of the line before it
+//! as `prev_hash`, so deleted, reordered or edited lines break the chain. With
+//! a key, each line's `hmac` is checked too, which also catches a rewritten
+//! chain. A transcript that does not end in a `seal` event may have lost its
+//! tail (or the session is still running), which is reported as a warning.
+
+use crate::config::Config;
+use crate::transcript::{hmac_sha256, load_key, sha256_hex};
+use crate::TranscriptCommand;
+use serde_json::Value;
+use std::path::Path;
+
+/// Outcome of verifying one transcript
+#[derive(Debug, Default)]
+pub struct Verification {
+    pub events: usize,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+    /// Lines whose HMAC was checked and matched
+    pub signed: usize,
+    pub sealed: bool,
+}
+
+impl Verification {
+    pub fn render(&self, path: &Path) -> String {
+        let mut out = String::new();
+        for error in &self.errors {
+            out.push_str(&format!("error: {}\n", error));
+        }
+        for warning in &self.warnings {
+            out.push_str(&format!("warning: {}\n", warning));
+        }
+        let status = if self.errors.is_empty() {
+            "OK"
+        } else {
+            "FAILED"
+        };
+        let mut facts = vec![format!("{} events", self.events)];
+        if self.errors.is_empty() {
+            facts.push("hash chain intact".to_string());
+        }
+        if self.signed > 0 {
+            facts.push(format!("{} HMACs verified", self.signed));
+        }
+        facts.push(if self.sealed { "sealed" } else { "not sealed" }.to_string());
+        out.push_str(&format!(
+            "{}: {} ({})\n",
+            status,
+            path.display(),
+            facts.join(", ")
+        ));
+        out
+    }
+}
+
+/// Verify transcript `content`; `key` enables HMAC checks
+pub fn verify(content: &str, key: Option<&[u8]>) -> Verification {
+    let mut result = Verification::default();
+    let mut expected_seq = 0;
+    let mut prev_hash = String::new();
+    let mut unsigned_hmacs = false;
+
+    for (i, line) in content.lines().enumerate() {
+        let n = i + 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        result.events += 1;
+        result.sealed = false;
+        let hash = sha256_hex(line.as_bytes());
+
+        let event: Value = match serde_json::from_str(line) {
+            Ok(v @ Value::Object(_)) => v,
+            _ => {
+                result
+                    .errors
+                    .push(format!("line {}: not a JSON event (modified?)", n));
+                prev_hash = hash;
+                expected_seq += 1;
+                continue;
+            }
+        };
+        let Some(seq) = event["seq"].as_u64() else {
+            result
+                .errors
+                .push(format!("line {}: event has no seq (not hash-chained)", n));
+            prev_hash = hash;
+            expected_seq += 1;
+            continue;
+        };
+
+        if seq > expected_seq {
+            let missing = match seq - expected_seq {
+                1 => format!("event {} is", expected_seq),
+                _ => format!("events {}..{} are", expected_seq, seq - 1),
+            };
+            result
+                .errors
+                .push(format!("line {}: {} missing before this line", n, missing));
+        } else if seq < expected_seq {
+            result.errors.push(format!(
+                "line {}: seq {} out of order (expected {})",
+                n, seq, expected_seq
+            ));
+        } else if event["prev_hash"].as_str() != Some(prev_hash.as_str()) {
+            let what = match n {
+                1 => "the chain does not start here (first event removed?)".to_string(),
+                _ => format!("line {} was modified", n - 1),
+            };
+            result
+                .errors
+                .push(format!("line {}: prev_hash mismatch: {}", n, what));
+        }
+
+        match (event["hmac"].as_str(), key) {
+            (Some(mac), Some(key)) => {
+                let signed = line
+                    .strip_suffix(&format!(",\"hmac\":\"{}\"}}", mac))
+                    .map(|body| format!("{}}}", body));
+                match signed {
+                    Some(body) if hmac_sha256(key, body.as_bytes()) == mac => result.signed += 1,
+                    _ => result
+                        .errors
+                        .push(format!("line {}: HMAC mismatch (modified or wrong key)", n)),
+                }
+            }
+            (None, Some(_)) => result.errors.push(format!("line {}: missing HMAC", n)),
+            (Some(_), None) => unsigned_hmacs = true,
+            (None, None) => {}
+        }
+
+        if event["type"] == "seal" {
+            if event["events"].as_u64() != Some(seq) {
+                result.errors.push(format!(
+                    "line {}: seal counts {} events before it, chain has {}",
+                    n, event["events"], seq
+                ));
+            }
+            result.sealed = true;
+        }
+
+        expected_seq = seq + 1;
+        prev_hash = hash;
+    }
+
+    if result.events > 0 && !result.sealed {
+        result.warnings.push(
+            "no seal after the last event: the session is still running, ended abnormally, or its tail was removed".to_string(),
+        );
+    }
+    if unsigned_hmacs {
+        result
+            .warnings
+            .push("events carry HMACs but no key was given; signatures not checked".to_string());
+    }
+    result
+}
+
+/// Run `yo transcript ...`
+pub fn run(command: &TranscriptCommand, config: &Config) -> anyhow::Result<()> {
+    match command {
+        TranscriptCommand::Verify { file, key } => {
+            // A configured key that can't be read fails rather than
+            // silently skipping the signature checks
+            let key_path = match key {
+                Some(key) => Some(key.clone()),
+                None => config.transcript.hmac_key_path(&std::env::current_dir()?),
+            };
+            let key = key_path.as_deref().map(load_key).transpose()?;
+            let content = std::fs::read_to_string(file)
+                .map_err(|e| anyhow::anyhow!("Cannot read {}: {}", file.display(), e))?;
+            let result = verify(&content, key.as_deref());
+            print!("{}", result.render(file));
+            // The report already ends with the FAILED line
+            if !result.errors.is_empty() {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Transcript;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn write_session(key: Option<&[u8]>) -> (TempDir, String) {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("s.jsonl");
+        let mut transcript = Transcript::new(&path, "s1", dir.path()).unwrap();
+        if let Some(key) = key {
+            transcript = transcript.with_hmac_key(key.to_vec());
+        }
+        transcript.user_message("hello").unwrap();
+        transcript
+            .tool_call("Read", &json!({ "path": "a" }))
+            .unwrap();
+        transcript.assistant_message("done").unwrap();
+        drop(transcript);
+        let content = std::fs::read_to_string(&path).unwrap();
+        (dir, content)
+    }
+
+    #[test]
+    fn test_intact_transcript_verifies() {
+        let (_dir, content) = write_session(None);
+        let result = verify(&content, None);
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert_eq!(result.events, 4);
+        assert!(result.sealed);
+
+        // Existing readers still see the same fields
+        let first: Value = serde_json::from_str(content.lines().next().unwrap()).unwrap();
+        assert_eq!(first["type"], "user_message");
+        assert_eq!(first["content"], "hello");
+        assert_eq!(first["seq"], 0);
+    }
+
+    #[test]
+    fn test_gaps_reordering_and_edits_detected() {
+        let (_dir, content) = write_session(None);
+        let lines: Vec<&str> = content.lines().collect();
+
+        let gap = [lines[0], lines[2], lines[3]].join("\n");
+        let result = verify(&gap, None);
+        assert_eq!(
+            result.errors,
+            ["line 2: event 1 is missing before this line"]
+        );
+
+        let swapped = [lines[0], lines[2], lines[1], lines[3]].join("\n");
+        assert!(!verify(&swapped, None).errors.is_empty());
+
+        let edited = content.replace("\"path\":\"a\"", "\"path\":\"b\"");
+        let result = verify(&edited, None);
+        assert_eq!(
+            result.errors,
+            ["line 3: prev_hash mismatch: line 2 was modified"]
+        );
+
+        let truncated = lines[..3].join("\n");
+        let result = verify(&truncated, None);
+        assert!(result.errors.is_empty());
+        assert!(!result.sealed);
+        assert_eq!(result.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_hmac_detects_rewritten_chain() {
+        let (_dir, content) = write_session(Some(b"secret"));
+        let result = verify(&content, Some(b"secret"));
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert_eq!(result.signed, 4);
+
+        // Editing the seal leaves the hash chain intact but breaks its HMAC
+        let forged = content.replace("\"events\":3", "\"events\":3 ");
+        let result = verify(&forged, Some(b"secret"));
+        assert_eq!(
+            result.errors,
+            ["line 4: HMAC mismatch (modified or wrong key)"]
+        );
+        assert_eq!(verify(&content, Some(b"other")).errors.len(), 4);
+    }
+}